@@ -1,7 +1,20 @@
 use crate::io::AxPollState;
+use alloc::boxed::Box;
+#[cfg(feature = "fs")]
+use alloc::{sync::Arc, vec::Vec};
 use axerrno::AxResult;
-use axnet::{UdpSocket, TcpSocket};
+use axnet::{CaptureSink, ConsoleSink, IcmpSocket, TcpSocket, UdpSocket};
 use core::net::{IpAddr, SocketAddr};
+use core::time::Duration;
+
+#[cfg(feature = "fs")]
+use axfs::fops::File;
+#[cfg(feature = "fs")]
+use axsync::spin::SpinNoIrq;
+
+pub use axnet::CaptureFilter as AxCaptureFilter;
+pub use axnet::NetInterfaceInfo as AxNetIfaceInfo;
+pub use axnet::RouteEntry as AxRouteEntry;
 
 /// A handle to a TCP socket.
 pub struct AxTcpSocketHandle(TcpSocket);
@@ -9,6 +22,156 @@ pub struct AxTcpSocketHandle(TcpSocket);
 /// A handle to a UDP socket.
 pub struct AxUdpSocketHandle(UdpSocket);
 
+/// A handle to an ICMP socket.
+pub struct AxIcmpSocketHandle(IcmpSocket);
+
+/// Max number of bytes of the pcap stream buffered by a [`FileSink`].
+#[cfg(feature = "fs")]
+const FILE_SINK_BUF_LEN: usize = 1024 * 1024;
+
+/// The pcap stream buffered by a [`FileSink`].
+#[cfg(feature = "fs")]
+#[derive(Default)]
+struct SinkBuffer {
+    data: Vec<u8>,
+    /// Number of records dropped as the buffer is full.
+    dropped: usize,
+    /// Whether the capture has stopped.
+    stopped: bool,
+}
+
+/// State shared by a [`FileSink`] and its writer task.
+#[cfg(feature = "fs")]
+struct SinkShared {
+    /// Locked without sleeping, as the writer task checks it in
+    /// `WaitQueue::wait_until` with preemption disabled.
+    buf: SpinNoIrq<SinkBuffer>,
+    #[cfg(feature = "multitask")]
+    wq: axtask::WaitQueue,
+}
+
+/// A [`CaptureSink`] that writes the pcap stream to a file.
+///
+/// Frames are captured with the interface locked, so the stream is buffered in
+/// memory, and written by a separate task, or when the capture stops if
+/// `multitask` is not enabled. Records are dropped if the buffer is full.
+#[cfg(feature = "fs")]
+struct FileSink {
+    shared: Arc<SinkShared>,
+    #[cfg(feature = "multitask")]
+    writer: axtask::AxTaskRef,
+    #[cfg(not(feature = "multitask"))]
+    file: File,
+}
+
+#[cfg(feature = "fs")]
+impl FileSink {
+    fn new(file: File) -> Self {
+        let shared = Arc::new(SinkShared {
+            buf: SpinNoIrq::new(SinkBuffer::default()),
+            #[cfg(feature = "multitask")]
+            wq: axtask::WaitQueue::new(),
+        });
+        #[cfg(feature = "multitask")]
+        {
+            let writer_shared = shared.clone();
+            let writer = axtask::spawn_raw(
+                move || writer_shared.write_file(file),
+                "pcap-writer".into(),
+                axconfig::TASK_STACK_SIZE,
+            );
+            Self { shared, writer }
+        }
+        #[cfg(not(feature = "multitask"))]
+        Self { shared, file }
+    }
+}
+
+#[cfg(feature = "fs")]
+impl SinkShared {
+    /// Writes the buffered stream to `file` until the capture stops.
+    #[cfg(feature = "multitask")]
+    fn write_file(&self, mut file: File) {
+        loop {
+            self.wq.wait_until(|| {
+                let buf = self.buf.lock();
+                !buf.data.is_empty() || buf.stopped
+            });
+            let (data, stopped) = {
+                let mut buf = self.buf.lock();
+                (core::mem::take(&mut buf.data), buf.stopped)
+            };
+            write_all(&mut file, &data);
+            if stopped {
+                break;
+            }
+        }
+        file.flush().ok();
+    }
+
+    fn stop(&self) {
+        self.buf.lock().stopped = true;
+        #[cfg(feature = "multitask")]
+        self.wq.notify_one(false);
+    }
+}
+
+#[cfg(feature = "fs")]
+fn write_all(file: &mut File, mut data: &[u8]) {
+    while !data.is_empty() {
+        match file.write(data) {
+            Ok(0) => break,
+            Ok(n) => data = &data[n..],
+            Err(e) => {
+                axlog::warn!("packet capture: failed to write file: {:?}", e);
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(feature = "fs")]
+impl CaptureSink for FileSink {
+    fn write(&mut self, data: &[u8]) {
+        let mut buf = self.shared.buf.lock();
+        if buf.data.len() + data.len() > FILE_SINK_BUF_LEN {
+            buf.dropped += 1;
+            return;
+        }
+        buf.data.extend_from_slice(data);
+        drop(buf);
+        #[cfg(feature = "multitask")]
+        self.shared.wq.notify_one(false);
+    }
+
+    fn flush(&mut self) {
+        self.shared.stop();
+        #[cfg(feature = "multitask")]
+        self.writer.join();
+        #[cfg(not(feature = "multitask"))]
+        {
+            let data = core::mem::take(&mut self.shared.buf.lock().data);
+            write_all(&mut self.file, &data);
+            self.file.flush().ok();
+        }
+        let dropped = self.shared.buf.lock().dropped;
+        if dropped > 0 {
+            axlog::warn!(
+                "packet capture: {} records dropped as the buffer is full",
+                dropped
+            );
+        }
+    }
+}
+
+#[cfg(feature = "fs")]
+impl Drop for FileSink {
+    fn drop(&mut self) {
+        // let the writer task exit if the capture has not started
+        self.shared.stop();
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // TCP socket
 ////////////////////////////////////////////////////////////////////////////////
@@ -38,8 +201,8 @@ pub fn ax_tcp_bind(socket: &AxTcpSocketHandle, addr: SocketAddr) -> AxResult {
     socket.0.bind(addr)
 }
 
-pub fn ax_tcp_listen(socket: &AxTcpSocketHandle, _backlog: usize) -> AxResult {
-    socket.0.listen()
+pub fn ax_tcp_listen(socket: &AxTcpSocketHandle, backlog: usize) -> AxResult {
+    socket.0.listen(backlog)
 }
 
 pub fn ax_tcp_accept(socket: &AxTcpSocketHandle) -> AxResult<(AxTcpSocketHandle, SocketAddr)> {
@@ -117,6 +280,97 @@ pub fn ax_udp_poll(socket: &AxUdpSocketHandle) -> AxResult<AxPollState> {
     socket.0.poll()
 }
 
+////////////////////////////////////////////////////////////////////////////////
+// ICMP socket
+////////////////////////////////////////////////////////////////////////////////
+
+pub fn ax_icmp_socket() -> AxIcmpSocketHandle {
+    AxIcmpSocketHandle(IcmpSocket::new())
+}
+
+pub fn ax_icmp_set_nonblocking(socket: &AxIcmpSocketHandle, nonblocking: bool) -> AxResult {
+    socket.0.set_nonblocking(nonblocking);
+    Ok(())
+}
+
+pub fn ax_icmp_set_recv_timeout(
+    socket: &AxIcmpSocketHandle,
+    timeout: Option<Duration>,
+) -> AxResult {
+    socket.0.set_recv_timeout(timeout)
+}
+
+pub fn ax_icmp_send_to(socket: &AxIcmpSocketHandle, buf: &[u8], addr: IpAddr) -> AxResult<usize> {
+    socket.0.send_to(buf, addr)
+}
+
+pub fn ax_icmp_recv_from(socket: &AxIcmpSocketHandle, buf: &mut [u8]) -> AxResult<(usize, IpAddr)> {
+    socket.0.recv_from(buf)
+}
+
+pub fn ax_icmp_poll(socket: &AxIcmpSocketHandle) -> AxResult<AxPollState> {
+    socket.0.poll()
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Interfaces and routing
+////////////////////////////////////////////////////////////////////////////////
+
+pub fn ax_net_interfaces() -> alloc::vec::Vec<AxNetIfaceInfo> {
+    axnet::interfaces()
+}
+
+pub fn ax_net_set_interface_config(
+    name: &str,
+    addr: IpAddr,
+    prefix_len: u8,
+    gateway: Option<IpAddr>,
+) -> AxResult {
+    axnet::set_interface_config(name, addr, prefix_len, gateway)
+}
+
+pub fn ax_net_routes() -> alloc::vec::Vec<AxRouteEntry> {
+    axnet::routes()
+}
+
+pub fn ax_net_add_route(
+    dest: IpAddr,
+    prefix_len: u8,
+    gateway: Option<IpAddr>,
+    iface: &str,
+) -> AxResult {
+    axnet::add_route(dest, prefix_len, gateway, iface)
+}
+
+pub fn ax_net_del_route(dest: IpAddr, prefix_len: u8) -> AxResult {
+    axnet::del_route(dest, prefix_len)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Packet capture
+////////////////////////////////////////////////////////////////////////////////
+
+pub fn ax_net_capture_start(path: Option<&str>, filter: AxCaptureFilter) -> AxResult {
+    let sink: Box<dyn CaptureSink> = match path {
+        None => Box::new(ConsoleSink::new()),
+        #[cfg(feature = "fs")]
+        Some(path) => {
+            let mut opts = axfs::fops::OpenOptions::new();
+            opts.write(true);
+            opts.create(true);
+            opts.truncate(true);
+            Box::new(FileSink::new(File::open(path, &opts)?))
+        }
+        #[cfg(not(feature = "fs"))]
+        Some(_) => return axerrno::ax_err!(Unsupported, "capturing to a file requires `fs`"),
+    };
+    axnet::start_capture(sink, filter)
+}
+
+pub fn ax_net_capture_stop() -> AxResult<usize> {
+    axnet::stop_capture()
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // Miscellaneous
 ////////////////////////////////////////////////////////////////////////////////
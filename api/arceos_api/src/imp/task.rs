@@ -42,6 +42,28 @@ cfg_task! {
     /// A mask to specify the CPU affinity.
     pub use axtask::AxCpuMask;
 
+    /// The possible states of a task.
+    pub use axtask::TaskState as AxTaskState;
+
+    /// A snapshot of the information of a task.
+    #[derive(Debug, Clone)]
+    pub struct AxTaskInfo {
+        /// The task ID.
+        pub id: u64,
+        /// The task name.
+        pub name: alloc::string::String,
+        /// The state of the task.
+        pub state: AxTaskState,
+        /// Whether the task is blocked in a wait queue, rather than sleeping.
+        pub waiting: bool,
+        /// Whether the task is the idle task of a CPU.
+        pub idle: bool,
+        /// The ID of the CPU that the task runs on or last ran on.
+        pub cpu: usize,
+        /// The priority of the task.
+        pub priority: isize,
+    }
+
     /// A handle to a wait queue.
     ///
     /// A wait queue is used to store sleeping tasks waiting for a certain event
@@ -70,6 +92,20 @@ cfg_task! {
         }
     }
 
+    pub fn ax_for_each_task(mut f: impl FnMut(&AxTaskInfo)) {
+        axtask::for_each_task(|task| {
+            f(&AxTaskInfo {
+                id: task.id().as_u64(),
+                name: task.name().into(),
+                state: task.state(),
+                waiting: task.is_waiting(),
+                idle: task.is_idle(),
+                cpu: task.cpu_id(),
+                priority: task.priority(),
+            })
+        });
+    }
+
     pub fn ax_wait_for_exit(task: AxTaskHandle) -> Option<i32> {
         task.inner.join()
     }
@@ -122,6 +122,8 @@ pub mod task {
         pub type AxTaskHandle;
         pub type AxWaitQueueHandle;
         pub type AxCpuMask;
+        pub type AxTaskInfo;
+        pub type AxTaskState;
     }
 
     define_api! {
@@ -154,6 +156,12 @@ pub mod task {
         /// Waits for the given task to exit, and returns its exit code (the
         /// argument of [`ax_exit`]).
         pub fn ax_wait_for_exit(task: AxTaskHandle) -> Option<i32>;
+        /// Calls `f` with the information of each alive task, in the order of
+        /// task IDs.
+        ///
+        /// The information is a snapshot, which may be out of date when `f`
+        /// is called.
+        pub fn ax_for_each_task(f: impl FnMut(&AxTaskInfo));
         /// Sets the priority of the current task.
         pub fn ax_set_current_priority(prio: isize) -> crate::AxResult;
         /// Sets the cpu affinity of the current task.
@@ -272,6 +280,10 @@ pub mod net {
         @cfg "net";
         pub type AxTcpSocketHandle;
         pub type AxUdpSocketHandle;
+        pub type AxIcmpSocketHandle;
+        pub type AxCaptureFilter;
+        pub type AxNetIfaceInfo;
+        pub type AxRouteEntry;
     }
 
     define_api! {
@@ -292,8 +304,9 @@ pub mod net {
         pub fn ax_tcp_connect(handle: &AxTcpSocketHandle, addr: SocketAddr) -> AxResult;
         /// Binds the TCP socket to the given address and port.
         pub fn ax_tcp_bind(socket: &AxTcpSocketHandle, addr: SocketAddr) -> AxResult;
-        /// Starts listening on the bound address and port.
-        pub fn ax_tcp_listen(socket: &AxTcpSocketHandle, _backlog: usize) -> AxResult;
+        /// Starts listening on the bound address and port, with at most
+        /// `backlog` pending connections.
+        pub fn ax_tcp_listen(socket: &AxTcpSocketHandle, backlog: usize) -> AxResult;
         /// Accepts a new connection on the TCP socket.
         ///
         /// This function will block the calling thread until a new TCP connection
@@ -346,6 +359,51 @@ pub mod net {
         /// Returns whether the UDP socket is readable or writable.
         pub fn ax_udp_poll(socket: &AxUdpSocketHandle) -> AxResult<AxPollState>;
 
+        // ICMP socket
+
+        /// Creates a new ICMP socket, which is a raw socket of ICMP over IPv4.
+        pub fn ax_icmp_socket() -> AxIcmpSocketHandle;
+        /// Moves this ICMP socket into or out of nonblocking mode.
+        pub fn ax_icmp_set_nonblocking(socket: &AxIcmpSocketHandle, nonblocking: bool) -> AxResult;
+        /// Sets the timeout of receiving on the ICMP socket, or blocks
+        /// indefinitely if it is [`None`].
+        pub fn ax_icmp_set_recv_timeout(socket: &AxIcmpSocketHandle, timeout: Option<core::time::Duration>) -> AxResult;
+        /// Sends an ICMP message, without the IP header, to the given address.
+        /// The checksum of the message must be filled by the caller.
+        pub fn ax_icmp_send_to(socket: &AxIcmpSocketHandle, buf: &[u8], addr: IpAddr) -> AxResult<usize>;
+        /// Receives a single ICMP packet, including the IP header, on the ICMP
+        /// socket. On success, returns the number of bytes read and the origin.
+        pub fn ax_icmp_recv_from(socket: &AxIcmpSocketHandle, buf: &mut [u8]) -> AxResult<(usize, IpAddr)>;
+        /// Returns whether the ICMP socket is readable or writable.
+        pub fn ax_icmp_poll(socket: &AxIcmpSocketHandle) -> AxResult<AxPollState>;
+
+        // Interfaces and routing
+
+        /// Returns information of all network interfaces, i.e., `lo` and
+        /// `eth0`, `eth1`, ... of the NICs.
+        pub fn ax_net_interfaces() -> alloc::vec::Vec<AxNetIfaceInfo>;
+        /// Sets the address of the network interface to `addr/prefix_len`,
+        /// replacing its addresses of the same IP version, and the default
+        /// route via `gateway` if given.
+        pub fn ax_net_set_interface_config(name: &str, addr: IpAddr, prefix_len: u8, gateway: Option<IpAddr>) -> AxResult;
+        /// Returns all entries of the routing table.
+        pub fn ax_net_routes() -> alloc::vec::Vec<AxRouteEntry>;
+        /// Adds a route to the network `dest/prefix_len` through the network
+        /// interface, via `gateway` if the network is not directly connected.
+        pub fn ax_net_add_route(dest: IpAddr, prefix_len: u8, gateway: Option<IpAddr>, iface: &str) -> AxResult;
+        /// Removes the route to the network `dest/prefix_len`.
+        pub fn ax_net_del_route(dest: IpAddr, prefix_len: u8) -> AxResult;
+
+        // Packet capture
+
+        /// Starts capturing the frames of the NICs that match `filter` as a
+        /// pcap stream, which is written to the file at `path`, or printed to
+        /// the console in base64 if `path` is [`None`].
+        pub fn ax_net_capture_start(path: Option<&str>, filter: AxCaptureFilter) -> AxResult;
+        /// Stops the running packet capture. On success, returns the number
+        /// of captured frames.
+        pub fn ax_net_capture_stop() -> AxResult<usize>;
+
         // Miscellaneous
 
         /// Resolves the host name to a list of IP addresses.
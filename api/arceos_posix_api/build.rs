@@ -57,7 +57,11 @@ typedef struct {{
             "iovec",
             "clockid_t",
             "rlimit",
+            "rusage",
+            "tms",
             "aibuf",
+            "linger",
+            "msghdr",
         ];
         let allow_vars = [
             "CLOCK_.*",
@@ -65,14 +69,22 @@ typedef struct {{
             "AF_.*",
             "SOCK_.*",
             "IPPROTO_.*",
+            "SOL_.*",
+            "SO_.*",
+            "MSG_.*",
+            "TCP_NODELAY",
             "FD_.*",
             "F_.*",
             "_SC_.*",
             "EPOLL_CTL_.*",
             "EPOLL.*",
             "RLIMIT_.*",
+            "RUSAGE_.*",
             "EAI_.*",
             "MAXADDRS",
+            "MS_.*",
+            "MNT_.*",
+            "[FRWX]_OK",
         ];
 
         #[derive(Debug)]
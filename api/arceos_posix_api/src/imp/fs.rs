@@ -1,8 +1,8 @@
-use alloc::sync::Arc;
-use core::ffi::{c_char, c_int};
+use alloc::{string::String, sync::Arc};
+use core::ffi::{c_char, c_int, c_ulong, c_void};
 
-use axerrno::{LinuxError, LinuxResult};
-use axfs::fops::OpenOptions;
+use axerrno::{AxError, LinuxError, LinuxResult};
+use axfs::fops::{DirEntry, FileAttr, FileTimes, OpenOptions};
 use axio::{PollState, SeekFrom};
 use axsync::Mutex;
 
@@ -46,21 +46,8 @@ impl FileLike for File {
     }
 
     fn stat(&self) -> LinuxResult<ctypes::stat> {
-        let metadata = self.inner.lock().get_attr()?;
-        let ty = metadata.file_type() as u8;
-        let perm = metadata.perm().bits() as u32;
-        let st_mode = ((ty as u32) << 12) | perm;
-        Ok(ctypes::stat {
-            st_ino: 1,
-            st_nlink: 1,
-            st_mode,
-            st_uid: 1000,
-            st_gid: 1000,
-            st_size: metadata.size() as _,
-            st_blocks: metadata.blocks() as _,
-            st_blksize: 512,
-            ..Default::default()
-        })
+        let file = self.inner.lock();
+        Ok(to_stat(file.get_attr()?, file.get_times()?))
     }
 
     fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
@@ -79,6 +66,106 @@ impl FileLike for File {
     }
 }
 
+pub struct Directory {
+    inner: Mutex<axfs::fops::Directory>,
+    /// Absolute path of the directory, for `fchdir`.
+    path: String,
+}
+
+impl Directory {
+    fn open(path: &str) -> LinuxResult<Self> {
+        let mut options = OpenOptions::new();
+        options.read(true);
+        Ok(Self {
+            inner: Mutex::new(
+                axfs::fops::Directory::open_dir(path, &options).map_err(path_err(path))?,
+            ),
+            path: axfs::api::canonicalize(path)?,
+        })
+    }
+
+    fn add_to_fd_table(self) -> LinuxResult<c_int> {
+        super::fd_ops::add_file_like(Arc::new(self))
+    }
+
+    pub(crate) fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>> {
+        let f = super::fd_ops::get_file_like(fd)?;
+        f.into_any()
+            .downcast::<Self>()
+            .map_err(|_| LinuxError::ENOTDIR)
+    }
+}
+
+impl FileLike for Directory {
+    fn read(&self, _buf: &mut [u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EISDIR)
+    }
+
+    fn write(&self, _buf: &[u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EISDIR)
+    }
+
+    fn stat(&self) -> LinuxResult<ctypes::stat> {
+        let dir = self.inner.lock();
+        Ok(to_stat(dir.get_attr()?, dir.get_times()?))
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        Ok(PollState {
+            readable: true,
+            writable: false,
+        })
+    }
+
+    fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
+        Ok(())
+    }
+}
+
+/// Converts an error of an operation on `paths`.
+///
+/// Loops of symbolic links fail the operation with `InvalidInput`, which
+/// becomes `ELOOP` if expanding one of `paths` loops.
+pub(crate) fn paths_err(e: AxError, paths: &[&str]) -> LinuxError {
+    let is_loop = |path: &&str| {
+        axfs::api::resolve_symlinks(path) == Err(axfs::api::ResolveError::SymlinkLoop)
+    };
+    match e {
+        AxError::InvalidInput if paths.iter().any(is_loop) => LinuxError::ELOOP,
+        e => e.into(),
+    }
+}
+
+/// Returns the converter of errors of an operation on `path`, see
+/// [`paths_err`].
+pub(crate) fn path_err(path: &str) -> impl Fn(AxError) -> LinuxError + '_ {
+    move |e| paths_err(e, &[path])
+}
+
+fn to_stat(attr: FileAttr, times: FileTimes) -> ctypes::stat {
+    let ty = attr.file_type() as u8;
+    let perm = attr.perm().bits() as u32;
+    let st_mode = ((ty as u32) << 12) | perm;
+    ctypes::stat {
+        st_ino: 1,
+        st_nlink: 1,
+        st_mode,
+        st_uid: 1000,
+        st_gid: 1000,
+        st_size: attr.size() as _,
+        st_blocks: attr.blocks() as _,
+        st_blksize: 512,
+        st_atim: times.atime.into(),
+        st_mtim: times.mtime.into(),
+        st_ctim: times.ctime.into(),
+        ..Default::default()
+    }
+}
+
 /// Convert open flags to [`OpenOptions`].
 fn flags_to_options(flags: c_int, _mode: ctypes::mode_t) -> OpenOptions {
     let flags = flags as u32;
@@ -114,8 +201,17 @@ pub fn sys_open(filename: *const c_char, flags: c_int, mode: ctypes::mode_t) ->
     let filename = char_ptr_to_str(filename);
     debug!("sys_open <= {:?} {:#o} {:#o}", filename, flags, mode);
     syscall_body!(sys_open, {
+        let filename = filename?;
+        if flags as u32 & ctypes::O_DIRECTORY != 0 {
+            return Directory::open(filename)?.add_to_fd_table();
+        }
         let options = flags_to_options(flags, mode);
-        let file = axfs::fops::File::open(filename?, &options)?;
+        let file = axfs::fops::File::open(filename, &options).map_err(path_err(filename))?;
+        if file.get_attr()?.is_dir() {
+            // directories are read by `getdents64`, not `read`
+            drop(file);
+            return Directory::open(filename)?.add_to_fd_table();
+        }
         File::new(file).add_to_fd_table()
     })
 }
@@ -132,6 +228,14 @@ pub fn sys_lseek(fd: c_int, offset: ctypes::off_t, whence: c_int) -> ctypes::off
             2 => SeekFrom::End(offset as _),
             _ => return Err(LinuxError::EINVAL),
         };
+        if let Ok(dir) = Directory::from_fd(fd) {
+            // only rewinding or going back to a `d_off` from `getdents64`
+            let SeekFrom::Start(idx) = pos else {
+                return Err(LinuxError::EINVAL);
+            };
+            dir.inner.lock().set_entry_idx(idx as usize);
+            return Ok(idx);
+        }
         let off = File::from_fd(fd)?.inner.lock().seek(pos)?;
         Ok(off)
     })
@@ -149,7 +253,8 @@ pub unsafe fn sys_stat(path: *const c_char, buf: *mut ctypes::stat) -> c_int {
         }
         let mut options = OpenOptions::new();
         options.read(true);
-        let file = axfs::fops::File::open(path?, &options)?;
+        let path = path?;
+        let file = axfs::fops::File::open(path, &options).map_err(path_err(path))?;
         let st = File::new(file).stat()?;
         unsafe { *buf = st };
         Ok(0)
@@ -181,7 +286,176 @@ pub unsafe fn sys_lstat(path: *const c_char, buf: *mut ctypes::stat) -> ctypes::
         if buf.is_null() {
             return Err(LinuxError::EFAULT);
         }
-        unsafe { *buf = Default::default() }; // TODO
+        let mut options = OpenOptions::new();
+        options.read(true);
+        options.nofollow(true);
+        let path = path?;
+        let file = axfs::fops::File::open(path, &options).map_err(path_err(path))?;
+        let st = File::new(file).stat()?;
+        unsafe { *buf = st };
+        Ok(0)
+    })
+}
+
+/// The header of `struct linux_dirent64`, followed by the null-terminated name.
+#[repr(C)]
+struct Dirent64Header {
+    d_ino: u64,
+    d_off: i64,
+    d_reclen: u16,
+    d_type: u8,
+}
+
+/// Read directory entries of the directory `fd` into `dirp`, as
+/// `struct linux_dirent64` records.
+///
+/// Return the number of bytes read, or 0 at the end of the directory.
+pub unsafe fn sys_getdents64(fd: c_int, dirp: *mut c_void, count: usize) -> ctypes::ssize_t {
+    debug!("sys_getdents64 <= {} {:#x} {}", fd, dirp as usize, count);
+    syscall_body!(sys_getdents64, {
+        if dirp.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let buf = unsafe { core::slice::from_raw_parts_mut(dirp.cast::<u8>(), count) };
+        let dir = Directory::from_fd(fd)?;
+        let mut dir = dir.inner.lock();
+        let mut entry = [DirEntry::default()];
+        let mut written = 0;
+        loop {
+            let idx = dir.entry_idx();
+            if dir.read_dir(&mut entry)? == 0 {
+                break;
+            }
+            let name = entry[0].name_as_bytes();
+            let name_offset = core::mem::offset_of!(Dirent64Header, d_type) + 1;
+            let reclen = (name_offset + name.len() + 1).next_multiple_of(8);
+            if written + reclen > count {
+                // keep the entry for the next call
+                dir.set_entry_idx(idx);
+                if written == 0 {
+                    return Err(LinuxError::EINVAL);
+                }
+                break;
+            }
+
+            let record = &mut buf[written..written + reclen];
+            let header = Dirent64Header {
+                d_ino: idx as u64 + 1,
+                d_off: idx as i64 + 1,
+                d_reclen: reclen as u16,
+                d_type: entry[0].entry_type() as u8,
+            };
+            unsafe {
+                record
+                    .as_mut_ptr()
+                    .cast::<Dirent64Header>()
+                    .write_unaligned(header)
+            };
+            record[name_offset..name_offset + name.len()].copy_from_slice(name);
+            record[name_offset + name.len()..].fill(0);
+            written += reclen;
+        }
+        Ok(written as ctypes::ssize_t)
+    })
+}
+
+/// Truncate the file `fd` to `length` bytes.
+///
+/// Return 0 if the operation succeeds.
+pub fn sys_ftruncate(fd: c_int, length: ctypes::off_t) -> c_int {
+    debug!("sys_ftruncate <= {} {}", fd, length);
+    syscall_body!(sys_ftruncate, {
+        if length < 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        File::from_fd(fd)?.inner.lock().truncate(length as u64)?;
+        Ok(0)
+    })
+}
+
+/// Write the cached data of the file `fd` to the storage device.
+///
+/// Return 0 if the operation succeeds.
+pub fn sys_fsync(fd: c_int) -> c_int {
+    debug!("sys_fsync <= {}", fd);
+    syscall_body!(sys_fsync, {
+        if let Ok(dir) = Directory::from_fd(fd) {
+            axfs::api::sync_fs(&dir.path)?;
+            return Ok(0);
+        }
+        File::from_fd(fd)?.inner.lock().flush()?;
+        Ok(0)
+    })
+}
+
+/// Create a directory `pathname`.
+///
+/// `mode` is ignored. Return 0 if the operation succeeds.
+pub fn sys_mkdir(pathname: *const c_char, mode: ctypes::mode_t) -> c_int {
+    syscall_body!(sys_mkdir, {
+        let pathname = char_ptr_to_str(pathname)?;
+        debug!("sys_mkdir <= {:?} {:#o}", pathname, mode);
+        axfs::api::create_dir(pathname).map_err(path_err(pathname))?;
+        Ok(0)
+    })
+}
+
+/// Remove the empty directory `pathname`.
+///
+/// Return 0 if the operation succeeds.
+pub fn sys_rmdir(pathname: *const c_char) -> c_int {
+    syscall_body!(sys_rmdir, {
+        let pathname = char_ptr_to_str(pathname)?;
+        debug!("sys_rmdir <= {:?}", pathname);
+        axfs::api::remove_dir(pathname).map_err(path_err(pathname))?;
+        Ok(0)
+    })
+}
+
+/// Change the current directory to `path`.
+///
+/// Return 0 if the operation succeeds.
+pub fn sys_chdir(path: *const c_char) -> c_int {
+    syscall_body!(sys_chdir, {
+        let path = char_ptr_to_str(path)?;
+        debug!("sys_chdir <= {:?}", path);
+        axfs::api::set_current_dir(path).map_err(path_err(path))?;
+        Ok(0)
+    })
+}
+
+/// Change the current directory to the directory `fd`.
+///
+/// Return 0 if the operation succeeds.
+pub fn sys_fchdir(fd: c_int) -> c_int {
+    debug!("sys_fchdir <= {}", fd);
+    syscall_body!(sys_fchdir, {
+        axfs::api::set_current_dir(&Directory::from_fd(fd)?.path)?;
+        Ok(0)
+    })
+}
+
+/// Check whether the file `pathname` can be accessed with `mode`, which is
+/// `F_OK` or a mask of `R_OK`, `W_OK` and `X_OK`.
+///
+/// The owner permissions are checked. Return 0 if the access is allowed.
+pub fn sys_access(pathname: *const c_char, mode: c_int) -> c_int {
+    syscall_body!(sys_access, {
+        let pathname = char_ptr_to_str(pathname)?;
+        debug!("sys_access <= {:?} {:#o}", pathname, mode);
+        let mode = mode as u32;
+        if mode & !(ctypes::R_OK | ctypes::W_OK | ctypes::X_OK) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let perm = axfs::api::metadata(pathname)
+            .map_err(path_err(pathname))?
+            .permissions();
+        if (mode & ctypes::R_OK != 0 && !perm.owner_readable())
+            || (mode & ctypes::W_OK != 0 && !perm.owner_writable())
+            || (mode & ctypes::X_OK != 0 && !perm.owner_executable())
+        {
+            return Err(LinuxError::EACCES);
+        }
         Ok(0)
     })
 }
@@ -216,7 +490,122 @@ pub fn sys_rename(old: *const c_char, new: *const c_char) -> c_int {
         let old_path = char_ptr_to_str(old)?;
         let new_path = char_ptr_to_str(new)?;
         debug!("sys_rename <= old: {:?}, new: {:?}", old_path, new_path);
-        axfs::api::rename(old_path, new_path)?;
+        axfs::api::rename(old_path, new_path).map_err(|e| paths_err(e, &[old_path, new_path]))?;
+        Ok(0)
+    })
+}
+
+/// Create a symbolic link `linkpath` which contains the string `target`.
+///
+/// Return 0 if the operation succeeds.
+pub fn sys_symlink(target: *const c_char, linkpath: *const c_char) -> c_int {
+    syscall_body!(sys_symlink, {
+        let target = char_ptr_to_str(target)?;
+        let linkpath = char_ptr_to_str(linkpath)?;
+        debug!(
+            "sys_symlink <= target: {:?}, linkpath: {:?}",
+            target, linkpath
+        );
+        axfs::api::symlink(target, linkpath).map_err(path_err(linkpath))?;
+        Ok(0)
+    })
+}
+
+/// Read the target of the symbolic link `path` into `buf`, without a
+/// terminating null byte. It is truncated if `buf` is too small.
+///
+/// Return the number of bytes placed in `buf`.
+pub unsafe fn sys_readlink(
+    path: *const c_char,
+    buf: *mut c_char,
+    bufsize: usize,
+) -> ctypes::ssize_t {
+    syscall_body!(sys_readlink, {
+        let path = char_ptr_to_str(path)?;
+        debug!("sys_readlink <= {:?} {:#x} {}", path, buf as usize, bufsize);
+        if buf.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let target = axfs::api::read_link(path).map_err(path_err(path))?;
+        let len = target.len().min(bufsize);
+        let dst = unsafe { core::slice::from_raw_parts_mut(buf.cast::<u8>(), len) };
+        dst.copy_from_slice(&target.as_bytes()[..len]);
+        Ok(len as ctypes::ssize_t)
+    })
+}
+
+/// Create a new hard link `new` to the existing file `old`.
+///
+/// Return 0 if the operation succeeds, `EXDEV` if `old` and `new` are on
+/// different filesystems, or `EPERM` if `old` is a directory or the filesystem
+/// does not support hard links.
+pub fn sys_link(old: *const c_char, new: *const c_char) -> c_int {
+    syscall_body!(sys_link, {
+        let old_path = char_ptr_to_str(old)?;
+        let new_path = char_ptr_to_str(new)?;
+        debug!("sys_link <= old: {:?}, new: {:?}", old_path, new_path);
+        axfs::api::hard_link(old_path, new_path).map_err(|e| match e {
+            AxError::Unsupported => LinuxError::EXDEV,
+            AxError::PermissionDenied => LinuxError::EPERM,
+            e => paths_err(e, &[old_path, new_path]),
+        })?;
+        Ok(0)
+    })
+}
+
+/// Remove the name `pathname` from the filesystem. If it is a symbolic link,
+/// the link itself is removed.
+///
+/// Return 0 if the operation succeeds.
+pub fn sys_unlink(pathname: *const c_char) -> c_int {
+    syscall_body!(sys_unlink, {
+        let pathname = char_ptr_to_str(pathname)?;
+        debug!("sys_unlink <= {:?}", pathname);
+        axfs::api::remove_file(pathname).map_err(path_err(pathname))?;
+        Ok(0)
+    })
+}
+
+/// Mount the filesystem of type `fstype` from `source` on the directory
+/// `target`.
+///
+/// Only the filesystem types known by [`axfs::api::mount_by_type`] are
+/// supported, `flags` and `data` are ignored.
+///
+/// Return 0 if the operation succeeds.
+pub fn sys_mount(
+    source: *const c_char,
+    target: *const c_char,
+    fstype: *const c_char,
+    flags: c_ulong,
+    _data: *const c_void,
+) -> c_int {
+    syscall_body!(sys_mount, {
+        let source = char_ptr_to_str(source)?;
+        let target = char_ptr_to_str(target)?;
+        let fstype = char_ptr_to_str(fstype)?;
+        debug!(
+            "sys_mount <= source: {:?}, target: {:?}, fstype: {:?}, flags: {:#x}",
+            source, target, fstype, flags
+        );
+        axfs::api::mount_by_type(source, target, fstype).map_err(|e| match e {
+            AxError::Unsupported => LinuxError::ENODEV,
+            e => paths_err(e, &[target]),
+        })?;
+        Ok(0)
+    })
+}
+
+/// Unmount the filesystem mounted on `target`.
+///
+/// `flags` is ignored.
+///
+/// Return 0 if the operation succeeds.
+pub fn sys_umount2(target: *const c_char, flags: c_int) -> c_int {
+    syscall_body!(sys_umount2, {
+        let target = char_ptr_to_str(target)?;
+        debug!("sys_umount2 <= target: {:?}, flags: {:#x}", target, flags);
+        axfs::api::umount(target).map_err(path_err(target))?;
         Ok(0)
     })
 }
@@ -21,6 +21,8 @@ pub mod net;
 pub mod pipe;
 #[cfg(feature = "multitask")]
 pub mod pthread;
+#[cfg(feature = "net")]
+pub mod unix;
 
 #[cfg(all(feature = "multitask", feature = "irq"))]
 pub mod timer;
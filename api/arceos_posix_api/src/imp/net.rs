@@ -1,20 +1,61 @@
 use alloc::{sync::Arc, vec, vec::Vec};
 use core::ffi::{c_char, c_int, c_void};
-use core::mem::size_of;
+use core::mem::{offset_of, size_of};
 use core::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
+use core::time::Duration;
 
-use axerrno::{LinuxError, LinuxResult};
+use axerrno::{AxError, LinuxError, LinuxResult};
 use axio::PollState;
-use axnet::{TcpSocket, UdpSocket};
+use axnet::{IcmpSocket, MsgFlags, TcpSocket, UdpSocket};
 use axsync::Mutex;
 
 use super::fd_ops::FileLike;
+use super::unix::{self, UnixAddr, UnixSocket};
 use crate::ctypes;
 use crate::utils::char_ptr_to_str;
 
 pub enum Socket {
     Udp(Mutex<UdpSocket>),
     Tcp(Mutex<TcpSocket>),
+    Icmp(Mutex<IcmpSocket>),
+    Unix(UnixSocket),
+}
+
+/// Address of an internet or Unix domain socket.
+#[derive(Debug)]
+enum SockAddr {
+    Inet(SocketAddr),
+    Unix(UnixAddr),
+}
+
+impl SockAddr {
+    /// Returns the internet address, or `EAFNOSUPPORT` for other families.
+    fn inet(self) -> LinuxResult<SocketAddr> {
+        match self {
+            SockAddr::Inet(addr) => Ok(addr),
+            SockAddr::Unix(_) => Err(LinuxError::EAFNOSUPPORT),
+        }
+    }
+
+    /// Returns the Unix domain address, or `EINVAL` for other families.
+    fn unix(self) -> LinuxResult<UnixAddr> {
+        match self {
+            SockAddr::Unix(addr) => Ok(addr),
+            SockAddr::Inet(_) => Err(LinuxError::EINVAL),
+        }
+    }
+}
+
+impl From<SocketAddr> for SockAddr {
+    fn from(addr: SocketAddr) -> SockAddr {
+        SockAddr::Inet(addr)
+    }
+}
+
+impl From<UnixAddr> for SockAddr {
+    fn from(addr: UnixAddr) -> SockAddr {
+        SockAddr::Unix(addr)
+    }
 }
 
 impl Socket {
@@ -29,17 +70,12 @@ impl Socket {
             .map_err(|_| LinuxError::EINVAL)
     }
 
-    fn send(&self, buf: &[u8]) -> LinuxResult<usize> {
-        match self {
-            Socket::Udp(udpsocket) => Ok(udpsocket.lock().send(buf)?),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().send(buf)?),
-        }
-    }
-
-    fn recv(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+    fn send(&self, buf: &[u8], flags: MsgFlags) -> LinuxResult<usize> {
         match self {
-            Socket::Udp(udpsocket) => Ok(udpsocket.lock().recv_from(buf).map(|e| e.0)?),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().recv(buf)?),
+            Socket::Udp(udpsocket) => Ok(udpsocket.lock().send_with(buf, flags)?),
+            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().send_with(buf, flags)?),
+            Socket::Icmp(_) => Err(LinuxError::EDESTADDRREQ),
+            Socket::Unix(unixsocket) => unixsocket.send(buf, flags),
         }
     }
 
@@ -47,67 +83,105 @@ impl Socket {
         match self {
             Socket::Udp(udpsocket) => Ok(udpsocket.lock().poll()?),
             Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().poll()?),
+            Socket::Icmp(icmpsocket) => Ok(icmpsocket.lock().poll()?),
+            Socket::Unix(unixsocket) => Ok(unixsocket.poll()),
         }
     }
 
-    fn local_addr(&self) -> LinuxResult<SocketAddr> {
+    fn local_addr(&self) -> LinuxResult<SockAddr> {
         match self {
-            Socket::Udp(udpsocket) => Ok(udpsocket.lock().local_addr()?),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().local_addr()?),
+            Socket::Udp(udpsocket) => Ok(udpsocket.lock().local_addr()?.into()),
+            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().local_addr()?.into()),
+            Socket::Icmp(_) => Err(LinuxError::EOPNOTSUPP),
+            Socket::Unix(unixsocket) => Ok(unixsocket.local_addr().into()),
         }
     }
 
-    fn peer_addr(&self) -> LinuxResult<SocketAddr> {
+    fn peer_addr(&self) -> LinuxResult<SockAddr> {
         match self {
-            Socket::Udp(udpsocket) => Ok(udpsocket.lock().peer_addr()?),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().peer_addr()?),
+            Socket::Udp(udpsocket) => Ok(udpsocket.lock().peer_addr()?.into()),
+            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().peer_addr()?.into()),
+            Socket::Icmp(_) => Err(LinuxError::ENOTCONN),
+            Socket::Unix(unixsocket) => Ok(unixsocket.peer_addr()?.into()),
         }
     }
 
-    fn bind(&self, addr: SocketAddr) -> LinuxResult {
+    fn bind(&self, addr: SockAddr) -> LinuxResult {
         match self {
-            Socket::Udp(udpsocket) => Ok(udpsocket.lock().bind(addr)?),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().bind(addr)?),
+            Socket::Udp(udpsocket) => Ok(udpsocket.lock().bind(addr.inet()?)?),
+            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().bind(addr.inet()?)?),
+            Socket::Icmp(_) => Err(LinuxError::EOPNOTSUPP),
+            Socket::Unix(unixsocket) => unixsocket.bind(addr.unix()?),
         }
     }
 
-    fn connect(&self, addr: SocketAddr) -> LinuxResult {
+    fn connect(&self, addr: SockAddr) -> LinuxResult {
         match self {
-            Socket::Udp(udpsocket) => Ok(udpsocket.lock().connect(addr)?),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().connect(addr)?),
+            Socket::Udp(udpsocket) => Ok(udpsocket.lock().connect(addr.inet()?)?),
+            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().connect(addr.inet()?)?),
+            Socket::Icmp(_) => Err(LinuxError::EOPNOTSUPP),
+            Socket::Unix(unixsocket) => unixsocket.connect(addr.unix()?),
         }
     }
 
-    fn sendto(&self, buf: &[u8], addr: SocketAddr) -> LinuxResult<usize> {
+    fn sendto(&self, buf: &[u8], addr: SockAddr, flags: MsgFlags) -> LinuxResult<usize> {
         match self {
             // diff: must bind before sendto
-            Socket::Udp(udpsocket) => Ok(udpsocket.lock().send_to(buf, addr)?),
+            Socket::Udp(udpsocket) => {
+                Ok(udpsocket.lock().send_to_with(buf, addr.inet()?, flags)?)
+            }
             Socket::Tcp(_) => Err(LinuxError::EISCONN),
+            // the port is ignored
+            Socket::Icmp(icmpsocket) => {
+                Ok(icmpsocket
+                    .lock()
+                    .send_to_with(buf, addr.inet()?.ip(), flags)?)
+            }
+            Socket::Unix(unixsocket) => unixsocket.send_to(buf, addr.unix()?, flags),
         }
     }
 
-    fn recvfrom(&self, buf: &mut [u8]) -> LinuxResult<(usize, Option<SocketAddr>)> {
+    /// Receives data with `flags`, returns the number of bytes read, the full
+    /// length of the datagram, which is larger if it's truncated, and the
+    /// source address.
+    fn recvfrom(
+        &self,
+        buf: &mut [u8],
+        flags: MsgFlags,
+    ) -> LinuxResult<(usize, usize, Option<SockAddr>)> {
         match self {
             // diff: must bind before recvfrom
             Socket::Udp(udpsocket) => Ok(udpsocket
                 .lock()
-                .recv_from(buf)
-                .map(|res| (res.0, Some(res.1)))?),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().recv(buf).map(|res| (res, None))?),
+                .recv_from_with(buf, flags)
+                .map(|res| (res.0, res.1, Some(res.2.into())))?),
+            Socket::Tcp(tcpsocket) => Ok(tcpsocket
+                .lock()
+                .recv_with(buf, flags)
+                .map(|res| (res, res, None))?),
+            Socket::Icmp(icmpsocket) => Ok(icmpsocket
+                .lock()
+                .recv_from_with(buf, flags)
+                .map(|res| (res.0, res.1, Some(SocketAddr::new(res.2, 0).into())))?),
+            Socket::Unix(unixsocket) => unixsocket
+                .recv_from(buf, flags)
+                .map(|res| (res.0, res.1, res.2.map(SockAddr::Unix))),
         }
     }
 
-    fn listen(&self) -> LinuxResult {
+    fn listen(&self, backlog: usize) -> LinuxResult {
         match self {
-            Socket::Udp(_) => Err(LinuxError::EOPNOTSUPP),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().listen()?),
+            Socket::Udp(_) | Socket::Icmp(_) => Err(LinuxError::EOPNOTSUPP),
+            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().listen(backlog)?),
+            Socket::Unix(unixsocket) => unixsocket.listen(backlog),
         }
     }
 
-    fn accept(&self) -> LinuxResult<TcpSocket> {
+    fn accept(&self) -> LinuxResult<Socket> {
         match self {
-            Socket::Udp(_) => Err(LinuxError::EOPNOTSUPP),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().accept()?),
+            Socket::Udp(_) | Socket::Icmp(_) => Err(LinuxError::EOPNOTSUPP),
+            Socket::Tcp(tcpsocket) => Ok(Socket::Tcp(Mutex::new(tcpsocket.lock().accept()?))),
+            Socket::Unix(unixsocket) => Ok(Socket::Unix(unixsocket.accept()?)),
         }
     }
 
@@ -126,17 +200,123 @@ impl Socket {
                 tcpsocket.shutdown()?;
                 Ok(())
             }
+
+            Socket::Icmp(_) => Err(LinuxError::ENOTCONN),
+            Socket::Unix(unixsocket) => unixsocket.shutdown(),
+        }
+    }
+
+    /// Returns the TCP socket, or `ENOPROTOOPT` for options only of TCP.
+    fn tcp(&self) -> LinuxResult<&Mutex<TcpSocket>> {
+        match self {
+            Socket::Udp(_) | Socket::Icmp(_) | Socket::Unix(_) => Err(LinuxError::ENOPROTOOPT),
+            Socket::Tcp(tcpsocket) => Ok(tcpsocket),
+        }
+    }
+
+    fn reuse_address(&self) -> LinuxResult<bool> {
+        match self {
+            Socket::Udp(udpsocket) => Ok(udpsocket.lock().reuse_address()),
+            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().reuse_address()),
+            Socket::Icmp(_) | Socket::Unix(_) => Err(LinuxError::ENOPROTOOPT),
+        }
+    }
+
+    fn set_reuse_address(&self, reuse: bool) -> LinuxResult {
+        match self {
+            Socket::Udp(udpsocket) => udpsocket.lock().set_reuse_address(reuse),
+            Socket::Tcp(tcpsocket) => tcpsocket.lock().set_reuse_address(reuse),
+            Socket::Icmp(_) | Socket::Unix(_) => return Err(LinuxError::ENOPROTOOPT),
+        }
+        Ok(())
+    }
+
+    fn recv_buffer_size(&self) -> LinuxResult<usize> {
+        match self {
+            Socket::Udp(udpsocket) => Ok(udpsocket.lock().recv_buffer_size()),
+            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().recv_buffer_size()),
+            Socket::Icmp(_) | Socket::Unix(_) => Err(LinuxError::ENOPROTOOPT),
+        }
+    }
+
+    fn set_recv_buffer_size(&self, size: usize) -> LinuxResult {
+        match self {
+            Socket::Udp(udpsocket) => udpsocket.lock().set_recv_buffer_size(size),
+            Socket::Tcp(tcpsocket) => tcpsocket.lock().set_recv_buffer_size(size),
+            Socket::Icmp(_) | Socket::Unix(_) => return Err(LinuxError::ENOPROTOOPT),
+        }
+        Ok(())
+    }
+
+    fn send_buffer_size(&self) -> LinuxResult<usize> {
+        match self {
+            Socket::Udp(udpsocket) => Ok(udpsocket.lock().send_buffer_size()),
+            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().send_buffer_size()),
+            Socket::Icmp(_) | Socket::Unix(_) => Err(LinuxError::ENOPROTOOPT),
+        }
+    }
+
+    fn set_send_buffer_size(&self, size: usize) -> LinuxResult {
+        match self {
+            Socket::Udp(udpsocket) => udpsocket.lock().set_send_buffer_size(size),
+            Socket::Tcp(tcpsocket) => tcpsocket.lock().set_send_buffer_size(size),
+            Socket::Icmp(_) | Socket::Unix(_) => return Err(LinuxError::ENOPROTOOPT),
+        }
+        Ok(())
+    }
+
+    fn recv_timeout(&self) -> Option<Duration> {
+        match self {
+            Socket::Udp(udpsocket) => udpsocket.lock().recv_timeout(),
+            Socket::Tcp(tcpsocket) => tcpsocket.lock().recv_timeout(),
+            Socket::Icmp(icmpsocket) => icmpsocket.lock().recv_timeout(),
+            Socket::Unix(_) => None,
+        }
+    }
+
+    fn set_recv_timeout(&self, timeout: Option<Duration>) -> LinuxResult {
+        match self {
+            Socket::Udp(udpsocket) => Ok(udpsocket.lock().set_recv_timeout(timeout)?),
+            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().set_recv_timeout(timeout)?),
+            Socket::Icmp(icmpsocket) => Ok(icmpsocket.lock().set_recv_timeout(timeout)?),
+            Socket::Unix(_) => Err(LinuxError::ENOPROTOOPT),
+        }
+    }
+
+    fn send_timeout(&self) -> Option<Duration> {
+        match self {
+            Socket::Udp(udpsocket) => udpsocket.lock().send_timeout(),
+            Socket::Tcp(tcpsocket) => tcpsocket.lock().send_timeout(),
+            Socket::Icmp(icmpsocket) => icmpsocket.lock().send_timeout(),
+            Socket::Unix(_) => None,
+        }
+    }
+
+    fn set_send_timeout(&self, timeout: Option<Duration>) -> LinuxResult {
+        match self {
+            Socket::Udp(udpsocket) => Ok(udpsocket.lock().set_send_timeout(timeout)?),
+            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().set_send_timeout(timeout)?),
+            Socket::Icmp(icmpsocket) => Ok(icmpsocket.lock().set_send_timeout(timeout)?),
+            Socket::Unix(_) => Err(LinuxError::ENOPROTOOPT),
+        }
+    }
+
+    fn take_error(&self) -> Option<AxError> {
+        match self {
+            // errors of other sockets are returned by the calls directly
+            Socket::Udp(_) | Socket::Icmp(_) | Socket::Unix(_) => None,
+            Socket::Tcp(tcpsocket) => tcpsocket.lock().take_error(),
         }
     }
 }
 
 impl FileLike for Socket {
     fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
-        self.recv(buf)
+        self.recvfrom(buf, MsgFlags::default()).map(|res| res.0)
     }
 
     fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
-        self.send(buf)
+        self.send(buf, MsgFlags::default())
     }
 
     fn stat(&self) -> LinuxResult<ctypes::stat> {
@@ -165,6 +345,8 @@ impl FileLike for Socket {
         match self {
             Socket::Udp(udpsocket) => udpsocket.lock().set_nonblocking(nonblock),
             Socket::Tcp(tcpsocket) => tcpsocket.lock().set_nonblocking(nonblock),
+            Socket::Icmp(icmpsocket) => icmpsocket.lock().set_nonblocking(nonblock),
+            Socket::Unix(unixsocket) => unixsocket.set_nonblocking(nonblock),
         }
         Ok(())
     }
@@ -194,40 +376,133 @@ impl From<ctypes::sockaddr_in> for SocketAddrV4 {
     }
 }
 
-fn into_sockaddr(addr: SocketAddr) -> (ctypes::sockaddr, ctypes::socklen_t) {
-    debug!("    Sockaddr: {}", addr);
+/// Writes `addr` to `dst`, truncated to the buffer size in `*addrlen`, and
+/// sets `*addrlen` to the actual size of the address.
+unsafe fn write_sockaddr(
+    addr: SockAddr,
+    dst: *mut ctypes::sockaddr,
+    addrlen: *mut ctypes::socklen_t,
+) -> LinuxResult {
+    debug!("    Sockaddr: {:?}", addr);
+    if dst.is_null() || addrlen.is_null() {
+        return Err(LinuxError::EFAULT);
+    }
+    let mut buf = Vec::with_capacity(size_of::<ctypes::sockaddr_un>());
     match addr {
-        SocketAddr::V4(addr) => (
-            unsafe { *(&ctypes::sockaddr_in::from(addr) as *const _ as *const ctypes::sockaddr) },
-            size_of::<ctypes::sockaddr>() as _,
-        ),
-        SocketAddr::V6(_) => panic!("IPv6 is not supported"),
+        SockAddr::Inet(SocketAddr::V4(addr)) => {
+            let addr = ctypes::sockaddr_in::from(addr);
+            buf.extend_from_slice(unsafe {
+                core::slice::from_raw_parts(
+                    &addr as *const _ as *const u8,
+                    size_of::<ctypes::sockaddr_in>(),
+                )
+            });
+        }
+        SockAddr::Inet(SocketAddr::V6(_)) => panic!("IPv6 is not supported"),
+        SockAddr::Unix(addr) => {
+            buf.extend_from_slice(&(ctypes::AF_UNIX as ctypes::sa_family_t).to_ne_bytes());
+            match addr {
+                UnixAddr::Unnamed => {}
+                UnixAddr::Path(path) => {
+                    buf.extend_from_slice(path.as_bytes());
+                    buf.push(0);
+                }
+                UnixAddr::Abstract(name) => {
+                    buf.push(0);
+                    buf.extend_from_slice(&name);
+                }
+            }
+        }
+    }
+    unsafe {
+        let len = buf.len().min(*addrlen as usize);
+        core::ptr::copy_nonoverlapping(buf.as_ptr(), dst as *mut u8, len);
+        *addrlen = buf.len() as _;
     }
+    Ok(())
 }
 
 fn from_sockaddr(
     addr: *const ctypes::sockaddr,
     addrlen: ctypes::socklen_t,
-) -> LinuxResult<SocketAddr> {
+) -> LinuxResult<SockAddr> {
     if addr.is_null() {
         return Err(LinuxError::EFAULT);
     }
-    if addrlen != size_of::<ctypes::sockaddr>() as _ {
+    if (addrlen as usize) < size_of::<ctypes::sa_family_t>() {
         return Err(LinuxError::EINVAL);
     }
 
-    let mid = unsafe { *(addr as *const ctypes::sockaddr_in) };
-    if mid.sin_family != ctypes::AF_INET as u16 {
-        return Err(LinuxError::EINVAL);
-    }
-
-    let res = SocketAddr::V4(mid.into());
+    let res = match unsafe { (*addr).sa_family } as u32 {
+        ctypes::AF_INET => {
+            if addrlen != size_of::<ctypes::sockaddr>() as _ {
+                return Err(LinuxError::EINVAL);
+            }
+            let mid = unsafe { *(addr as *const ctypes::sockaddr_in) };
+            SockAddr::Inet(SocketAddr::V4(mid.into()))
+        }
+        ctypes::AF_UNIX => {
+            if addrlen as usize > size_of::<ctypes::sockaddr_un>() {
+                return Err(LinuxError::EINVAL);
+            }
+            let offset = offset_of!(ctypes::sockaddr_un, sun_path);
+            let path = unsafe {
+                core::slice::from_raw_parts(
+                    (addr as *const u8).add(offset),
+                    addrlen as usize - offset,
+                )
+            };
+            SockAddr::Unix(UnixAddr::from_sun_path(path)?)
+        }
+        _ => return Err(LinuxError::EINVAL),
+    };
     debug!("    load sockaddr:{:#x} => {:?}", addr as usize, res);
     Ok(res)
 }
 
+/// Converts the `MSG_*` flags of sending and receiving.
+///
+/// `MSG_NOSIGNAL` is ignored as `EPIPE` never raises signals, and `MSG_TRUNC`
+/// only changes the return value of receiving, so it's handled by the callers.
+fn msg_flags(flags: c_int) -> LinuxResult<MsgFlags> {
+    let flags = flags as u32;
+    if flags & ctypes::MSG_OOB != 0 {
+        return Err(LinuxError::EOPNOTSUPP);
+    }
+    Ok(MsgFlags {
+        peek: flags & ctypes::MSG_PEEK != 0,
+        dont_wait: flags & ctypes::MSG_DONTWAIT != 0,
+        wait_all: flags & ctypes::MSG_WAITALL != 0,
+    })
+}
+
+/// Returns the I/O vectors of `msg`.
+unsafe fn msg_iovecs<'a>(msg: &ctypes::msghdr) -> LinuxResult<&'a [ctypes::iovec]> {
+    if !(0..=1024).contains(&msg.msg_iovlen) {
+        return Err(LinuxError::EMSGSIZE);
+    }
+    if msg.msg_iovlen == 0 {
+        return Ok(&[]);
+    }
+    if msg.msg_iov.is_null() {
+        return Err(LinuxError::EFAULT);
+    }
+    let iovs = unsafe { core::slice::from_raw_parts(msg.msg_iov, msg.msg_iovlen as usize) };
+    if iovs
+        .iter()
+        .any(|iov| iov.iov_base.is_null() && iov.iov_len > 0)
+    {
+        return Err(LinuxError::EFAULT);
+    }
+    Ok(iovs)
+}
+
 /// Create an socket for communication.
 ///
+/// Supports TCP and UDP sockets of `AF_INET`, raw sockets of `IPPROTO_ICMP`,
+/// which receive whole IP packets and send ICMP messages without IP headers,
+/// and stream and datagram sockets of `AF_UNIX`.
+///
 /// Return the socket file descriptor.
 pub fn sys_socket(domain: c_int, socktype: c_int, protocol: c_int) -> c_int {
     debug!("sys_socket <= {} {} {}", domain, socktype, protocol);
@@ -242,11 +517,52 @@ pub fn sys_socket(domain: c_int, socktype: c_int, protocol: c_int) -> c_int {
             | (ctypes::AF_INET, ctypes::SOCK_DGRAM, 0) => {
                 Socket::Udp(Mutex::new(UdpSocket::new())).add_to_fd_table()
             }
+            (ctypes::AF_INET, ctypes::SOCK_RAW, ctypes::IPPROTO_ICMP) => {
+                Socket::Icmp(Mutex::new(IcmpSocket::new())).add_to_fd_table()
+            }
+            (ctypes::AF_UNIX, _, _) => {
+                let ty = unix::socket_type(socktype, protocol)?;
+                Socket::Unix(UnixSocket::new(ty)).add_to_fd_table()
+            }
             _ => Err(LinuxError::EINVAL),
         }
     })
 }
 
+/// Create a pair of sockets connected to each other, which must be of
+/// `AF_UNIX`.
+///
+/// Return 0 if success.
+pub fn sys_socketpair(domain: c_int, socktype: c_int, protocol: c_int, sv: &mut [c_int]) -> c_int {
+    debug!(
+        "sys_socketpair <= {} {} {} {:#x}",
+        domain,
+        socktype,
+        protocol,
+        sv.as_ptr() as usize
+    );
+    syscall_body!(sys_socketpair, {
+        if sv.len() != 2 {
+            return Err(LinuxError::EFAULT);
+        }
+        if domain as u32 != ctypes::AF_UNIX {
+            return Err(LinuxError::EOPNOTSUPP);
+        }
+
+        let ty = unix::socket_type(socktype as u32, protocol as u32)?;
+        let (socket0, socket1) = UnixSocket::new_pair(ty);
+        let fd0 = Socket::Unix(socket0).add_to_fd_table()?;
+        let fd1 = Socket::Unix(socket1).add_to_fd_table().inspect_err(|_| {
+            super::fd_ops::close_file_like(fd0).ok();
+        })?;
+
+        sv[0] = fd0;
+        sv[1] = fd1;
+
+        Ok(0)
+    })
+}
+
 /// Bind a address to a socket.
 ///
 /// Return 0 if success.
@@ -292,7 +608,7 @@ pub fn sys_sendto(
     socket_fd: c_int,
     buf_ptr: *const c_void,
     len: ctypes::size_t,
-    flag: c_int, // currently not used
+    flag: c_int,
     socket_addr: *const ctypes::sockaddr,
     addrlen: ctypes::socklen_t,
 ) -> ctypes::ssize_t {
@@ -306,7 +622,7 @@ pub fn sys_sendto(
         }
         let addr = from_sockaddr(socket_addr, addrlen)?;
         let buf = unsafe { core::slice::from_raw_parts(buf_ptr as *const u8, len) };
-        Socket::from_fd(socket_fd)?.sendto(buf, addr)
+        Socket::from_fd(socket_fd)?.sendto(buf, addr, msg_flags(flag)?)
     })
 }
 
@@ -317,7 +633,7 @@ pub fn sys_send(
     socket_fd: c_int,
     buf_ptr: *const c_void,
     len: ctypes::size_t,
-    flag: c_int, // currently not used
+    flag: c_int,
 ) -> ctypes::ssize_t {
     debug!(
         "sys_sendto <= {} {:#x} {} {}",
@@ -328,18 +644,19 @@ pub fn sys_send(
             return Err(LinuxError::EFAULT);
         }
         let buf = unsafe { core::slice::from_raw_parts(buf_ptr as *const u8, len) };
-        Socket::from_fd(socket_fd)?.send(buf)
+        Socket::from_fd(socket_fd)?.send(buf, msg_flags(flag)?)
     })
 }
 
 /// Receive a message on a socket and get its source address.
 ///
-/// Return the number of bytes received if success.
+/// Return the number of bytes received if success, or the full length of the
+/// datagram with `MSG_TRUNC`.
 pub unsafe fn sys_recvfrom(
     socket_fd: c_int,
     buf_ptr: *mut c_void,
     len: ctypes::size_t,
-    flag: c_int, // currently not used
+    flag: c_int,
     socket_addr: *mut ctypes::sockaddr,
     addrlen: *mut ctypes::socklen_t,
 ) -> ctypes::ssize_t {
@@ -354,24 +671,27 @@ pub unsafe fn sys_recvfrom(
         let socket = Socket::from_fd(socket_fd)?;
         let buf = unsafe { core::slice::from_raw_parts_mut(buf_ptr as *mut u8, len) };
 
-        let res = socket.recvfrom(buf)?;
-        if let Some(addr) = res.1 {
-            unsafe {
-                (*socket_addr, *addrlen) = into_sockaddr(addr);
-            }
+        let (len, full_len, addr) = socket.recvfrom(buf, msg_flags(flag)?)?;
+        if let Some(addr) = addr {
+            unsafe { write_sockaddr(addr, socket_addr, addrlen)? };
         }
-        Ok(res.0)
+        Ok(if flag as u32 & ctypes::MSG_TRUNC != 0 {
+            full_len
+        } else {
+            len
+        })
     })
 }
 
 /// Receive a message on a socket.
 ///
-/// Return the number of bytes received if success.
+/// Return the number of bytes received if success, or the full length of the
+/// datagram with `MSG_TRUNC`.
 pub fn sys_recv(
     socket_fd: c_int,
     buf_ptr: *mut c_void,
     len: ctypes::size_t,
-    flag: c_int, // currently not used
+    flag: c_int,
 ) -> ctypes::ssize_t {
     debug!(
         "sys_recv <= {} {:#x} {} {}",
@@ -382,20 +702,114 @@ pub fn sys_recv(
             return Err(LinuxError::EFAULT);
         }
         let buf = unsafe { core::slice::from_raw_parts_mut(buf_ptr as *mut u8, len) };
-        Socket::from_fd(socket_fd)?.recv(buf)
+        let (len, full_len, _) = Socket::from_fd(socket_fd)?.recvfrom(buf, msg_flags(flag)?)?;
+        Ok(if flag as u32 & ctypes::MSG_TRUNC != 0 {
+            full_len
+        } else {
+            len
+        })
+    })
+}
+
+/// Send a message gathered from multiple buffers on a socket, to the address
+/// in `msg_name`, or the address connected if it's NULL.
+///
+/// Ancillary data in `msg_control` is not supported and ignored.
+///
+/// Return the number of bytes sent if success.
+pub unsafe fn sys_sendmsg(
+    socket_fd: c_int,
+    msg: *const ctypes::msghdr,
+    flags: c_int,
+) -> ctypes::ssize_t {
+    debug!("sys_sendmsg <= {} {:#x} {}", socket_fd, msg as usize, flags);
+    syscall_body!(sys_sendmsg, {
+        if msg.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let msg = unsafe { &*msg };
+        let mut buf = Vec::new();
+        for iov in unsafe { msg_iovecs(msg)? } {
+            buf.extend_from_slice(unsafe {
+                core::slice::from_raw_parts(iov.iov_base as *const u8, iov.iov_len)
+            });
+        }
+        let socket = Socket::from_fd(socket_fd)?;
+        if msg.msg_name.is_null() {
+            socket.send(&buf, msg_flags(flags)?)
+        } else {
+            let addr = from_sockaddr(msg.msg_name as *const _, msg.msg_namelen)?;
+            socket.sendto(&buf, addr, msg_flags(flags)?)
+        }
+    })
+}
+
+/// Receive a message on a socket, scattered into multiple buffers, and get
+/// its source address in `msg_name` if it's not NULL.
+///
+/// `MSG_TRUNC` is set in `msg_flags` if the datagram is truncated. Ancillary
+/// data is not supported, so `msg_controllen` is always set to 0.
+///
+/// Return the number of bytes received if success, or the full length of the
+/// datagram with `MSG_TRUNC`.
+pub unsafe fn sys_recvmsg(
+    socket_fd: c_int,
+    msg: *mut ctypes::msghdr,
+    flags: c_int,
+) -> ctypes::ssize_t {
+    debug!("sys_recvmsg <= {} {:#x} {}", socket_fd, msg as usize, flags);
+    syscall_body!(sys_recvmsg, {
+        if msg.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let msg = unsafe { &mut *msg };
+        let iovs = unsafe { msg_iovecs(msg)? };
+        let socket = Socket::from_fd(socket_fd)?;
+
+        let mut buf = vec![0; iovs.iter().map(|iov| iov.iov_len).sum()];
+        let (len, full_len, addr) = socket.recvfrom(&mut buf, msg_flags(flags)?)?;
+        let mut data = &buf[..len];
+        for iov in iovs {
+            let n = data.len().min(iov.iov_len);
+            unsafe {
+                core::ptr::copy_nonoverlapping(data.as_ptr(), iov.iov_base as *mut u8, n);
+            }
+            data = &data[n..];
+        }
+
+        if !msg.msg_name.is_null() {
+            match addr {
+                Some(addr) => unsafe {
+                    write_sockaddr(addr, msg.msg_name as *mut _, &mut msg.msg_namelen)?
+                },
+                None => msg.msg_namelen = 0,
+            }
+        }
+        msg.msg_controllen = 0;
+        msg.msg_flags = if full_len > len {
+            ctypes::MSG_TRUNC as _
+        } else {
+            0
+        };
+        Ok(if flags as u32 & ctypes::MSG_TRUNC != 0 {
+            full_len
+        } else {
+            len
+        })
     })
 }
 
 /// Listen for connections on a socket
 ///
+/// At most `backlog` connections can be pending, it's truncated to the
+/// maximum if larger or negative.
+///
 /// Return 0 if success.
-pub fn sys_listen(
-    socket_fd: c_int,
-    backlog: c_int, // currently not used
-) -> c_int {
+pub fn sys_listen(socket_fd: c_int, backlog: c_int) -> c_int {
     debug!("sys_listen <= {} {}", socket_fd, backlog);
     syscall_body!(sys_listen, {
-        Socket::from_fd(socket_fd)?.listen()?;
+        // negative backlogs are taken as unsigned, like Linux
+        Socket::from_fd(socket_fd)?.listen(backlog as u32 as usize)?;
         Ok(0)
     })
 }
@@ -419,10 +833,8 @@ pub unsafe fn sys_accept(
         let socket = Socket::from_fd(socket_fd)?;
         let new_socket = socket.accept()?;
         let addr = new_socket.peer_addr()?;
-        let new_fd = Socket::add_to_fd_table(Socket::Tcp(Mutex::new(new_socket)))?;
-        unsafe {
-            (*socket_addr, *socket_len) = into_sockaddr(addr);
-        }
+        let new_fd = Socket::add_to_fd_table(new_socket)?;
+        unsafe { write_sockaddr(addr, socket_addr, socket_len)? };
         Ok(new_fd)
     })
 }
@@ -441,6 +853,164 @@ pub fn sys_shutdown(
     })
 }
 
+/// Reads the option value of type `T` set by `setsockopt`.
+unsafe fn read_optval<T: Copy>(optval: *const c_void, optlen: ctypes::socklen_t) -> LinuxResult<T> {
+    if optval.is_null() {
+        return Err(LinuxError::EFAULT);
+    }
+    if (optlen as usize) < size_of::<T>() {
+        return Err(LinuxError::EINVAL);
+    }
+    Ok(unsafe { (optval as *const T).read_unaligned() })
+}
+
+/// Writes the option value `val` got by `getsockopt`.
+unsafe fn write_optval<T>(
+    optval: *mut c_void,
+    optlen: *mut ctypes::socklen_t,
+    val: T,
+) -> LinuxResult {
+    if optval.is_null() || optlen.is_null() {
+        return Err(LinuxError::EFAULT);
+    }
+    if (unsafe { *optlen } as usize) < size_of::<T>() {
+        return Err(LinuxError::EINVAL);
+    }
+    unsafe {
+        (optval as *mut T).write_unaligned(val);
+        *optlen = size_of::<T>() as _;
+    }
+    Ok(())
+}
+
+/// Converts the timeout of `SO_RCVTIMEO` and `SO_SNDTIMEO`, where zero means
+/// no timeout.
+fn timeval_to_timeout(tv: ctypes::timeval) -> LinuxResult<Option<Duration>> {
+    if tv.tv_sec < 0 || !(0..1_000_000).contains(&tv.tv_usec) {
+        return Err(LinuxError::EDOM);
+    }
+    let timeout = Duration::from(tv);
+    Ok((!timeout.is_zero()).then_some(timeout))
+}
+
+/// Set options on a socket.
+///
+/// Supported options are `SO_REUSEADDR`, `SO_KEEPALIVE`, `SO_RCVBUF`,
+/// `SO_SNDBUF`, `SO_RCVTIMEO`, `SO_SNDTIMEO` and `SO_LINGER` of level
+/// `SOL_SOCKET`, and `TCP_NODELAY` of level `IPPROTO_TCP`.
+///
+/// Return 0 if success.
+pub unsafe fn sys_setsockopt(
+    socket_fd: c_int,
+    level: c_int,
+    optname: c_int,
+    optval: *const c_void,
+    optlen: ctypes::socklen_t,
+) -> c_int {
+    debug!(
+        "sys_setsockopt <= {} {} {} {:#x} {}",
+        socket_fd, level, optname, optval as usize, optlen
+    );
+    syscall_body!(sys_setsockopt, {
+        let socket = Socket::from_fd(socket_fd)?;
+        let int_val = || unsafe { read_optval::<c_int>(optval, optlen) };
+        let timeval = || unsafe { read_optval::<ctypes::timeval>(optval, optlen) };
+        match (level as u32, optname as u32) {
+            (ctypes::SOL_SOCKET, ctypes::SO_REUSEADDR) => {
+                socket.set_reuse_address(int_val()? != 0)?
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_KEEPALIVE) => {
+                socket.tcp()?.lock().set_keep_alive(int_val()? != 0)
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_RCVBUF) => {
+                socket.set_recv_buffer_size(int_val()?.max(0) as usize)?
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_SNDBUF) => {
+                socket.set_send_buffer_size(int_val()?.max(0) as usize)?
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_RCVTIMEO) => {
+                socket.set_recv_timeout(timeval_to_timeout(timeval()?)?)?
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_SNDTIMEO) => {
+                socket.set_send_timeout(timeval_to_timeout(timeval()?)?)?
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_LINGER) => {
+                let linger = unsafe { read_optval::<ctypes::linger>(optval, optlen)? };
+                let timeout = (linger.l_onoff != 0)
+                    .then(|| Duration::from_secs(linger.l_linger.max(0) as u64));
+                socket.tcp()?.lock().set_linger(timeout);
+            }
+            (ctypes::IPPROTO_TCP, ctypes::TCP_NODELAY) => {
+                socket.tcp()?.lock().set_nodelay(int_val()? != 0)?
+            }
+            _ => return Err(LinuxError::ENOPROTOOPT),
+        }
+        Ok(0)
+    })
+}
+
+/// Get options on a socket.
+///
+/// Supports the options of [`sys_setsockopt`], and `SO_ERROR` of level
+/// `SOL_SOCKET`.
+///
+/// Return 0 if success.
+pub unsafe fn sys_getsockopt(
+    socket_fd: c_int,
+    level: c_int,
+    optname: c_int,
+    optval: *mut c_void,
+    optlen: *mut ctypes::socklen_t,
+) -> c_int {
+    debug!(
+        "sys_getsockopt <= {} {} {} {:#x} {:#x}",
+        socket_fd, level, optname, optval as usize, optlen as usize
+    );
+    syscall_body!(sys_getsockopt, {
+        let socket = Socket::from_fd(socket_fd)?;
+        let put_int = |val: c_int| unsafe { write_optval(optval, optlen, val) };
+        let put_timeval = |timeout: Option<Duration>| unsafe {
+            write_optval(
+                optval,
+                optlen,
+                ctypes::timeval::from(timeout.unwrap_or_default()),
+            )
+        };
+        match (level as u32, optname as u32) {
+            (ctypes::SOL_SOCKET, ctypes::SO_REUSEADDR) => put_int(socket.reuse_address()? as _)?,
+            (ctypes::SOL_SOCKET, ctypes::SO_KEEPALIVE) => {
+                put_int(socket.tcp()?.lock().keep_alive() as _)?
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_RCVBUF) => {
+                put_int(socket.recv_buffer_size()?.min(c_int::MAX as usize) as _)?
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_SNDBUF) => {
+                put_int(socket.send_buffer_size()?.min(c_int::MAX as usize) as _)?
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_RCVTIMEO) => put_timeval(socket.recv_timeout())?,
+            (ctypes::SOL_SOCKET, ctypes::SO_SNDTIMEO) => put_timeval(socket.send_timeout())?,
+            (ctypes::SOL_SOCKET, ctypes::SO_ERROR) => put_int(
+                socket
+                    .take_error()
+                    .map_or(0, |e| LinuxError::from(e).code()),
+            )?,
+            (ctypes::SOL_SOCKET, ctypes::SO_LINGER) => {
+                let timeout = socket.tcp()?.lock().linger();
+                let linger = ctypes::linger {
+                    l_onoff: timeout.is_some() as _,
+                    l_linger: timeout.map_or(0, |t| t.as_secs().min(c_int::MAX as u64) as _),
+                };
+                unsafe { write_optval(optval, optlen, linger)? }
+            }
+            (ctypes::IPPROTO_TCP, ctypes::TCP_NODELAY) => {
+                put_int(socket.tcp()?.lock().nodelay()? as _)?
+            }
+            _ => return Err(LinuxError::ENOPROTOOPT),
+        }
+        Ok(0)
+    })
+}
+
 /// Query addresses for a domain name.
 ///
 /// Only IPv4. Ports are always 0. Ignore servname and hint.
@@ -543,15 +1113,8 @@ pub unsafe fn sys_getsockname(
         sock_fd, addr as usize, addrlen as usize
     );
     syscall_body!(sys_getsockname, {
-        if addr.is_null() || addrlen.is_null() {
-            return Err(LinuxError::EFAULT);
-        }
-        if unsafe { *addrlen } < size_of::<ctypes::sockaddr>() as u32 {
-            return Err(LinuxError::EINVAL);
-        }
-        unsafe {
-            (*addr, *addrlen) = into_sockaddr(Socket::from_fd(sock_fd)?.local_addr()?);
-        }
+        let sock_addr = Socket::from_fd(sock_fd)?.local_addr()?;
+        unsafe { write_sockaddr(sock_addr, addr, addrlen)? };
         Ok(0)
     })
 }
@@ -567,15 +1130,8 @@ pub unsafe fn sys_getpeername(
         sock_fd, addr as usize, addrlen as usize
     );
     syscall_body!(sys_getpeername, {
-        if addr.is_null() || addrlen.is_null() {
-            return Err(LinuxError::EFAULT);
-        }
-        if unsafe { *addrlen } < size_of::<ctypes::sockaddr>() as u32 {
-            return Err(LinuxError::EINVAL);
-        }
-        unsafe {
-            (*addr, *addrlen) = into_sockaddr(Socket::from_fd(sock_fd)?.peer_addr()?);
-        }
+        let sock_addr = Socket::from_fd(sock_fd)?.peer_addr()?;
+        unsafe { write_sockaddr(sock_addr, addr, addrlen)? };
         Ok(0)
     })
 }
@@ -33,6 +33,33 @@ pub unsafe fn sys_getrlimit(resource: c_int, rlimits: *mut ctypes::rlimit) -> c_
     })
 }
 
+/// Get resource usages
+///
+/// Only the CPU times are reported, see [`cpu_time`](super::time::cpu_time).
+/// There are no child processes, so their usages are always zero.
+pub unsafe fn sys_getrusage(who: c_int, usage: *mut ctypes::rusage) -> c_int {
+    debug!("sys_getrusage <= {} {:#x}", who, usage as usize);
+    syscall_body!(sys_getrusage, {
+        if usage.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let (utime, stime) = match who as u32 {
+            ctypes::RUSAGE_SELF => super::time::cpu_time(false),
+            ctypes::RUSAGE_THREAD => super::time::cpu_time(true),
+            _ if who == ctypes::RUSAGE_CHILDREN => Default::default(),
+            _ => return Err(LinuxError::EINVAL),
+        };
+        unsafe {
+            *usage = ctypes::rusage {
+                ru_utime: utime.into(),
+                ru_stime: stime.into(),
+                ..Default::default()
+            };
+        }
+        Ok(0)
+    })
+}
+
 /// Set resource limitations
 ///
 /// TODO: support more resource types
@@ -14,6 +14,8 @@ pub fn sys_sysconf(name: c_int) -> c_long {
         match name as u32 {
             // Page size
             ctypes::_SC_PAGE_SIZE => Ok(PAGE_SIZE_4K),
+            // Clock ticks per second of `times`
+            ctypes::_SC_CLK_TCK => Ok(super::time::CLK_TCK as usize),
             // Number of processors in use
             ctypes::_SC_NPROCESSORS_ONLN => Ok(axconfig::plat::CPU_NUM),
             // Total physical pages
@@ -3,7 +3,44 @@ use core::ffi::{c_int, c_long};
 use core::time::Duration;
 
 use crate::ctypes;
-use crate::ctypes::{CLOCK_MONOTONIC, CLOCK_REALTIME};
+use crate::ctypes::{
+    CLOCK_MONOTONIC, CLOCK_PROCESS_CPUTIME_ID, CLOCK_REALTIME, CLOCK_THREAD_CPUTIME_ID,
+};
+
+/// Number of clock ticks per second reported by `sysconf(_SC_CLK_TCK)`, in
+/// which the times of [`sys_times`] are measured.
+pub(crate) const CLK_TCK: u64 = 100;
+/// Number of clock ticks per second of [`sys_clock`], i.e., `CLOCKS_PER_SEC`.
+const CLOCKS_PER_SEC: u64 = 1_000_000;
+
+/// CPU time used by the current thread or the whole process, as the user and
+/// system time.
+///
+/// All code runs in the kernel, so the user time is the time spent running
+/// the tasks, and the system time is the time spent in IRQ handlers while
+/// they were running.
+pub(crate) fn cpu_time(thread: bool) -> (Duration, Duration) {
+    #[cfg(feature = "multitask")]
+    {
+        let time = if thread {
+            axtask::current_cpu_time()
+        } else {
+            axtask::total_cpu_time()
+        };
+        (time.run, time.irq)
+    }
+    #[cfg(not(feature = "multitask"))]
+    {
+        // The only thread runs all the time.
+        let _ = thread;
+        (axhal::time::monotonic_time(), Duration::ZERO)
+    }
+}
+
+/// Converts `dur` to clock ticks of the given frequency.
+fn to_clock_ticks(dur: Duration, freq: u64) -> ctypes::clock_t {
+    (dur.as_nanos() * freq as u128 / 1_000_000_000) as ctypes::clock_t
+}
 
 impl From<ctypes::timespec> for Duration {
     fn from(ts: ctypes::timespec) -> Self {
@@ -35,14 +72,37 @@ impl From<Duration> for ctypes::timeval {
     }
 }
 
-/// Get the clock tick
+/// Get the CPU time used by the process, in `CLOCKS_PER_SEC` ticks
 pub unsafe fn sys_clock() -> ctypes::clock_t {
     syscall_body!(sys_clock, {
-        Ok(axhal::time::monotonic_time_nanos() as ctypes::clock_t)
+        let (utime, stime) = cpu_time(false);
+        Ok(to_clock_ticks(utime + stime, CLOCKS_PER_SEC))
+    })
+}
+
+/// Get the CPU times of the process, in `CLK_TCK` ticks
+///
+/// Returns the ticks elapsed since booting. There are no child processes, so
+/// their times are always zero.
+pub unsafe fn sys_times(buf: *mut ctypes::tms) -> ctypes::clock_t {
+    syscall_body!(sys_times, {
+        if !buf.is_null() {
+            let (utime, stime) = cpu_time(false);
+            unsafe {
+                *buf = ctypes::tms {
+                    tms_utime: to_clock_ticks(utime, CLK_TCK),
+                    tms_stime: to_clock_ticks(stime, CLK_TCK),
+                    tms_cutime: 0,
+                    tms_cstime: 0,
+                };
+            }
+        }
+        Ok(to_clock_ticks(axhal::time::monotonic_time(), CLK_TCK))
     })
 }
 
-/// Get clock time since booting
+/// Get clock time since booting, or the CPU time of the current thread or
+/// process
 pub unsafe fn sys_clock_gettime(clk: ctypes::clockid_t, ts: *mut ctypes::timespec) -> c_int {
     syscall_body!(sys_clock_gettime, {
         if ts.is_null() {
@@ -51,6 +111,10 @@ pub unsafe fn sys_clock_gettime(clk: ctypes::clockid_t, ts: *mut ctypes::timespe
         let now = match clk as u32 {
             CLOCK_REALTIME => axhal::time::wall_time().into(),
             CLOCK_MONOTONIC => axhal::time::monotonic_time().into(),
+            CLOCK_PROCESS_CPUTIME_ID | CLOCK_THREAD_CPUTIME_ID => {
+                let (utime, stime) = cpu_time(clk as u32 == CLOCK_THREAD_CPUTIME_ID);
+                (utime + stime).into()
+            }
             _ => {
                 warn!("Called sys_clock_gettime for unsupported clock {}", clk);
                 return Err(LinuxError::EINVAL);
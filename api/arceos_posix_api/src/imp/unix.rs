@@ -0,0 +1,833 @@
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::sync::{Arc, Weak};
+use alloc::{format, string::String, vec::Vec};
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use axerrno::{LinuxError, LinuxResult};
+use axio::PollState;
+use axnet::MsgFlags;
+use axsync::Mutex;
+
+#[cfg(feature = "multitask")]
+use axtask::WaitQueue;
+
+/// Size of the buffer of each direction of a stream connection, and of the
+/// queue of received datagrams.
+const BUF_SIZE: usize = 64 * 1024;
+
+/// Maximum number of pending connections of a listening socket, larger
+/// backlogs are truncated to it.
+const MAX_BACKLOG: usize = 512;
+
+/// Address of a Unix domain socket.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum UnixAddr {
+    /// Not bound to any name.
+    Unnamed,
+    /// Bound to a socket file in the filesystem, by the path given to `bind`.
+    Path(String),
+    /// Bound to a name in the abstract namespace, which has no socket file.
+    Abstract(Vec<u8>),
+}
+
+impl UnixAddr {
+    /// Parses `sun_path` of `sockaddr_un`, without the bytes beyond `addrlen`.
+    pub fn from_sun_path(path: &[u8]) -> LinuxResult<Self> {
+        match path.split_first() {
+            None => Ok(Self::Unnamed),
+            Some((0, name)) => Ok(Self::Abstract(name.into())),
+            Some(_) => {
+                let len = path.iter().position(|&c| c == 0).unwrap_or(path.len());
+                let path = core::str::from_utf8(&path[..len]).map_err(|_| LinuxError::EINVAL)?;
+                Ok(Self::Path(path.into()))
+            }
+        }
+    }
+
+    /// Returns the key of the name in [`NAMES`], where paths are absolute.
+    fn key(&self) -> LinuxResult<Self> {
+        match self {
+            #[cfg(feature = "fs")]
+            Self::Path(path) => Ok(Self::Path(axfs::api::canonicalize(path)?)),
+            _ => Ok(self.clone()),
+        }
+    }
+}
+
+/// The name that a socket is bound to.
+struct BoundName {
+    /// The address given to `bind`.
+    addr: UnixAddr,
+    /// The key in [`NAMES`], which does not change with the working directory.
+    key: UnixAddr,
+}
+
+/// Tasks blocked on a buffer or queue of sockets, which are woken up when it
+/// changes.
+struct Waiter {
+    #[cfg(feature = "multitask")]
+    wq: WaitQueue,
+    /// Incremented on each change, so that a change between checking the
+    /// buffer and going to sleep is not missed.
+    seq: AtomicUsize,
+}
+
+impl Waiter {
+    const fn new() -> Self {
+        Self {
+            #[cfg(feature = "multitask")]
+            wq: WaitQueue::new(),
+            seq: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the number of changes so far.
+    fn seq(&self) -> usize {
+        self.seq.load(Ordering::Acquire)
+    }
+
+    /// Wakes up all tasks waiting for a change.
+    fn notify(&self) {
+        self.seq.fetch_add(1, Ordering::AcqRel);
+        #[cfg(feature = "multitask")]
+        self.wq.notify_all(false);
+    }
+
+    /// Blocks until there are changes after [`seq`](Self::seq) returned
+    /// `seq`, or only yields the CPU without `multitask`.
+    fn wait_change(&self, seq: usize) {
+        #[cfg(feature = "multitask")]
+        self.wq.wait_until(|| self.seq() != seq);
+        #[cfg(not(feature = "multitask"))]
+        {
+            let _ = seq;
+            crate::sys_sched_yield();
+        }
+    }
+}
+
+/// Type of a Unix domain socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnixSocketType {
+    /// `SOCK_STREAM`
+    Stream,
+    /// `SOCK_DGRAM`
+    Dgram,
+}
+
+/// One direction of a stream connection.
+struct StreamBuffer {
+    data: Mutex<VecDeque<u8>>,
+    /// The writing end is shut down, readers get EOF after the data.
+    write_closed: AtomicBool,
+    /// The reading end is shut down, writers get `EPIPE`.
+    read_closed: AtomicBool,
+    /// Tasks waiting for data, space, or either end to be shut down.
+    waiter: Waiter,
+}
+
+impl StreamBuffer {
+    fn new() -> Arc<Self> {
+        Arc::new(Self {
+            data: Mutex::new(VecDeque::new()),
+            write_closed: AtomicBool::new(false),
+            read_closed: AtomicBool::new(false),
+            waiter: Waiter::new(),
+        })
+    }
+}
+
+/// A connected stream socket.
+struct Connection {
+    rx: Arc<StreamBuffer>,
+    tx: Arc<StreamBuffer>,
+    local_addr: UnixAddr,
+    peer_addr: UnixAddr,
+}
+
+impl Connection {
+    fn shutdown(&self) {
+        self.rx.read_closed.store(true, Ordering::Release);
+        self.tx.write_closed.store(true, Ordering::Release);
+        self.rx.waiter.notify();
+        self.tx.waiter.notify();
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// The queue of pending connections of a listening socket.
+struct Listener {
+    queue: Mutex<VecDeque<UnixSocket>>,
+    /// Maximum length of `queue`.
+    backlog: AtomicUsize,
+    /// The listening socket is closed, connecting gets `ECONNREFUSED`.
+    closed: AtomicBool,
+    /// Tasks waiting for pending connections, or space in `queue`.
+    waiter: Waiter,
+}
+
+impl Listener {
+    /// Refuses further connections, and resets the pending ones.
+    fn close(&self) {
+        self.closed.store(true, Ordering::Release);
+        let pending = core::mem::take(&mut *self.queue.lock());
+        drop(pending);
+        self.waiter.notify();
+    }
+}
+
+enum StreamState {
+    /// Created or bound, but neither listening nor connected.
+    Idle,
+    /// Waiting for the listening socket to have space for the connection.
+    Connecting,
+    Listening(Arc<Listener>),
+    Connected(Connection),
+}
+
+/// Datagrams received by a socket, with their sources.
+#[derive(Default)]
+struct DgramQueue {
+    packets: VecDeque<(Vec<u8>, UnixAddr)>,
+    /// Total size of the packets in bytes.
+    len: usize,
+}
+
+/// The receiving side of a datagram socket, which others send to.
+struct DgramReceiver {
+    queue: Mutex<DgramQueue>,
+    /// The socket is closed, sending gets `ECONNREFUSED`.
+    closed: AtomicBool,
+    /// Tasks waiting for datagrams, or space in `queue`.
+    waiter: Waiter,
+}
+
+impl DgramReceiver {
+    fn new() -> Arc<Self> {
+        Arc::new(Self {
+            queue: Mutex::new(DgramQueue::default()),
+            closed: AtomicBool::new(false),
+            waiter: Waiter::new(),
+        })
+    }
+}
+
+/// The default destination of a connected datagram socket.
+struct DgramPeer {
+    queue: Weak<DgramReceiver>,
+    addr: UnixAddr,
+}
+
+enum Inner {
+    Stream(Mutex<StreamState>),
+    Dgram {
+        rx: Arc<DgramReceiver>,
+        peer: Mutex<Option<DgramPeer>>,
+    },
+}
+
+/// A socket bound to a name, which others connect or send to.
+enum Binding {
+    /// A stream socket, which accepts connections only if listening.
+    Stream(Weak<Listener>),
+    Dgram(Weak<DgramReceiver>),
+}
+
+/// Names bound by sockets, by [`UnixAddr::key`].
+static NAMES: Mutex<BTreeMap<UnixAddr, Binding>> = Mutex::new(BTreeMap::new());
+
+/// A Unix domain socket (`AF_UNIX`) of stream or datagram type.
+///
+/// It can be bound to a path, where a socket file is created if the `fs`
+/// feature is enabled, or to a name in the abstract namespace. The socket file
+/// remains after the socket is closed, until it is removed by `unlink`.
+pub struct UnixSocket {
+    inner: Inner,
+    /// The name that this socket bound to.
+    name: Mutex<Option<BoundName>>,
+    nonblock: AtomicBool,
+}
+
+impl UnixSocket {
+    /// Creates a new Unix domain socket of type `ty`.
+    pub fn new(ty: UnixSocketType) -> Self {
+        let inner = match ty {
+            UnixSocketType::Stream => Inner::Stream(Mutex::new(StreamState::Idle)),
+            UnixSocketType::Dgram => Inner::Dgram {
+                rx: DgramReceiver::new(),
+                peer: Mutex::new(None),
+            },
+        };
+        Self {
+            inner,
+            name: Mutex::new(None),
+            nonblock: AtomicBool::new(false),
+        }
+    }
+
+    /// Creates a pair of sockets of type `ty` connected to each other.
+    pub fn new_pair(ty: UnixSocketType) -> (Self, Self) {
+        match ty {
+            UnixSocketType::Stream => {
+                let (rx, tx) = (StreamBuffer::new(), StreamBuffer::new());
+                (
+                    Self::connected(rx.clone(), tx.clone(), UnixAddr::Unnamed, UnixAddr::Unnamed),
+                    Self::connected(tx, rx, UnixAddr::Unnamed, UnixAddr::Unnamed),
+                )
+            }
+            UnixSocketType::Dgram => {
+                let (a, b) = (Self::new(ty), Self::new(ty));
+                if let (
+                    Inner::Dgram {
+                        rx: rx_a,
+                        peer: peer_a,
+                    },
+                    Inner::Dgram {
+                        rx: rx_b,
+                        peer: peer_b,
+                    },
+                ) = (&a.inner, &b.inner)
+                {
+                    *peer_a.lock() = Some(DgramPeer {
+                        queue: Arc::downgrade(rx_b),
+                        addr: UnixAddr::Unnamed,
+                    });
+                    *peer_b.lock() = Some(DgramPeer {
+                        queue: Arc::downgrade(rx_a),
+                        addr: UnixAddr::Unnamed,
+                    });
+                }
+                (a, b)
+            }
+        }
+    }
+
+    /// Returns whether this socket is in nonblocking mode.
+    pub fn is_nonblocking(&self) -> bool {
+        self.nonblock.load(Ordering::Acquire)
+    }
+
+    /// Moves this socket into or out of nonblocking mode.
+    pub fn set_nonblocking(&self, nonblocking: bool) {
+        self.nonblock.store(nonblocking, Ordering::Release);
+    }
+
+    /// Returns the address that this socket is bound to, or the address of the
+    /// listening socket that accepted this connection.
+    pub fn local_addr(&self) -> UnixAddr {
+        if let Inner::Stream(state) = &self.inner {
+            if let StreamState::Connected(conn) = &*state.lock() {
+                return conn.local_addr.clone();
+            }
+        }
+        self.bound_addr()
+    }
+
+    /// Returns the address of the peer, or `ENOTCONN` if not connected.
+    pub fn peer_addr(&self) -> LinuxResult<UnixAddr> {
+        match &self.inner {
+            Inner::Stream(state) => match &*state.lock() {
+                StreamState::Connected(conn) => Ok(conn.peer_addr.clone()),
+                _ => Err(LinuxError::ENOTCONN),
+            },
+            Inner::Dgram { peer, .. } => match &*peer.lock() {
+                Some(peer) => Ok(peer.addr.clone()),
+                None => Err(LinuxError::ENOTCONN),
+            },
+        }
+    }
+
+    /// Binds this socket to `addr`, which creates the socket file if it is a
+    /// path, or binds to an unused abstract name if it is unnamed.
+    pub fn bind(&self, addr: UnixAddr) -> LinuxResult {
+        let state = match &self.inner {
+            Inner::Stream(state) => Some(state.lock()),
+            Inner::Dgram { .. } => None,
+        };
+        if matches!(
+            state.as_deref(),
+            Some(StreamState::Connecting | StreamState::Connected(_))
+        ) {
+            return Err(LinuxError::EINVAL);
+        }
+        let mut name = self.name.lock();
+        if name.is_some() {
+            return Err(LinuxError::EINVAL);
+        }
+        let binding = match (&self.inner, state.as_deref()) {
+            (_, Some(StreamState::Listening(listener))) => {
+                Binding::Stream(Arc::downgrade(listener))
+            }
+            (Inner::Dgram { rx, .. }, _) => Binding::Dgram(Arc::downgrade(rx)),
+            _ => Binding::Stream(Weak::new()),
+        };
+        *name = Some(bind_name(addr, binding)?);
+        Ok(())
+    }
+
+    /// Starts listening for connections, with at most `backlog` pending ones,
+    /// the socket is bound to an unused abstract name if not bound.
+    ///
+    /// Calling it again on a listening socket changes the backlog.
+    pub fn listen(&self, backlog: usize) -> LinuxResult {
+        let backlog = backlog.clamp(1, MAX_BACKLOG);
+        let Inner::Stream(state) = &self.inner else {
+            return Err(LinuxError::EOPNOTSUPP);
+        };
+        let mut state = state.lock();
+        match &*state {
+            StreamState::Idle => {}
+            StreamState::Listening(listener) => {
+                listener.backlog.store(backlog, Ordering::Release);
+                return Ok(());
+            }
+            StreamState::Connecting | StreamState::Connected(_) => {
+                return Err(LinuxError::EINVAL);
+            }
+        }
+        let listener = Arc::new(Listener {
+            queue: Mutex::new(VecDeque::new()),
+            backlog: AtomicUsize::new(backlog),
+            closed: AtomicBool::new(false),
+            waiter: Waiter::new(),
+        });
+        let binding = Binding::Stream(Arc::downgrade(&listener));
+        let mut name = self.name.lock();
+        match &*name {
+            Some(name) => {
+                NAMES.lock().insert(name.key.clone(), binding);
+            }
+            None => *name = Some(bind_name(UnixAddr::Unnamed, binding)?),
+        }
+        *state = StreamState::Listening(listener);
+        Ok(())
+    }
+
+    /// Accepts a new connection, blocks until there is one unless in
+    /// nonblocking mode.
+    pub fn accept(&self) -> LinuxResult<UnixSocket> {
+        let Inner::Stream(state) = &self.inner else {
+            return Err(LinuxError::EOPNOTSUPP);
+        };
+        let listener = match &*state.lock() {
+            StreamState::Listening(listener) => listener.clone(),
+            _ => return Err(LinuxError::EINVAL),
+        };
+        self.block_on(false, &listener.waiter, || {
+            let conn = listener
+                .queue
+                .lock()
+                .pop_front()
+                .ok_or(LinuxError::EAGAIN)?;
+            listener.waiter.notify(); // space for connecting
+            Ok(conn)
+        })
+    }
+
+    /// Connects a stream socket to the listening socket at `addr`, or sets the
+    /// default destination of a datagram socket.
+    ///
+    /// A stream socket waits without its state locked until the listening
+    /// socket has space for the connection, and it's `Connecting` meanwhile.
+    pub fn connect(&self, addr: UnixAddr) -> LinuxResult {
+        let binding = lookup_name(&addr)?;
+        let local_addr = self.bound_addr();
+        match (&self.inner, binding) {
+            (Inner::Stream(state), Binding::Stream(listener)) => {
+                {
+                    let mut state = state.lock();
+                    match &*state {
+                        StreamState::Idle => {}
+                        StreamState::Connecting => return Err(LinuxError::EALREADY),
+                        StreamState::Listening(_) => return Err(LinuxError::EINVAL),
+                        StreamState::Connected(_) => return Err(LinuxError::EISCONN),
+                    }
+                    *state = StreamState::Connecting;
+                }
+                let (rx, tx) = (StreamBuffer::new(), StreamBuffer::new());
+                let server =
+                    Self::connected(tx.clone(), rx.clone(), addr.clone(), local_addr.clone());
+                let res = match listener.upgrade() {
+                    Some(listener) => {
+                        let mut server = Some(server);
+                        self.block_on(false, &listener.waiter, || {
+                            if listener.closed.load(Ordering::Acquire) {
+                                return Err(LinuxError::ECONNREFUSED);
+                            }
+                            let mut queue = listener.queue.lock();
+                            if queue.len() >= listener.backlog.load(Ordering::Acquire) {
+                                return Err(LinuxError::EAGAIN);
+                            }
+                            queue.extend(server.take());
+                            drop(queue);
+                            listener.waiter.notify(); // a pending connection
+                            Ok(())
+                        })
+                    }
+                    None => Err(LinuxError::ECONNREFUSED),
+                };
+                let mut state = state.lock();
+                *state = match res {
+                    Ok(()) => StreamState::Connected(Connection {
+                        rx,
+                        tx,
+                        local_addr,
+                        peer_addr: addr,
+                    }),
+                    Err(_) => StreamState::Idle,
+                };
+                res
+            }
+            (Inner::Dgram { peer, .. }, Binding::Dgram(queue)) => {
+                *peer.lock() = Some(DgramPeer { queue, addr });
+                Ok(())
+            }
+            _ => Err(LinuxError::EPROTOTYPE),
+        }
+    }
+
+    /// Sends data to the peer, or to the default destination of a datagram
+    /// socket. Only [`dont_wait`](MsgFlags::dont_wait) of `flags` is used.
+    pub fn send(&self, buf: &[u8], flags: MsgFlags) -> LinuxResult<usize> {
+        match &self.inner {
+            Inner::Stream(state) => {
+                let tx = match &*state.lock() {
+                    StreamState::Connected(conn) => conn.tx.clone(),
+                    _ => return Err(LinuxError::ENOTCONN),
+                };
+                self.send_stream(&tx, buf, flags.dont_wait)
+            }
+            Inner::Dgram { peer, .. } => {
+                let queue = match &*peer.lock() {
+                    Some(peer) => peer.queue.clone(),
+                    None => return Err(LinuxError::ENOTCONN),
+                };
+                self.send_dgram(&queue, buf, flags.dont_wait)
+            }
+        }
+    }
+
+    /// Sends a datagram to `addr`, or `EISCONN` for stream sockets.
+    pub fn send_to(&self, buf: &[u8], addr: UnixAddr, flags: MsgFlags) -> LinuxResult<usize> {
+        match (&self.inner, lookup_name(&addr)?) {
+            (Inner::Dgram { .. }, Binding::Dgram(queue)) => {
+                self.send_dgram(&queue, buf, flags.dont_wait)
+            }
+            (Inner::Dgram { .. }, Binding::Stream(_)) => Err(LinuxError::EPROTOTYPE),
+            (Inner::Stream(_), _) => Err(LinuxError::EISCONN),
+        }
+    }
+
+    /// Receives data with the given flags. Returns the number of bytes read,
+    /// the full length of the datagram, and the source of the datagram, which
+    /// is [`None`] for stream sockets.
+    ///
+    /// A datagram is truncated if `buf` is too small, and a stream returns 0
+    /// at EOF.
+    pub fn recv_from(
+        &self,
+        buf: &mut [u8],
+        flags: MsgFlags,
+    ) -> LinuxResult<(usize, usize, Option<UnixAddr>)> {
+        match &self.inner {
+            Inner::Stream(state) => {
+                let rx = match &*state.lock() {
+                    StreamState::Connected(conn) => conn.rx.clone(),
+                    _ => return Err(LinuxError::ENOTCONN),
+                };
+                let wait_all = flags.wait_all && !buf.is_empty();
+                let mut received = 0;
+                let res = self.block_on(flags.dont_wait, &rx.waiter, || {
+                    let mut data = rx.data.lock();
+                    let eof = rx.write_closed.load(Ordering::Acquire);
+                    if flags.peek {
+                        let ready = if wait_all {
+                            data.len() >= buf.len()
+                        } else {
+                            !data.is_empty() || buf.is_empty()
+                        };
+                        if !ready && !eof {
+                            return Err(LinuxError::EAGAIN);
+                        }
+                        for (dst, src) in buf.iter_mut().zip(data.iter()) {
+                            *dst = *src;
+                        }
+                        return Ok(data.len().min(buf.len()));
+                    }
+                    let len = data.len().min(buf.len() - received);
+                    for (dst, src) in buf[received..].iter_mut().zip(data.drain(..len)) {
+                        *dst = src;
+                    }
+                    drop(data);
+                    if len > 0 {
+                        rx.waiter.notify(); // space for writing
+                    }
+                    received += len;
+                    if received == buf.len() || eof || (received > 0 && !wait_all) {
+                        Ok(received)
+                    } else {
+                        Err(LinuxError::EAGAIN)
+                    }
+                });
+                match res {
+                    // some data is received before `EAGAIN` with `wait_all`
+                    Err(LinuxError::EAGAIN) if received > 0 => Ok(received),
+                    res => res,
+                }
+                .map(|len| (len, len, None))
+            }
+            Inner::Dgram { rx, .. } => self.block_on(flags.dont_wait, &rx.waiter, || {
+                let mut queue = rx.queue.lock();
+                let (packet, addr) = queue.packets.front().ok_or(LinuxError::EAGAIN)?;
+                let len = packet.len().min(buf.len());
+                buf[..len].copy_from_slice(&packet[..len]);
+                let res = (len, packet.len(), Some(addr.clone()));
+                if !flags.peek {
+                    queue.packets.pop_front();
+                    queue.len -= res.1;
+                    drop(queue);
+                    rx.waiter.notify(); // space for sending
+                }
+                Ok(res)
+            }),
+        }
+    }
+
+    /// Shuts down both directions of the connection.
+    pub fn shutdown(&self) -> LinuxResult {
+        match &self.inner {
+            Inner::Stream(state) => match &*state.lock() {
+                StreamState::Connected(conn) => {
+                    conn.shutdown();
+                    Ok(())
+                }
+                _ => Err(LinuxError::ENOTCONN),
+            },
+            Inner::Dgram { peer, .. } => match &*peer.lock() {
+                Some(_) => Ok(()),
+                None => Err(LinuxError::ENOTCONN),
+            },
+        }
+    }
+
+    /// Returns whether the socket is readable or writable.
+    ///
+    /// A stream socket is also readable at EOF, and writable if the peer is
+    /// closed, so that the following call returns immediately.
+    pub fn poll(&self) -> PollState {
+        match &self.inner {
+            Inner::Stream(state) => match &*state.lock() {
+                StreamState::Idle | StreamState::Connecting => PollState {
+                    readable: false,
+                    writable: false,
+                },
+                StreamState::Listening(listener) => PollState {
+                    readable: !listener.queue.lock().is_empty(),
+                    writable: false,
+                },
+                StreamState::Connected(conn) => PollState {
+                    readable: !conn.rx.data.lock().is_empty()
+                        || conn.rx.write_closed.load(Ordering::Acquire),
+                    writable: conn.tx.data.lock().len() < BUF_SIZE
+                        || conn.tx.read_closed.load(Ordering::Acquire),
+                },
+            },
+            Inner::Dgram { rx, peer } => PollState {
+                readable: !rx.queue.lock().packets.is_empty(),
+                writable: peer.lock().as_ref().is_none_or(|peer| {
+                    peer.queue
+                        .upgrade()
+                        .is_none_or(|rx| rx.queue.lock().len < BUF_SIZE)
+                }),
+            },
+        }
+    }
+}
+
+/// Private methods
+impl UnixSocket {
+    /// Creates a stream socket connected to `peer_addr`.
+    fn connected(
+        rx: Arc<StreamBuffer>,
+        tx: Arc<StreamBuffer>,
+        local_addr: UnixAddr,
+        peer_addr: UnixAddr,
+    ) -> Self {
+        let conn = Connection {
+            rx,
+            tx,
+            local_addr,
+            peer_addr,
+        };
+        Self {
+            inner: Inner::Stream(Mutex::new(StreamState::Connected(conn))),
+            name: Mutex::new(None),
+            nonblock: AtomicBool::new(false),
+        }
+    }
+
+    /// Returns the address that this socket is bound to.
+    fn bound_addr(&self) -> UnixAddr {
+        match &*self.name.lock() {
+            Some(name) => name.addr.clone(),
+            None => UnixAddr::Unnamed,
+        }
+    }
+
+    /// Calls `f` until it completes or fails with an error other than
+    /// `EAGAIN`, and waits for changes notified to `waiter` between the calls,
+    /// unless `dont_wait` or in nonblocking mode.
+    fn block_on<F, T>(&self, dont_wait: bool, waiter: &Waiter, mut f: F) -> LinuxResult<T>
+    where
+        F: FnMut() -> LinuxResult<T>,
+    {
+        let nonblocking = dont_wait || self.is_nonblocking();
+        loop {
+            let seq = waiter.seq();
+            match f() {
+                Err(LinuxError::EAGAIN) if !nonblocking => waiter.wait_change(seq),
+                res => return res,
+            }
+        }
+    }
+
+    fn send_stream(&self, tx: &StreamBuffer, buf: &[u8], dont_wait: bool) -> LinuxResult<usize> {
+        let nonblocking = dont_wait || self.is_nonblocking();
+        let mut written = 0;
+        self.block_on(dont_wait, &tx.waiter, || {
+            if tx.read_closed.load(Ordering::Acquire) || tx.write_closed.load(Ordering::Acquire) {
+                return if written > 0 {
+                    Ok(written)
+                } else {
+                    Err(LinuxError::EPIPE)
+                };
+            }
+            let mut data = tx.data.lock();
+            let len = (BUF_SIZE - data.len()).min(buf.len() - written);
+            data.extend(&buf[written..written + len]);
+            drop(data);
+            if len > 0 {
+                tx.waiter.notify(); // data for reading
+            }
+            written += len;
+            if written == buf.len() || (written > 0 && nonblocking) {
+                Ok(written)
+            } else {
+                Err(LinuxError::EAGAIN)
+            }
+        })
+    }
+
+    fn send_dgram(
+        &self,
+        dest: &Weak<DgramReceiver>,
+        buf: &[u8],
+        dont_wait: bool,
+    ) -> LinuxResult<usize> {
+        if buf.len() > BUF_SIZE {
+            return Err(LinuxError::EMSGSIZE);
+        }
+        let source = self.bound_addr();
+        let dest = dest.upgrade().ok_or(LinuxError::ECONNREFUSED)?;
+        self.block_on(dont_wait, &dest.waiter, || {
+            if dest.closed.load(Ordering::Acquire) {
+                return Err(LinuxError::ECONNREFUSED);
+            }
+            let mut queue = dest.queue.lock();
+            if queue.len + buf.len() > BUF_SIZE {
+                return Err(LinuxError::EAGAIN);
+            }
+            queue.len += buf.len();
+            queue.packets.push_back((buf.into(), source.clone()));
+            drop(queue);
+            dest.waiter.notify(); // a datagram for receiving
+            Ok(buf.len())
+        })
+    }
+}
+
+impl Drop for UnixSocket {
+    fn drop(&mut self) {
+        if let Some(name) = self.name.lock().take() {
+            NAMES.lock().remove(&name.key);
+        }
+        // wake up the tasks blocked on this socket by others
+        match &self.inner {
+            Inner::Stream(state) => {
+                if let StreamState::Listening(listener) = &*state.lock() {
+                    listener.close();
+                }
+            }
+            Inner::Dgram { rx, .. } => {
+                rx.closed.store(true, Ordering::Release);
+                rx.waiter.notify();
+            }
+        }
+    }
+}
+
+/// Binds `addr` to `binding`, or an unused abstract name if it is unnamed.
+/// Returns the bound name.
+fn bind_name(addr: UnixAddr, binding: Binding) -> LinuxResult<BoundName> {
+    static NEXT_AUTOBIND: AtomicUsize = AtomicUsize::new(0);
+
+    let mut names = NAMES.lock();
+    let addr = match addr {
+        UnixAddr::Unnamed => loop {
+            let id = NEXT_AUTOBIND.fetch_add(1, Ordering::Relaxed) % 0x100000;
+            let addr = UnixAddr::Abstract(format!("{:05x}", id).into_bytes());
+            if !names.contains_key(&addr) {
+                break addr;
+            }
+        },
+        addr => addr,
+    };
+    let key = addr.key()?;
+    if names.contains_key(&key) {
+        return Err(LinuxError::EADDRINUSE);
+    }
+    #[cfg(feature = "fs")]
+    if let UnixAddr::Path(path) = &key {
+        axfs::api::create_socket(path).map_err(|e| match e {
+            axerrno::AxError::AlreadyExists => LinuxError::EADDRINUSE,
+            e => super::fs::paths_err(e, &[path]),
+        })?;
+    }
+    names.insert(key.clone(), binding);
+    Ok(BoundName { addr, key })
+}
+
+/// Looks up the socket bound to `addr`.
+fn lookup_name(addr: &UnixAddr) -> LinuxResult<Binding> {
+    let key = addr.key()?;
+    #[cfg(feature = "fs")]
+    if let UnixAddr::Path(path) = &key {
+        let meta = axfs::api::metadata(path).map_err(super::fs::path_err(path))?;
+        if !meta.file_type().is_socket() {
+            return Err(LinuxError::ECONNREFUSED);
+        }
+    }
+    match NAMES.lock().get(&key) {
+        Some(Binding::Stream(listener)) => Ok(Binding::Stream(listener.clone())),
+        Some(Binding::Dgram(queue)) => Ok(Binding::Dgram(queue.clone())),
+        None if key == UnixAddr::Unnamed => Err(LinuxError::EINVAL),
+        None => Err(LinuxError::ECONNREFUSED),
+    }
+}
+
+/// Parses the socket type of `socket` and `socketpair`.
+pub fn socket_type(socktype: u32, protocol: u32) -> LinuxResult<UnixSocketType> {
+    if protocol != 0 {
+        return Err(LinuxError::EPROTONOSUPPORT);
+    }
+    match socktype {
+        crate::ctypes::SOCK_STREAM => Ok(UnixSocketType::Stream),
+        crate::ctypes::SOCK_DGRAM => Ok(UnixSocketType::Dgram),
+        _ => Err(LinuxError::ESOCKTNOSUPPORT),
+    }
+}
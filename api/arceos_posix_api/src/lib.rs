@@ -31,15 +31,19 @@ pub mod config {
 pub mod ctypes;
 
 pub use imp::io::{sys_read, sys_readv, sys_write, sys_writev};
-pub use imp::resources::{sys_getrlimit, sys_setrlimit};
+pub use imp::resources::{sys_getrlimit, sys_getrusage, sys_setrlimit};
 pub use imp::sys::sys_sysconf;
 pub use imp::task::{sys_exit, sys_getpid, sys_sched_yield};
-pub use imp::time::{sys_clock, sys_clock_gettime, sys_nanosleep};
+pub use imp::time::{sys_clock, sys_clock_gettime, sys_nanosleep, sys_times};
 
 #[cfg(feature = "fd")]
 pub use imp::fd_ops::{sys_close, sys_dup, sys_dup2, sys_fcntl};
 #[cfg(feature = "fs")]
-pub use imp::fs::{sys_fstat, sys_getcwd, sys_lseek, sys_lstat, sys_open, sys_rename, sys_stat};
+pub use imp::fs::{
+    sys_access, sys_chdir, sys_fchdir, sys_fstat, sys_fsync, sys_ftruncate, sys_getcwd,
+    sys_getdents64, sys_link, sys_lseek, sys_lstat, sys_mkdir, sys_mount, sys_open, sys_readlink,
+    sys_rename, sys_rmdir, sys_stat, sys_symlink, sys_umount2, sys_unlink,
+};
 #[cfg(feature = "fs")]
 pub use imp::io::{sys_pread, sys_preadv, sys_pwrite, sys_pwritev};
 #[cfg(feature = "select")]
@@ -51,8 +55,8 @@ pub use imp::malloc::{sys_free, sys_malloc, sys_page_alloc, sys_page_free};
 #[cfg(feature = "net")]
 pub use imp::net::{
     sys_accept, sys_bind, sys_connect, sys_freeaddrinfo, sys_getaddrinfo, sys_getpeername,
-    sys_getsockname, sys_listen, sys_recv, sys_recvfrom, sys_send, sys_sendto, sys_shutdown,
-    sys_socket,
+    sys_getsockname, sys_getsockopt, sys_listen, sys_recv, sys_recvfrom, sys_recvmsg, sys_send,
+    sys_sendmsg, sys_sendto, sys_setsockopt, sys_shutdown, sys_socket, sys_socketpair,
 };
 #[cfg(feature = "pipe")]
 pub use imp::pipe::sys_pipe;
@@ -7,6 +7,8 @@
 //!     - `fp-simd`: Enable floating point and SIMD support.
 //! - Interrupts:
 //!     - `irq`: Enable interrupt handling support.
+//!     - `tickless`: Stop the periodic timer tick when it's not needed, e.g., on idle CPUs.
+//!       It can't be used with `smp`, as idle CPUs can't be woken up by IPIs yet.
 //! - Memory
 //!     - `alloc`: Enable dynamic memory allocation.
 //!     - `alloc-tlsf`: Use the TLSF allocator.
@@ -22,7 +24,10 @@
 //! - Upperlayer stacks (fs, net, display)
 //!     - `fs`: Enable file system support.
 //!     - `myfs`: Allow users to define their custom filesystems to override the default.
+//!     - `ext4`: Enable the ext4 filesystem, and use it as the root if the disk is ext4.
+//!     - `initramfs`: Unpack the initramfs (cpio archive) embedded or from the bootloader to `/`.
 //!     - `net`: Enable networking support.
+//!     - `dhcp`: Configure the network address by DHCP instead of `AX_IP`/`AX_GW`.
 //!     - `display`: Enable graphics support.
 //! - Device drivers
 //!     - `bus-mmio`: Use device tree to probe all MMIO devices.
@@ -38,3 +43,6 @@
 //! [ArceOS]: https://github.com/arceos-org/arceos
 
 #![no_std]
+
+#[cfg(all(feature = "tickless", feature = "smp"))]
+compile_error!("`tickless` does not support `smp`, idle CPUs can't be woken up by IPIs yet");
@@ -26,6 +26,12 @@ const CMD_TABLE: &[(&str, CmdHandler)] = &[
     ("help", do_help),
     ("ls", do_ls),
     ("mkdir", do_mkdir),
+    #[cfg(feature = "net")]
+    ("pcap", crate::pcap::do_pcap),
+    #[cfg(feature = "net")]
+    ("ping", crate::ping::do_ping),
+    #[cfg(feature = "multitask")]
+    ("ps", crate::ps::do_ps),
     ("pwd", do_pwd),
     ("rm", do_rm),
     ("uname", do_uname),
@@ -17,6 +17,13 @@ fn path_to_str(path: &str) -> &str {
 
 mod cmd;
 
+#[cfg(feature = "net")]
+mod pcap;
+#[cfg(feature = "net")]
+mod ping;
+#[cfg(feature = "multitask")]
+mod ps;
+
 #[cfg(feature = "use-ramfs")]
 mod ramfs;
 
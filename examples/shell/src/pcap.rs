@@ -0,0 +1,67 @@
+use core::str::SplitWhitespace;
+use std::format;
+use std::os::arceos::api::net::*;
+use std::string::{String, ToString};
+
+const USAGE: &str = "usage: pcap start [-i iface] [-w file] [arp|ip|ip6|tcp|udp|icmp] [host addr] [port num]\n       pcap stop";
+
+/// Parses the arguments of `pcap start` into the output file and the filter.
+fn parse_filter<'a>(
+    args: &mut SplitWhitespace<'a>,
+) -> Result<(Option<&'a str>, AxCaptureFilter), String> {
+    let mut path = None;
+    let mut filter = AxCaptureFilter::default();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("missing value of {}", arg))
+        };
+        match arg {
+            "-i" => filter.iface = Some(value()?.to_string()),
+            "-w" => path = Some(value()?),
+            "arp" => filter.ether_type = Some(0x0806),
+            "ip" => filter.ether_type = Some(0x0800),
+            "ip6" => filter.ether_type = Some(0x86dd),
+            "icmp" => filter.ip_protocol = Some(1),
+            "tcp" => filter.ip_protocol = Some(6),
+            "udp" => filter.ip_protocol = Some(17),
+            "host" => {
+                let addr = value()?;
+                filter.host = Some(
+                    addr.parse()
+                        .map_err(|_| format!("invalid address {}", addr))?,
+                );
+            }
+            "port" => {
+                let port = value()?;
+                filter.port = Some(port.parse().map_err(|_| format!("invalid port {}", port))?);
+            }
+            _ => return Err(format!("unknown argument {}", arg)),
+        }
+    }
+    Ok((path, filter))
+}
+
+pub fn do_pcap(args: &str) {
+    let mut args = args.split_whitespace();
+    match args.next() {
+        Some("start") => {
+            let (path, filter) = match parse_filter(&mut args) {
+                Ok(res) => res,
+                Err(e) => {
+                    println!("pcap: {}\n{}", e, USAGE);
+                    return;
+                }
+            };
+            match ax_net_capture_start(path, filter) {
+                Ok(()) => println!("pcap: capturing to {}", path.unwrap_or("console")),
+                Err(e) => println!("pcap: {}", e),
+            }
+        }
+        Some("stop") => match ax_net_capture_stop() {
+            Ok(count) => println!("pcap: {} frames captured", count),
+            Err(e) => println!("pcap: {}", e),
+        },
+        _ => println!("{}", USAGE),
+    }
+}
@@ -0,0 +1,150 @@
+use core::net::IpAddr;
+use std::os::arceos::api::AxError;
+use std::os::arceos::api::net::*;
+use std::time::{Duration, Instant};
+use std::vec::Vec;
+
+const ICMP_ECHO_REPLY: u8 = 0;
+const ICMP_ECHO_REQUEST: u8 = 8;
+const ICMP_HEADER_LEN: usize = 8;
+
+const DEFAULT_COUNT: u16 = 4;
+const PAYLOAD_LEN: usize = 56;
+const IDENT: u16 = 0xa2ce;
+const INTERVAL: Duration = Duration::from_secs(1);
+
+/// Returns the internet checksum of `data`.
+fn checksum(data: &[u8]) -> u16 {
+    let mut sum = data
+        .chunks(2)
+        .map(|c| u16::from_be_bytes([c[0], c.get(1).copied().unwrap_or(0)]) as u32)
+        .sum::<u32>();
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+fn echo_request(seq: u16) -> Vec<u8> {
+    let mut msg = Vec::with_capacity(ICMP_HEADER_LEN + PAYLOAD_LEN);
+    msg.extend_from_slice(&[ICMP_ECHO_REQUEST, 0, 0, 0]);
+    msg.extend_from_slice(&IDENT.to_be_bytes());
+    msg.extend_from_slice(&seq.to_be_bytes());
+    msg.extend((0..PAYLOAD_LEN).map(|i| i as u8));
+    let sum = checksum(&msg);
+    msg[2..4].copy_from_slice(&sum.to_be_bytes());
+    msg
+}
+
+/// Returns the TTL and the ICMP message of an IPv4 packet.
+fn parse_ipv4(packet: &[u8]) -> Option<(u8, &[u8])> {
+    let header_len = (*packet.first()? & 0xf) as usize * 4;
+    Some((*packet.get(8)?, packet.get(header_len..)?))
+}
+
+/// Waits for the echo reply of `seq` from `addr`, until [`INTERVAL`] after
+/// `start`. Returns the TTL and the size of the reply.
+fn wait_reply(
+    socket: &AxIcmpSocketHandle,
+    addr: IpAddr,
+    seq: u16,
+    start: Instant,
+) -> Result<(u8, usize), AxError> {
+    let mut buf = [0; 1500];
+    loop {
+        let elapsed = start.elapsed();
+        if elapsed >= INTERVAL {
+            return Err(AxError::WouldBlock);
+        }
+        ax_icmp_set_recv_timeout(socket, Some(INTERVAL - elapsed))?;
+        let (len, from) = ax_icmp_recv_from(socket, &mut buf)?;
+        let Some((ttl, msg)) = parse_ipv4(&buf[..len]) else {
+            continue;
+        };
+        // skip other messages, including our requests looped back
+        if from == addr
+            && msg.len() >= ICMP_HEADER_LEN
+            && msg[0] == ICMP_ECHO_REPLY
+            && msg[4..6] == IDENT.to_be_bytes()
+            && msg[6..8] == seq.to_be_bytes()
+        {
+            return Ok((ttl, msg.len()));
+        }
+    }
+}
+
+fn resolve(host: &str) -> Result<IpAddr, AxError> {
+    match host.parse() {
+        Ok(addr) => Ok(addr),
+        Err(_) => ax_dns_query(host)?
+            .into_iter()
+            .find(IpAddr::is_ipv4)
+            .ok_or(AxError::NotFound),
+    }
+}
+
+pub fn do_ping(args: &str) {
+    let mut count = DEFAULT_COUNT;
+    let mut host = None;
+    let mut args = args.split_whitespace();
+    while let Some(arg) = args.next() {
+        match arg {
+            "-c" => match args.next().and_then(|n| n.parse().ok()) {
+                Some(n) if n > 0 => count = n,
+                _ => {
+                    println!("ping: invalid count");
+                    return;
+                }
+            },
+            _ => host = Some(arg),
+        }
+    }
+    let Some(host) = host else {
+        println!("ping: usage: ping [-c count] host");
+        return;
+    };
+    let addr = match resolve(host) {
+        Ok(addr) => addr,
+        Err(e) => {
+            println!("ping: {}: {}", host, e);
+            return;
+        }
+    };
+
+    let socket = ax_icmp_socket();
+    println!("PING {} ({}): {} data bytes", host, addr, PAYLOAD_LEN);
+    let mut received = 0;
+    for seq in 0..count {
+        let start = Instant::now();
+        if let Err(e) = ax_icmp_send_to(&socket, &echo_request(seq), addr) {
+            println!("ping: {}: {}", host, e);
+            return;
+        }
+        match wait_reply(&socket, addr, seq, start) {
+            Ok((ttl, len)) => {
+                let time = start.elapsed().as_micros() as f64 / 1000.0;
+                println!(
+                    "{} bytes from {}: icmp_seq={} ttl={} time={:.3} ms",
+                    len, addr, seq, ttl, time
+                );
+                received += 1;
+            }
+            Err(AxError::WouldBlock) => println!("Request timeout for icmp_seq {}", seq),
+            Err(e) => {
+                println!("ping: {}: {}", host, e);
+                return;
+            }
+        }
+        if seq + 1 < count {
+            std::thread::sleep(INTERVAL.saturating_sub(start.elapsed()));
+        }
+    }
+
+    println!("--- {} ping statistics ---", host);
+    println!(
+        "{} packets transmitted, {} packets received, {:.1}% packet loss",
+        count,
+        received,
+        (count - received) as f64 * 100.0 / count as f64
+    );
+}
@@ -0,0 +1,29 @@
+use std::os::arceos::api::task::*;
+
+fn state_str(task: &AxTaskInfo) -> &'static str {
+    match task.state {
+        AxTaskState::Running => "running",
+        AxTaskState::Ready => "ready",
+        AxTaskState::Blocked if task.waiting => "waiting",
+        AxTaskState::Blocked => "sleeping",
+        AxTaskState::Exited => "exited",
+    }
+}
+
+pub fn do_ps(_args: &str) {
+    println!(
+        "{:>5} {:>4} {:>5} {:<8} NAME",
+        "TID", "CPU", "PRIO", "STATE"
+    );
+    ax_for_each_task(|task| {
+        let name = if task.idle { "[idle]" } else { &task.name };
+        println!(
+            "{:>5} {:>4} {:>5} {:<8} {}",
+            task.id,
+            task.cpu,
+            task.priority,
+            state_str(task),
+            name
+        );
+    });
+}
@@ -14,7 +14,7 @@ impl AllDevices {
                         reg.0, reg.0 + reg.1,
                         dev.device_name(),
                     );
-                    self.add_device(dev);
+                    self.add_device(dev, None);
                     continue; // skip to the next device
                 }
             });
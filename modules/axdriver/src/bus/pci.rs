@@ -1,4 +1,4 @@
-use crate::{AllDevices, prelude::*};
+use crate::{AllDevices, PciAddress, prelude::*};
 use axdriver_pci::{
     BarInfo, Cam, Command, DeviceFunction, HeaderType, MemoryBarType, PciRangeAllocator, PciRoot,
 };
@@ -107,7 +107,12 @@ impl AllDevices {
                                 bdf,
                                 dev.device_name(),
                             );
-                            self.add_device(dev);
+                            let addr = PciAddress {
+                                bus: bdf.bus,
+                                device: bdf.device,
+                                function: bdf.function,
+                            };
+                            self.add_device(dev, Some(addr));
                             continue; // skip to the next device
                         }
                     }),
@@ -61,7 +61,6 @@
 #[macro_use]
 extern crate log;
 
-#[cfg(feature = "dyn")]
 extern crate alloc;
 
 #[macro_use]
@@ -80,6 +79,9 @@ mod ixgbe;
 
 pub mod prelude;
 
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+
 #[allow(unused_imports)]
 use self::prelude::*;
 pub use self::structs::{AxDeviceContainer, AxDeviceEnum};
@@ -91,6 +93,40 @@ pub use self::structs::AxDisplayDevice;
 #[cfg(feature = "net")]
 pub use self::structs::AxNetDevice;
 
+/// Location of a device on the PCI bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PciAddress {
+    /// Bus number.
+    pub bus: u8,
+    /// Device number on the bus.
+    pub device: u8,
+    /// Function number of the device.
+    pub function: u8,
+}
+
+impl fmt::Display for PciAddress {
+    /// Formats the address as `domain:bus:device.function`, e.g. `0000:00:01.0`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "0000:{:02x}:{:02x}.{:x}",
+            self.bus, self.device, self.function
+        )
+    }
+}
+
+/// Information of a probed device, which is kept after the device itself is
+/// taken by its subsystem.
+#[derive(Debug, Clone)]
+pub struct DeviceInfo {
+    /// Category of the device.
+    pub device_type: DeviceType,
+    /// Name of the device driver, e.g. `virtio-blk`.
+    pub name: String,
+    /// Location of the device if it is on the PCI bus.
+    pub pci_address: Option<PciAddress>,
+}
+
 /// A structure that contains all device drivers, organized by their category.
 #[derive(Default)]
 pub struct AllDevices {
@@ -103,6 +139,9 @@ pub struct AllDevices {
     /// All graphics device drivers.
     #[cfg(feature = "display")]
     pub display: AxDeviceContainer<AxDisplayDevice>,
+    /// Information of all devices above, in the order they were probed, so
+    /// the devices of a category are in the same order as in its container.
+    pub infos: Vec<DeviceInfo>,
 }
 
 impl AllDevices {
@@ -126,7 +165,7 @@ impl AllDevices {
                     dev.device_type(),
                     dev.device_name(),
                 );
-                self.add_device(dev);
+                self.add_device(dev, None);
             }
         });
 
@@ -134,19 +173,38 @@ impl AllDevices {
     }
 
     /// Adds one device into the corresponding container, according to its device category.
+    ///
+    /// Its information is recorded if the container accepts it.
     #[allow(dead_code)]
-    fn add_device(&mut self, dev: AxDeviceEnum) {
-        match dev {
+    fn add_device(&mut self, dev: AxDeviceEnum, pci_address: Option<PciAddress>) {
+        let info = DeviceInfo {
+            device_type: dev.device_type(),
+            name: dev.device_name().into(),
+            pci_address,
+        };
+        let accepted = match dev {
             #[cfg(feature = "net")]
-            AxDeviceEnum::Net(dev) => self.net.push(dev),
+            AxDeviceEnum::Net(dev) => push_device(&mut self.net, dev),
             #[cfg(feature = "block")]
-            AxDeviceEnum::Block(dev) => self.block.push(dev),
+            AxDeviceEnum::Block(dev) => push_device(&mut self.block, dev),
             #[cfg(feature = "display")]
-            AxDeviceEnum::Display(dev) => self.display.push(dev),
+            AxDeviceEnum::Display(dev) => push_device(&mut self.display, dev),
+        };
+        if accepted {
+            self.infos.push(info);
         }
     }
 }
 
+/// Pushes `dev` into `container`, returns whether it is accepted, as the
+/// static device model only keeps the first device.
+#[allow(dead_code)]
+fn push_device<D>(container: &mut AxDeviceContainer<D>, dev: D) -> bool {
+    let len = container.len();
+    container.push(dev);
+    container.len() > len
+}
+
 /// Probes and initializes all device drivers, returns the [`AllDevices`] struct.
 pub fn init_drivers() -> AllDevices {
     info!("Initialize device drivers...");
@@ -0,0 +1,31 @@
+#!/bin/bash
+
+# Creates the ext4 test image with the same contents as `create_test_img.sh`,
+# plus a symbolic link and a hard link to `short.txt`, but without mounting it
+# (requires e2fsprogs >= 1.43).
+
+CUR_DIR=`dirname $0`
+
+create_ext4_img() {
+	local name=$1
+	local blkcount=$2
+	local root=$(mktemp -d)
+	for i in $(seq 1 1000); do
+	  echo "Rust is cool!" >>"$root/long.txt"
+	done
+	echo "Rust is cool!" >>"$root/short.txt"
+	ln -s short.txt "$root/short-link.txt"
+	ln "$root/short.txt" "$root/short-hardlink.txt"
+	mkdir -p "$root/very/long/path"
+	echo "Rust is cool!" >>"$root/very/long/path/test.txt"
+	mkdir -p "$root/very-long-dir-name"
+	echo "Rust is cool!" >>"$root/very-long-dir-name/very-long-file-name.txt"
+
+	rm -f "$name"
+	dd if=/dev/zero of="$name" bs=1024 count=$blkcount
+	mkfs.ext4 -b 4096 -L "Test!" -U 12345678-1234-1234-1234-123456789abc \
+	  -E root_owner=0:0 -d "$root" "$name"
+	rm -rf "$root"
+}
+
+create_ext4_img "$CUR_DIR/ext4.img" 4096
@@ -70,6 +70,12 @@ impl OpenOptions {
         self
     }
 
+    /// Sets the option to open a symbolic link itself instead of its target.
+    pub fn nofollow(&mut self, nofollow: bool) -> &mut Self {
+        self.0.nofollow(nofollow);
+        self
+    }
+
     /// Opens a file at `path` with the options specified by `self`.
     pub fn open(&self, path: &str) -> Result<File> {
         fops::File::open(path, &self.0).map(|inner| File { inner })
@@ -96,6 +102,12 @@ impl Metadata {
         self.0.is_file()
     }
 
+    /// Returns `true` if this metadata is for a symbolic link, which is only
+    /// possible if it is queried by [`symlink_metadata`](super::symlink_metadata).
+    pub const fn is_symlink(&self) -> bool {
+        self.0.file_type().is_symlink()
+    }
+
     /// Returns the size of the file, in bytes, this metadata is for.
     #[allow(clippy::len_without_is_empty)]
     pub const fn len(&self) -> u64 {
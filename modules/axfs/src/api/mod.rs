@@ -5,8 +5,11 @@ mod file;
 
 pub use self::dir::{DirBuilder, DirEntry, ReadDir};
 pub use self::file::{File, FileType, Metadata, OpenOptions, Permissions};
+pub use crate::cache::BlockCacheStats;
+pub use crate::root::ResolveError;
 
-use alloc::{string::String, vec::Vec};
+use alloc::{string::String, sync::Arc, vec::Vec};
+use axfs_vfs::VfsOps;
 use axio::{self as io, prelude::*};
 
 /// Returns an iterator over the entries within a directory.
@@ -20,6 +23,15 @@ pub fn canonicalize(path: &str) -> io::Result<String> {
     crate::root::absolute_path(path)
 }
 
+/// Expands the symbolic links in `path`, and returns the expanded path.
+///
+/// It tells errors that [`io::Error`] has no kind for, e.g., a loop of
+/// symbolic links, which fails other functions with
+/// [`InvalidInput`](io::Error::InvalidInput).
+pub fn resolve_symlinks(path: &str) -> Result<String, ResolveError> {
+    crate::root::resolve(None, path, true).map(|(path, _)| path)
+}
+
 /// Returns the current working directory as a [`String`].
 pub fn current_dir() -> io::Result<String> {
     crate::root::current_dir()
@@ -80,6 +92,45 @@ pub fn remove_file(path: &str) -> io::Result<()> {
     crate::root::remove_file(None, path)
 }
 
+/// Creates a symbolic link at `path` pointing to `target`.
+///
+/// `target` is not checked, it can be relative to the directory of `path` or
+/// even not exist.
+pub fn symlink(target: &str, path: &str) -> io::Result<()> {
+    crate::root::symlink(None, target, path)
+}
+
+/// Reads the target of a symbolic link.
+pub fn read_link(path: &str) -> io::Result<String> {
+    crate::root::read_link(None, path)
+}
+
+/// Creates a socket file at `path`, which is the name of a Unix domain socket
+/// bound to it.
+///
+/// Only filesystems that support socket files, such as the RAM filesystem, can
+/// create them.
+pub fn create_socket(path: &str) -> io::Result<()> {
+    crate::root::create_socket(None, path)
+}
+
+/// Creates a new hard link `new` to the file `old`.
+///
+/// This only works when both are in the same mounted fs which supports hard
+/// links, and `old` is not a directory. It fails with `Unsupported` in the
+/// former case, and `PermissionDenied` in the latter ones.
+pub fn hard_link(old: &str, new: &str) -> io::Result<()> {
+    crate::root::hard_link(None, old, new)
+}
+
+/// Queries the metadata of a file like [`metadata`], but does not follow
+/// symbolic links.
+pub fn symlink_metadata(path: &str) -> io::Result<Metadata> {
+    let mut opts = OpenOptions::new();
+    opts.read(true).nofollow(true);
+    opts.open(path)?.metadata()
+}
+
 /// Rename a file or directory to a new name.
 /// Delete the original file if `old` already exists.
 ///
@@ -87,3 +138,74 @@ pub fn remove_file(path: &str) -> io::Result<()> {
 pub fn rename(old: &str, new: &str) -> io::Result<()> {
     crate::root::rename(old, new)
 }
+
+/// Mounts the filesystem `fs` at `path`.
+///
+/// The mount point is created as a directory if it does not exist. It can be
+/// inside another mounted filesystem. Both the source and the type of `fs` are
+/// listed as `none` in `/proc/mounts`.
+///
+/// The nodes of `fs` must implement [`VfsNodeOps::as_any`](axfs_vfs::VfsNodeOps::as_any).
+pub fn mount(path: &str, fs: Arc<dyn VfsOps>) -> io::Result<()> {
+    crate::root::mount(path, fs, "none", "none")
+}
+
+/// Creates a filesystem of type `fstype` from `source` and mounts it at
+/// `path`.
+///
+/// Currently supported types are:
+///
+/// - `ramfs` (alias `tmpfs`): a new empty RAM filesystem, `source` is ignored.
+/// - `vfat` (alias `fat`): the FAT filesystem on the block device `source`,
+///   e.g. `/dev/vdb`.
+/// - `ext4`: the ext4 filesystem on the block device `source`, only one ext4
+///   filesystem can be used at a time.
+///
+/// A block device can only be mounted as the type it is first mounted as,
+/// other types fail with `InvalidInput`.
+pub fn mount_by_type(source: &str, path: &str, fstype: &str) -> io::Result<()> {
+    crate::root::mount(path, crate::mounts::new_fs(fstype, source)?, source, fstype)
+}
+
+/// Unmounts the filesystem mounted at `path`.
+///
+/// It fails if other filesystems are mounted inside it, or the current
+/// directory is inside it.
+pub fn umount(path: &str) -> io::Result<()> {
+    crate::root::umount(path)
+}
+
+/// Writes all cached data of all block devices back to the devices.
+pub fn sync() -> io::Result<()> {
+    crate::dev::sync_all()
+}
+
+/// Writes the cached data of the filesystem containing `path` back to its
+/// device.
+///
+/// Filesystems in memory, e.g. ramfs, have nothing to write.
+pub fn sync_fs(path: &str) -> io::Result<()> {
+    crate::root::sync_fs(path)
+}
+
+/// Evicts all clean blocks from the block caches, dirty blocks are kept.
+///
+/// Call [`sync`] first to drop all cached data.
+pub fn drop_caches() {
+    crate::dev::drop_caches()
+}
+
+/// Returns the names of all block devices (e.g. `vda`), in the order they are
+/// registered, i.e., the order of the devices from the driver.
+pub fn block_devices() -> Vec<String> {
+    crate::dev::block_devices()
+        .iter()
+        .map(|dev| dev.name().into())
+        .collect()
+}
+
+/// Returns the block cache statistics of a block device, by its name (`vda`)
+/// or path (`/dev/vda`).
+pub fn block_cache_stats(dev: &str) -> io::Result<BlockCacheStats> {
+    Ok(crate::dev::find_block_device(dev)?.cache_stats())
+}
@@ -0,0 +1,163 @@
+//! Block cache between filesystems and block devices.
+//!
+//! Recently used blocks are kept in memory and evicted in LRU order. Writes
+//! only update the cache (write-back), dirty blocks reach the device when
+//! evicted or when [`CachedDevice::sync`] is called.
+
+use alloc::{boxed::Box, collections::BTreeMap};
+use axdriver::prelude::*;
+
+use crate::dev::BLOCK_SIZE;
+
+/// Statistics of the block cache of a device.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BlockCacheStats {
+    /// Number of block reads and writes served by the cache.
+    pub hits: u64,
+    /// Number of block reads and writes that missed the cache.
+    pub misses: u64,
+    /// Number of blocks evicted from the cache.
+    pub evictions: u64,
+    /// Number of dirty blocks written back to the device.
+    pub writebacks: u64,
+    /// Number of blocks in the cache.
+    pub cached: usize,
+    /// Number of dirty blocks in the cache.
+    pub dirty: usize,
+}
+
+struct CachedBlock {
+    data: Box<[u8; BLOCK_SIZE]>,
+    dirty: bool,
+    /// Last use of the block, the key in [`CachedDevice::lru`].
+    stamp: u64,
+}
+
+/// A block device with an LRU write-back cache of `capacity` blocks.
+///
+/// If `capacity` is 0, all reads and writes go to the device directly.
+pub(crate) struct CachedDevice {
+    dev: AxBlockDevice,
+    capacity: usize,
+    blocks: BTreeMap<u64, CachedBlock>,
+    /// Block IDs by their last use, the first one is the least recently used.
+    lru: BTreeMap<u64, u64>,
+    clock: u64,
+    stats: BlockCacheStats,
+}
+
+impl CachedDevice {
+    pub fn new(dev: AxBlockDevice, capacity: usize) -> Self {
+        Self {
+            dev,
+            capacity,
+            blocks: BTreeMap::new(),
+            lru: BTreeMap::new(),
+            clock: 0,
+            stats: BlockCacheStats::default(),
+        }
+    }
+
+    pub fn device(&self) -> &AxBlockDevice {
+        &self.dev
+    }
+
+    pub fn stats(&self) -> BlockCacheStats {
+        BlockCacheStats {
+            cached: self.blocks.len(),
+            dirty: self.blocks.values().filter(|b| b.dirty).count(),
+            ..self.stats
+        }
+    }
+
+    pub fn read_block(&mut self, block_id: u64, buf: &mut [u8]) -> DevResult {
+        if self.capacity == 0 {
+            return self.dev.read_block(block_id, buf);
+        }
+        if let Some(block) = self.lookup(block_id) {
+            buf.copy_from_slice(&block.data[..]);
+            return Ok(());
+        }
+        let mut data = Box::new([0u8; BLOCK_SIZE]);
+        self.dev.read_block(block_id, &mut data[..])?;
+        buf.copy_from_slice(&data[..]);
+        self.insert(block_id, data, false)
+    }
+
+    pub fn write_block(&mut self, block_id: u64, buf: &[u8]) -> DevResult {
+        if self.capacity == 0 {
+            return self.dev.write_block(block_id, buf);
+        }
+        if let Some(block) = self.lookup(block_id) {
+            block.data.copy_from_slice(buf);
+            block.dirty = true;
+            return Ok(());
+        }
+        // the whole block is overwritten, no need to read it first
+        let mut data = Box::new([0u8; BLOCK_SIZE]);
+        data.copy_from_slice(buf);
+        self.insert(block_id, data, true)
+    }
+
+    /// Writes all dirty blocks back to the device, and flushes the device.
+    pub fn sync(&mut self) -> DevResult {
+        for (&block_id, block) in self.blocks.iter_mut().filter(|(_, b)| b.dirty) {
+            self.dev.write_block(block_id, &block.data[..])?;
+            block.dirty = false;
+            self.stats.writebacks += 1;
+        }
+        self.dev.flush()
+    }
+
+    /// Evicts all clean blocks, dirty ones stay until written back.
+    pub fn drop_clean(&mut self) {
+        let cached = self.blocks.len();
+        self.blocks.retain(|_, block| block.dirty);
+        self.lru
+            .retain(|_, block_id| self.blocks.contains_key(block_id));
+        self.stats.evictions += (cached - self.blocks.len()) as u64;
+    }
+
+    /// Looks up a cached block and marks it as the most recently used.
+    fn lookup(&mut self, block_id: u64) -> Option<&mut CachedBlock> {
+        let Some(block) = self.blocks.get_mut(&block_id) else {
+            self.stats.misses += 1;
+            return None;
+        };
+        self.stats.hits += 1;
+        self.clock += 1;
+        self.lru.remove(&block.stamp);
+        self.lru.insert(self.clock, block_id);
+        block.stamp = self.clock;
+        Some(block)
+    }
+
+    fn insert(&mut self, block_id: u64, data: Box<[u8; BLOCK_SIZE]>, dirty: bool) -> DevResult {
+        while self.blocks.len() >= self.capacity {
+            self.evict()?;
+        }
+        self.clock += 1;
+        self.lru.insert(self.clock, block_id);
+        let stamp = self.clock;
+        self.blocks
+            .insert(block_id, CachedBlock { data, dirty, stamp });
+        Ok(())
+    }
+
+    /// Evicts the least recently used block, writes it back if dirty.
+    fn evict(&mut self) -> DevResult {
+        let Some((&stamp, &block_id)) = self.lru.first_key_value() else {
+            return Ok(());
+        };
+        let block = &self.blocks[&block_id];
+        if block.dirty {
+            // keep the block if the write fails, so no data is lost
+            self.dev.write_block(block_id, &block.data[..])?;
+            self.stats.writebacks += 1;
+        }
+        self.lru.remove(&stamp);
+        self.blocks.remove(&block_id);
+        self.stats.evictions += 1;
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+//! Unpacking of `newc` [cpio] archives, i.e., the format of initramfs images
+//! built by `find . | cpio -o -H newc`.
+//!
+//! [cpio]: https://www.kernel.org/doc/html/latest/driver-api/early-userspace/buffer-format.html
+
+use alloc::collections::BTreeMap;
+use alloc::{format, string::String, vec::Vec};
+use axerrno::{AxError, AxResult, ax_err};
+
+use crate::api as fs;
+
+const MAGIC: &[u8] = b"070701";
+const HEADER_LEN: usize = 110;
+const TRAILER: &str = "TRAILER!!!";
+
+const S_IFMT: u32 = 0o170000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFREG: u32 = 0o100000;
+const S_IFLNK: u32 = 0o120000;
+
+/// Fields of an entry header that are used.
+struct Header {
+    ino: u32,
+    mode: u32,
+    nlink: u32,
+    file_size: usize,
+    name_size: usize,
+}
+
+impl Header {
+    fn parse(buf: &[u8]) -> AxResult<Self> {
+        if buf.len() < HEADER_LEN || &buf[..6] != MAGIC {
+            return ax_err!(InvalidData, "bad cpio header");
+        }
+        // 13 fields of 8 hex digits after the magic
+        let field = |i: usize| {
+            let hex = core::str::from_utf8(&buf[6 + i * 8..14 + i * 8]).unwrap_or("");
+            u32::from_str_radix(hex, 16).map_err(|_| AxError::InvalidData)
+        };
+        Ok(Self {
+            ino: field(0)?,
+            mode: field(1)?,
+            nlink: field(4)?,
+            file_size: field(6)? as usize,
+            name_size: field(11)? as usize,
+        })
+    }
+}
+
+/// Rounds `offset` up to the 4-byte boundary that entries are aligned to.
+const fn align4(offset: usize) -> usize {
+    (offset + 3) & !3
+}
+
+/// Unpacks a `newc` cpio archive into the directory `dir`, which is created if
+/// it does not exist.
+///
+/// Directories, regular files and symbolic links are created, existing files
+/// are overwritten. Hard links are created only if the filesystem supports
+/// them, otherwise the linked paths get copies of the content. Other types of
+/// entries (e.g. devices) and the permissions are ignored.
+///
+/// Returns the number of entries unpacked.
+pub fn unpack(archive: &[u8], dir: &str) -> AxResult<usize> {
+    let dir = dir.trim_end_matches('/');
+    fs::create_dir_all(if dir.is_empty() { "/" } else { dir })?;
+
+    // paths of each inode with multiple links, the first one and its copies
+    let mut links = BTreeMap::<u32, Vec<String>>::new();
+    let mut offset = 0;
+    let mut count = 0;
+    loop {
+        let header = Header::parse(&archive[offset.min(archive.len())..])?;
+        let name_start = offset + HEADER_LEN;
+        let data_start = align4(name_start + header.name_size);
+        let data_end = data_start + header.file_size;
+        if header.name_size == 0 || data_end > archive.len() {
+            return ax_err!(InvalidData, "truncated cpio archive");
+        }
+        offset = align4(data_end);
+
+        let name = &archive[name_start..name_start + header.name_size - 1]; // without NUL
+        let name = core::str::from_utf8(name).map_err(|_| AxError::InvalidData)?;
+        let data = &archive[data_start..data_end];
+        if name == TRAILER {
+            break;
+        }
+        let name = name.trim_start_matches("./").trim_start_matches('/');
+        if name.is_empty() || name == "." {
+            continue;
+        }
+        let path = format!("{}/{}", dir, name);
+
+        match header.mode & S_IFMT {
+            S_IFDIR => match fs::create_dir(&path) {
+                Err(e) if e != AxError::AlreadyExists => return Err(e),
+                _ => {}
+            },
+            S_IFREG if header.nlink > 1 => {
+                // content of hard links is stored in the last one of them
+                let paths = links.entry(header.ino).or_default();
+                let linked = match paths.first() {
+                    Some(first) => {
+                        let _ = fs::remove_file(&path);
+                        fs::hard_link(first, &path).is_ok()
+                    }
+                    None => false,
+                };
+                if !linked {
+                    fs::write(&path, data)?;
+                    paths.push(path); // a copy if not the first
+                }
+                if !data.is_empty() {
+                    for path in paths.iter() {
+                        fs::write(path, data)?;
+                    }
+                }
+            }
+            S_IFREG => fs::write(&path, data)?,
+            S_IFLNK => {
+                let target = core::str::from_utf8(data).map_err(|_| AxError::InvalidData)?;
+                let _ = fs::remove_file(&path);
+                fs::symlink(target, &path)?;
+            }
+            _ => {
+                warn!("cpio: skip {} of mode {:#o}", name, header.mode);
+                continue;
+            }
+        }
+        count += 1;
+    }
+    Ok(count)
+}
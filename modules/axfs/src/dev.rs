@@ -1,18 +1,168 @@
+use alloc::{format, string::String, sync::Arc, vec::Vec};
 use axdriver::prelude::*;
+use axerrno::{AxError, AxResult, ax_err};
+use axfs_vfs::VfsOps;
+use axsync::Mutex;
 
-const BLOCK_SIZE: usize = 512;
+#[cfg(feature = "devfs")]
+use axfs_vfs::{VfsError, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeType, VfsResult};
+
+use crate::cache::{BlockCacheStats, CachedDevice};
+
+pub(crate) const BLOCK_SIZE: usize = 512;
+
+/// All block devices probed at boot, indexed by their registration order.
+static BLOCK_DEVICES: Mutex<Vec<Arc<BlockDevice>>> = Mutex::new(Vec::new());
+
+/// A block device registered in axfs.
+///
+/// It is shared by all [`Disk`]s opened on it, and its raw node in devfs.
+pub struct BlockDevice {
+    name: &'static str,
+    dev: Mutex<CachedDevice>,
+    /// The filesystem on the device with its type name, e.g. `vfat`.
+    fs: Mutex<Option<(&'static str, Arc<dyn VfsOps>)>>,
+}
+
+impl BlockDevice {
+    fn new(name: &'static str, dev: AxBlockDevice) -> Self {
+        assert_eq!(BLOCK_SIZE, dev.block_size());
+        Self {
+            name,
+            dev: Mutex::new(CachedDevice::new(dev, axconfig::BLOCK_CACHE_SIZE)),
+            fs: Mutex::new(None),
+        }
+    }
+
+    /// Returns the name of the device, e.g. `vda`.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the size of the device in bytes.
+    pub fn size(&self) -> u64 {
+        self.dev.lock().device().num_blocks() * BLOCK_SIZE as u64
+    }
+
+    /// Writes all dirty blocks in the cache back to the device.
+    pub fn sync(&self) -> AxResult {
+        self.dev.lock().sync().map_err(|_| AxError::Io)
+    }
+
+    /// Evicts all clean blocks from the cache, so later reads go to the
+    /// device.
+    pub fn drop_cache(&self) {
+        self.dev.lock().drop_clean()
+    }
+
+    /// Returns the statistics of the block cache.
+    pub fn cache_stats(&self) -> BlockCacheStats {
+        self.dev.lock().stats()
+    }
+
+    /// Returns the filesystem of type `fstype` on this device, or creates it
+    /// with `f` if the device has not been mounted before.
+    ///
+    /// Each device has at most one filesystem instance, it is kept even if
+    /// the filesystem is unmounted, and reused for later mounts. Mounting the
+    /// device as another type fails with `InvalidInput`.
+    pub(crate) fn filesystem_or_init<F>(
+        self: &Arc<Self>,
+        fstype: &'static str,
+        f: F,
+    ) -> AxResult<Arc<dyn VfsOps>>
+    where
+        F: FnOnce(Disk) -> AxResult<Arc<dyn VfsOps>>,
+    {
+        let mut fs = self.fs.lock();
+        match fs.as_ref() {
+            Some((ty, fs)) if *ty == fstype => return Ok(fs.clone()),
+            Some((ty, _)) => {
+                warn!("{} is {}, cannot be mounted as {}", self.name, ty, fstype);
+                return ax_err!(InvalidInput, "the device has another filesystem type");
+            }
+            None => {}
+        }
+        let new_fs = f(Disk::new(self.clone()))?;
+        *fs = Some((fstype, new_fs.clone()));
+        Ok(new_fs)
+    }
+
+    fn read_block(&self, block_id: u64, buf: &mut [u8]) -> DevResult {
+        self.dev.lock().read_block(block_id, buf)
+    }
+
+    fn write_block(&self, block_id: u64, buf: &[u8]) -> DevResult {
+        self.dev.lock().write_block(block_id, buf)
+    }
+}
+
+/// Returns the device name of the `idx`-th block device, i.e. `vda`, `vdb`,
+/// ..., `vdz`, `vdaa`, ...
+fn block_device_name(mut idx: usize) -> String {
+    let mut suffix = Vec::new();
+    loop {
+        suffix.push(b'a' + (idx % 26) as u8);
+        if idx < 26 {
+            break;
+        }
+        idx = idx / 26 - 1;
+    }
+    suffix.reverse();
+    format!("vd{}", core::str::from_utf8(&suffix).unwrap())
+}
+
+/// Registers a block device, and returns it with the assigned name.
+///
+/// Devices are never unregistered, so their names live forever.
+pub(crate) fn register_block_device(dev: AxBlockDevice) -> Arc<BlockDevice> {
+    let mut devices = BLOCK_DEVICES.lock();
+    let name = block_device_name(devices.len()).leak();
+    info!("  block device {}: {:?}", name, dev.device_name());
+    let dev = Arc::new(BlockDevice::new(name, dev));
+    devices.push(dev.clone());
+    dev
+}
+
+/// Writes all dirty blocks of all block devices back.
+pub(crate) fn sync_all() -> AxResult {
+    for dev in block_devices() {
+        dev.sync()?;
+    }
+    Ok(())
+}
+
+/// Evicts the clean blocks of all block devices from their caches.
+pub(crate) fn drop_caches() {
+    for dev in block_devices() {
+        dev.drop_cache();
+    }
+}
+
+/// Returns all registered block devices.
+pub(crate) fn block_devices() -> Vec<Arc<BlockDevice>> {
+    BLOCK_DEVICES.lock().clone()
+}
+
+/// Finds a registered block device by its name (`vdb`) or path (`/dev/vdb`).
+pub(crate) fn find_block_device(name: &str) -> AxResult<Arc<BlockDevice>> {
+    let name = name.strip_prefix("/dev/").unwrap_or(name);
+    match BLOCK_DEVICES.lock().iter().find(|dev| dev.name == name) {
+        Some(dev) => Ok(dev.clone()),
+        None => ax_err!(NotFound, "block device not found"),
+    }
+}
 
 /// A disk device with a cursor.
 pub struct Disk {
     block_id: u64,
     offset: usize,
-    dev: AxBlockDevice,
+    dev: Arc<BlockDevice>,
 }
 
 impl Disk {
     /// Create a new disk.
-    pub fn new(dev: AxBlockDevice) -> Self {
-        assert_eq!(BLOCK_SIZE, dev.block_size());
+    pub fn new(dev: Arc<BlockDevice>) -> Self {
         Self {
             block_id: 0,
             offset: 0,
@@ -22,7 +172,7 @@ impl Disk {
 
     /// Get the size of the disk.
     pub fn size(&self) -> u64 {
-        self.dev.num_blocks() * BLOCK_SIZE as u64
+        self.dev.size()
     }
 
     /// Get the position of the cursor.
@@ -36,6 +186,16 @@ impl Disk {
         self.offset = pos as usize % BLOCK_SIZE;
     }
 
+    /// Returns the underlying block device.
+    pub(crate) fn device(&self) -> &Arc<BlockDevice> {
+        &self.dev
+    }
+
+    /// Write the cached dirty blocks of the underlying device back.
+    pub fn sync(&self) -> AxResult {
+        self.dev.sync()
+    }
+
     /// Read within one block, returns the number of bytes read.
     pub fn read_one(&mut self, buf: &mut [u8]) -> DevResult<usize> {
         let read_size = if self.offset == 0 && buf.len() >= BLOCK_SIZE {
@@ -90,3 +250,75 @@ impl Disk {
         Ok(write_size)
     }
 }
+
+/// The raw node of a block device in devfs, e.g. `/dev/vda`.
+#[cfg(feature = "devfs")]
+pub(crate) struct BlockDeviceNode(Arc<BlockDevice>);
+
+#[cfg(feature = "devfs")]
+impl BlockDeviceNode {
+    pub fn new(dev: Arc<BlockDevice>) -> Self {
+        Self(dev)
+    }
+}
+
+#[cfg(feature = "devfs")]
+impl VfsNodeOps for BlockDeviceNode {
+    axfs_vfs::impl_vfs_non_dir_default! {}
+
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let size = self.0.size();
+        Ok(VfsNodeAttr::new(
+            VfsNodePerm::from_bits_truncate(0o660),
+            VfsNodeType::BlockDevice,
+            size,
+            size / BLOCK_SIZE as u64,
+        ))
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let size = self.0.size();
+        if offset >= size {
+            return Ok(0);
+        }
+        let len = buf.len().min((size - offset) as usize);
+        let mut disk = Disk::new(self.0.clone());
+        disk.set_position(offset);
+        let mut read_len = 0;
+        while read_len < len {
+            read_len += disk
+                .read_one(&mut buf[read_len..len])
+                .map_err(|_| VfsError::Io)?;
+        }
+        Ok(read_len)
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        let size = self.0.size();
+        if offset >= size {
+            return ax_err!(StorageFull);
+        }
+        let len = buf.len().min((size - offset) as usize);
+        let mut disk = Disk::new(self.0.clone());
+        disk.set_position(offset);
+        let mut write_len = 0;
+        while write_len < len {
+            write_len += disk
+                .write_one(&buf[write_len..len])
+                .map_err(|_| VfsError::Io)?;
+        }
+        Ok(write_len)
+    }
+
+    fn fsync(&self) -> VfsResult {
+        self.0.sync()
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+}
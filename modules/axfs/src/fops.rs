@@ -1,10 +1,12 @@
 //! Low-level filesystem operations.
 
+use alloc::string::String;
 use axerrno::{AxError, AxResult, ax_err, ax_err_type};
 use axfs_vfs::{VfsError, VfsNodeRef};
 use axio::SeekFrom;
 use cap_access::{Cap, WithCap};
 use core::fmt;
+use core::time::Duration;
 
 #[cfg(feature = "myfs")]
 pub use crate::dev::Disk;
@@ -20,6 +22,19 @@ pub type FileAttr = axfs_vfs::VfsNodeAttr;
 /// Alias of [`axfs_vfs::VfsNodePerm`].
 pub type FilePerm = axfs_vfs::VfsNodePerm;
 
+/// Timestamps of a file, as durations since the Unix epoch.
+///
+/// They are all zeros if the filesystem does not keep timestamps.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FileTimes {
+    /// Time of last access.
+    pub atime: Duration,
+    /// Time of last modification.
+    pub mtime: Duration,
+    /// Time of last status change.
+    pub ctime: Duration,
+}
+
 /// An opened file object, with open permissions and a cursor.
 pub struct File {
     node: WithCap<VfsNodeRef>,
@@ -44,6 +59,7 @@ pub struct OpenOptions {
     truncate: bool,
     create: bool,
     create_new: bool,
+    nofollow: bool,
     // system-specific
     _custom_flags: i32,
     _mode: u32,
@@ -66,6 +82,7 @@ impl OpenOptions {
             truncate: false,
             create: false,
             create_new: false,
+            nofollow: false,
             // system-specific
             _custom_flags: 0,
             _mode: 0o666,
@@ -95,6 +112,10 @@ impl OpenOptions {
     pub fn create_new(&mut self, create_new: bool) {
         self.create_new = create_new;
     }
+    /// Sets the option to open a symbolic link itself instead of its target.
+    pub fn nofollow(&mut self, nofollow: bool) {
+        self.nofollow = nofollow;
+    }
 
     const fn is_valid(&self) -> bool {
         if !self.read && !self.write && !self.append {
@@ -128,7 +149,12 @@ impl File {
             return ax_err!(InvalidInput);
         }
 
-        let node_option = crate::root::lookup(dir, path);
+        // `create_new` fails on any existing symbolic link, even a dangling one
+        let node_option = if opts.nofollow || opts.create_new {
+            crate::root::lookup_nofollow(dir, path)
+        } else {
+            crate::root::lookup(dir, path)
+        };
         let node = if opts.create || opts.create_new {
             match node_option {
                 Ok(node) => {
@@ -252,6 +278,11 @@ impl File {
     pub fn get_attr(&self) -> AxResult<FileAttr> {
         self.access_node(Cap::empty())?.get_attr()
     }
+
+    /// Gets the file timestamps.
+    pub fn get_times(&self) -> AxResult<FileTimes> {
+        crate::fs::node_times(self.access_node(Cap::empty())?)
+    }
 }
 
 impl Directory {
@@ -331,6 +362,26 @@ impl Directory {
         crate::root::remove_dir(self.access_at(path)?, path)
     }
 
+    /// Creates a symbolic link at the path relative to this directory, which
+    /// points to `target`.
+    pub fn symlink(&self, target: &str, path: &str) -> AxResult {
+        crate::root::symlink(self.access_at(path)?, target, path)
+    }
+
+    /// Returns the target of the symbolic link at the path relative to this
+    /// directory.
+    pub fn read_link(&self, path: &str) -> AxResult<String> {
+        crate::root::read_link(self.access_at(path)?, path)
+    }
+
+    /// Creates a hard link `new` to the file `old`.
+    ///
+    /// They must be absolute paths in the same filesystem, and `old` cannot be
+    /// a directory.
+    pub fn hard_link(&self, old: &str, new: &str) -> AxResult {
+        crate::root::hard_link(self.access_at(new)?, old, new)
+    }
+
     /// Reads directory entries starts from the current position into the
     /// given buffer. Returns the number of entries read.
     ///
@@ -351,6 +402,28 @@ impl Directory {
     pub fn rename(&self, old: &str, new: &str) -> AxResult {
         crate::root::rename(old, new)
     }
+
+    /// Returns the index of the next entry to be read by
+    /// [`read_dir`](Directory::read_dir).
+    pub fn entry_idx(&self) -> usize {
+        self.entry_idx
+    }
+
+    /// Moves the cursor of [`read_dir`](Directory::read_dir) to the entry at
+    /// `idx`. Index 0 is the first entry.
+    pub fn set_entry_idx(&mut self, idx: usize) {
+        self.entry_idx = idx;
+    }
+
+    /// Gets the directory attributes.
+    pub fn get_attr(&self) -> AxResult<FileAttr> {
+        self.access_node(Cap::empty())?.get_attr()
+    }
+
+    /// Gets the directory timestamps.
+    pub fn get_times(&self) -> AxResult<FileTimes> {
+        crate::fs::node_times(self.access_node(Cap::empty())?)
+    }
 }
 
 impl Drop for File {
@@ -386,6 +459,7 @@ impl fmt::Debug for OpenOptions {
         fmt_opt!(truncate, "TRUNC");
         fmt_opt!(create, "CREATE");
         fmt_opt!(create_new, "CREATE_NEW");
+        fmt_opt!(nofollow, "NOFOLLOW");
         Ok(())
     }
 }
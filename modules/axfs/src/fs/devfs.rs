@@ -0,0 +1,316 @@
+//! Device filesystem mounted on `/dev`.
+//!
+//! Its nodes are added when it is created, and can't be created or removed by
+//! path operations. It replaces `axfs_devfs`, whose nodes do not implement
+//! [`VfsNodeOps::as_any`], so the extensions of nodes can be queried on any
+//! node (see [`crate::fs::NodeExt`]).
+
+use alloc::collections::BTreeMap;
+use alloc::sync::{Arc, Weak};
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use axfs_vfs::{VfsDirEntry, VfsError, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeRef};
+use axfs_vfs::{VfsNodeType, VfsOps, VfsResult};
+use axsync::Mutex;
+
+use crate::fs::split_path;
+
+/// A device filesystem that implements [`axfs_vfs::VfsOps`].
+pub struct DeviceFileSystem {
+    /// Parent of the mount point, kept alive for `..` of the root.
+    parent: Mutex<Option<VfsNodeRef>>,
+    root: Arc<DirNode>,
+}
+
+impl DeviceFileSystem {
+    /// Create a new instance.
+    pub fn new() -> Self {
+        Self {
+            parent: Mutex::new(None),
+            root: DirNode::new(None),
+        }
+    }
+
+    /// Create a subdirectory at the root directory.
+    pub fn mkdir(&self, name: &'static str) -> Arc<DirNode> {
+        self.root.mkdir(name)
+    }
+
+    /// Add a node to the root directory.
+    ///
+    /// The node must implement [`VfsNodeOps::as_any`].
+    pub fn add(&self, name: &'static str, node: VfsNodeRef) {
+        self.root.add(name, node);
+    }
+}
+
+impl Default for DeviceFileSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VfsOps for DeviceFileSystem {
+    fn mount(&self, _path: &str, mount_point: VfsNodeRef) -> VfsResult {
+        if let Some(parent) = mount_point.parent() {
+            *self.root.parent.lock() = Arc::downgrade(&parent);
+            *self.parent.lock() = Some(parent);
+        }
+        Ok(())
+    }
+
+    fn root_dir(&self) -> VfsNodeRef {
+        self.root.clone()
+    }
+}
+
+/// The directory node in the device filesystem.
+pub struct DirNode {
+    parent: Mutex<Weak<dyn VfsNodeOps>>,
+    children: Mutex<BTreeMap<&'static str, VfsNodeRef>>,
+}
+
+impl DirNode {
+    fn new(parent: Option<Weak<dyn VfsNodeOps>>) -> Arc<Self> {
+        Arc::new(Self {
+            parent: Mutex::new(parent.unwrap_or_else(|| Weak::<Self>::new() as _)),
+            children: Mutex::new(BTreeMap::new()),
+        })
+    }
+
+    /// Create a subdirectory at this directory.
+    pub fn mkdir(self: &Arc<Self>, name: &'static str) -> Arc<Self> {
+        let parent = Arc::downgrade(self) as Weak<dyn VfsNodeOps>;
+        let node = Self::new(Some(parent));
+        self.children.lock().insert(name, node.clone());
+        node
+    }
+
+    /// Add a node to this directory.
+    ///
+    /// The node must implement [`VfsNodeOps::as_any`].
+    pub fn add(&self, name: &'static str, node: VfsNodeRef) {
+        self.children.lock().insert(name, node);
+    }
+}
+
+impl VfsNodeOps for DirNode {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(VfsNodeAttr::new_dir(4096, 0))
+    }
+
+    fn parent(&self) -> Option<VfsNodeRef> {
+        self.parent.lock().upgrade()
+    }
+
+    fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
+        let (name, rest) = split_path(path);
+        let node = match name {
+            "" | "." => Ok(self.clone() as VfsNodeRef),
+            ".." => self.parent().ok_or(VfsError::NotFound),
+            _ => self
+                .children
+                .lock()
+                .get(name)
+                .cloned()
+                .ok_or(VfsError::NotFound),
+        }?;
+
+        if let Some(rest) = rest {
+            node.lookup(rest)
+        } else {
+            Ok(node)
+        }
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        let children = self.children.lock();
+        let mut children = children.iter().skip(start_idx.max(2) - 2);
+        for (i, ent) in dirents.iter_mut().enumerate() {
+            match i + start_idx {
+                0 => *ent = VfsDirEntry::new(".", VfsNodeType::Dir),
+                1 => *ent = VfsDirEntry::new("..", VfsNodeType::Dir),
+                _ => {
+                    if let Some((name, node)) = children.next() {
+                        *ent = VfsDirEntry::new(name, node.get_attr()?.file_type());
+                    } else {
+                        return Ok(i);
+                    }
+                }
+            }
+        }
+        Ok(dirents.len())
+    }
+
+    fn create(&self, path: &str, ty: VfsNodeType) -> VfsResult {
+        debug!("create {:?} at devfs: {}", ty, path);
+        let (name, rest) = split_path(path);
+        if let Some(rest) = rest {
+            match name {
+                "" | "." => self.create(rest, ty),
+                ".." => self.parent().ok_or(VfsError::NotFound)?.create(rest, ty),
+                _ => {
+                    let subdir = self
+                        .children
+                        .lock()
+                        .get(name)
+                        .ok_or(VfsError::NotFound)?
+                        .clone();
+                    subdir.create(rest, ty)
+                }
+            }
+        } else if name.is_empty() || name == "." || name == ".." {
+            Ok(()) // already exists
+        } else {
+            Err(VfsError::PermissionDenied) // nodes are only added on creation
+        }
+    }
+
+    fn remove(&self, path: &str) -> VfsResult {
+        debug!("remove at devfs: {}", path);
+        let (name, rest) = split_path(path);
+        if let Some(rest) = rest {
+            match name {
+                "" | "." => self.remove(rest),
+                ".." => self.parent().ok_or(VfsError::NotFound)?.remove(rest),
+                _ => {
+                    let subdir = self
+                        .children
+                        .lock()
+                        .get(name)
+                        .ok_or(VfsError::NotFound)?
+                        .clone();
+                    subdir.remove(rest)
+                }
+            }
+        } else {
+            Err(VfsError::PermissionDenied) // nodes are never removed
+        }
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+
+    axfs_vfs::impl_vfs_dir_default! {}
+}
+
+/// Attributes of a character device.
+fn char_device_attr() -> VfsNodeAttr {
+    VfsNodeAttr::new(VfsNodePerm::default_file(), VfsNodeType::CharDevice, 0, 0)
+}
+
+/// A null device, reads nothing and discards all writes (`/dev/null`).
+pub struct NullDev;
+
+impl VfsNodeOps for NullDev {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(char_device_attr())
+    }
+
+    fn read_at(&self, _offset: u64, _buf: &mut [u8]) -> VfsResult<usize> {
+        Ok(0)
+    }
+
+    fn write_at(&self, _offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        Ok(buf.len())
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
+
+/// A zero device, reads zeros and discards all writes (`/dev/zero`).
+pub struct ZeroDev;
+
+impl VfsNodeOps for ZeroDev {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(char_device_attr())
+    }
+
+    fn read_at(&self, _offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        buf.fill(0);
+        Ok(buf.len())
+    }
+
+    fn write_at(&self, _offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        Ok(buf.len())
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
+
+/// A random device, reads pseudo-random bytes and discards all writes
+/// (`/dev/urandom`).
+///
+/// The bytes come from a linear congruential generator, which is not suitable
+/// for cryptography.
+pub struct UrandomDev {
+    seed: AtomicU64,
+}
+
+impl UrandomDev {
+    /// Create a new instance with the given seed.
+    pub const fn new(seed: u64) -> Self {
+        Self {
+            seed: AtomicU64::new(seed),
+        }
+    }
+
+    fn next_u64(&self) -> u64 {
+        let next = |seed: u64| seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+        let prev = self
+            .seed
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |s| Some(next(s)));
+        next(prev.unwrap())
+    }
+}
+
+impl Default for UrandomDev {
+    fn default() -> Self {
+        Self::new(0xa2ce_a2ce)
+    }
+}
+
+impl VfsNodeOps for UrandomDev {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(char_device_attr())
+    }
+
+    fn read_at(&self, _offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        for chunk in buf.chunks_mut(8) {
+            let bytes = self.next_u64().to_ne_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+        Ok(buf.len())
+    }
+
+    fn write_at(&self, _offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        Ok(buf.len())
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
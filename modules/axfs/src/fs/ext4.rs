@@ -0,0 +1,566 @@
+//! The ext4 filesystem, backed by [lwext4](https://github.com/gkostka/lwext4).
+//!
+//! lwext4 keeps its state in globals and always mounts its device at `/`, so
+//! there is at most one ext4 filesystem at a time, and all calls into it are
+//! serialized by a global lock.
+
+use alloc::{ffi::CString, format, string::String, sync::Arc, vec, vec::Vec};
+use core::ffi::{CStr, c_char, c_int};
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+
+use axfs_vfs::{VfsDirEntry, VfsError, VfsNodePerm, VfsResult};
+use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType, VfsOps};
+use axsync::Mutex;
+use lwext4_rust::bindings::{
+    O_CREAT, O_RDONLY, O_RDWR, O_TRUNC, O_WRONLY, SEEK_CUR, SEEK_END, SEEK_SET,
+};
+use lwext4_rust::{Ext4BlockWrapper, Ext4File, InodeTypes, KernelDevOp};
+
+use crate::dev::{BlockDevice, Disk};
+use crate::fops::FileTimes;
+use crate::fs::NodeExt;
+
+const BLOCK_SIZE: usize = 512;
+
+/// Byte offset of `s_magic` in the ext2/3/4 superblock.
+const EXT4_MAGIC_OFFSET: u64 = 1024 + 0x38;
+const EXT4_MAGIC: u16 = 0xEF53;
+
+/// Whether the only lwext4 instance is in use.
+static EXT4_IN_USE: AtomicBool = AtomicBool::new(false);
+/// Serializes all calls into lwext4.
+static EXT4_LOCK: Mutex<()> = Mutex::new(());
+/// The parent of the mount point, which is the parent of the root directory.
+static MOUNT_PARENT: Mutex<Option<VfsNodeRef>> = Mutex::new(None);
+/// The device of the filesystem, lwext4 does not flush it with its cache.
+static DEVICE: Mutex<Option<Arc<BlockDevice>>> = Mutex::new(None);
+
+pub struct Ext4FileSystem {
+    _inner: Ext4BlockWrapper<Disk>,
+}
+
+unsafe impl Sync for Ext4FileSystem {}
+unsafe impl Send for Ext4FileSystem {}
+
+impl Ext4FileSystem {
+    /// Returns whether `disk` contains an ext2/3/4 superblock.
+    ///
+    /// The cursor of `disk` is reset to the beginning.
+    pub fn probe(disk: &mut Disk) -> bool {
+        let mut magic = [0u8; 2];
+        disk.set_position(EXT4_MAGIC_OFFSET);
+        let res = disk.read_one(&mut magic);
+        disk.set_position(0);
+        matches!(res, Ok(2)) && u16::from_le_bytes(magic) == EXT4_MAGIC
+    }
+
+    /// Mounts the ext4 filesystem on `disk`.
+    ///
+    /// Returns [`VfsError::ResourceBusy`] if another ext4 filesystem is in use.
+    pub fn open(disk: Disk) -> VfsResult<Arc<Self>> {
+        if EXT4_IN_USE.swap(true, Ordering::Acquire) {
+            warn!("lwext4 supports only one ext4 filesystem");
+            return Err(VfsError::ResourceBusy);
+        }
+        let _guard = EXT4_LOCK.lock();
+        let dev = disk.device().clone();
+        match Ext4BlockWrapper::<Disk>::new(disk) {
+            Ok(inner) => {
+                *DEVICE.lock() = Some(dev);
+                Ok(Arc::new(Self { _inner: inner }))
+            }
+            Err(err) => {
+                EXT4_IN_USE.store(false, Ordering::Release);
+                Err(as_vfs_err(err))
+            }
+        }
+    }
+}
+
+impl VfsOps for Ext4FileSystem {
+    fn mount(&self, _path: &str, mount_point: VfsNodeRef) -> VfsResult {
+        *MOUNT_PARENT.lock() = mount_point.parent();
+        Ok(())
+    }
+
+    fn umount(&self) -> VfsResult {
+        *MOUNT_PARENT.lock() = None;
+        let _guard = EXT4_LOCK.lock();
+        cache_flush()
+    }
+
+    fn root_dir(&self) -> VfsNodeRef {
+        Ext4Node::new(String::from("/"))
+    }
+}
+
+impl Drop for Ext4FileSystem {
+    fn drop(&mut self) {
+        *DEVICE.lock() = None;
+        EXT4_IN_USE.store(false, Ordering::Release);
+    }
+}
+
+/// A file or directory in the ext4 filesystem, identified by its absolute
+/// path in lwext4.
+pub struct Ext4Node {
+    path: String,
+}
+
+impl Ext4Node {
+    fn new(path: String) -> Arc<Self> {
+        Arc::new(Self { path })
+    }
+
+    /// Returns the absolute path of `rel` relative to this node, with `.` and
+    /// `..` resolved.
+    ///
+    /// If `rel` goes above the root, returns the rest of `rel` starting with
+    /// that `..` as the error.
+    fn join<'a>(&self, rel: &'a str) -> Result<String, &'a str> {
+        let mut comps: Vec<&str> = self.path.split('/').filter(|s| !s.is_empty()).collect();
+        let mut rest = rel;
+        while !rest.is_empty() {
+            let (comp, next) = rest.split_once('/').unwrap_or((rest, ""));
+            match comp {
+                "" | "." => {}
+                ".." => {
+                    if comps.pop().is_none() {
+                        return Err(rest);
+                    }
+                }
+                _ => comps.push(comp),
+            }
+            rest = next;
+        }
+        Ok(format!("/{}", comps.join("/")))
+    }
+
+    fn resolve(&self, rel: &str) -> VfsResult<String> {
+        self.join(rel).map_err(|_| VfsError::InvalidInput)
+    }
+}
+
+impl VfsNodeOps for Ext4Node {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let _guard = EXT4_LOCK.lock();
+        let mut file = Ext4File::new(&self.path, InodeTypes::EXT4_DE_UNKNOWN);
+        let mode = file.file_mode_get().map_err(as_vfs_err)?;
+        let perm = VfsNodePerm::from_bits_truncate((mode & 0o777) as u16);
+        let ty = mode_to_type(mode);
+        let size = match ty {
+            VfsNodeType::File => file_size(&mut file, &self.path)?,
+            VfsNodeType::SymLink => read_symlink(&self.path)?.len() as u64,
+            _ => BLOCK_SIZE as u64,
+        };
+        let blocks = size.div_ceil(BLOCK_SIZE as u64);
+        Ok(VfsNodeAttr::new(perm, ty, size, blocks))
+    }
+
+    fn fsync(&self) -> VfsResult {
+        let _guard = EXT4_LOCK.lock();
+        cache_flush()
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let _guard = EXT4_LOCK.lock();
+        if node_type(&self.path)? == VfsNodeType::SymLink {
+            // the target of a fast symlink is kept in the inode, not in blocks
+            let target = read_symlink(&self.path)?;
+            let start = target.len().min(offset as usize);
+            let end = target.len().min(start + buf.len());
+            buf[..end - start].copy_from_slice(&target[start..end]);
+            return Ok(end - start);
+        }
+        let mut file = Ext4File::new(&self.path, InodeTypes::EXT4_DE_REG_FILE);
+        file.file_open(&self.path, O_RDONLY).map_err(as_vfs_err)?;
+        let res = (|| {
+            if offset >= file.file_size() {
+                return Ok(0);
+            }
+            file.file_seek(offset as i64, SEEK_SET)?;
+            file.file_read(buf)
+        })();
+        let _ = file.file_close();
+        res.map_err(as_vfs_err)
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        let _guard = EXT4_LOCK.lock();
+        if node_type(&self.path)? == VfsNodeType::SymLink {
+            // the target is set once as a whole, by recreating the empty link
+            if offset != 0 || !read_symlink(&self.path)?.is_empty() {
+                return Err(VfsError::PermissionDenied);
+            }
+            let target = CString::new(buf).map_err(|_| VfsError::InvalidInput)?;
+            let mut file = Ext4File::new(&self.path, InodeTypes::EXT4_DE_SYMLINK);
+            file.file_remove(&self.path).map_err(as_vfs_err)?;
+            create_symlink(&target, &self.path)?;
+            return Ok(buf.len());
+        }
+        let mut file = Ext4File::new(&self.path, InodeTypes::EXT4_DE_REG_FILE);
+        file.file_open(&self.path, O_RDWR).map_err(as_vfs_err)?;
+        let res = (|| {
+            // lwext4 does not seek beyond the end, fill the hole with zeros
+            let size = file.file_size();
+            if offset > size {
+                file.file_seek(0, SEEK_END)?;
+                write_zeros(&mut file, offset - size)?;
+            }
+            file.file_seek(offset as i64, SEEK_SET)?;
+            file.file_write(buf)
+        })();
+        let _ = file.file_close();
+        res.map_err(as_vfs_err)
+    }
+
+    fn truncate(&self, size: u64) -> VfsResult {
+        let _guard = EXT4_LOCK.lock();
+        let mut file = Ext4File::new(&self.path, InodeTypes::EXT4_DE_REG_FILE);
+        file.file_open(&self.path, O_RDWR).map_err(as_vfs_err)?;
+        let res = (|| {
+            let current_size = file.file_size();
+            if size <= current_size {
+                file.file_truncate(size)
+            } else {
+                // lwext4 can only shrink files
+                file.file_seek(0, SEEK_END)?;
+                write_zeros(&mut file, size - current_size)
+            }
+        })();
+        let _ = file.file_close();
+        res.map(|_| ()).map_err(as_vfs_err)
+    }
+
+    fn parent(&self) -> Option<VfsNodeRef> {
+        match self.join("..") {
+            Ok(path) => Some(Ext4Node::new(path)),
+            Err(_) => MOUNT_PARENT.lock().clone(),
+        }
+    }
+
+    fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
+        debug!("lookup at ext4: {}", path);
+        let path = match self.join(path) {
+            Ok(path) => path,
+            Err(rest) => {
+                // go above the root, continue in the parent filesystem
+                let parent = MOUNT_PARENT.lock().clone().ok_or(VfsError::NotFound)?;
+                let rest = rest.strip_prefix("..").unwrap_or(rest);
+                return parent.lookup(rest.trim_start_matches('/'));
+            }
+        };
+        let _guard = EXT4_LOCK.lock();
+        node_type(&path)?;
+        Ok(Ext4Node::new(path))
+    }
+
+    fn create(&self, path: &str, ty: VfsNodeType) -> VfsResult {
+        debug!("create {:?} at ext4: {}", ty, path);
+        let path = self.resolve(path)?;
+        if path == "/" {
+            return Ok(());
+        }
+        let _guard = EXT4_LOCK.lock();
+        let (parent, _) = path.rsplit_once('/').unwrap();
+        if !parent.is_empty() && node_type(parent)? != VfsNodeType::Dir {
+            return Err(VfsError::NotADirectory);
+        }
+        if node_type(&path).is_ok() {
+            return Err(VfsError::AlreadyExists);
+        }
+
+        match ty {
+            VfsNodeType::File => {
+                let mut file = Ext4File::new(&path, InodeTypes::EXT4_DE_REG_FILE);
+                file.file_open(&path, O_WRONLY | O_CREAT | O_TRUNC)
+                    .map_err(as_vfs_err)?;
+                file.file_close().map_err(as_vfs_err)?;
+                Ok(())
+            }
+            VfsNodeType::Dir => {
+                let mut dir = Ext4File::new(&path, InodeTypes::EXT4_DE_DIR);
+                dir.dir_mk(&path).map_err(as_vfs_err)?;
+                Ok(())
+            }
+            // the target is set by the following `write_at`
+            VfsNodeType::SymLink => create_symlink(c"", &path),
+            _ => Err(VfsError::Unsupported),
+        }
+    }
+
+    fn remove(&self, path: &str) -> VfsResult {
+        debug!("remove at ext4: {}", path);
+        let path = self.resolve(path)?;
+        assert!(path != "/"); // already check at `root.rs`
+        let _guard = EXT4_LOCK.lock();
+        if node_type(&path)? == VfsNodeType::Dir {
+            let mut dir = Ext4File::new(&path, InodeTypes::EXT4_DE_DIR);
+            // `ext4_dir_rm` removes the directory recursively
+            let (names, _) = dir.lwext4_dir_entries().map_err(as_vfs_err)?;
+            if names
+                .iter()
+                .any(|name| !matches!(entry_name(name), "." | ".."))
+            {
+                return Err(VfsError::DirectoryNotEmpty);
+            }
+            dir.dir_rm(&path).map_err(as_vfs_err)?;
+        } else {
+            let mut file = Ext4File::new(&path, InodeTypes::EXT4_DE_REG_FILE);
+            file.file_remove(&path).map_err(as_vfs_err)?;
+        }
+        Ok(())
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        let _guard = EXT4_LOCK.lock();
+        if node_type(&self.path)? != VfsNodeType::Dir {
+            return Err(VfsError::NotADirectory);
+        }
+        let dir = Ext4File::new(&self.path, InodeTypes::EXT4_DE_DIR);
+        let (names, types) = dir.lwext4_dir_entries().map_err(as_vfs_err)?;
+        let mut iter = names.iter().zip(types).skip(start_idx);
+        for (i, out_entry) in dirents.iter_mut().enumerate() {
+            match iter.next() {
+                Some((name, ty)) => {
+                    *out_entry = VfsDirEntry::new(entry_name(name), dirent_type(ty));
+                }
+                None => return Ok(i),
+            }
+        }
+        Ok(dirents.len())
+    }
+
+    fn rename(&self, src_path: &str, dst_path: &str) -> VfsResult {
+        // `src_path` and `dst_path` should in the same mounted fs
+        debug!(
+            "rename at ext4, src_path: {}, dst_path: {}",
+            src_path, dst_path
+        );
+        let src_path = self.resolve(src_path)?;
+        let dst_path = self.resolve(dst_path)?;
+        let _guard = EXT4_LOCK.lock();
+        let mut file = Ext4File::new(&src_path, InodeTypes::EXT4_DE_UNKNOWN);
+        file.file_rename(&src_path, &dst_path).map_err(as_vfs_err)?;
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+}
+
+impl NodeExt for Ext4Node {
+    fn times(&self) -> VfsResult<FileTimes> {
+        use lwext4_rust::bindings::{ext4_atime_get, ext4_ctime_get, ext4_mtime_get};
+
+        let path = CString::new(self.path.as_str()).map_err(|_| VfsError::InvalidInput)?;
+        let get = |f: unsafe extern "C" fn(*const c_char, *mut u32) -> c_int| {
+            let mut secs = 0;
+            match unsafe { f(path.as_ptr(), &mut secs) } {
+                0 => Ok(Duration::from_secs(secs as u64)),
+                err => Err(as_vfs_err(err)),
+            }
+        };
+        let _guard = EXT4_LOCK.lock();
+        Ok(FileTimes {
+            atime: get(ext4_atime_get)?,
+            mtime: get(ext4_mtime_get)?,
+            ctime: get(ext4_ctime_get)?,
+        })
+    }
+
+    fn link(&self, path: &str, node: &VfsNodeRef) -> VfsResult {
+        let node = node
+            .as_any()
+            .downcast_ref::<Ext4Node>()
+            .ok_or(VfsError::InvalidInput)?;
+        let path = self.resolve(path)?;
+        let old_path = CString::new(node.path.as_str()).map_err(|_| VfsError::InvalidInput)?;
+        let new_path = CString::new(path).map_err(|_| VfsError::InvalidInput)?;
+        let _guard = EXT4_LOCK.lock();
+        match unsafe { lwext4_rust::bindings::ext4_flink(old_path.as_ptr(), new_path.as_ptr()) } {
+            0 => Ok(()),
+            err => Err(as_vfs_err(err)),
+        }
+    }
+}
+
+impl KernelDevOp for Disk {
+    type DevType = Disk;
+
+    fn read(dev: &mut Disk, mut buf: &mut [u8]) -> Result<usize, i32> {
+        let mut read_len = 0;
+        while !buf.is_empty() {
+            match dev.read_one(buf) {
+                Ok(0) => break,
+                Ok(n) => {
+                    let tmp = buf;
+                    buf = &mut tmp[n..];
+                    read_len += n;
+                }
+                Err(_) => return Err(EIO),
+            }
+        }
+        Ok(read_len)
+    }
+
+    fn write(dev: &mut Disk, mut buf: &[u8]) -> Result<usize, i32> {
+        let mut write_len = 0;
+        while !buf.is_empty() {
+            match dev.write_one(buf) {
+                Ok(0) => break,
+                Ok(n) => {
+                    buf = &buf[n..];
+                    write_len += n;
+                }
+                Err(_) => return Err(EIO),
+            }
+        }
+        Ok(write_len)
+    }
+
+    fn flush(dev: &mut Disk) -> Result<usize, i32> {
+        dev.sync().map_err(|_| EIO)?;
+        Ok(0)
+    }
+
+    fn seek(dev: &mut Disk, off: i64, whence: i32) -> Result<i64, i32> {
+        let size = dev.size();
+        let new_pos = match whence as u32 {
+            SEEK_SET => u64::try_from(off).ok(),
+            SEEK_CUR => dev.position().checked_add_signed(off),
+            SEEK_END => size.checked_add_signed(off),
+            _ => None,
+        }
+        .ok_or(EINVAL)?;
+        if new_pos > size {
+            warn!("Seek beyond the end of the block device");
+        }
+        dev.set_position(new_pos);
+        Ok(new_pos as i64)
+    }
+}
+
+const EIO: i32 = 5;
+const EINVAL: i32 = 22;
+
+/// Writes the lwext4 cache, then the block cache back to the device.
+fn cache_flush() -> VfsResult {
+    match unsafe { lwext4_rust::bindings::ext4_cache_flush(c"/".as_ptr()) } {
+        0 => {}
+        err => return Err(as_vfs_err(err)),
+    }
+    match DEVICE.lock().as_ref() {
+        Some(dev) => dev.sync(),
+        None => Ok(()),
+    }
+}
+
+/// Returns the type of the node at `path`, the caller must hold `EXT4_LOCK`.
+fn node_type(path: &str) -> VfsResult<VfsNodeType> {
+    let mut file = Ext4File::new(path, InodeTypes::EXT4_DE_UNKNOWN);
+    let mode = file.file_mode_get().map_err(as_vfs_err)?;
+    Ok(mode_to_type(mode))
+}
+
+/// Creates a symbolic link at `path` to `target`, the caller must hold
+/// `EXT4_LOCK`.
+fn create_symlink(target: &CStr, path: &str) -> VfsResult {
+    let path = CString::new(path).map_err(|_| VfsError::InvalidInput)?;
+    match unsafe { lwext4_rust::bindings::ext4_fsymlink(target.as_ptr(), path.as_ptr()) } {
+        0 => Ok(()),
+        err => Err(as_vfs_err(err)),
+    }
+}
+
+/// Returns the target of the symbolic link at `path`, the caller must hold
+/// `EXT4_LOCK`.
+fn read_symlink(path: &str) -> VfsResult<Vec<u8>> {
+    let path = CString::new(path).map_err(|_| VfsError::InvalidInput)?;
+    // lwext4 keeps targets in at most one block
+    let mut buf = vec![0u8; 4096];
+    let mut len = 0;
+    let ret = unsafe {
+        lwext4_rust::bindings::ext4_readlink(
+            path.as_ptr(),
+            buf.as_mut_ptr() as *mut c_char,
+            buf.len(),
+            &mut len,
+        )
+    };
+    if ret != 0 {
+        return Err(as_vfs_err(ret));
+    }
+    buf.truncate(len);
+    Ok(buf)
+}
+
+fn file_size(file: &mut Ext4File, path: &str) -> VfsResult<u64> {
+    file.file_open(path, O_RDONLY).map_err(as_vfs_err)?;
+    let size = file.file_size();
+    let _ = file.file_close();
+    Ok(size)
+}
+
+/// Appends `len` zero bytes at the cursor of `file`.
+fn write_zeros(file: &mut Ext4File, mut len: u64) -> Result<usize, i32> {
+    let zeros = [0u8; 4096];
+    while len > 0 {
+        let to_write = core::cmp::min(len, zeros.len() as u64);
+        let n = file.file_write(&zeros[..to_write as usize])?;
+        if n == 0 {
+            return Err(28); // ENOSPC
+        }
+        len -= n as u64;
+    }
+    Ok(0)
+}
+
+/// Directory entry names from lwext4 may be NUL-terminated.
+fn entry_name(name: &[u8]) -> &str {
+    let len = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+    core::str::from_utf8(&name[..len]).unwrap_or("")
+}
+
+const fn mode_to_type(mode: u32) -> VfsNodeType {
+    match mode & 0o170000 {
+        0o010000 => VfsNodeType::Fifo,
+        0o020000 => VfsNodeType::CharDevice,
+        0o040000 => VfsNodeType::Dir,
+        0o060000 => VfsNodeType::BlockDevice,
+        0o120000 => VfsNodeType::SymLink,
+        0o140000 => VfsNodeType::Socket,
+        _ => VfsNodeType::File,
+    }
+}
+
+fn dirent_type(ty: InodeTypes) -> VfsNodeType {
+    match ty {
+        InodeTypes::EXT4_DE_DIR => VfsNodeType::Dir,
+        InodeTypes::EXT4_DE_CHRDEV => VfsNodeType::CharDevice,
+        InodeTypes::EXT4_DE_BLKDEV => VfsNodeType::BlockDevice,
+        InodeTypes::EXT4_DE_FIFO => VfsNodeType::Fifo,
+        InodeTypes::EXT4_DE_SOCK => VfsNodeType::Socket,
+        InodeTypes::EXT4_DE_SYMLINK => VfsNodeType::SymLink,
+        _ => VfsNodeType::File,
+    }
+}
+
+const fn as_vfs_err(errno: i32) -> VfsError {
+    match errno {
+        1 | 13 | 30 => VfsError::PermissionDenied, // EPERM, EACCES, EROFS
+        2 => VfsError::NotFound,                   // ENOENT
+        12 => VfsError::NoMemory,                  // ENOMEM
+        16 => VfsError::ResourceBusy,              // EBUSY
+        17 => VfsError::AlreadyExists,             // EEXIST
+        20 => VfsError::NotADirectory,             // ENOTDIR
+        21 => VfsError::IsADirectory,              // EISDIR
+        22 => VfsError::InvalidInput,              // EINVAL
+        27 | 28 => VfsError::StorageFull,          // EFBIG, ENOSPC
+        39 => VfsError::DirectoryNotEmpty,         // ENOTEMPTY
+        95 => VfsError::Unsupported,               // ENOTSUP
+        _ => VfsError::Io,
+    }
+}
@@ -1,22 +1,42 @@
 use alloc::sync::Arc;
 use core::cell::UnsafeCell;
+use core::time::Duration;
 
 use axfs_vfs::{VfsDirEntry, VfsError, VfsNodePerm, VfsResult};
 use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType, VfsOps};
 use axsync::Mutex;
-use fatfs::{Dir, File, LossyOemCpConverter, NullTimeProvider, Read, Seek, SeekFrom, Write};
+use fatfs::{Date, DateTime, Dir, DirEntry, File, LossyOemCpConverter, Read, Seek, SeekFrom};
+use fatfs::{TimeProvider as _, Write};
 
 use crate::dev::Disk;
+use crate::fops::FileTimes;
+use crate::fs::NodeExt;
 
 const BLOCK_SIZE: usize = 512;
 
+#[cfg(feature = "rtc")]
+type TimeProvider = RtcTimeProvider;
+#[cfg(not(feature = "rtc"))]
+type TimeProvider = fatfs::NullTimeProvider;
+
 pub struct FatFileSystem {
-    inner: fatfs::FileSystem<Disk, NullTimeProvider, LossyOemCpConverter>,
+    inner: fatfs::FileSystem<Disk, TimeProvider, LossyOemCpConverter>,
     root_dir: UnsafeCell<Option<VfsNodeRef>>,
 }
 
-pub struct FileWrapper<'a>(Mutex<File<'a, Disk, NullTimeProvider, LossyOemCpConverter>>);
-pub struct DirWrapper<'a>(Dir<'a, Disk, NullTimeProvider, LossyOemCpConverter>);
+/// A file, with the timestamps of its directory entry.
+///
+/// The timestamps are read when the file is looked up, and the modification
+/// time is updated by writes as fatfs does to the entry.
+pub struct FileWrapper<'a>(
+    Mutex<File<'a, Disk, TimeProvider, LossyOemCpConverter>>,
+    Mutex<FileTimes>,
+);
+/// A directory, with the timestamps of its directory entry.
+///
+/// The root directory and directories opened by `..` have no entry, and their
+/// timestamps are zeros.
+pub struct DirWrapper<'a>(Dir<'a, Disk, TimeProvider, LossyOemCpConverter>, FileTimes);
 
 unsafe impl Sync for FatFileSystem {}
 unsafe impl Send for FatFileSystem {}
@@ -26,39 +46,55 @@ unsafe impl Send for DirWrapper<'_> {}
 unsafe impl Sync for DirWrapper<'_> {}
 
 impl FatFileSystem {
-    #[cfg(feature = "use-ramdisk")]
-    pub fn new(mut disk: Disk) -> Self {
-        let opts = fatfs::FormatVolumeOptions::new();
-        fatfs::format_volume(&mut disk, opts).expect("failed to format volume");
-        let inner = fatfs::FileSystem::new(disk, fatfs::FsOptions::new())
-            .expect("failed to initialize FAT filesystem");
-        Self {
+    /// Creates a FAT filesystem on `disk` and initializes it.
+    ///
+    /// The returned filesystem is never dropped, since its nodes borrow the
+    /// inner [`fatfs::FileSystem`] for `'static`.
+    pub fn open(disk: Disk) -> VfsResult<Arc<Self>> {
+        let opts = fatfs::FsOptions::new().time_provider(TimeProvider::new());
+        let inner = fatfs::FileSystem::new(disk, opts).map_err(as_vfs_err)?;
+        let fs = Arc::new(Self {
             inner,
             root_dir: UnsafeCell::new(None),
-        }
+        });
+        // Safety: the leaked strong reference keeps the filesystem alive.
+        let fs_ref: &'static Self = unsafe { &*Arc::into_raw(fs.clone()) };
+        fs_ref.init();
+        Ok(fs)
     }
 
-    #[cfg(not(feature = "use-ramdisk"))]
-    pub fn new(disk: Disk) -> Self {
-        let inner = fatfs::FileSystem::new(disk, fatfs::FsOptions::new())
-            .expect("failed to initialize FAT filesystem");
-        Self {
-            inner,
-            root_dir: UnsafeCell::new(None),
-        }
+    /// Formats `disk` as a new FAT volume.
+    #[cfg(feature = "use-ramdisk")]
+    pub fn format(disk: &mut Disk) -> VfsResult {
+        let opts = fatfs::FormatVolumeOptions::new();
+        fatfs::format_volume(disk, opts).map_err(as_vfs_err)
     }
 
-    pub fn init(&'static self) {
+    fn init(&'static self) {
         // must be called before later operations
-        unsafe { *self.root_dir.get() = Some(Self::new_dir(self.inner.root_dir())) }
+        let root_dir = DirWrapper(self.inner.root_dir(), FileTimes::default());
+        unsafe { *self.root_dir.get() = Some(Arc::new(root_dir)) }
     }
+}
 
-    fn new_file(file: File<'_, Disk, NullTimeProvider, LossyOemCpConverter>) -> Arc<FileWrapper> {
-        Arc::new(FileWrapper(Mutex::new(file)))
+impl NodeExt for FileWrapper<'static> {
+    fn times(&self) -> VfsResult<FileTimes> {
+        Ok(*self.1.lock())
     }
+}
 
-    fn new_dir(dir: Dir<'_, Disk, NullTimeProvider, LossyOemCpConverter>) -> Arc<DirWrapper> {
-        Arc::new(DirWrapper(dir))
+impl NodeExt for DirWrapper<'static> {
+    fn times(&self) -> VfsResult<FileTimes> {
+        Ok(self.1)
+    }
+}
+
+impl FileWrapper<'_> {
+    /// Updates the modification time after a write.
+    fn touch(&self) {
+        let mut times = self.1.lock();
+        times.mtime = modified_now();
+        times.ctime = times.mtime;
     }
 }
 
@@ -73,6 +109,10 @@ impl VfsNodeOps for FileWrapper<'static> {
         Ok(VfsNodeAttr::new(perm, VfsNodeType::File, size, blocks))
     }
 
+    fn fsync(&self) -> VfsResult {
+        self.0.lock().flush().map_err(as_vfs_err)
+    }
+
     fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
         let mut file = self.0.lock();
         file.seek(SeekFrom::Start(offset)).map_err(as_vfs_err)?; // TODO: more efficient
@@ -82,7 +122,9 @@ impl VfsNodeOps for FileWrapper<'static> {
     fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
         let mut file = self.0.lock();
         file.seek(SeekFrom::Start(offset)).map_err(as_vfs_err)?; // TODO: more efficient
-        file.write(buf).map_err(as_vfs_err)
+        let len = file.write(buf).map_err(as_vfs_err)?;
+        self.touch();
+        Ok(len)
     }
 
     fn truncate(&self, size: u64) -> VfsResult {
@@ -105,9 +147,50 @@ impl VfsNodeOps for FileWrapper<'static> {
                 file.write(write_buf).map_err(as_vfs_err)?;
                 zeros_needed -= to_write;
             }
+            self.touch();
             Ok(())
         }
     }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+}
+
+impl DirWrapper<'static> {
+    /// Finds the entry of `path` relative to this directory, and returns the
+    /// node of it.
+    fn find_entry(&self, path: &str) -> VfsResult<VfsNodeRef> {
+        let (parent, name) = match path.rsplit_once('/') {
+            Some((parent, name)) => (self.0.open_dir(parent).map_err(as_vfs_err)?, name),
+            None => (self.0.clone(), path),
+        };
+        if matches!(name, "" | "." | "..") {
+            // the root directory has no `..` entry, leave it to fatfs
+            let dir = self.0.open_dir(path).map_err(as_vfs_err)?;
+            return Ok(Arc::new(DirWrapper(dir, FileTimes::default())));
+        }
+
+        // TODO: use `fatfs::Dir::find_entry`, but it's not public.
+        for entry in parent.iter() {
+            let entry = entry.map_err(as_vfs_err)?;
+            if !entry.file_name().eq_ignore_ascii_case(name)
+                && !entry.short_file_name().eq_ignore_ascii_case(name)
+            {
+                continue;
+            }
+            let times = entry_times(&entry);
+            return if entry.is_dir() {
+                Ok(Arc::new(DirWrapper(entry.to_dir(), times)))
+            } else {
+                Ok(Arc::new(FileWrapper(
+                    Mutex::new(entry.to_file()),
+                    Mutex::new(times),
+                )))
+            };
+        }
+        Err(VfsError::NotFound)
+    }
 }
 
 impl VfsNodeOps for DirWrapper<'static> {
@@ -124,9 +207,8 @@ impl VfsNodeOps for DirWrapper<'static> {
     }
 
     fn parent(&self) -> Option<VfsNodeRef> {
-        self.0
-            .open_dir("..")
-            .map_or(None, |dir| Some(FatFileSystem::new_dir(dir)))
+        let dir = self.0.open_dir("..").ok()?;
+        Some(Arc::new(DirWrapper(dir, FileTimes::default())))
     }
 
     fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
@@ -138,15 +220,7 @@ impl VfsNodeOps for DirWrapper<'static> {
         if let Some(rest) = path.strip_prefix("./") {
             return self.lookup(rest);
         }
-
-        // TODO: use `fatfs::Dir::find_entry`, but it's not public.
-        if let Ok(file) = self.0.open_file(path) {
-            Ok(FatFileSystem::new_file(file))
-        } else if let Ok(dir) = self.0.open_dir(path) {
-            Ok(FatFileSystem::new_dir(dir))
-        } else {
-            Err(VfsError::NotFound)
-        }
+        self.find_entry(path)
     }
 
     fn create(&self, path: &str, ty: VfsNodeType) -> VfsResult {
@@ -214,6 +288,10 @@ impl VfsNodeOps for DirWrapper<'static> {
             .rename(src_path, &self.0, dst_path)
             .map_err(as_vfs_err)
     }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
 }
 
 impl VfsOps for FatFileSystem {
@@ -261,7 +339,7 @@ impl Write for Disk {
         Ok(write_len)
     }
     fn flush(&mut self) -> Result<(), Self::Error> {
-        Ok(())
+        self.sync().map_err(|_| ())
     }
 }
 
@@ -299,3 +377,133 @@ const fn as_vfs_err(err: fatfs::Error<()>) -> VfsError {
         _ => VfsError::Io,
     }
 }
+
+/// Returns the number of days since the Unix epoch of a date.
+const fn days_from_civil(year: u16, month: u16, day: u16) -> u64 {
+    // entries never written may have zero month and day
+    let (month, day) = (
+        if month < 1 { 1 } else { month },
+        if day < 1 { 1 } else { day },
+    );
+    let y = if month <= 2 { year - 1 } else { year } as u64;
+    let (era, yoe) = (y / 400, y % 400);
+    let doy = (153 * ((month as u64 + 9) % 12) + 2) / 5 + day as u64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+const fn date_to_duration(date: Date) -> Duration {
+    Duration::from_secs(days_from_civil(date.year, date.month, date.day) * 86400)
+}
+
+const fn datetime_to_duration(dt: DateTime) -> Duration {
+    let t = dt.time;
+    let secs = t.hour as u64 * 3600 + t.min as u64 * 60 + t.sec as u64;
+    let date = date_to_duration(dt.date);
+    Duration::new(date.as_secs() + secs, t.millis as u32 * 1_000_000)
+}
+
+/// Returns the timestamps of a directory entry.
+fn entry_times(entry: &DirEntry<'_, Disk, TimeProvider, LossyOemCpConverter>) -> FileTimes {
+    // FAT has no status change time, use the modification time
+    let mtime = datetime_to_duration(entry.modified());
+    FileTimes {
+        atime: date_to_duration(entry.accessed()),
+        mtime,
+        ctime: mtime,
+    }
+}
+
+/// Returns the modification time that fatfs stamps on a file written now,
+/// which has a resolution of two seconds.
+fn modified_now() -> Duration {
+    let now = TimeProvider::new().get_current_date_time();
+    let time = fatfs::Time::new(now.time.hour, now.time.min, now.time.sec & !1, 0);
+    datetime_to_duration(DateTime::new(now.date, time))
+}
+
+/// A [`fatfs::TimeProvider`] that stamps files with the wall time from RTC.
+///
+/// FAT timestamps have no timezone, the wall time is used as is (UTC).
+#[cfg(feature = "rtc")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RtcTimeProvider;
+
+#[cfg(feature = "rtc")]
+impl RtcTimeProvider {
+    const fn new() -> Self {
+        Self
+    }
+}
+
+#[cfg(feature = "rtc")]
+impl fatfs::TimeProvider for RtcTimeProvider {
+    fn get_current_date(&self) -> Date {
+        self.get_current_date_time().date
+    }
+
+    fn get_current_date_time(&self) -> DateTime {
+        duration_to_datetime(axhal::time::wall_time())
+    }
+}
+
+/// Converts a duration since the Unix epoch to a FAT date and time, clamped
+/// to the range of FAT dates.
+#[cfg(any(feature = "rtc", test))]
+fn duration_to_datetime(time: Duration) -> DateTime {
+    // FAT dates range from 1980-01-01 to 2107-12-31
+    const MIN_SECS: u64 = days_from_civil(1980, 1, 1) * 86400;
+    const MAX_SECS: u64 = days_from_civil(2108, 1, 1) * 86400 - 1;
+    let secs = time.as_secs().clamp(MIN_SECS, MAX_SECS);
+    let millis = time.subsec_millis() as u16;
+
+    // civil date from days, see `days_from_civil`
+    let z = secs / 86400 + 719468;
+    let (era, doe) = (z / 146097, z % 146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u16;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u16;
+    let year = (yoe + era * 400) as u16 + (month <= 2) as u16;
+
+    let secs_of_day = secs % 86400;
+    let hour = (secs_of_day / 3600) as u16;
+    let min = (secs_of_day / 60 % 60) as u16;
+    let sec = (secs_of_day % 60) as u16;
+    DateTime::new(
+        Date::new(year, month, day),
+        fatfs::Time::new(hour, min, sec, millis),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(dt: DateTime) -> (u16, u16, u16, u16, u16, u16, u16) {
+        let (d, t) = (dt.date, dt.time);
+        (d.year, d.month, d.day, t.hour, t.min, t.sec, t.millis)
+    }
+
+    #[test]
+    fn test_duration_to_datetime() {
+        // 2024-02-29 12:34:56.789
+        let time = Duration::new(1709210096, 789_000_000);
+        let dt = duration_to_datetime(time);
+        assert_eq!(fields(dt), (2024, 2, 29, 12, 34, 56, 789));
+        assert_eq!(datetime_to_duration(dt), time);
+
+        // 1999-12-31 23:59:59, 2000-01-01 00:00:00
+        let dt = duration_to_datetime(Duration::from_secs(946684799));
+        assert_eq!(fields(dt), (1999, 12, 31, 23, 59, 59, 0));
+        let dt = duration_to_datetime(Duration::from_secs(946684800));
+        assert_eq!(fields(dt), (2000, 1, 1, 0, 0, 0, 0));
+
+        // clamped to 1980-01-01 and 2107-12-31
+        let dt = duration_to_datetime(Duration::ZERO);
+        assert_eq!(fields(dt), (1980, 1, 1, 0, 0, 0, 0));
+        let dt = duration_to_datetime(Duration::from_secs(u64::MAX));
+        assert_eq!(fields(dt), (2107, 12, 31, 23, 59, 59, 0));
+    }
+}
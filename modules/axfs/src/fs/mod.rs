@@ -1,13 +1,82 @@
-cfg_if::cfg_if! {
-    if #[cfg(feature = "myfs")] {
-        pub mod myfs;
-    } else if #[cfg(feature = "fatfs")] {
-        pub mod fatfs;
-    }
-}
+use axfs_vfs::{VfsError, VfsNodeRef, VfsResult};
+
+use crate::fops::FileTimes;
+
+#[cfg(feature = "myfs")]
+pub mod myfs;
+
+#[cfg(feature = "fatfs")]
+pub mod fatfs;
+
+#[cfg(feature = "ext4")]
+pub mod ext4;
 
 #[cfg(feature = "devfs")]
-pub use axfs_devfs as devfs;
+pub mod devfs;
 
 #[cfg(feature = "ramfs")]
-pub use axfs_ramfs as ramfs;
+pub mod ramfs;
+
+#[cfg(any(feature = "procfs", feature = "sysfs"))]
+pub(crate) mod pseudofs;
+
+#[cfg(feature = "procfs")]
+pub mod procfs;
+
+#[cfg(feature = "sysfs")]
+pub mod sysfs;
+
+/// Node operations that [`axfs_vfs::VfsNodeOps`] does not have.
+pub(crate) trait NodeExt: Send + Sync {
+    /// Returns the timestamps of the node, zeros if they are not kept.
+    fn times(&self) -> VfsResult<FileTimes> {
+        Ok(FileTimes::default())
+    }
+
+    /// Creates a hard link at `path` (relative to this directory) to `node`,
+    /// which must be in the same filesystem.
+    fn link(&self, _path: &str, _node: &VfsNodeRef) -> VfsResult {
+        Err(VfsError::PermissionDenied)
+    }
+}
+
+/// Returns the extensions of `node`, if its filesystem has any.
+///
+/// All nodes of this crate implement [`axfs_vfs::VfsNodeOps::as_any`], and so
+/// must the nodes of the filesystems from users, see [`crate::api::mount`].
+pub(crate) fn node_ext(node: &VfsNodeRef) -> Option<&dyn NodeExt> {
+    let any = node.as_any();
+    #[cfg(feature = "fatfs")]
+    if let Some(node) = any.downcast_ref::<fatfs::FileWrapper<'static>>() {
+        return Some(node);
+    }
+    #[cfg(feature = "fatfs")]
+    if let Some(node) = any.downcast_ref::<fatfs::DirWrapper<'static>>() {
+        return Some(node);
+    }
+    #[cfg(feature = "ext4")]
+    if let Some(node) = any.downcast_ref::<ext4::Ext4Node>() {
+        return Some(node);
+    }
+    #[cfg(feature = "ramfs")]
+    if let Some(node) = any.downcast_ref::<ramfs::DirNode>() {
+        return Some(node);
+    }
+    let _ = any;
+    None
+}
+
+/// Returns the timestamps of `node`, or zeros if the filesystem does not keep
+/// them.
+pub(crate) fn node_times(node: &VfsNodeRef) -> VfsResult<FileTimes> {
+    node_ext(node).map_or(Ok(FileTimes::default()), |node| node.times())
+}
+
+/// Splits `path` into the first component and the rest.
+#[cfg(any(feature = "devfs", feature = "ramfs"))]
+fn split_path(path: &str) -> (&str, Option<&str>) {
+    let trimmed_path = path.trim_start_matches('/');
+    trimmed_path.find('/').map_or((trimmed_path, None), |n| {
+        (&trimmed_path[..n], Some(&trimmed_path[n + 1..]))
+    })
+}
@@ -7,6 +7,9 @@ use axfs_vfs::VfsOps;
 pub trait MyFileSystemIf {
     /// Creates a new instance of the filesystem with initialization.
     ///
+    /// The nodes of the filesystem must implement
+    /// [`VfsNodeOps::as_any`](axfs_vfs::VfsNodeOps::as_any).
+    ///
     /// TODO: use generic disk type
     fn new_myfs(disk: Disk) -> Arc<dyn VfsOps>;
 }
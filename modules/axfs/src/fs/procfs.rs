@@ -0,0 +1,149 @@
+//! Process information filesystem mounted on `/proc`.
+//!
+//! Files in procfs store nothing, their content is generated from the live
+//! system state when they are read. Generators are registered by [`add_file`]
+//! and [`add_task_file`], and directories are implied by the paths of the
+//! files. Modules that know about the system state (e.g. the runtime, with
+//! the memory allocator and the scheduler) register them during
+//! initialization.
+//!
+//! If tasks are listed by [`set_task_source`], each task has a directory
+//! `/proc/<tid>`, and `/proc/self` links to the one of the current task.
+
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::{string::String, string::ToString, vec::Vec};
+
+use axfs_vfs::{VfsError, VfsNodeType, VfsResult};
+use axsync::Mutex;
+
+use super::pseudofs::{Entry, FileTree, PseudoFileSystem, Tree};
+
+type TaskFileFn = Arc<dyn Fn(u64) -> Option<String> + Send + Sync>;
+
+struct TaskSource {
+    /// Returns the IDs of all tasks.
+    ids: fn() -> Vec<u64>,
+    /// Returns the ID of the current task.
+    current: fn() -> Option<u64>,
+}
+
+struct Registry {
+    files: FileTree,
+    task_source: Option<TaskSource>,
+    /// Generators of the files in each task directory, by the file names.
+    task_files: BTreeMap<String, TaskFileFn>,
+}
+
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+    files: FileTree::new(),
+    task_source: None,
+    task_files: BTreeMap::new(),
+});
+
+/// Adds a file at `path` (relative to `/proc`, e.g. `sys/vm/overcommit_memory`)
+/// whose content is generated by `generator` each time it is opened and read.
+///
+/// The parent directories are created implicitly. An existing file at the
+/// same path is replaced.
+pub fn add_file<F>(path: &str, generator: F)
+where
+    F: Fn() -> String + Send + Sync + 'static,
+{
+    let read = Arc::new(move || Some(generator()));
+    REGISTRY.lock().files.insert(path, read, None);
+}
+
+/// Lists tasks in procfs, `ids` returns the IDs of all tasks, and `current`
+/// returns the ID of the current task.
+pub fn set_task_source(ids: fn() -> Vec<u64>, current: fn() -> Option<u64>) {
+    REGISTRY.lock().task_source = Some(TaskSource { ids, current });
+}
+
+/// Adds a file named `name` to the directory of every task, its content is
+/// generated by `generator` with the task ID, which returns [`None`] if the
+/// task does not exist any more.
+pub fn add_task_file<F>(name: &str, generator: F)
+where
+    F: Fn(u64) -> Option<String> + Send + Sync + 'static,
+{
+    REGISTRY
+        .lock()
+        .task_files
+        .insert(name.into(), Arc::new(generator));
+}
+
+/// Parses a task directory name, which has only digits.
+fn parse_task_id(name: &str) -> Option<u64> {
+    if name.bytes().all(|b| b.is_ascii_digit()) {
+        name.parse().ok()
+    } else {
+        None
+    }
+}
+
+struct ProcTree;
+
+impl Tree for ProcTree {
+    fn find(&self, comps: &[&str]) -> VfsResult<Entry> {
+        let registry = REGISTRY.lock();
+        if let (Some(tasks), Some(&first)) = (&registry.task_source, comps.first()) {
+            if first == "self" {
+                let id = (tasks.current)().ok_or(VfsError::NotFound)?;
+                return match comps.len() {
+                    1 => Ok(Entry::SymLink(id.to_string())),
+                    _ => Err(VfsError::NotADirectory), // resolved by following the link
+                };
+            }
+            if let Some(id) = parse_task_id(first) {
+                if !(tasks.ids)().contains(&id) {
+                    return Err(VfsError::NotFound);
+                }
+                return match comps[1..] {
+                    [] => Ok(Entry::Dir),
+                    [name] => match registry.task_files.get(name) {
+                        Some(generator) => {
+                            let generator = generator.clone();
+                            Ok(Entry::File(Arc::new(move || generator(id)), None))
+                        }
+                        None => Err(VfsError::NotFound),
+                    },
+                    _ => Err(VfsError::NotFound),
+                };
+            }
+        }
+        registry.files.find(comps)
+    }
+
+    fn list(&self, comps: &[&str]) -> Vec<(String, VfsNodeType)> {
+        let registry = REGISTRY.lock();
+        let Some(tasks) = &registry.task_source else {
+            return registry.files.list(comps);
+        };
+        match comps {
+            [] => {
+                let mut children = registry.files.list(comps);
+                if (tasks.current)().is_some() {
+                    children.push(("self".into(), VfsNodeType::SymLink));
+                }
+                for id in (tasks.ids)() {
+                    children.push((id.to_string(), VfsNodeType::Dir));
+                }
+                children
+            }
+            [first] if parse_task_id(first).is_some() => registry
+                .task_files
+                .keys()
+                .map(|name| (name.clone(), VfsNodeType::File))
+                .collect(),
+            _ => registry.files.list(comps),
+        }
+    }
+}
+
+static TREE: ProcTree = ProcTree;
+
+/// Creates the procfs, all instances share the same files.
+pub(crate) fn new_procfs() -> PseudoFileSystem {
+    PseudoFileSystem::new(&TREE)
+}
@@ -0,0 +1,295 @@
+//! Filesystems whose files store nothing, but are generated when read, and
+//! handled by callbacks when written, i.e., procfs and sysfs.
+//!
+//! The nodes are created on each lookup from a [`Tree`], which tells what a
+//! path refers to.
+
+use alloc::collections::BTreeMap;
+use alloc::sync::{Arc, Weak};
+use alloc::{string::String, vec::Vec};
+
+use axfs_vfs::{VfsDirEntry, VfsError, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeRef};
+use axfs_vfs::{VfsNodeType, VfsOps, VfsResult};
+use axsync::Mutex;
+
+/// Generates the content of a file, or returns [`None`] if it does not exist
+/// any more.
+pub(crate) type ReadFn = Arc<dyn Fn() -> Option<String> + Send + Sync>;
+
+/// Handles a value written to a file.
+pub(crate) type WriteFn = Arc<dyn Fn(&str) -> VfsResult + Send + Sync>;
+
+/// What a path refers to.
+#[derive(Clone)]
+pub(crate) enum Entry {
+    Dir,
+    File(ReadFn, Option<WriteFn>),
+    SymLink(String),
+}
+
+/// The directory structure of a pseudo filesystem.
+pub(crate) trait Tree: Send + Sync {
+    /// Finds what the path of `comps` refers to.
+    fn find(&self, comps: &[&str]) -> VfsResult<Entry>;
+
+    /// Lists the names and types in the directory at the path of `comps`.
+    fn list(&self, comps: &[&str]) -> Vec<(String, VfsNodeType)>;
+}
+
+/// Files by their paths, with directories implied by the paths.
+pub(crate) struct FileTree {
+    files: BTreeMap<String, (ReadFn, Option<WriteFn>)>,
+}
+
+impl FileTree {
+    pub const fn new() -> Self {
+        Self {
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// Adds a file at `path`, replacing the existing one.
+    pub fn insert(&mut self, path: &str, read: ReadFn, write: Option<WriteFn>) {
+        let comps = path
+            .split('/')
+            .filter(|c| !c.is_empty())
+            .collect::<Vec<_>>();
+        if comps.is_empty() {
+            warn!("cannot add a file at the root of a pseudo filesystem");
+            return;
+        }
+        self.files.insert(comps.join("/"), (read, write));
+    }
+
+    pub fn find(&self, comps: &[&str]) -> VfsResult<Entry> {
+        let path = comps.join("/");
+        if path.is_empty() {
+            return Ok(Entry::Dir);
+        }
+        if let Some((read, write)) = self.files.get(&path) {
+            return Ok(Entry::File(read.clone(), write.clone()));
+        }
+        let prefix = path + "/";
+        match self.files.range(prefix.clone()..).next() {
+            Some((file, _)) if file.starts_with(&prefix) => Ok(Entry::Dir),
+            _ => Err(VfsError::NotFound),
+        }
+    }
+
+    pub fn list(&self, comps: &[&str]) -> Vec<(String, VfsNodeType)> {
+        let mut prefix = comps.join("/");
+        if !prefix.is_empty() {
+            prefix.push('/');
+        }
+        let mut children: Vec<(String, VfsNodeType)> = Vec::new();
+        for file in self.files.keys().filter(|f| f.starts_with(&prefix)) {
+            let rest = &file[prefix.len()..];
+            let (name, ty) = match rest.split_once('/') {
+                Some((name, _)) => (name, VfsNodeType::Dir),
+                None => (rest, VfsNodeType::File),
+            };
+            // files are sorted, so entries of the same name are adjacent
+            if children.last().is_none_or(|(last, _)| last != name) {
+                children.push((name.into(), ty));
+            }
+        }
+        children
+    }
+}
+
+/// A pseudo filesystem that implements [`axfs_vfs::VfsOps`].
+pub struct PseudoFileSystem {
+    tree: &'static dyn Tree,
+    /// Parent of the root directory, i.e., the parent of the mount point.
+    parent: Arc<Mutex<Option<Weak<dyn VfsNodeOps>>>>,
+}
+
+impl PseudoFileSystem {
+    pub(crate) fn new(tree: &'static dyn Tree) -> Self {
+        Self {
+            tree,
+            parent: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+impl VfsOps for PseudoFileSystem {
+    fn mount(&self, _path: &str, mount_point: VfsNodeRef) -> VfsResult {
+        *self.parent.lock() = mount_point.parent().as_ref().map(Arc::downgrade);
+        Ok(())
+    }
+
+    fn root_dir(&self) -> VfsNodeRef {
+        Arc::new(PseudoNode {
+            tree: self.tree,
+            parent: self.parent.clone(),
+            path: String::new(),
+            entry: Entry::Dir,
+            content: Mutex::new(None),
+        })
+    }
+}
+
+/// A node in a pseudo filesystem, created on each lookup.
+struct PseudoNode {
+    tree: &'static dyn Tree,
+    parent: Arc<Mutex<Option<Weak<dyn VfsNodeOps>>>>,
+    /// Path relative to the root, empty for the root directory.
+    path: String,
+    entry: Entry,
+    /// Content of the file, generated when first read, so that reading it
+    /// piece by piece gives a consistent snapshot.
+    content: Mutex<Option<Vec<u8>>>,
+}
+
+impl PseudoNode {
+    /// Looks up the path that `paths` joined into, relative to the root.
+    ///
+    /// `..` out of the root goes to the parent of the mount point.
+    fn lookup_path(&self, paths: &[&str]) -> VfsResult<VfsNodeRef> {
+        let parts = paths
+            .iter()
+            .flat_map(|p| p.split('/'))
+            .filter(|c| !c.is_empty() && *c != ".")
+            .collect::<Vec<_>>();
+        let mut comps = Vec::new();
+        for (i, &part) in parts.iter().enumerate() {
+            if part != ".." {
+                comps.push(part);
+            } else if comps.pop().is_none() {
+                let parent = self.parent.lock().as_ref().and_then(Weak::upgrade);
+                let parent = parent.ok_or(VfsError::NotFound)?;
+                let rest = parts[i + 1..].join("/");
+                return if rest.is_empty() {
+                    Ok(parent)
+                } else {
+                    parent.lookup(&rest)
+                };
+            }
+        }
+        Ok(Arc::new(PseudoNode {
+            tree: self.tree,
+            parent: self.parent.clone(),
+            path: comps.join("/"),
+            entry: self.tree.find(&comps)?,
+            content: Mutex::new(None),
+        }))
+    }
+
+    fn comps(&self) -> Vec<&str> {
+        self.path.split('/').filter(|c| !c.is_empty()).collect()
+    }
+}
+
+impl VfsNodeOps for PseudoNode {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let (perm, ty, size) = match &self.entry {
+            Entry::Dir => (0o555, VfsNodeType::Dir, 0),
+            Entry::File(_, None) => (0o444, VfsNodeType::File, 0),
+            Entry::File(_, Some(_)) => (0o644, VfsNodeType::File, 0),
+            Entry::SymLink(target) => (0o777, VfsNodeType::SymLink, target.len()),
+        };
+        let perm = VfsNodePerm::from_bits_truncate(perm);
+        Ok(VfsNodeAttr::new(perm, ty, size as u64, 0))
+    }
+
+    fn parent(&self) -> Option<VfsNodeRef> {
+        if !matches!(self.entry, Entry::Dir) {
+            return None;
+        }
+        if self.path.is_empty() {
+            return self.parent.lock().as_ref()?.upgrade();
+        }
+        let parent = self.path.rsplit_once('/').map_or("", |(parent, _)| parent);
+        self.lookup_path(&[parent]).ok()
+    }
+
+    fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
+        match self.entry {
+            Entry::Dir => self.lookup_path(&[&self.path, path]),
+            _ => Err(VfsError::NotADirectory),
+        }
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        if !matches!(self.entry, Entry::Dir) {
+            return Err(VfsError::NotADirectory);
+        }
+        let children = self.tree.list(&self.comps());
+        let mut children = children.iter().skip(start_idx.max(2) - 2);
+        for (i, ent) in dirents.iter_mut().enumerate() {
+            match i + start_idx {
+                0 => *ent = VfsDirEntry::new(".", VfsNodeType::Dir),
+                1 => *ent = VfsDirEntry::new("..", VfsNodeType::Dir),
+                _ => {
+                    if let Some((name, ty)) = children.next() {
+                        *ent = VfsDirEntry::new(name, *ty);
+                    } else {
+                        return Ok(i);
+                    }
+                }
+            }
+        }
+        Ok(dirents.len())
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let mut content = self.content.lock();
+        if content.is_none() {
+            *content = Some(match &self.entry {
+                Entry::Dir => return Err(VfsError::IsADirectory),
+                Entry::File(read, _) => read().ok_or(VfsError::NotFound)?.into_bytes(),
+                Entry::SymLink(target) => target.clone().into_bytes(),
+            });
+        }
+        let content = content.as_ref().unwrap();
+        let start = content.len().min(offset as usize);
+        let end = content.len().min(start + buf.len());
+        let src = &content[start..end];
+        buf[..src.len()].copy_from_slice(src);
+        Ok(src.len())
+    }
+
+    /// Passes the written value to the handler of the file, with surrounding
+    /// whitespaces (e.g. the trailing newline from `echo`) trimmed.
+    fn write_at(&self, _offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        match &self.entry {
+            Entry::Dir => Err(VfsError::IsADirectory),
+            Entry::File(_, Some(write)) => {
+                let value = core::str::from_utf8(buf).map_err(|_| VfsError::InvalidInput)?;
+                write(value.trim())?;
+                *self.content.lock() = None; // read the new value next time
+                Ok(buf.len())
+            }
+            _ => Err(VfsError::PermissionDenied),
+        }
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        match &self.entry {
+            Entry::Dir => Err(VfsError::IsADirectory),
+            Entry::File(_, Some(_)) => Ok(()), // for opening with `O_TRUNC`
+            _ => Err(VfsError::PermissionDenied),
+        }
+    }
+
+    fn fsync(&self) -> VfsResult {
+        Ok(())
+    }
+
+    fn create(&self, _path: &str, _ty: VfsNodeType) -> VfsResult {
+        Err(VfsError::PermissionDenied)
+    }
+
+    fn remove(&self, _path: &str) -> VfsResult {
+        Err(VfsError::PermissionDenied)
+    }
+
+    fn rename(&self, _src_path: &str, _dst_path: &str) -> VfsResult {
+        Err(VfsError::PermissionDenied)
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+}
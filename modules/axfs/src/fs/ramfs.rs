@@ -0,0 +1,454 @@
+//! RAM filesystem used by `/tmp`, and by the root if there is no block device.
+//!
+//! Besides regular files and directories, it supports symbolic links, hard
+//! links and socket files, which `axfs_ramfs` does not, so it replaces that
+//! crate. A symbolic link stores its target as its content, so reading it with
+//! [`VfsNodeOps::read_at`] gives the target. Hard links are the same file node
+//! shared by several directory entries.
+
+use alloc::collections::BTreeMap;
+use alloc::sync::{Arc, Weak};
+use alloc::{format, string::String, vec::Vec};
+
+use axfs_vfs::{VfsDirEntry, VfsError, VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType};
+use axfs_vfs::{VfsOps, VfsResult};
+use axsync::Mutex;
+
+use crate::fs::{NodeExt, split_path};
+
+/// A RAM filesystem that implements [`axfs_vfs::VfsOps`].
+pub struct RamFileSystem {
+    root: Arc<DirNode>,
+}
+
+impl RamFileSystem {
+    /// Create a new instance.
+    pub fn new() -> Self {
+        Self {
+            root: DirNode::new(None),
+        }
+    }
+}
+
+impl Default for RamFileSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VfsOps for RamFileSystem {
+    fn mount(&self, _path: &str, mount_point: VfsNodeRef) -> VfsResult {
+        self.root.set_mount_parent(mount_point.parent().as_ref());
+        Ok(())
+    }
+
+    fn root_dir(&self) -> VfsNodeRef {
+        self.root.clone()
+    }
+}
+
+/// The directory node in the RAM filesystem.
+pub struct DirNode {
+    this: Weak<DirNode>,
+    /// The parent directory, none for the root.
+    parent: Option<Weak<DirNode>>,
+    /// The parent of the mount point, which is in another filesystem, for the
+    /// root of a mounted filesystem.
+    mount_parent: Mutex<Option<Weak<dyn VfsNodeOps>>>,
+    children: Mutex<BTreeMap<String, VfsNodeRef>>,
+}
+
+impl DirNode {
+    fn new(parent: Option<Weak<DirNode>>) -> Arc<Self> {
+        Arc::new_cyclic(|this| Self {
+            this: this.clone(),
+            parent,
+            mount_parent: Mutex::new(None),
+            children: Mutex::new(BTreeMap::new()),
+        })
+    }
+
+    fn set_mount_parent(&self, parent: Option<&VfsNodeRef>) {
+        *self.mount_parent.lock() = parent.map(Arc::downgrade);
+    }
+
+    /// Creates a new node with the given name and type in this directory.
+    fn create_node(&self, name: &str, ty: VfsNodeType) -> VfsResult {
+        let mut children = self.children.lock();
+        if children.contains_key(name) {
+            return Err(VfsError::AlreadyExists);
+        }
+        let node: VfsNodeRef = match ty {
+            VfsNodeType::File => Arc::new(FileNode::new()),
+            VfsNodeType::Dir => DirNode::new(Some(self.this.clone())),
+            VfsNodeType::SymLink => Arc::new(SymLinkNode::new()),
+            VfsNodeType::Socket => Arc::new(SocketNode),
+            _ => return Err(VfsError::Unsupported),
+        };
+        children.insert(name.into(), node);
+        Ok(())
+    }
+
+    /// Removes a node by the given name in this directory.
+    fn remove_node(&self, name: &str) -> VfsResult {
+        let mut children = self.children.lock();
+        let node = children.get(name).ok_or(VfsError::NotFound)?;
+        if let Some(dir) = node.as_any_dir() {
+            if !dir.children.lock().is_empty() {
+                return Err(VfsError::DirectoryNotEmpty);
+            }
+        }
+        children.remove(name);
+        Ok(())
+    }
+
+    /// Looks up the directory at `path`, which must be in this filesystem.
+    fn lookup_dir(self: Arc<Self>, path: &str) -> VfsResult<Arc<DirNode>> {
+        let (name, rest) = split_path(path);
+        let node = match name {
+            "" | "." => self,
+            ".." => match &self.parent {
+                Some(parent) => parent.upgrade().ok_or(VfsError::NotFound)?,
+                None => return Err(VfsError::Unsupported), // out of this filesystem
+            },
+            _ => self
+                .children
+                .lock()
+                .get(name)
+                .cloned()
+                .ok_or(VfsError::NotFound)?
+                .as_any_arc_dir()?,
+        };
+        match rest {
+            Some(rest) => node.lookup_dir(rest),
+            None => Ok(node),
+        }
+    }
+}
+
+impl VfsNodeOps for DirNode {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(VfsNodeAttr::new_dir(4096, 0))
+    }
+
+    fn parent(&self) -> Option<VfsNodeRef> {
+        match &self.parent {
+            Some(parent) => parent.upgrade().map(|dir| dir as VfsNodeRef),
+            None => self.mount_parent.lock().as_ref().and_then(Weak::upgrade),
+        }
+    }
+
+    fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
+        let (name, rest) = split_path(path);
+        let node = match name {
+            "" | "." => Ok(self.clone() as VfsNodeRef),
+            ".." => self.parent().ok_or(VfsError::NotFound),
+            _ => self
+                .children
+                .lock()
+                .get(name)
+                .cloned()
+                .ok_or(VfsError::NotFound),
+        }?;
+
+        if let Some(rest) = rest {
+            node.lookup(rest)
+        } else {
+            Ok(node)
+        }
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        let children = self.children.lock();
+        let mut children = children.iter().skip(start_idx.max(2) - 2);
+        for (i, ent) in dirents.iter_mut().enumerate() {
+            match i + start_idx {
+                0 => *ent = VfsDirEntry::new(".", VfsNodeType::Dir),
+                1 => *ent = VfsDirEntry::new("..", VfsNodeType::Dir),
+                _ => {
+                    if let Some((name, node)) = children.next() {
+                        *ent = VfsDirEntry::new(name, node.get_attr()?.file_type());
+                    } else {
+                        return Ok(i);
+                    }
+                }
+            }
+        }
+        Ok(dirents.len())
+    }
+
+    fn create(&self, path: &str, ty: VfsNodeType) -> VfsResult {
+        debug!("create {:?} at ramfs: {}", ty, path);
+        let (name, rest) = split_path(path);
+        if let Some(rest) = rest {
+            match name {
+                "" | "." => self.create(rest, ty),
+                ".." => self.parent().ok_or(VfsError::NotFound)?.create(rest, ty),
+                _ => {
+                    let subdir = self
+                        .children
+                        .lock()
+                        .get(name)
+                        .ok_or(VfsError::NotFound)?
+                        .clone();
+                    subdir.create(rest, ty)
+                }
+            }
+        } else if name.is_empty() || name == "." || name == ".." {
+            Ok(()) // already exists
+        } else {
+            self.create_node(name, ty)
+        }
+    }
+
+    fn remove(&self, path: &str) -> VfsResult {
+        debug!("remove at ramfs: {}", path);
+        let (name, rest) = split_path(path);
+        if let Some(rest) = rest {
+            match name {
+                "" | "." => self.remove(rest),
+                ".." => self.parent().ok_or(VfsError::NotFound)?.remove(rest),
+                _ => {
+                    let subdir = self
+                        .children
+                        .lock()
+                        .get(name)
+                        .ok_or(VfsError::NotFound)?
+                        .clone();
+                    subdir.remove(rest)
+                }
+            }
+        } else if name.is_empty() || name == "." || name == ".." {
+            Err(VfsError::InvalidInput) // remove '.' or '..
+        } else {
+            self.remove_node(name)
+        }
+    }
+
+    fn rename(&self, src_path: &str, dst_path: &str) -> VfsResult {
+        debug!("rename at ramfs: {} -> {}", src_path, dst_path);
+        let this = self.this.upgrade().unwrap();
+        let (src_dir, src_name) = split_parent(src_path);
+        let (dst_dir, dst_name) = split_parent(dst_path);
+        let src_dir = this.clone().lookup_dir(src_dir)?;
+        let dst_dir = this.lookup_dir(dst_dir)?;
+        if matches!(src_name, "" | "." | "..") || matches!(dst_name, "" | "." | "..") {
+            return Err(VfsError::InvalidInput);
+        }
+        if dst_path.starts_with(&format!("{}/", src_path.trim_end_matches('/'))) {
+            return Err(VfsError::InvalidInput); // move a directory into itself
+        }
+
+        let node = src_dir
+            .children
+            .lock()
+            .get(src_name)
+            .cloned()
+            .ok_or(VfsError::NotFound)?;
+        if let Some(old) = dst_dir.children.lock().get(dst_name) {
+            if Arc::ptr_eq(old, &node) {
+                return Ok(()); // the same node
+            }
+        }
+        dst_dir.remove_node(dst_name).or_else(|e| match e {
+            VfsError::NotFound => Ok(()),
+            e => Err(e),
+        })?;
+        dst_dir.children.lock().insert(dst_name.into(), node);
+        src_dir.children.lock().remove(src_name);
+        Ok(())
+    }
+
+    fn fsync(&self) -> VfsResult {
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+
+    axfs_vfs::impl_vfs_dir_default! {}
+}
+
+impl NodeExt for DirNode {
+    fn link(&self, path: &str, node: &VfsNodeRef) -> VfsResult {
+        let (dir, name) = split_parent(path);
+        let dir = self.this.upgrade().unwrap().lookup_dir(dir)?;
+        if matches!(name, "" | "." | "..") {
+            return Err(VfsError::InvalidInput);
+        }
+        if node.get_attr()?.is_dir() {
+            return Err(VfsError::PermissionDenied); // no hard links to directories
+        }
+        let mut children = dir.children.lock();
+        if children.contains_key(name) {
+            return Err(VfsError::AlreadyExists);
+        }
+        children.insert(name.into(), node.clone());
+        Ok(())
+    }
+}
+
+/// The file node in the RAM filesystem.
+pub struct FileNode {
+    content: Mutex<Vec<u8>>,
+}
+
+impl FileNode {
+    const fn new() -> Self {
+        Self {
+            content: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl VfsNodeOps for FileNode {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(VfsNodeAttr::new_file(self.content.lock().len() as _, 0))
+    }
+
+    fn truncate(&self, size: u64) -> VfsResult {
+        let mut content = self.content.lock();
+        if size < content.len() as u64 {
+            content.truncate(size as _);
+        } else {
+            content.resize(size as _, 0);
+        }
+        Ok(())
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let content = self.content.lock();
+        let start = content.len().min(offset as usize);
+        let end = content.len().min(offset as usize + buf.len());
+        let src = &content[start..end];
+        buf[..src.len()].copy_from_slice(src);
+        Ok(src.len())
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        let offset = offset as usize;
+        let mut content = self.content.lock();
+        if offset + buf.len() > content.len() {
+            content.resize(offset + buf.len(), 0);
+        }
+        let dst = &mut content[offset..offset + buf.len()];
+        dst.copy_from_slice(&buf[..dst.len()]);
+        Ok(buf.len())
+    }
+
+    fn fsync(&self) -> VfsResult {
+        Ok(()) // the content only lives in memory
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
+
+/// The symbolic link node in the RAM filesystem, its content is the target.
+pub struct SymLinkNode {
+    target: Mutex<String>,
+}
+
+impl SymLinkNode {
+    const fn new() -> Self {
+        Self {
+            target: Mutex::new(String::new()),
+        }
+    }
+}
+
+impl VfsNodeOps for SymLinkNode {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let size = self.target.lock().len() as u64;
+        Ok(VfsNodeAttr::new(
+            axfs_vfs::VfsNodePerm::from_bits_truncate(0o777),
+            VfsNodeType::SymLink,
+            size,
+            0,
+        ))
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let target = self.target.lock();
+        let start = target.len().min(offset as usize);
+        let end = target.len().min(offset as usize + buf.len());
+        let src = &target.as_bytes()[start..end];
+        buf[..src.len()].copy_from_slice(src);
+        Ok(src.len())
+    }
+
+    /// Sets the target, it can only be written once as a whole.
+    fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        let mut target = self.target.lock();
+        if offset != 0 || !target.is_empty() {
+            return Err(VfsError::PermissionDenied);
+        }
+        *target = String::from_utf8(buf.into()).map_err(|_| VfsError::InvalidInput)?;
+        Ok(buf.len())
+    }
+
+    fn fsync(&self) -> VfsResult {
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
+
+/// The socket node in the RAM filesystem, which is only the name of a Unix
+/// domain socket bound to it, without any content.
+pub struct SocketNode;
+
+impl VfsNodeOps for SocketNode {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(VfsNodeAttr::new(
+            axfs_vfs::VfsNodePerm::from_bits_truncate(0o755),
+            VfsNodeType::Socket,
+            0,
+            0,
+        ))
+    }
+
+    fn fsync(&self) -> VfsResult {
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
+
+trait AsDirNode {
+    fn as_any_dir(&self) -> Option<&DirNode>;
+    fn as_any_arc_dir(self) -> VfsResult<Arc<DirNode>>;
+}
+
+impl AsDirNode for VfsNodeRef {
+    fn as_any_dir(&self) -> Option<&DirNode> {
+        // only called on children, which are all nodes of this filesystem
+        self.as_any().downcast_ref::<DirNode>()
+    }
+
+    fn as_any_arc_dir(self) -> VfsResult<Arc<DirNode>> {
+        match self.as_any_dir() {
+            Some(dir) => Ok(dir.this.upgrade().unwrap()),
+            None if self.get_attr()?.is_dir() => Err(VfsError::Unsupported),
+            None => Err(VfsError::NotADirectory),
+        }
+    }
+}
+
+/// Splits `path` into the parent path and the last component.
+fn split_parent(path: &str) -> (&str, &str) {
+    let path = path.trim_end_matches('/');
+    path.rsplit_once('/').unwrap_or(("", path))
+}
@@ -0,0 +1,66 @@
+//! System information filesystem mounted on `/sys`.
+//!
+//! Like procfs, files in sysfs are generated when read. Some of them are
+//! tunables, and writing to them changes the kernel state. Files are
+//! registered by [`add_file`] and [`add_tunable`], usually by the runtime
+//! that knows about the devices and other modules.
+
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use axerrno::AxResult;
+use axfs_vfs::{VfsNodeType, VfsResult};
+use axsync::Mutex;
+
+use super::pseudofs::{Entry, FileTree, PseudoFileSystem, Tree};
+
+static FILES: Mutex<FileTree> = Mutex::new(FileTree::new());
+
+/// Adds a read-only file at `path` (relative to `/sys`, e.g.
+/// `class/net/eth0/address`) whose content is generated by `show` each time
+/// it is opened and read.
+///
+/// The parent directories are created implicitly. An existing file at the
+/// same path is replaced.
+pub fn add_file<F>(path: &str, show: F)
+where
+    F: Fn() -> String + Send + Sync + 'static,
+{
+    FILES
+        .lock()
+        .insert(path, Arc::new(move || Some(show())), None);
+}
+
+/// Adds a writable file at `path` like [`add_file`], and values written to it
+/// are passed to `store`, with surrounding whitespaces trimmed.
+///
+/// If `store` fails, the error is returned to the writer.
+pub fn add_tunable<F, G>(path: &str, show: F, store: G)
+where
+    F: Fn() -> String + Send + Sync + 'static,
+    G: Fn(&str) -> AxResult + Send + Sync + 'static,
+{
+    FILES
+        .lock()
+        .insert(path, Arc::new(move || Some(show())), Some(Arc::new(store)));
+}
+
+struct SysTree;
+
+impl Tree for SysTree {
+    fn find(&self, comps: &[&str]) -> VfsResult<Entry> {
+        FILES.lock().find(comps)
+    }
+
+    fn list(&self, comps: &[&str]) -> Vec<(String, VfsNodeType)> {
+        FILES.lock().list(comps)
+    }
+}
+
+static TREE: SysTree = SysTree;
+
+/// Creates the sysfs, all instances share the same files.
+pub(crate) fn new_sysfs() -> PseudoFileSystem {
+    PseudoFileSystem::new(&TREE)
+}
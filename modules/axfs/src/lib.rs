@@ -6,10 +6,25 @@
 //!
 //! - `fatfs`: Use [FAT] as the main filesystem and mount it on `/`. This feature
 //!   is **enabled** by default.
-//! - `devfs`: Mount [`axfs_devfs::DeviceFileSystem`] on `/dev`. This feature is
-//!   **enabled** by default.
-//! - `ramfs`: Mount [`axfs_ramfs::RamFileSystem`] on `/tmp`. This feature is
-//!   **enabled** by default.
+//! - `ext4`: Support the [ext4] filesystem. If the root block device is
+//!   formatted as ext4, it is mounted on `/` instead of FAT. Other ext4 devices
+//!   can be mounted by [`api::mount_by_type`], but only one ext4 filesystem can
+//!   be used at a time. This feature is **disabled** by default.
+//! - `devfs`: Mount the device filesystem on `/dev`, with `null`, `zero`,
+//!   `urandom` and the block devices. This feature is **enabled** by default.
+//! - `ramfs`: Mount a RAM filesystem ([`ramfs`]) on `/tmp`, which supports
+//!   symbolic links and hard links. It is also used as the root filesystem if
+//!   there is no block device. This feature is **enabled** by default.
+//! - `procfs`: Mount the process information filesystem on `/proc`, whose
+//!   files are generated from the live system state when read. See [`procfs`]
+//!   for adding files to it. This feature is **enabled** by default.
+//! - `sysfs`: Mount the system information filesystem on `/sys`, whose files
+//!   are generated when read, and some of them can be written to tune the
+//!   kernel. See [`sysfs`] for adding files to it. This feature is **enabled**
+//!   by default.
+//! - `rtc`: Stamp files created or modified on FAT with the wall time from
+//!   `axhal::time::wall_time`, instead of 1980-01-01. This feature is
+//!   **disabled** by default.
 //! - `myfs`: Allow users to define their custom filesystems to override the
 //!   default. In this case, [`MyFileSystemIf`] is required to be implemented
 //!   to create and initialize other filesystems. This feature is **disabled** by
@@ -17,6 +32,7 @@
 //!   both are enabled.
 //!
 //! [FAT]: https://en.wikipedia.org/wiki/File_Allocation_Table
+//! [ext4]: https://en.wikipedia.org/wiki/Ext4
 //! [`MyFileSystemIf`]: fops::MyFileSystemIf
 
 #![cfg_attr(all(not(test), not(doc)), no_std)]
@@ -26,21 +42,42 @@
 extern crate log;
 extern crate alloc;
 
+mod cache;
 mod dev;
 mod fs;
 mod mounts;
 mod root;
 
 pub mod api;
+pub mod cpio;
 pub mod fops;
 
+#[cfg(feature = "procfs")]
+pub use self::fs::procfs;
+#[cfg(feature = "ramfs")]
+pub use self::fs::ramfs;
+#[cfg(feature = "sysfs")]
+pub use self::fs::sysfs;
+
 use axdriver::{AxDeviceContainer, prelude::*};
 
 /// Initializes filesystems by block devices.
+///
+/// All block devices are registered as `/dev/vda`, `/dev/vdb`, ..., and the
+/// first one is used as the root filesystem. Others can be mounted later by
+/// [`api::mount_by_type`]. If there is no block device, a RAM filesystem is
+/// used as the root (requires the `ramfs` feature), which can be populated by
+/// [`cpio::unpack`].
 pub fn init_filesystems(mut blk_devs: AxDeviceContainer<AxBlockDevice>) {
     info!("Initialize filesystems...");
 
-    let dev = blk_devs.take_one().expect("No block device found!");
-    info!("  use block device 0: {:?}", dev.device_name());
-    self::root::init_rootfs(self::dev::Disk::new(dev));
+    while let Some(dev) = blk_devs.take_one() {
+        self::dev::register_block_device(dev);
+    }
+    let root_dev = self::dev::block_devices().into_iter().next();
+    match &root_dev {
+        Some(dev) => info!("  use block device {} as the root filesystem", dev.name()),
+        None => info!("  no block device found, use ramfs as the root filesystem"),
+    }
+    self::root::init_rootfs(root_dev);
 }
@@ -1,8 +1,36 @@
 use alloc::sync::Arc;
-use axfs_vfs::{VfsNodeType, VfsOps, VfsResult};
+use axerrno::{AxResult, ax_err};
+use axfs_vfs::VfsOps;
 
 use crate::fs;
 
+/// Creates a new filesystem instance by its type name, for mounting at
+/// runtime.
+pub(crate) fn new_fs(fstype: &str, source: &str) -> AxResult<Arc<dyn VfsOps>> {
+    debug!("create filesystem {:?} from {:?}", fstype, source);
+    match fstype {
+        #[cfg(feature = "ramfs")]
+        "ramfs" | "tmpfs" => Ok(ramfs()),
+        #[cfg(feature = "fatfs")]
+        "vfat" | "fat" => {
+            let dev = crate::dev::find_block_device(source)?;
+            dev.filesystem_or_init(
+                "vfat",
+                |disk| Ok(fs::fatfs::FatFileSystem::open(disk)? as _),
+            )
+        }
+        #[cfg(feature = "ext4")]
+        "ext4" => {
+            let dev = crate::dev::find_block_device(source)?;
+            dev.filesystem_or_init(
+                "ext4",
+                |disk| Ok(fs::ext4::Ext4FileSystem::open(disk)? as _),
+            )
+        }
+        _ => ax_err!(Unsupported, "unsupported filesystem type"),
+    }
+}
+
 #[cfg(feature = "devfs")]
 pub(crate) fn devfs() -> Arc<fs::devfs::DeviceFileSystem> {
     let null = fs::devfs::NullDev;
@@ -15,6 +43,9 @@ pub(crate) fn devfs() -> Arc<fs::devfs::DeviceFileSystem> {
     devfs.add("zero", Arc::new(zero));
     devfs.add("urandom", Arc::new(urandom));
     foo_dir.add("bar", Arc::new(bar));
+    for dev in crate::dev::block_devices() {
+        devfs.add(dev.name(), Arc::new(crate::dev::BlockDeviceNode::new(dev)));
+    }
     Arc::new(devfs)
 }
 
@@ -24,59 +55,25 @@ pub(crate) fn ramfs() -> Arc<fs::ramfs::RamFileSystem> {
 }
 
 #[cfg(feature = "procfs")]
-pub(crate) fn procfs() -> VfsResult<Arc<fs::ramfs::RamFileSystem>> {
-    let procfs = fs::ramfs::RamFileSystem::new();
-    let proc_root = procfs.root_dir();
-
-    // Create /proc/sys/net/core/somaxconn
-    proc_root.create("sys", VfsNodeType::Dir)?;
-    proc_root.create("sys/net", VfsNodeType::Dir)?;
-    proc_root.create("sys/net/core", VfsNodeType::Dir)?;
-    proc_root.create("sys/net/core/somaxconn", VfsNodeType::File)?;
-    let file_somaxconn = proc_root.clone().lookup("./sys/net/core/somaxconn")?;
-    file_somaxconn.write_at(0, b"4096\n")?;
-
-    // Create /proc/sys/vm/overcommit_memory
-    proc_root.create("sys/vm", VfsNodeType::Dir)?;
-    proc_root.create("sys/vm/overcommit_memory", VfsNodeType::File)?;
-    let file_over = proc_root.clone().lookup("./sys/vm/overcommit_memory")?;
-    file_over.write_at(0, b"0\n")?;
+pub(crate) fn procfs() -> Arc<fs::pseudofs::PseudoFileSystem> {
+    use alloc::{format, string::String};
 
-    // Create /proc/self/stat
-    proc_root.create("self", VfsNodeType::Dir)?;
-    proc_root.create("self/stat", VfsNodeType::File)?;
-
-    Ok(Arc::new(procfs))
+    fs::procfs::add_file("mounts", || {
+        let mut mounts = String::new();
+        for (source, path, fstype) in crate::root::mount_table() {
+            mounts += &format!("{} {} {} rw 0 0\n", source, path, fstype);
+        }
+        mounts
+    });
+    fs::procfs::add_file("sys/net/core/somaxconn", || "4096\n".into());
+    fs::procfs::add_file("sys/vm/overcommit_memory", || "0\n".into());
+    Arc::new(fs::procfs::new_procfs())
 }
 
 #[cfg(feature = "sysfs")]
-pub(crate) fn sysfs() -> VfsResult<Arc<fs::ramfs::RamFileSystem>> {
-    let sysfs = fs::ramfs::RamFileSystem::new();
-    let sys_root = sysfs.root_dir();
-
-    // Create /sys/kernel/mm/transparent_hugepage/enabled
-    sys_root.create("kernel", VfsNodeType::Dir)?;
-    sys_root.create("kernel/mm", VfsNodeType::Dir)?;
-    sys_root.create("kernel/mm/transparent_hugepage", VfsNodeType::Dir)?;
-    sys_root.create("kernel/mm/transparent_hugepage/enabled", VfsNodeType::File)?;
-    let file_hp = sys_root
-        .clone()
-        .lookup("./kernel/mm/transparent_hugepage/enabled")?;
-    file_hp.write_at(0, b"always [madvise] never\n")?;
-
-    // Create /sys/devices/system/clocksource/clocksource0/current_clocksource
-    sys_root.create("devices", VfsNodeType::Dir)?;
-    sys_root.create("devices/system", VfsNodeType::Dir)?;
-    sys_root.create("devices/system/clocksource", VfsNodeType::Dir)?;
-    sys_root.create("devices/system/clocksource/clocksource0", VfsNodeType::Dir)?;
-    sys_root.create(
-        "devices/system/clocksource/clocksource0/current_clocksource",
-        VfsNodeType::File,
-    )?;
-    let file_cc = sys_root
-        .clone()
-        .lookup("devices/system/clocksource/clocksource0/current_clocksource")?;
-    file_cc.write_at(0, b"tsc\n")?;
-
-    Ok(Arc::new(sysfs))
+pub(crate) fn sysfs() -> Arc<fs::pseudofs::PseudoFileSystem> {
+    fs::sysfs::add_file("kernel/mm/transparent_hugepage/enabled", || {
+        "always [madvise] never\n".into()
+    });
+    Arc::new(fs::sysfs::new_sysfs())
 }
@@ -1,8 +1,9 @@
-//! Root directory of the filesystem
+//! Root directory of the filesystem, with a table of mounted filesystems.
 //!
-//! TODO: it doesn't work very well if the mount points have containment relationships.
+//! Mount points are matched component-wise, and the longest match wins, so
+//! filesystems can be mounted inside other mounted filesystems.
 
-use alloc::{string::String, sync::Arc, vec::Vec};
+use alloc::{format, string::String, sync::Arc, vec, vec::Vec};
 use axerrno::{AxError, AxResult, ax_err};
 use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType, VfsOps, VfsResult};
 use axns::{ResArc, def_resource};
@@ -11,26 +12,79 @@ use lazyinit::LazyInit;
 
 use crate::{api::FileType, fs, mounts};
 
+/// Maximum number of symbolic links followed when resolving a path.
+const MAX_SYMLINK_HOPS: usize = 40;
+
 def_resource! {
     static CURRENT_DIR_PATH: ResArc<Mutex<String>> = ResArc::new();
     static CURRENT_DIR: ResArc<Mutex<VfsNodeRef>> = ResArc::new();
 }
 
+/// An error of expanding the symbolic links in a path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResolveError {
+    /// More than [`MAX_SYMLINK_HOPS`] symbolic links are followed, usually
+    /// because they form a loop (`ELOOP`).
+    SymlinkLoop,
+    /// Looking up a component failed.
+    Lookup(AxError),
+}
+
+impl From<AxError> for ResolveError {
+    fn from(e: AxError) -> Self {
+        Self::Lookup(e)
+    }
+}
+
+/// [`AxError`] has no kind for loops of symbolic links, they become
+/// [`InvalidInput`](AxError::InvalidInput).
+impl From<ResolveError> for AxError {
+    fn from(e: ResolveError) -> Self {
+        match e {
+            ResolveError::SymlinkLoop => AxError::InvalidInput,
+            ResolveError::Lookup(e) => e,
+        }
+    }
+}
+
 struct MountPoint {
-    path: &'static str,
+    /// Canonical absolute path of the mount point, e.g. `/mnt/usb`.
+    path: String,
     fs: Arc<dyn VfsOps>,
+    /// Where the filesystem comes from, e.g. `/dev/vdb`.
+    source: String,
+    /// Type name of the filesystem, e.g. `vfat`.
+    fstype: String,
 }
 
 struct RootDirectory {
     main_fs: Arc<dyn VfsOps>,
-    mounts: Vec<MountPoint>,
+    main_source: String,
+    main_fstype: String,
+    mounts: Mutex<Vec<MountPoint>>,
 }
 
 static ROOT_DIR: LazyInit<Arc<RootDirectory>> = LazyInit::new();
 
 impl MountPoint {
-    pub fn new(path: &'static str, fs: Arc<dyn VfsOps>) -> Self {
-        Self { path, fs }
+    pub fn new(path: String, fs: Arc<dyn VfsOps>, source: &str, fstype: &str) -> Self {
+        Self {
+            path,
+            fs,
+            source: source.into(),
+            fstype: fstype.into(),
+        }
+    }
+
+    /// Returns the number of path components of the mount point.
+    fn depth(&self) -> usize {
+        path_components(&self.path).count()
+    }
+
+    /// Whether the mount point is `path` itself or one of its ancestors.
+    fn is_prefix_of(&self, path: &[&str]) -> bool {
+        let mut comps = path.iter();
+        path_components(&self.path).all(|c| comps.next() == Some(&c))
     }
 }
 
@@ -40,37 +94,103 @@ impl Drop for MountPoint {
     }
 }
 
+/// Splits a path into components, skipping empty and `.` components.
+///
+/// `..` components are preserved and left to the filesystems to resolve.
+fn path_components(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|c| !c.is_empty() && *c != ".")
+}
+
 impl RootDirectory {
-    pub const fn new(main_fs: Arc<dyn VfsOps>) -> Self {
+    pub const fn new(main_fs: Arc<dyn VfsOps>, main_source: String, main_fstype: String) -> Self {
         Self {
             main_fs,
-            mounts: Vec::new(),
+            main_source,
+            main_fstype,
+            mounts: Mutex::new(Vec::new()),
         }
     }
 
-    pub fn mount(&mut self, path: &'static str, fs: Arc<dyn VfsOps>) -> AxResult {
+    pub fn mount(&self, path: &str, fs: Arc<dyn VfsOps>, source: &str, fstype: &str) -> AxResult {
+        if !path.starts_with('/') {
+            return ax_err!(InvalidInput, "mount path must start with '/'");
+        }
+        let path = axfs_vfs::path::canonicalize(path);
         if path == "/" {
             return ax_err!(InvalidInput, "cannot mount root filesystem");
         }
-        if !path.starts_with('/') {
-            return ax_err!(InvalidInput, "mount path must start with '/'");
+        if self.contains(&path) {
+            return ax_err!(InvalidInput, "mount point already exists");
         }
-        if self.mounts.iter().any(|mp| mp.path == path) {
+        if self.is_mounted(&fs) {
+            return ax_err!(ResourceBusy, "filesystem is already mounted");
+        }
+
+        // create the mount point in the parent filesystem if it does not exist
+        let mount_point = self.lookup_mounted_fs(&path, |parent_fs, rest_path| {
+            match parent_fs.root_dir().create(rest_path, FileType::Dir) {
+                Ok(()) | Err(AxError::AlreadyExists) => {}
+                Err(e) => return Err(e),
+            }
+            parent_fs.root_dir().lookup(rest_path)
+        })?;
+        if !mount_point.get_attr()?.is_dir() {
+            return ax_err!(NotADirectory, "mount point must be a directory");
+        }
+        fs.mount(&path, mount_point)?;
+
+        let mut mounts = self.mounts.lock();
+        if mounts.iter().any(|mp| mp.path == path) {
             return ax_err!(InvalidInput, "mount point already exists");
         }
-        // create the mount point in the main filesystem if it does not exist
-        self.main_fs.root_dir().create(path, FileType::Dir)?;
-        fs.mount(path, self.main_fs.root_dir().lookup(path)?)?;
-        self.mounts.push(MountPoint::new(path, fs));
+        mounts.push(MountPoint::new(path, fs, source, fstype));
         Ok(())
     }
 
-    pub fn _umount(&mut self, path: &str) {
-        self.mounts.retain(|mp| mp.path != path);
+    pub fn umount(&self, path: &str) -> AxResult {
+        let path = axfs_vfs::path::canonicalize(path);
+        let mut mounts = self.mounts.lock();
+        let idx = mounts
+            .iter()
+            .position(|mp| mp.path == path)
+            .ok_or(AxError::InvalidInput)?;
+        let prefix = format!("{}/", path);
+        if mounts.iter().any(|mp| mp.path.starts_with(&prefix)) {
+            return ax_err!(
+                ResourceBusy,
+                "filesystems are mounted under the mount point"
+            );
+        }
+        let mp = mounts.remove(idx);
+        drop(mounts);
+        drop(mp); // calls `VfsOps::umount` without holding the lock
+        Ok(())
+    }
+
+    /// Returns `(source, path, fstype)` of the main filesystem and all mounted
+    /// filesystems, in the order they are mounted.
+    pub fn mount_table(&self) -> Vec<(String, String, String)> {
+        let main = (
+            self.main_source.clone(),
+            "/".into(),
+            self.main_fstype.clone(),
+        );
+        let mounts = self.mounts.lock();
+        let mounted = mounts
+            .iter()
+            .map(|mp| (mp.source.clone(), mp.path.clone(), mp.fstype.clone()));
+        core::iter::once(main).chain(mounted).collect()
     }
 
     pub fn contains(&self, path: &str) -> bool {
-        self.mounts.iter().any(|mp| mp.path == path)
+        self.mounts.lock().iter().any(|mp| mp.path == path)
+    }
+
+    /// Whether the filesystem instance is the main filesystem or mounted
+    /// somewhere.
+    fn is_mounted(&self, fs: &Arc<dyn VfsOps>) -> bool {
+        Arc::ptr_eq(fs, &self.main_fs)
+            || self.mounts.lock().iter().any(|mp| Arc::ptr_eq(fs, &mp.fs))
     }
 
     fn lookup_mounted_fs<F, T>(&self, path: &str, f: F) -> AxResult<T>
@@ -78,29 +198,30 @@ impl RootDirectory {
         F: FnOnce(Arc<dyn VfsOps>, &str) -> AxResult<T>,
     {
         debug!("lookup at root: {}", path);
-        let path = path.trim_matches('/');
-        if let Some(rest) = path.strip_prefix("./") {
-            return self.lookup_mounted_fs(rest, f);
-        }
-
-        let mut idx = 0;
-        let mut max_len = 0;
+        let comps = path_components(path).collect::<Vec<_>>();
 
         // Find the filesystem that has the longest mounted path match
-        // TODO: more efficient, e.g. trie
-        for (i, mp) in self.mounts.iter().enumerate() {
-            // skip the first '/'
-            if path.starts_with(&mp.path[1..]) && mp.path.len() - 1 > max_len {
-                max_len = mp.path.len() - 1;
-                idx = i;
-            }
-        }
+        let (fs, depth) = {
+            let mounts = self.mounts.lock();
+            mounts
+                .iter()
+                .filter(|mp| mp.is_prefix_of(&comps))
+                .map(|mp| (mp.fs.clone(), mp.depth()))
+                .max_by_key(|(_, depth)| *depth)
+                .unwrap_or_else(|| (self.main_fs.clone(), 0)) // not matched any mount point
+        };
+        f(fs, &comps[depth..].join("/"))
+    }
 
-        if max_len == 0 {
-            f(self.main_fs.clone(), path) // not matched any mount point
-        } else {
-            f(self.mounts[idx].fs.clone(), &path[max_len..]) // matched at `idx`
-        }
+    /// Returns the source of the filesystem containing `path`, e.g. `/dev/vda`.
+    fn mount_source(&self, path: &str) -> String {
+        let comps = path_components(path).collect::<Vec<_>>();
+        let mounts = self.mounts.lock();
+        mounts
+            .iter()
+            .filter(|mp| mp.is_prefix_of(&comps))
+            .max_by_key(|mp| mp.depth())
+            .map_or(self.main_source.clone(), |mp| mp.source.clone())
     }
 }
 
@@ -136,50 +257,102 @@ impl VfsNodeOps for RootDirectory {
     }
 
     fn rename(&self, src_path: &str, dst_path: &str) -> VfsResult {
+        let (dst_fs, dst_rest) =
+            self.lookup_mounted_fs(dst_path, |fs, rest_path| Ok((fs, String::from(rest_path))))?;
         self.lookup_mounted_fs(src_path, |fs, rest_path| {
-            if rest_path.is_empty() {
+            if rest_path.is_empty() || dst_rest.is_empty() {
                 ax_err!(PermissionDenied) // cannot rename mount points
+            } else if !Arc::ptr_eq(&fs, &dst_fs) {
+                ax_err!(Unsupported, "cannot rename across filesystems")
             } else {
-                fs.root_dir().rename(rest_path, dst_path)
+                fs.root_dir().rename(rest_path, &dst_rest)
             }
         })
     }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
 }
 
-pub(crate) fn init_rootfs(disk: crate::dev::Disk) {
+/// Returns the type name of the main filesystem on `disk`.
+fn main_fstype(disk: &mut crate::dev::Disk) -> &'static str {
     cfg_if::cfg_if! {
         if #[cfg(feature = "myfs")] { // override the default filesystem
-            let main_fs = fs::myfs::new_myfs(disk);
+            let _ = disk;
+            "myfs"
+        } else if #[cfg(all(feature = "ext4", not(feature = "fatfs")))] {
+            let _ = disk;
+            "ext4"
         } else if #[cfg(feature = "fatfs")] {
-            static FAT_FS: LazyInit<Arc<fs::fatfs::FatFileSystem>> = LazyInit::new();
-            FAT_FS.init_once(Arc::new(fs::fatfs::FatFileSystem::new(disk)));
-            FAT_FS.init();
-            let main_fs = FAT_FS.clone();
+            // use ext4 if the disk has been formatted as ext4, otherwise FAT
+            #[cfg(feature = "ext4")]
+            if fs::ext4::Ext4FileSystem::probe(disk) {
+                return "ext4";
+            }
+            let _ = disk;
+            "vfat"
+        }
+    }
+}
+
+/// Creates the main filesystem of type `fstype` on `disk`, see
+/// [`main_fstype`].
+fn new_main_fs(disk: crate::dev::Disk, fstype: &str) -> AxResult<Arc<dyn VfsOps>> {
+    match fstype {
+        #[cfg(feature = "myfs")]
+        "myfs" => Ok(fs::myfs::new_myfs(disk)),
+        #[cfg(feature = "ext4")]
+        "ext4" => Ok(fs::ext4::Ext4FileSystem::open(disk)?),
+        #[cfg(feature = "fatfs")]
+        "vfat" => {
+            #[cfg(feature = "use-ramdisk")]
+            let disk = {
+                let mut disk = disk;
+                fs::fatfs::FatFileSystem::format(&mut disk)?;
+                disk
+            };
+            Ok(fs::fatfs::FatFileSystem::open(disk)?)
         }
+        _ => ax_err!(Unsupported, "unsupported filesystem type"),
     }
+}
 
-    let mut root_dir = RootDirectory::new(main_fs);
+/// Initializes the root directory with the filesystem on `dev`, or a RAM
+/// filesystem if there is no block device.
+pub(crate) fn init_rootfs(dev: Option<Arc<crate::dev::BlockDevice>>) {
+    let root_dir = match dev {
+        Some(dev) => {
+            let fstype = main_fstype(&mut crate::dev::Disk::new(dev.clone()));
+            let main_fs = dev
+                .filesystem_or_init(fstype, |disk| new_main_fs(disk, fstype))
+                .expect("failed to initialize the root filesystem");
+            RootDirectory::new(main_fs, format!("/dev/{}", dev.name()), fstype.into())
+        }
+        #[cfg(feature = "ramfs")]
+        None => RootDirectory::new(mounts::ramfs(), "rootfs".into(), "rootfs".into()),
+        #[cfg(not(feature = "ramfs"))]
+        None => panic!("No block device found!"),
+    };
 
     #[cfg(feature = "devfs")]
     root_dir
-        .mount("/dev", mounts::devfs())
+        .mount("/dev", mounts::devfs(), "devfs", "devfs")
         .expect("failed to mount devfs at /dev");
 
     #[cfg(feature = "ramfs")]
     root_dir
-        .mount("/tmp", mounts::ramfs())
+        .mount("/tmp", mounts::ramfs(), "tmpfs", "tmpfs")
         .expect("failed to mount ramfs at /tmp");
 
-    // Mount another ramfs as procfs
     #[cfg(feature = "procfs")]
-    root_dir // should not fail
-        .mount("/proc", mounts::procfs().unwrap())
+    root_dir
+        .mount("/proc", mounts::procfs(), "proc", "proc")
         .expect("fail to mount procfs at /proc");
 
-    // Mount another ramfs as sysfs
     #[cfg(feature = "sysfs")]
-    root_dir // should not fail
-        .mount("/sys", mounts::sysfs().unwrap())
+    root_dir
+        .mount("/sys", mounts::sysfs(), "sysfs", "sysfs")
         .expect("fail to mount sysfs at /sys");
 
     ROOT_DIR.init_once(Arc::new(root_dir));
@@ -204,11 +377,93 @@ pub(crate) fn absolute_path(path: &str) -> AxResult<String> {
     }
 }
 
-pub(crate) fn lookup(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsNodeRef> {
+/// Reads the target of the symbolic link `node`.
+fn read_link_node(node: &VfsNodeRef) -> AxResult<String> {
+    let mut buf = vec![0; node.get_attr()?.size() as usize];
+    let len = node.read_at(0, &mut buf)?;
+    buf.truncate(len);
+    String::from_utf8(buf).map_err(|_| AxError::InvalidData)
+}
+
+/// Joins `comps` into a path, which is absolute if `path` is.
+fn join_components(path: &str, comps: &[&str]) -> String {
+    let joined = comps.join("/");
+    if path.starts_with('/') {
+        format!("/{}", joined)
+    } else {
+        joined
+    }
+}
+
+/// Expands the symbolic links in `path`, except the last component if
+/// `follow_last` is false and `path` does not end with `/`.
+///
+/// Returns the expanded path and the node it refers to, or [`None`] if only
+/// the last component does not exist.
+pub(crate) fn resolve(
+    dir: Option<&VfsNodeRef>,
+    path: &str,
+    follow_last: bool,
+) -> Result<(String, Option<VfsNodeRef>), ResolveError> {
+    let follow_last = follow_last || path.ends_with('/');
+    let mut path = String::from(path);
+    'resolve: for _ in 0..=MAX_SYMLINK_HOPS {
+        let base = parent_node_of(dir, &path);
+        // fast path: no symbolic links on the way
+        if let Ok(node) = base.clone().lookup(&path) {
+            if !follow_last || !node.get_attr()?.file_type().is_symlink() {
+                return Ok((path, Some(node)));
+            }
+        }
+
+        // find the first symbolic link on the way and expand it
+        let comps = path_components(&path).collect::<Vec<_>>();
+        for i in 0..comps.len() {
+            let last = i + 1 == comps.len();
+            let node = match base.clone().lookup(&join_components(&path, &comps[..=i])) {
+                Ok(node) => node,
+                Err(AxError::NotFound) if last => return Ok((path, None)),
+                Err(e) => return Err(e.into()),
+            };
+            if !node.get_attr()?.file_type().is_symlink() || (last && !follow_last) {
+                if last {
+                    return Ok((path, Some(node)));
+                }
+                continue;
+            }
+
+            let target = read_link_node(&node)?;
+            let mut new_path = if target.starts_with('/') {
+                String::new()
+            } else {
+                join_components(&path, &comps[..i])
+            };
+            let rest = comps[i + 1..].join("/");
+            for part in [target.as_str(), rest.as_str()] {
+                if !part.is_empty() {
+                    if !new_path.is_empty() && !new_path.ends_with('/') {
+                        new_path.push('/');
+                    }
+                    new_path.push_str(part);
+                }
+            }
+            if path.ends_with('/') {
+                new_path.push('/');
+            }
+            path = new_path;
+            continue 'resolve;
+        }
+        return Ok((path, Some(base)));
+    }
+    debug!("too many levels of symbolic links: {}", path);
+    Err(ResolveError::SymlinkLoop)
+}
+
+fn lookup_at(dir: Option<&VfsNodeRef>, path: &str, follow: bool) -> AxResult<VfsNodeRef> {
     if path.is_empty() {
         return ax_err!(NotFound);
     }
-    let node = parent_node_of(dir, path).lookup(path)?;
+    let node = resolve(dir, path, follow)?.1.ok_or(AxError::NotFound)?;
     if path.ends_with('/') && !node.get_attr()?.is_dir() {
         ax_err!(NotADirectory)
     } else {
@@ -216,34 +471,52 @@ pub(crate) fn lookup(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsNodeRe
     }
 }
 
+/// Looks up `path`, following symbolic links.
+pub(crate) fn lookup(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsNodeRef> {
+    lookup_at(dir, path, true)
+}
+
+/// Looks up `path`, but returns the symbolic link itself if it is one.
+pub(crate) fn lookup_nofollow(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsNodeRef> {
+    lookup_at(dir, path, false)
+}
+
 pub(crate) fn create_file(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsNodeRef> {
     if path.is_empty() {
         return ax_err!(NotFound);
     } else if path.ends_with('/') {
         return ax_err!(NotADirectory);
     }
-    let parent = parent_node_of(dir, path);
-    parent.create(path, VfsNodeType::File)?;
-    parent.lookup(path)
+    // a dangling symbolic link is followed, and its target is created
+    let (path, _) = resolve(dir, path, true)?;
+    let parent = parent_node_of(dir, &path);
+    parent.create(&path, VfsNodeType::File)?;
+    parent.lookup(&path)
 }
 
 pub(crate) fn create_dir(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
-    match lookup(dir, path) {
-        Ok(_) => ax_err!(AlreadyExists),
-        Err(AxError::NotFound) => parent_node_of(dir, path).create(path, VfsNodeType::Dir),
-        Err(e) => Err(e),
+    if path.is_empty() {
+        return ax_err!(NotFound);
+    }
+    match resolve(dir, path, false)? {
+        (_, Some(_)) => ax_err!(AlreadyExists),
+        (path, None) => parent_node_of(dir, &path).create(&path, VfsNodeType::Dir),
     }
 }
 
 pub(crate) fn remove_file(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
-    let node = lookup(dir, path)?;
-    let attr = node.get_attr()?;
+    if path.is_empty() {
+        return ax_err!(NotFound);
+    }
+    // removes the symbolic link itself, not its target
+    let (path, node) = resolve(dir, path, false)?;
+    let attr = node.ok_or(AxError::NotFound)?.get_attr()?;
     if attr.is_dir() {
         ax_err!(IsADirectory)
     } else if !attr.perm().owner_writable() {
         ax_err!(PermissionDenied)
     } else {
-        parent_node_of(dir, path).remove(path)
+        parent_node_of(dir, &path).remove(&path)
     }
 }
 
@@ -265,15 +538,119 @@ pub(crate) fn remove_dir(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
         return ax_err!(PermissionDenied);
     }
 
-    let node = lookup(dir, path)?;
-    let attr = node.get_attr()?;
+    let (path, node) = resolve(dir, path, false)?;
+    let attr = node.ok_or(AxError::NotFound)?.get_attr()?;
     if !attr.is_dir() {
         ax_err!(NotADirectory)
     } else if !attr.perm().owner_writable() {
         ax_err!(PermissionDenied)
     } else {
-        parent_node_of(dir, path).remove(path)
+        parent_node_of(dir, &path).remove(&path)
+    }
+}
+
+pub(crate) fn read_link(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<String> {
+    let node = lookup_nofollow(dir, path)?;
+    if !node.get_attr()?.file_type().is_symlink() {
+        return ax_err!(InvalidInput, "not a symbolic link");
+    }
+    read_link_node(&node)
+}
+
+pub(crate) fn symlink(dir: Option<&VfsNodeRef>, target: &str, path: &str) -> AxResult {
+    if path.is_empty() || target.is_empty() {
+        return ax_err!(NotFound);
+    }
+    let (path, node) = resolve(dir, path, false)?;
+    if node.is_some() {
+        return ax_err!(AlreadyExists);
+    }
+    let parent = parent_node_of(dir, &path);
+    parent.create(&path, VfsNodeType::SymLink)?;
+    parent.lookup(&path)?.write_at(0, target.as_bytes())?;
+    Ok(())
+}
+
+/// Writes the cached data of the filesystem containing `path` back to its
+/// block device, if it is on one.
+pub(crate) fn sync_fs(path: &str) -> AxResult {
+    let source = ROOT_DIR.mount_source(&absolute_path(path)?);
+    match crate::dev::find_block_device(&source) {
+        Ok(dev) => dev.sync(),
+        Err(_) => Ok(()),
+    }
+}
+
+pub(crate) fn create_socket(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
+    if path.is_empty() {
+        return ax_err!(NotFound);
+    }
+    match resolve(dir, path, false)? {
+        (_, Some(_)) => ax_err!(AlreadyExists),
+        (path, None) => parent_node_of(dir, &path).create(&path, VfsNodeType::Socket),
+    }
+}
+
+pub(crate) fn hard_link(dir: Option<&VfsNodeRef>, old: &str, new: &str) -> AxResult {
+    if new.is_empty() {
+        return ax_err!(NotFound);
+    }
+    let node = lookup_nofollow(dir, old)?;
+    if node.get_attr()?.is_dir() {
+        return ax_err!(PermissionDenied, "cannot hard link directories");
+    }
+    let (new, new_node) = resolve(dir, new, false)?;
+    if new_node.is_some() {
+        return ax_err!(AlreadyExists);
+    }
+    let (old, _) = resolve(dir, old, false)?;
+    let (old_fs, _) = ROOT_DIR.lookup_mounted_fs(&link_path(dir, &old)?, |fs, rest| {
+        Ok((fs, String::from(rest)))
+    })?;
+    ROOT_DIR.lookup_mounted_fs(&link_path(dir, &new)?, |fs, rest| {
+        if !Arc::ptr_eq(&fs, &old_fs) {
+            return ax_err!(Unsupported, "cannot hard link across filesystems");
+        }
+        match fs::node_ext(&fs.root_dir()) {
+            Some(root) => root.link(rest, &node),
+            None => ax_err!(PermissionDenied, "filesystem does not support hard links"),
+        }
+    })
+}
+
+/// Returns the absolute path of `path`, which can only be relative to the
+/// current directory.
+fn link_path(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<String> {
+    if dir.is_some() && !path.starts_with('/') {
+        return ax_err!(Unsupported, "hard links must be created by absolute paths");
+    }
+    absolute_path(path)
+}
+
+pub(crate) fn mount(path: &str, fs: Arc<dyn VfsOps>, source: &str, fstype: &str) -> AxResult {
+    ROOT_DIR.mount(&absolute_path(path)?, fs, source, fstype)
+}
+
+/// Returns `(source, path, fstype)` of all mounted filesystems, the root
+/// first.
+pub(crate) fn mount_table() -> Vec<(String, String, String)> {
+    ROOT_DIR.mount_table()
+}
+
+pub(crate) fn umount(path: &str) -> AxResult {
+    let abs_path = absolute_path(path)?;
+    if CURRENT_DIR_PATH
+        .lock()
+        .starts_with(&format!("{}/", abs_path))
+    {
+        return ax_err!(
+            ResourceBusy,
+            "current directory is in the mounted filesystem"
+        );
     }
+    ROOT_DIR.umount(&abs_path)?;
+    // write back what the unmounted filesystem left in the block cache
+    crate::dev::sync_all()
 }
 
 pub(crate) fn current_dir() -> AxResult<String> {
@@ -305,9 +682,11 @@ pub(crate) fn set_current_dir(path: &str) -> AxResult {
 }
 
 pub(crate) fn rename(old: &str, new: &str) -> AxResult {
-    if parent_node_of(None, new).lookup(new).is_ok() {
+    let (old, _) = resolve(None, old, false)?;
+    let (new, new_node) = resolve(None, new, false)?;
+    if new_node.is_some() {
         warn!("dst file already exist, now remove it");
-        remove_file(None, new)?;
+        remove_file(None, &new)?;
     }
-    parent_node_of(None, old).rename(old, new)
+    parent_node_of(None, &old).rename(&old, &new)
 }
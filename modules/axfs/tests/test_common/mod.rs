@@ -252,6 +252,386 @@ fn test_devfs_ramfs() -> Result<()> {
     Ok(())
 }
 
+fn test_mount() -> Result<()> {
+    println!("test mount and umount:");
+
+    // mount points are matched by path components
+    fs::mount_by_type("none", "/mnt", "tmpfs")?;
+    fs::write("/mnt/a.txt", "outer")?;
+    fs::create_dir("/mntfoo")?;
+    assert_err!(fs::metadata("/mntfoo/a.txt"), NotFound);
+    assert_eq!(fs::read_to_string("///mnt/.//a.txt")?, "outer");
+
+    // nested mount points
+    fs::mount_by_type("none", "/mnt/inner", "tmpfs")?;
+    fs::write("/mnt//./inner/b.txt", "inner")?;
+    assert_eq!(fs::read_to_string("/mnt/inner/b.txt")?, "inner");
+    assert_err!(fs::metadata("/mnt/b.txt"), NotFound);
+    assert_err!(
+        fs::mount_by_type("none", "/mnt/inner/", "tmpfs"),
+        InvalidInput
+    );
+    assert_err!(
+        fs::mount_by_type("none", "/mnt/a.txt", "tmpfs"),
+        NotADirectory
+    );
+    assert_err!(fs::remove_dir("/mnt/inner"), PermissionDenied);
+    assert_err!(fs::umount("/mnt"), ResourceBusy);
+
+    // umount in reverse order
+    fs::umount("/mnt/inner")?;
+    assert_err!(fs::metadata("/mnt/inner/b.txt"), NotFound);
+    assert!(fs::metadata("/mnt/inner")?.is_dir());
+    fs::umount("/mnt/")?;
+    assert_err!(fs::metadata("/mnt/a.txt"), NotFound);
+    assert_err!(fs::umount("/mnt"), InvalidInput);
+
+    fs::remove_dir("/mntfoo")?;
+    fs::remove_dir("/mnt")?;
+
+    println!("test_mount() OK!");
+    Ok(())
+}
+
+fn test_procfs() -> Result<()> {
+    println!("test procfs:");
+
+    // files are generated when read
+    assert_eq!(fs::read_to_string("/proc/sys/vm/overcommit_memory")?, "0\n");
+    axfs::procfs::add_file("test/answer", || "42\n".into());
+    assert_eq!(fs::read_to_string("/proc/test/answer")?, "42\n");
+    assert!(fs::metadata("/proc/test")?.is_dir());
+    let dirents = fs::read_dir("/proc")?
+        .map(|e| e.unwrap().file_name())
+        .collect::<Vec<_>>();
+    assert!(dirents.contains(&"test".into()));
+    assert!(dirents.contains(&"mounts".into()));
+    assert_eq!(fs::read_to_string("/proc/test/.././test/answer")?, "42\n");
+    assert_err!(fs::write("/proc/test/answer", "0"), PermissionDenied);
+    assert_err!(fs::create_dir("/proc/test/dir"), PermissionDenied);
+    assert_err!(fs::metadata("/proc/test/none"), NotFound);
+
+    // mounts are listed as they change
+    let mounts = fs::read_to_string("/proc/mounts")?;
+    assert!(mounts.lines().any(|l| l.starts_with("tmpfs /tmp tmpfs ")));
+    assert!(!mounts.contains(" /mnt "));
+    fs::mount_by_type("none", "/mnt", "tmpfs")?;
+    assert!(fs::read_to_string("/proc/mounts")?.contains("none /mnt tmpfs "));
+    fs::umount("/mnt")?;
+    fs::remove_dir("/mnt")?;
+
+    // task directories and `/proc/self`
+    axfs::procfs::set_task_source(|| vec![1, 2], || Some(2));
+    axfs::procfs::add_task_file("status", |id| Some(format!("Tid:\t{}\n", id)));
+    assert_eq!(fs::read_to_string("/proc/1/status")?, "Tid:\t1\n");
+    assert_eq!(fs::read_link("/proc/self")?, "2");
+    assert_eq!(fs::read_to_string("/proc/self/status")?, "Tid:\t2\n");
+    assert!(fs::read_dir("/proc/2")?.any(|e| e.unwrap().file_name() == "status"));
+    assert_err!(fs::metadata("/proc/3"), NotFound);
+    assert_err!(fs::metadata("/proc/1/none"), NotFound);
+
+    println!("test_procfs() OK!");
+    Ok(())
+}
+
+fn test_sysfs() -> Result<()> {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    println!("test sysfs:");
+
+    static VALUE: AtomicUsize = AtomicUsize::new(1);
+    let path = "/sys/module/test/parameters/value";
+    axfs::sysfs::add_file("module/test/version", || "1.0\n".into());
+    axfs::sysfs::add_tunable(
+        &path[5..],
+        || format!("{}\n", VALUE.load(Ordering::Relaxed)),
+        |value| {
+            let value = value.parse().map_err(|_| Error::InvalidInput)?;
+            VALUE.store(value, Ordering::Relaxed);
+            Ok(())
+        },
+    );
+    assert_eq!(fs::read_to_string("/sys/module/test/version")?, "1.0\n");
+    assert!(fs::read_dir("/sys/module/test")?.any(|e| e.unwrap().file_name() == "parameters"));
+    assert_err!(
+        fs::write("/sys/module/test/version", "2.0"),
+        PermissionDenied
+    );
+
+    // tunables change the state when written
+    assert_eq!(fs::read_to_string(path)?, "1\n");
+    fs::write(path, "42\n")?;
+    assert_eq!(VALUE.load(Ordering::Relaxed), 42);
+    assert_eq!(fs::read_to_string(path)?, "42\n");
+    assert_err!(fs::write(path, "none"), InvalidInput);
+    assert_eq!(fs::read_to_string(path)?, "42\n");
+    assert_err!(fs::remove_file(path), PermissionDenied);
+
+    println!("test_sysfs() OK!");
+    Ok(())
+}
+
+/// Appends a `newc` cpio entry to `archive`.
+fn cpio_entry(archive: &mut Vec<u8>, ino: u32, mode: u32, nlink: u32, name: &str, data: &[u8]) {
+    let fields = [ino, mode, 0, 0, nlink, 0, data.len() as u32, 0, 0, 0, 0];
+    archive.extend_from_slice(b"070701");
+    for field in fields.iter().chain(&[name.len() as u32 + 1, 0]) {
+        archive.extend_from_slice(format!("{:08x}", field).as_bytes());
+    }
+    archive.extend_from_slice(name.as_bytes());
+    archive.push(0);
+    archive.resize(archive.len().next_multiple_of(4), 0);
+    archive.extend_from_slice(data);
+    archive.resize(archive.len().next_multiple_of(4), 0);
+}
+
+fn test_cpio() -> Result<()> {
+    println!("test unpacking cpio archives:");
+
+    let mut archive = Vec::new();
+    cpio_entry(&mut archive, 1, 0o040755, 2, ".", b"");
+    cpio_entry(&mut archive, 2, 0o040755, 2, "etc", b"");
+    cpio_entry(
+        &mut archive,
+        3,
+        0o100644,
+        1,
+        "etc/hosts",
+        b"127.0.0.1 localhost\n",
+    );
+    cpio_entry(&mut archive, 4, 0o120777, 1, "hosts", b"etc/hosts");
+    cpio_entry(&mut archive, 5, 0o100644, 2, "a.txt", b"");
+    cpio_entry(&mut archive, 5, 0o100644, 2, "etc/b.txt", b"linked");
+    cpio_entry(&mut archive, 6, 0o020644, 1, "console", b""); // skipped
+    cpio_entry(&mut archive, 0, 0, 1, "TRAILER!!!", b"");
+
+    assert_eq!(axfs::cpio::unpack(&archive, "/tmp/initramfs/")?, 5);
+    assert!(fs::metadata("/tmp/initramfs/etc")?.is_dir());
+    let hosts = "127.0.0.1 localhost\n";
+    assert_eq!(fs::read_to_string("/tmp/initramfs/etc/hosts")?, hosts);
+    assert_eq!(fs::read_link("/tmp/initramfs/hosts")?, "etc/hosts");
+    assert_eq!(fs::read_to_string("/tmp/initramfs/hosts")?, hosts);
+    assert_eq!(fs::read_to_string("/tmp/initramfs/a.txt")?, "linked");
+    assert_eq!(fs::read_to_string("/tmp/initramfs/etc/b.txt")?, "linked");
+    assert_err!(fs::metadata("/tmp/initramfs/console"), NotFound);
+
+    // unpacking again overwrites the files
+    assert_eq!(axfs::cpio::unpack(&archive, "/tmp/initramfs")?, 5);
+    assert_eq!(fs::read_to_string("/tmp/initramfs/a.txt")?, "linked");
+
+    // bad archives
+    assert_err!(axfs::cpio::unpack(b"not an archive", "/tmp"), InvalidData);
+    assert_err!(axfs::cpio::unpack(&archive[..200], "/tmp"), InvalidData);
+
+    println!("test_cpio() OK!");
+    Ok(())
+}
+
+fn test_links() -> Result<()> {
+    println!("test symbolic links and hard links:");
+
+    // symbolic links are followed, but can be read or removed themselves
+    fs::create_dir("/tmp/links")?;
+    fs::write("/tmp/links/file.txt", "linked")?;
+    fs::symlink("file.txt", "/tmp/links/rel")?;
+    fs::symlink("/tmp/links", "/tmp/abs")?;
+    assert_eq!(fs::read_link("/tmp/links/rel")?, "file.txt");
+    assert_eq!(fs::read_to_string("/tmp/links/rel")?, "linked");
+    assert_eq!(fs::read_to_string("/tmp/abs/./rel")?, "linked");
+    assert!(fs::metadata("/tmp/abs")?.is_dir());
+    assert!(fs::symlink_metadata("/tmp/abs")?.is_symlink());
+    assert!(fs::symlink_metadata("/tmp/abs/")?.is_dir());
+    fs::write("/tmp/abs/new.txt", "new")?;
+    assert_eq!(fs::read_to_string("/tmp/links/new.txt")?, "new");
+    assert_err!(fs::read_link("/tmp/links/file.txt"), InvalidInput);
+    assert_err!(fs::symlink("file.txt", "/tmp/links/rel"), AlreadyExists);
+
+    // dangling links and loops
+    fs::symlink("missing", "/tmp/links/dangling")?;
+    assert_err!(fs::metadata("/tmp/links/dangling"), NotFound);
+    assert!(fs::symlink_metadata("/tmp/links/dangling")?.is_symlink());
+    fs::symlink("loop2", "/tmp/links/loop1")?;
+    fs::symlink("loop1", "/tmp/links/loop2")?;
+    assert_err!(fs::metadata("/tmp/links/loop1"), InvalidInput);
+    assert_err!(fs::read("/tmp/links/loop2/file.txt"), InvalidInput);
+    let loop_err = Err(fs::ResolveError::SymlinkLoop);
+    assert_eq!(fs::resolve_symlinks("/tmp/links/loop2/file.txt"), loop_err);
+    assert_eq!(
+        fs::resolve_symlinks("/tmp/abs/rel"),
+        Ok("/tmp/links/file.txt".into())
+    );
+
+    // hard links share the content
+    fs::hard_link("/tmp/links/file.txt", "/tmp/links/hard")?;
+    fs::write("/tmp/links/hard", "changed")?;
+    assert_eq!(fs::read_to_string("/tmp/links/file.txt")?, "changed");
+    fs::remove_file("/tmp/links/file.txt")?;
+    assert_eq!(fs::read_to_string("/tmp/links/hard")?, "changed");
+    assert_err!(fs::read("/tmp/links/rel"), NotFound);
+    assert_err!(
+        fs::hard_link("/tmp/links", "/tmp/dir_link"),
+        PermissionDenied
+    );
+    assert_err!(fs::hard_link("/tmp/links/hard", "/very/hard"), Unsupported);
+    assert_err!(fs::hard_link("/dev/null", "/dev/null2"), PermissionDenied);
+
+    // removing a link does not remove its target
+    fs::remove_file("/tmp/abs")?;
+    assert!(fs::metadata("/tmp/links")?.is_dir());
+    for name in ["rel", "new.txt", "dangling", "loop1", "loop2", "hard"] {
+        fs::remove_file(&format!("/tmp/links/{}", name))?;
+    }
+    fs::remove_dir("/tmp/links")?;
+
+    println!("test_links() OK!");
+    Ok(())
+}
+
+fn test_socket_file() -> Result<()> {
+    println!("test socket files:");
+
+    fs::create_dir("/tmp/sockets")?;
+    fs::create_socket("/tmp/sockets/a.sock")?;
+    assert!(fs::metadata("/tmp/sockets/a.sock")?.file_type().is_socket());
+    assert_err!(fs::create_socket("/tmp/sockets/a.sock"), AlreadyExists);
+    assert_err!(fs::create_socket("/tmp/missing/a.sock"), NotFound);
+    fs::remove_file("/tmp/sockets/a.sock")?;
+    assert_err!(fs::metadata("/tmp/sockets/a.sock"), NotFound);
+    fs::remove_dir("/tmp/sockets")?;
+
+    println!("test_socket_file() OK!");
+    Ok(())
+}
+
+fn test_block_devices() -> Result<()> {
+    println!("test block devices:");
+
+    // the raw node of the root device
+    let md = fs::metadata("/dev/vda")?;
+    assert_eq!(md.file_type(), FileType::BlockDevice);
+    assert_eq!(fs::read("/dev/vda")?.len() as u64, md.len());
+    assert_err!(fs::metadata("/dev/vdb"), NotFound);
+
+    // the root device is already mounted, and has no filesystem of other types
+    let mounts = fs::read_to_string("/proc/mounts")?;
+    let root_fstype = mounts.split(' ').nth(2).unwrap();
+    for fstype in ["vfat", "ext4"] {
+        let err = if fstype == root_fstype {
+            Error::ResourceBusy
+        } else if fstype == "ext4" && !cfg!(feature = "ext4") {
+            Error::Unsupported
+        } else {
+            Error::InvalidInput
+        };
+        let res = fs::mount_by_type("/dev/vda", "/mnt", fstype);
+        assert_eq!(res.err(), Some(err));
+    }
+    assert_err!(fs::mount_by_type("/dev/vdb", "/mnt", "vfat"), NotFound);
+    assert_err!(fs::metadata("/mnt"), NotFound);
+
+    println!("test_block_devices() OK!");
+    Ok(())
+}
+
+fn test_block_cache() -> Result<()> {
+    println!("test block cache:");
+
+    // read the first block of the root device twice, the second is a hit
+    let mut block = [0; 512];
+    let mut file = File::open("/dev/vda")?;
+    file.read_exact(&mut block)?;
+    let stats = fs::block_cache_stats("vda")?;
+    file.seek(io::SeekFrom::Start(0))?;
+    file.read_exact(&mut block)?;
+    let new_stats = fs::block_cache_stats("/dev/vda")?;
+    assert_eq!(new_stats.hits, stats.hits + 1);
+    assert_eq!(new_stats.misses, stats.misses);
+    assert!(new_stats.cached > 0);
+    drop(file);
+
+    // writes stay in the cache until synced
+    fs::sync()?;
+    let mut file = OpenOptions::new().write(true).open("/dev/vda")?;
+    file.write_all(&block)?;
+    let stats = fs::block_cache_stats("vda")?;
+    assert_eq!(stats.dirty, 1);
+    fs::sync()?;
+    let new_stats = fs::block_cache_stats("vda")?;
+    assert_eq!(new_stats.dirty, 0);
+    assert_eq!(new_stats.writebacks, stats.writebacks + 1);
+    assert_err!(fs::block_cache_stats("vdb"), NotFound);
+
+    println!("test_block_cache() OK!");
+    Ok(())
+}
+
+fn test_flush() -> Result<()> {
+    println!("test flush:");
+
+    let mut opts = axfs::fops::OpenOptions::new();
+    opts.write(true);
+    opts.create(true);
+
+    // flushing a file writes the blocks of its device back
+    let file = axfs::fops::File::open("/flush.txt", &opts)?;
+    file.write_at(0, b"flushed")?;
+    file.flush()?;
+    assert_eq!(fs::block_cache_stats("vda")?.dirty, 0);
+
+    // files in memory have nothing to flush
+    let file = axfs::fops::File::open("/tmp/flush.txt", &opts)?;
+    file.write_at(0, b"flushed")?;
+    file.flush()?;
+
+    // so do directories
+    fs::create_dir("/flush-dir")?;
+    fs::sync_fs("/flush-dir")?;
+    assert_eq!(fs::block_cache_stats("vda")?.dirty, 0);
+    fs::sync_fs("/tmp")?;
+
+    fs::remove_dir("/flush-dir")?;
+    fs::remove_file("/tmp/flush.txt")?;
+    fs::remove_file("/flush.txt")?;
+    fs::sync()?;
+    println!("test_flush() OK!");
+    Ok(())
+}
+
+fn test_shutdown_sync() -> Result<()> {
+    println!("test shutdown sync:");
+
+    let mut block = [0; 512];
+    let mut file = OpenOptions::new().read(true).write(true).open("/dev/vda")?;
+    file.read_exact(&mut block)?;
+    let orig = block;
+    block.iter_mut().for_each(|b| *b = !*b);
+    file.seek(io::SeekFrom::Start(0))?;
+    file.write_all(&block)?;
+    assert_eq!(fs::block_cache_stats("vda")?.dirty, 1);
+
+    // exit without syncing, the shutdown hook writes the cache back
+    axhal::power::set_shutdown_hook(|| fs::sync().unwrap());
+    let res = std::panic::catch_unwind(|| {
+        axhal::power::system_off();
+    });
+    assert!(res.is_err()); // powering off is unimplemented in tests
+
+    // read the image back instead of the cache
+    assert_eq!(fs::block_cache_stats("vda")?.dirty, 0);
+    fs::drop_caches();
+    assert_eq!(fs::block_cache_stats("vda")?.cached, 0);
+    let mut read_back = [0; 512];
+    file.seek(io::SeekFrom::Start(0))?;
+    file.read_exact(&mut read_back)?;
+    assert_eq!(read_back, block);
+
+    file.seek(io::SeekFrom::Start(0))?;
+    file.write_all(&orig)?;
+    fs::sync()?;
+
+    println!("test_shutdown_sync() OK!");
+    Ok(())
+}
+
 pub fn test_all() {
     test_read_write_file().expect("test_read_write_file() failed");
     test_read_dir().expect("test_read_dir() failed");
@@ -259,4 +639,14 @@ pub fn test_all() {
     test_create_file_dir().expect("test_create_file_dir() failed");
     test_remove_file_dir().expect("test_remove_file_dir() failed");
     test_devfs_ramfs().expect("test_devfs_ramfs() failed");
+    test_mount().expect("test_mount() failed");
+    test_procfs().expect("test_procfs() failed");
+    test_sysfs().expect("test_sysfs() failed");
+    test_cpio().expect("test_cpio() failed");
+    test_links().expect("test_links() failed");
+    test_socket_file().expect("test_socket_file() failed");
+    test_block_devices().expect("test_block_devices() failed");
+    test_block_cache().expect("test_block_cache() failed");
+    test_flush().expect("test_flush() failed");
+    test_shutdown_sync().expect("test_shutdown_sync() failed");
 }
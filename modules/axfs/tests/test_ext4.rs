@@ -0,0 +1,59 @@
+#![cfg(all(feature = "ext4", not(feature = "myfs")))]
+
+mod test_common;
+
+use axdriver::AxDeviceContainer;
+use axdriver_block::ramdisk::RamDisk;
+use axfs::api as fs;
+use axio::Result;
+
+const IMG_PATH: &str = "resources/ext4.img";
+
+fn make_disk() -> std::io::Result<RamDisk> {
+    let path = std::env::current_dir()?.join(IMG_PATH);
+    println!("Loading disk image from {:?} ...", path);
+    let data = std::fs::read(path)?;
+    println!("size = {} bytes", data.len());
+    Ok(RamDisk::from(&data))
+}
+
+fn test_ext4_links() -> Result<()> {
+    // links in the image, the short target is kept in the inode
+    assert_eq!(fs::read_link("/short-link.txt")?, "short.txt");
+    assert_eq!(fs::read_to_string("/short-link.txt")?, "Rust is cool!\n");
+    assert!(fs::symlink_metadata("/short-link.txt")?.is_symlink());
+    assert_eq!(
+        fs::read_to_string("/short-hardlink.txt")?,
+        "Rust is cool!\n"
+    );
+
+    // a long target takes a block
+    let target = "./very/long/path/../../../very-long-dir-name/very-long-file-name.txt";
+    fs::symlink(target, "/long-link.txt")?;
+    assert_eq!(fs::read_link("/long-link.txt")?, target);
+    assert_eq!(fs::read_to_string("/long-link.txt")?, "Rust is cool!\n");
+
+    fs::hard_link("/very/long/path/test.txt", "/very/hard.txt")?;
+    fs::write("/very/hard.txt", "changed\n")?;
+    assert_eq!(fs::read_to_string("/very/long/path/test.txt")?, "changed\n");
+    fs::remove_file("/very/long/path/test.txt")?;
+    assert_eq!(fs::read_to_string("/very/hard.txt")?, "changed\n");
+
+    // restore the tree for `test_common`
+    fs::write("/very/long/path/test.txt", "Rust is cool!\n")?;
+    fs::remove_file("/very/hard.txt")?;
+    fs::remove_file("/long-link.txt")?;
+    Ok(())
+}
+
+#[test]
+fn test_ext4() {
+    println!("Testing ext4 with ramdisk ...");
+
+    let disk = make_disk().expect("failed to load disk image");
+    axtask::init_scheduler(); // call this to use `axsync::Mutex`.
+    axfs::init_filesystems(AxDeviceContainer::from_one(disk));
+
+    test_ext4_links().expect("test_ext4_links() failed");
+    test_common::test_all();
+}
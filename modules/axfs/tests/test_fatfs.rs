@@ -7,6 +7,45 @@ use axdriver_block::ramdisk::RamDisk;
 
 const IMG_PATH: &str = "resources/fat16.img";
 
+#[cfg(not(feature = "rtc"))]
+fn test_fat_times() {
+    use axfs::fops::{Directory, File, OpenOptions};
+    use std::time::Duration;
+
+    // 1980-01-01 00:00:00, the earliest FAT timestamp
+    const FAT_EPOCH: Duration = Duration::from_secs(315532800);
+
+    let mut read_only = OpenOptions::new();
+    read_only.read(true);
+    let file = File::open("/very-long-dir-name/very-long-file-name.txt", &read_only).unwrap();
+    let times = file.get_times().unwrap();
+    assert!(times.mtime > FAT_EPOCH);
+    assert_eq!(times.ctime, times.mtime);
+
+    // without RTC, new files are stamped with the FAT epoch
+    let mut opts = read_only.clone();
+    opts.write(true);
+    opts.create_new(true);
+    let mut file = File::open("/new-times.txt", &opts).unwrap();
+    file.write(b"Rust is cool!\n").unwrap();
+    let times = file.get_times().unwrap();
+    assert_eq!(times.mtime, FAT_EPOCH);
+    assert_eq!(times.atime, FAT_EPOCH);
+    drop(file);
+    axfs::api::remove_file("/new-times.txt").unwrap();
+
+    // filesystems without timestamps report zeros
+    for path in ["/dev/null", "/dev/vda", "/proc/mounts"] {
+        let file = File::open(path, &read_only).unwrap();
+        assert_eq!(file.get_times().unwrap(), Default::default());
+    }
+    for path in ["/dev", "/dev/foo", "/proc"] {
+        let dir = Directory::open_dir(path, &read_only).unwrap();
+        assert_eq!(dir.get_times().unwrap(), Default::default());
+    }
+    println!("test_fat_times() OK!");
+}
+
 fn make_disk() -> std::io::Result<RamDisk> {
     let path = std::env::current_dir()?.join(IMG_PATH);
     println!("Loading disk image from {:?} ...", path);
@@ -24,4 +63,6 @@ fn test_fatfs() {
     axfs::init_filesystems(AxDeviceContainer::from_one(disk));
 
     test_common::test_all();
+    #[cfg(not(feature = "rtc"))]
+    test_fat_times();
 }
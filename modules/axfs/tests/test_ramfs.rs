@@ -1,4 +1,4 @@
-#![cfg(feature = "myfs")]
+#![cfg(all(feature = "myfs", feature = "ramfs"))]
 
 mod test_common;
 
@@ -8,7 +8,7 @@ use axdriver::AxDeviceContainer;
 use axdriver_block::ramdisk::RamDisk;
 use axfs::api::{self as fs, File};
 use axfs::fops::{Disk, MyFileSystemIf};
-use axfs_ramfs::RamFileSystem;
+use axfs::ramfs::RamFileSystem;
 use axfs_vfs::VfsOps;
 use axio::{Result, Write};
 
@@ -0,0 +1,96 @@
+//! Stack backtraces by walking the chain of frame pointers.
+//!
+//! The kernel must be built with frame pointers (`-C force-frame-pointers=yes`)
+//! for the backtraces to be complete.
+
+/// The most frames to walk.
+const MAX_DEPTH: usize = 32;
+
+/// Returns the frame pointer of the caller.
+#[inline(always)]
+pub fn frame_pointer() -> usize {
+    let fp: usize;
+    unsafe {
+        #[cfg(target_arch = "x86_64")]
+        core::arch::asm!("mov {}, rbp", out(reg) fp);
+        #[cfg(target_arch = "aarch64")]
+        core::arch::asm!("mov {}, x29", out(reg) fp);
+        #[cfg(target_arch = "riscv64")]
+        core::arch::asm!("mv {}, s0", out(reg) fp);
+        #[cfg(target_arch = "loongarch64")]
+        core::arch::asm!("move {}, $fp", out(reg) fp);
+        #[cfg(not(any(
+            target_arch = "x86_64",
+            target_arch = "aarch64",
+            target_arch = "riscv64",
+            target_arch = "loongarch64"
+        )))]
+        {
+            fp = 0;
+        }
+    }
+    fp
+}
+
+/// An iterator over the return addresses of the frames on the stack, from the
+/// innermost one.
+///
+/// It stops at a null or misaligned frame pointer, or one that does not go up
+/// the stack, so that a corrupted chain is not followed far.
+pub struct Frames {
+    fp: usize,
+    depth: usize,
+}
+
+impl Frames {
+    /// Walks the frames from the one that `fp` points to.
+    ///
+    /// # Safety
+    ///
+    /// `fp` must be a frame pointer on a mapped stack, e.g., the one returned
+    /// by [`frame_pointer`].
+    pub unsafe fn new(fp: usize) -> Self {
+        Self { fp, depth: 0 }
+    }
+
+    /// Reads the saved frame pointer and return address of the frame.
+    unsafe fn read_frame(fp: usize) -> (usize, usize) {
+        let fp = fp as *const usize;
+        unsafe {
+            if cfg!(any(target_arch = "riscv64", target_arch = "loongarch64")) {
+                // fp points to the top of the frame, above the saved `ra`
+                (fp.sub(2).read(), fp.sub(1).read())
+            } else {
+                // fp points to the saved fp, below the return address
+                (fp.read(), fp.add(1).read())
+            }
+        }
+    }
+}
+
+impl Iterator for Frames {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.fp == 0 || self.fp % size_of::<usize>() != 0 || self.depth >= MAX_DEPTH {
+            return None;
+        }
+        let (next_fp, ra) = unsafe { Self::read_frame(self.fp) };
+        if ra == 0 {
+            return None;
+        }
+        self.fp = if next_fp > self.fp { next_fp } else { 0 };
+        self.depth += 1;
+        Some(ra)
+    }
+}
+
+/// Logs the backtrace of the caller with the `error` level.
+#[inline(never)]
+pub fn dump() {
+    error!("backtrace:");
+    let frames = unsafe { Frames::new(frame_pointer()) };
+    for (i, ra) in frames.enumerate() {
+        error!("  #{:<2} {:#018x}", i, ra);
+    }
+}
@@ -4,10 +4,27 @@ use axcpu::trap::{IRQ, register_trap_handler};
 
 pub use axplat::irq::{handle, register, set_enable, unregister};
 
+/// Total time spent in IRQ handlers on this CPU, in nanoseconds.
+#[percpu::def_percpu]
+static IRQ_TIME_NANOS: u64 = 0;
+
+/// Returns the total time spent in IRQ handlers on the current CPU since
+/// boot, in nanoseconds.
+///
+/// It's used to split the time spent in IRQs out of the CPU time of tasks.
+#[inline]
+pub fn irq_time_nanos() -> u64 {
+    IRQ_TIME_NANOS.read_current()
+}
+
 #[register_trap_handler(IRQ)]
 fn irq_handler(vector: usize) -> bool {
     let guard = kernel_guard::NoPreempt::new();
+    let start = crate::time::monotonic_time_nanos();
     handle(vector);
+    let elapsed = crate::time::monotonic_time_nanos() - start;
+    // Safety: IRQs are disabled in the handler.
+    unsafe { IRQ_TIME_NANOS.write_current_raw(IRQ_TIME_NANOS.read_current_raw() + elapsed) };
     drop(guard); // rescheduling may occur when preemption is re-enabled.
     true
 }
@@ -56,8 +56,10 @@ cfg_if::cfg_if! {
     }
 }
 
+pub mod backtrace;
 pub mod mem;
 pub mod percpu;
+pub mod power;
 pub mod time;
 
 #[cfg(feature = "tls")]
@@ -74,13 +76,6 @@ pub mod console {
     pub use axplat::console::{read_bytes, write_bytes};
 }
 
-/// CPU power management.
-pub mod power {
-    #[cfg(feature = "smp")]
-    pub use axplat::power::cpu_boot;
-    pub use axplat::power::system_off;
-}
-
 /// Trap handling.
 pub mod trap {
     #[cfg(feature = "uspace")]
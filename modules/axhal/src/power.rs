@@ -0,0 +1,39 @@
+//! CPU power management.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use lazyinit::LazyInit;
+
+#[cfg(feature = "smp")]
+pub use axplat::power::cpu_boot;
+
+static SHUTDOWN_HOOK: LazyInit<fn()> = LazyInit::new();
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+
+/// Sets the function called by [`system_off`] before the machine is powered
+/// off, e.g. to write cached data back to the disks.
+///
+/// It can only be set once.
+pub fn set_shutdown_hook(hook: fn()) {
+    SHUTDOWN_HOOK.init_once(hook);
+}
+
+/// Runs the shutdown hook, then powers the machine off.
+///
+/// The hook runs at most once, even if `system_off` is called again from it
+/// or from another CPU.
+pub fn system_off() -> ! {
+    if !SHUTTING_DOWN.swap(true, Ordering::AcqRel) {
+        if let Some(hook) = SHUTDOWN_HOOK.get() {
+            hook();
+        }
+    }
+    axplat::power::system_off()
+}
+
+/// Powers the machine off without running the shutdown hook.
+///
+/// Used when the system is in an inconsistent state, e.g. on panics.
+pub fn system_off_now() -> ! {
+    axplat::power::system_off()
+}
@@ -8,12 +8,44 @@
 //!
 //! - [`TcpSocket`]: A TCP socket that provides POSIX-like APIs.
 //! - [`UdpSocket`]: A UDP socket that provides POSIX-like APIs.
+//! - [`IcmpSocket`]: A raw socket of ICMP over IPv4, to send and receive ICMP
+//!   messages such as echo requests and replies.
+//! - [`MsgFlags`]: Flags of a single send or receive call of the sockets, such
+//!   as peeking and nonblocking.
 //! - [`dns_query`]: Function for DNS query.
+//! - [`start_capture`], [`stop_capture`]: Functions to capture the frames of
+//!   the NICs as a pcap stream, written to a [`CaptureSink`] such as
+//!   [`ConsoleSink`], with an optional [`CaptureFilter`].
+//! - [`interfaces`], [`routes`]: Functions to list the network interfaces and
+//!   the routing table, which can be changed by [`set_interface_config`],
+//!   [`add_route`] and [`del_route`].
+//!
+//! # Interfaces
+//!
+//! - `lo`: The loopback interface with addresses `127.0.0.1/8` and `::1/128`.
+//!   Packets to these addresses, and to the addresses of other interfaces,
+//!   are sent through it. It always exists, so sockets can communicate with
+//!   each other even if there is no NIC.
+//! - `eth0`, `eth1`, ...: The interfaces of the NICs. The address and the
+//!   default gateway of `ethN` are the `N`-th items of the comma-separated lists
+//!   in the `AX_IP` and `AX_GW` environment variables at build time, such as
+//!   `AX_IP=10.0.2.15,192.168.1.2/16`. The prefix length is 24 if not given.
+//!   `eth0` is configured by DHCP instead if the `dhcp` feature is enabled.
+//!
+//! Packets are sent through the interface selected by the routing table, which
+//! has a route to the network of each address, and a default route via each
+//! gateway. The route with the longest matching prefix is used.
 //!
 //! # Cargo Features
 //!
 //! - `smoltcp`: Use [smoltcp] as the underlying network stack. This is enabled
 //!   by default.
+//! - `dhcp`: Configure the address, the gateway and the DNS servers of `eth0`
+//!   by DHCP, instead of the static configuration. The static configuration is used if
+//!   no lease is acquired during initialization. This is disabled by default.
+//! - `multitask`, `irq`: If both are enabled, blocked sockets sleep until they
+//!   are ready instead of busy polling, and the interfaces are polled by a
+//!   background task. These are disabled by default.
 //!
 //! [smoltcp]: https://github.com/smoltcp-rs/smoltcp
 
@@ -30,18 +62,35 @@ cfg_if::cfg_if! {
     }
 }
 
+pub use self::net_impl::IcmpSocket;
+pub use self::net_impl::MsgFlags;
 pub use self::net_impl::TcpSocket;
 pub use self::net_impl::UdpSocket;
+pub use self::net_impl::{CaptureFilter, CaptureSink, ConsoleSink, start_capture, stop_capture};
+pub use self::net_impl::{NetInterfaceInfo, interfaces, set_interface_config};
+pub use self::net_impl::{RouteEntry, add_route, del_route, routes};
 pub use self::net_impl::{bench_receive, bench_transmit};
 pub use self::net_impl::{dns_query, poll_interfaces};
+pub use self::net_impl::{set_tcp_buffer_size, set_udp_buffer_size};
+pub use self::net_impl::{tcp_buffer_size, udp_buffer_size};
 
+use alloc::vec::Vec;
 use axdriver::{AxDeviceContainer, prelude::*};
 
 /// Initializes the network subsystem by NIC devices.
+///
+/// Each NIC gets an interface, and the loopback interface is always created,
+/// even if there is no NIC.
 pub fn init_network(mut net_devs: AxDeviceContainer<AxNetDevice>) {
     info!("Initialize network subsystem...");
 
-    let dev = net_devs.take_one().expect("No NIC device found!");
-    info!("  use NIC 0: {:?}", dev.device_name());
-    net_impl::init(dev);
+    let mut devs = Vec::new();
+    while let Some(dev) = net_devs.take_one() {
+        info!("  use NIC {}: {:?}", devs.len(), dev.device_name());
+        devs.push(dev);
+    }
+    if devs.is_empty() {
+        info!("  no NIC device found, use the loopback interface only");
+    }
+    net_impl::init(devs);
 }
@@ -0,0 +1,268 @@
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::net::IpAddr;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use axerrno::{AxResult, ax_err};
+use axhal::time::{NANOS_PER_MICROS, NANOS_PER_SEC, wall_time_nanos};
+use axsync::Mutex;
+use smoltcp::wire::{EthernetFrame, EthernetProtocol, IpProtocol, Ipv4Packet, Ipv6Packet};
+
+use super::{IFACES, LOOPBACK};
+
+/// Magic number of pcap files with timestamps in microseconds.
+const PCAP_MAGIC: u32 = 0xa1b2_c3d4;
+/// Link-layer header type of Ethernet frames.
+const LINKTYPE_ETHERNET: u32 = 1;
+/// Max number of bytes captured of a frame, longer frames are truncated.
+const SNAP_LEN: usize = 65535;
+
+/// Whether a capture is running, checked before locking [`CAPTURE`] to keep
+/// the fast path cheap.
+static CAPTURING: AtomicBool = AtomicBool::new(false);
+static CAPTURE: Mutex<Option<Capture>> = Mutex::new(None);
+
+/// Destination of the pcap stream of a packet capture.
+pub trait CaptureSink: Send {
+    /// Writes the next part of the stream.
+    ///
+    /// It's called with the interface locked, so it should not block for long
+    /// or use the network.
+    fn write(&mut self, data: &[u8]);
+
+    /// Flushes the stream when the capture stops.
+    fn flush(&mut self) {}
+}
+
+/// Filter of a packet capture. A frame is captured only if it matches all the
+/// given conditions.
+#[derive(Debug, Clone, Default)]
+pub struct CaptureFilter {
+    /// Name of the interface, such as `eth0`. All NICs are captured if it's
+    /// [`None`].
+    pub iface: Option<String>,
+    /// EtherType of the frame, such as `0x0806` for ARP.
+    pub ether_type: Option<u16>,
+    /// Protocol of the IPv4 or IPv6 packet, such as `6` for TCP. Extension
+    /// headers of IPv6 are not skipped.
+    pub ip_protocol: Option<u8>,
+    /// Source or destination address of the IPv4 or IPv6 packet.
+    pub host: Option<IpAddr>,
+    /// Source or destination port of the TCP or UDP segment.
+    pub port: Option<u16>,
+}
+
+struct Capture {
+    sink: Box<dyn CaptureSink>,
+    filter: CaptureFilter,
+    /// Index of the interface in [`IFACES`] given by the filter.
+    iface: Option<usize>,
+    count: usize,
+}
+
+/// A [`CaptureSink`] that prints the stream to the console in base64, in lines
+/// prefixed by `pcap: `.
+///
+/// The lines form a single base64 stream, so the pcap file can be recovered
+/// from the console log by `grep '^pcap: ' log | cut -c7- | base64 -di`.
+pub struct ConsoleSink {
+    /// Bytes not printed yet, less than 3 except during writing.
+    pending: Vec<u8>,
+}
+
+impl CaptureFilter {
+    fn matches(&self, frame: &[u8]) -> bool {
+        let Ok(frame) = EthernetFrame::new_checked(frame) else {
+            return false;
+        };
+        if self
+            .ether_type
+            .is_some_and(|ty| ty != u16::from(frame.ethertype()))
+        {
+            return false;
+        }
+        if self.ip_protocol.is_none() && self.host.is_none() && self.port.is_none() {
+            return true;
+        }
+
+        let (protocol, src_addr, dst_addr, payload) = match frame.ethertype() {
+            EthernetProtocol::Ipv4 => match Ipv4Packet::new_checked(frame.payload()) {
+                Ok(packet) => (
+                    packet.next_header(),
+                    IpAddr::V4(packet.src_addr()),
+                    IpAddr::V4(packet.dst_addr()),
+                    packet.payload(),
+                ),
+                Err(_) => return false,
+            },
+            EthernetProtocol::Ipv6 => match Ipv6Packet::new_checked(frame.payload()) {
+                Ok(packet) => (
+                    packet.next_header(),
+                    IpAddr::V6(packet.src_addr()),
+                    IpAddr::V6(packet.dst_addr()),
+                    packet.payload(),
+                ),
+                Err(_) => return false,
+            },
+            _ => return false,
+        };
+        if self
+            .ip_protocol
+            .is_some_and(|proto| proto != u8::from(protocol))
+        {
+            return false;
+        }
+        if self
+            .host
+            .is_some_and(|host| host != src_addr && host != dst_addr)
+        {
+            return false;
+        }
+        match self.port {
+            Some(port) => {
+                matches!(protocol, IpProtocol::Tcp | IpProtocol::Udp)
+                    && payload.len() >= 4
+                    && (payload[0..2] == port.to_be_bytes() || payload[2..4] == port.to_be_bytes())
+            }
+            None => true,
+        }
+    }
+}
+
+impl ConsoleSink {
+    /// Number of bytes encoded in a line, which must be a multiple of 3 so that
+    /// only the last line is padded.
+    const LINE_BYTES: usize = 57;
+
+    /// Creates a new console sink.
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            pending: Vec::new(),
+        }
+    }
+
+    /// Prints the first `len` pending bytes.
+    fn print(&mut self, len: usize) {
+        for chunk in self.pending[..len].chunks(Self::LINE_BYTES) {
+            let mut line = String::from("pcap: ");
+            encode_base64(chunk, &mut line);
+            line.push('\n');
+            axhal::console::write_bytes(line.as_bytes());
+        }
+        self.pending.drain(..len);
+    }
+}
+
+impl CaptureSink for ConsoleSink {
+    fn write(&mut self, data: &[u8]) {
+        self.pending.extend_from_slice(data);
+        self.print(self.pending.len() / 3 * 3);
+    }
+
+    fn flush(&mut self) {
+        self.print(self.pending.len());
+    }
+}
+
+/// Appends the base64 encoding of `data` to `out`.
+fn encode_base64(data: &[u8], out: &mut String) {
+    const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | ((b as u32) << (16 - 8 * i)));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(TABLE[(n >> (18 - 6 * i)) as usize & 0x3f] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+}
+
+/// Records a frame received or sent on the interface of index `iface`, if a
+/// capture is running and the frame matches its filter.
+pub(crate) fn capture_frame(iface: usize, frame: &[u8]) {
+    if !CAPTURING.load(Ordering::Acquire) {
+        return;
+    }
+    let mut capture = CAPTURE.lock();
+    let Some(capture) = capture.as_mut() else {
+        return;
+    };
+    if capture.iface.is_some_and(|i| i != iface) || !capture.filter.matches(frame) {
+        return;
+    }
+
+    let nanos = wall_time_nanos();
+    let len = frame.len().min(SNAP_LEN);
+    let mut record = Vec::with_capacity(16 + len);
+    record.extend_from_slice(&((nanos / NANOS_PER_SEC) as u32).to_le_bytes());
+    record.extend_from_slice(&((nanos % NANOS_PER_SEC / NANOS_PER_MICROS) as u32).to_le_bytes());
+    record.extend_from_slice(&(len as u32).to_le_bytes());
+    record.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+    record.extend_from_slice(&frame[..len]);
+    capture.sink.write(&record);
+    capture.count += 1;
+}
+
+/// Starts capturing the Ethernet frames received and sent by the NICs that
+/// match `filter`, and writes them to `sink` as a pcap stream, which can be
+/// opened by tools such as Wireshark.
+///
+/// Only one capture can run at a time, it returns
+/// [`ResourceBusy`](axerrno::AxError::ResourceBusy) if there is already one.
+/// The loopback interface can't be captured.
+pub fn start_capture(mut sink: Box<dyn CaptureSink>, filter: CaptureFilter) -> AxResult {
+    let iface = match &filter.iface {
+        Some(name) => match IFACES
+            .iter()
+            .position(|iface| iface.name() == name.as_str())
+        {
+            Some(LOOPBACK) => return ax_err!(InvalidInput, "cannot capture loopback"),
+            Some(index) => Some(index),
+            None => return ax_err!(NotFound, "no such interface"),
+        },
+        None => None,
+    };
+    let mut capture = CAPTURE.lock();
+    if capture.is_some() {
+        return ax_err!(ResourceBusy, "already capturing");
+    }
+
+    let mut header = Vec::with_capacity(24);
+    header.extend_from_slice(&PCAP_MAGIC.to_le_bytes());
+    header.extend_from_slice(&2u16.to_le_bytes()); // major version
+    header.extend_from_slice(&4u16.to_le_bytes()); // minor version
+    header.extend_from_slice(&0i32.to_le_bytes()); // time zone offset
+    header.extend_from_slice(&0u32.to_le_bytes()); // timestamp accuracy
+    header.extend_from_slice(&(SNAP_LEN as u32).to_le_bytes());
+    header.extend_from_slice(&LINKTYPE_ETHERNET.to_le_bytes());
+    sink.write(&header);
+
+    debug!("start capturing {:?}", filter);
+    *capture = Some(Capture {
+        sink,
+        filter,
+        iface,
+        count: 0,
+    });
+    CAPTURING.store(true, Ordering::Release);
+    Ok(())
+}
+
+/// Stops the running capture and flushes its sink. On success, returns the
+/// number of captured frames.
+///
+/// Returns [`BadState`](axerrno::AxError::BadState) if there is no capture.
+pub fn stop_capture() -> AxResult<usize> {
+    let Some(mut capture) = CAPTURE.lock().take() else {
+        return ax_err!(BadState, "not capturing");
+    };
+    CAPTURING.store(false, Ordering::Release);
+    capture.sink.flush();
+    debug!("stop capturing, {} frames captured", capture.count);
+    Ok(capture.count)
+}
@@ -0,0 +1,114 @@
+//! DHCPv4 client, which configures the address, the default gateway and the
+//! DNS servers of an interface from the lease.
+
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use axhal::time::monotonic_time;
+use axsync::Mutex;
+use lazyinit::LazyInit;
+use smoltcp::socket::dhcpv4::{self, Event};
+use smoltcp::wire::{IpAddress, IpCidr, IpVersion, Ipv4Address, Ipv4Cidr};
+
+use super::{IFACES, SOCKET_SET, SocketHandle, set_ip_config, setup_static_config};
+
+/// How long [`init`] waits for the first lease before using the static
+/// configuration.
+const LEASE_TIMEOUT: Duration = Duration::from_secs(5);
+
+static DHCP_HANDLE: LazyInit<SocketHandle> = LazyInit::new();
+
+/// DNS servers of the current lease, [`None`] if not leased.
+static DNS_SERVERS: Mutex<Option<Vec<IpAddress>>> = Mutex::new(None);
+
+/// The configuration from a lease.
+struct Lease {
+    address: Ipv4Cidr,
+    router: Option<Ipv4Address>,
+    dns_servers: Vec<IpAddress>,
+}
+
+/// Starts the DHCP client on the interface of index `iface`, and waits for the
+/// first lease.
+///
+/// If no lease is acquired in [`LEASE_TIMEOUT`], the static configuration
+/// from `AX_IP` and `AX_GW` is used until a lease is acquired later.
+pub(super) fn init(iface: usize) {
+    info!("DHCP: requesting a lease on {:?}...", IFACES[iface].name());
+    DHCP_HANDLE.init_once(SOCKET_SET.add(iface, dhcpv4::Socket::new()));
+
+    let deadline = monotonic_time() + LEASE_TIMEOUT;
+    while DNS_SERVERS.lock().is_none() && monotonic_time() < deadline {
+        SOCKET_SET.poll_interfaces();
+        axtask::yield_now();
+    }
+    if DNS_SERVERS.lock().is_none() {
+        warn!("DHCP: no lease in {:?}", LEASE_TIMEOUT);
+        use_static_config(iface);
+    }
+}
+
+/// Returns the DNS servers of the current lease, or [`None`] if not leased.
+pub(super) fn dns_servers() -> Option<Vec<IpAddress>> {
+    DNS_SERVERS
+        .lock()
+        .clone()
+        .filter(|servers| !servers.is_empty())
+}
+
+/// Applies the configuration changes of the DHCP client, which is called after
+/// polling interfaces.
+///
+/// The lease is renewed by the DHCP socket itself when polled, and a new
+/// configuration is reported if it changes.
+pub(super) fn handle_events() {
+    let Some(&handle) = DHCP_HANDLE.get() else {
+        return;
+    };
+    let event = SOCKET_SET.with_socket_mut::<dhcpv4::Socket, _, _>(handle, |socket| {
+        socket.poll().map(|event| match event {
+            Event::Configured(config) => Some(Lease {
+                address: config.address,
+                router: config.router,
+                dns_servers: config.dns_servers.iter().map(|&s| s.into()).collect(),
+            }),
+            Event::Deconfigured => None,
+        })
+    });
+    match event {
+        Some(Some(lease)) => {
+            let iface = handle.iface();
+            info!("DHCP: leased on {:?}:", iface.name());
+            info!("  ip:       {}", lease.address);
+            if let Some(router) = lease.router {
+                info!("  gateway:  {}", router);
+            }
+            for server in &lease.dns_servers {
+                info!("  dns:      {}", server);
+            }
+            let cidr = IpCidr::Ipv4(lease.address);
+            let gateway = lease.router.map(IpAddress::Ipv4);
+            if let Err(e) = set_ip_config(handle.iface, IpVersion::Ipv4, Some(cidr), gateway) {
+                warn!("DHCP: failed to configure {:?}: {:?}", iface.name(), e);
+            }
+            *DNS_SERVERS.lock() = Some(lease.dns_servers);
+        }
+        Some(None) => {
+            warn!("DHCP: lease lost on {:?}", handle.iface().name());
+            *DNS_SERVERS.lock() = None;
+            use_static_config(handle.iface);
+        }
+        None => {}
+    }
+}
+
+/// Replaces the IPv4 configuration of the interface of index `iface` by the
+/// static one, or clears it if there is none.
+fn use_static_config(iface: usize) {
+    set_ip_config(iface, IpVersion::Ipv4, None, None).ok();
+    info!(
+        "use the static configuration on {:?}:",
+        IFACES[iface].name()
+    );
+    setup_static_config(iface);
+}
@@ -2,11 +2,10 @@ use alloc::vec::Vec;
 use axerrno::{AxError, AxResult, ax_err_type};
 use core::net::IpAddr;
 
-use smoltcp::iface::SocketHandle;
 use smoltcp::socket::dns::{self, GetQueryResultError, StartQueryError};
 use smoltcp::wire::DnsQueryType;
 
-use super::{ETH0, SOCKET_SET, SocketSetWrapper};
+use super::{SOCKET_SET, SocketHandle, SocketSetWrapper, dns_servers, route};
 
 /// A DNS socket.
 struct DnsSocket {
@@ -14,12 +13,14 @@ struct DnsSocket {
 }
 
 impl DnsSocket {
-    #[allow(clippy::new_without_default)]
-    /// Creates a new DNS socket.
-    pub fn new() -> Self {
-        let socket = SocketSetWrapper::new_dns_socket();
-        let handle = Some(SOCKET_SET.add(socket));
-        Self { handle }
+    /// Creates a new DNS socket on the interface to reach the DNS server.
+    pub fn new() -> AxResult<Self> {
+        let servers = dns_servers();
+        let iface = route(servers[0])
+            .ok_or_else(|| ax_err_type!(ConnectionRefused, "socket query() failed: unreachable"))?;
+        let socket = SocketSetWrapper::new_dns_socket(&servers);
+        let handle = Some(SOCKET_SET.add(iface, socket));
+        Ok(Self { handle })
     }
 
     #[allow(dead_code)]
@@ -34,7 +35,7 @@ impl DnsSocket {
     pub fn query(&self, name: &str, query_type: DnsQueryType) -> AxResult<Vec<IpAddr>> {
         // let local_addr = self.local_addr.unwrap_or_else(f);
         let handle = self.handle.ok_or_else(|| ax_err_type!(InvalidInput))?;
-        let iface = &ETH0.iface;
+        let iface = &handle.iface().iface;
         let query_handle = SOCKET_SET
             .with_socket_mut::<dns::Socket, _, _>(handle, |socket| {
                 socket.start_query(iface.lock().context(), name, query_type)
@@ -84,6 +85,6 @@ impl Drop for DnsSocket {
 
 /// Public function for DNS query.
 pub fn dns_query(name: &str) -> AxResult<alloc::vec::Vec<IpAddr>> {
-    let socket = DnsSocket::new();
+    let socket = DnsSocket::new()?;
     socket.query(name, DnsQueryType::A)
 }
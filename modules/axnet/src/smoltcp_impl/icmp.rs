@@ -0,0 +1,259 @@
+use alloc::vec::Vec;
+use core::net::IpAddr;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::Waker;
+use core::time::Duration;
+
+use axerrno::{AxError, AxResult, ax_err, ax_err_type};
+use axio::PollState;
+use axsync::Mutex;
+
+use smoltcp::phy::ChecksumCapabilities;
+use smoltcp::socket::raw;
+use smoltcp::wire::{IpProtocol, IpVersion, Ipv4Packet, Ipv4Repr};
+
+use super::wait::SocketWaiter;
+use super::{IFACES, LOOPBACK, MsgFlags, SOCKET_SET, SocketHandle, SocketSetWrapper, route};
+
+/// Time to live of the sent IP packets.
+const HOP_LIMIT: u8 = 64;
+
+/// Options of an ICMP socket.
+#[derive(Clone, Copy)]
+struct IcmpOptions {
+    recv_timeout: Option<Duration>,
+    send_timeout: Option<Duration>,
+}
+
+/// A raw socket of ICMP over IPv4, like the one created by
+/// `socket(AF_INET, SOCK_RAW, IPPROTO_ICMP)`.
+///
+/// It receives a copy of every ICMP packet on all interfaces, with the IP
+/// header, and sends ICMP messages without the IP header, which is added by
+/// the socket. Echo requests are still replied by the interfaces.
+pub struct IcmpSocket {
+    /// Sockets on all interfaces, the `i`-th one is on `IFACES[i]`.
+    handles: Vec<SocketHandle>,
+    nonblock: AtomicBool,
+    waiter: SocketWaiter,
+    opts: Mutex<IcmpOptions>,
+}
+
+impl IcmpSocket {
+    /// Creates a new ICMP socket.
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        let handles = (0..IFACES.len())
+            .map(|i| {
+                let socket = SocketSetWrapper::new_raw_socket(IpVersion::Ipv4, IpProtocol::Icmp);
+                SOCKET_SET.add(i, socket)
+            })
+            .collect();
+        Self {
+            handles,
+            nonblock: AtomicBool::new(false),
+            waiter: SocketWaiter::new(),
+            opts: Mutex::new(IcmpOptions {
+                recv_timeout: None,
+                send_timeout: None,
+            }),
+        }
+    }
+
+    /// Returns whether this socket is in nonblocking mode.
+    #[inline]
+    pub fn is_nonblocking(&self) -> bool {
+        self.nonblock.load(Ordering::Acquire)
+    }
+
+    /// Moves this ICMP socket into or out of nonblocking mode.
+    ///
+    /// This will result in `recv_from` and `send_to` operations becoming
+    /// nonblocking, i.e., immediately returning from their calls. If the IO
+    /// operation could not be completed and needs to be retried, an error with
+    /// kind [`Err(WouldBlock)`](AxError::WouldBlock) is returned.
+    #[inline]
+    pub fn set_nonblocking(&self, nonblocking: bool) {
+        self.nonblock.store(nonblocking, Ordering::Release);
+    }
+
+    /// Sends an ICMP message in `buf` to the given address, whose checksum
+    /// must be filled by the caller. On success, returns the number of bytes
+    /// written, excluding the IP header.
+    pub fn send_to(&self, buf: &[u8], addr: IpAddr) -> AxResult<usize> {
+        self.send_to_with(buf, addr, MsgFlags::default())
+    }
+
+    /// Sends an ICMP message like [`send_to`](Self::send_to), with the given
+    /// flags, where only [`dont_wait`](MsgFlags::dont_wait) is used.
+    pub fn send_to_with(&self, buf: &[u8], addr: IpAddr, flags: MsgFlags) -> AxResult<usize> {
+        let IpAddr::V4(dst_addr) = addr else {
+            return ax_err!(InvalidInput, "socket send_to() failed: not an IPv4 address");
+        };
+        if dst_addr.is_unspecified() {
+            return ax_err!(InvalidInput, "socket send_to() failed: invalid address");
+        }
+        let iface = route(dst_addr.into()).ok_or_else(|| {
+            ax_err_type!(ConnectionRefused, "socket send_to() failed: unreachable")
+        })?;
+        // the loopback interface has all local addresses, reply to the same one
+        let src_addr = if iface == LOOPBACK {
+            Some(dst_addr)
+        } else {
+            IFACES[iface].iface.lock().ipv4_addr()
+        }
+        .ok_or_else(|| ax_err_type!(ConnectionRefused, "socket send_to() failed: no address"))?;
+
+        let repr = Ipv4Repr {
+            src_addr,
+            dst_addr,
+            next_header: IpProtocol::Icmp,
+            payload_len: buf.len(),
+            hop_limit: HOP_LIMIT,
+        };
+        let len = repr.buffer_len() + buf.len();
+        let handle = self.handles[iface];
+        let timeout = self.opts.lock().send_timeout;
+        self.block_on(timeout, flags.dont_wait, || {
+            SOCKET_SET.with_socket_mut::<raw::Socket, _, _>(handle, |socket| {
+                if len > socket.payload_send_capacity() {
+                    return ax_err!(InvalidInput, "socket send_to() failed: message too long");
+                }
+                // tx buffer is full
+                let packet = socket.send(len).map_err(|_| AxError::WouldBlock)?;
+                let mut packet = Ipv4Packet::new_unchecked(packet);
+                repr.emit(&mut packet, &ChecksumCapabilities::default());
+                packet.payload_mut().copy_from_slice(buf);
+                Ok(buf.len())
+            })
+        })
+    }
+
+    /// Receives a single ICMP packet on the socket, including the IP header.
+    /// On success, returns the number of bytes read and the origin.
+    ///
+    /// The packet is truncated if `buf` is too small.
+    pub fn recv_from(&self, buf: &mut [u8]) -> AxResult<(usize, IpAddr)> {
+        self.recv_from_with(buf, MsgFlags::default())
+            .map(|(len, _, addr)| (len, addr))
+    }
+
+    /// Receives a single ICMP packet like [`recv_from`](Self::recv_from), with
+    /// the given flags, where [`wait_all`](MsgFlags::wait_all) is ignored.
+    /// On success, returns the number of bytes read, the full length of the
+    /// packet, and the origin.
+    pub fn recv_from_with(
+        &self,
+        buf: &mut [u8],
+        flags: MsgFlags,
+    ) -> AxResult<(usize, usize, IpAddr)> {
+        let timeout = self.opts.lock().recv_timeout;
+        self.block_on(timeout, flags.dont_wait, || {
+            for &handle in &self.handles {
+                let res = SOCKET_SET.with_socket_mut::<raw::Socket, _, _>(handle, |socket| {
+                    // data available
+                    socket.can_recv().then(|| {
+                        let packet = if flags.peek {
+                            socket.peek()
+                        } else {
+                            socket.recv()
+                        }
+                        .map_err(|_| ax_err_type!(BadState, "socket recv_from() failed"))?;
+                        let src_addr = Ipv4Packet::new_checked(packet)
+                            .map_err(|_| ax_err_type!(InvalidData, "socket recv_from() failed"))?
+                            .src_addr();
+                        let len = packet.len().min(buf.len());
+                        buf[..len].copy_from_slice(&packet[..len]);
+                        Ok((len, packet.len(), IpAddr::V4(src_addr)))
+                    })
+                });
+                if let Some(res) = res {
+                    return res;
+                }
+            }
+            // no more data
+            Err(AxError::WouldBlock)
+        })
+    }
+
+    /// Returns the timeout of receiving, or [`None`] if it blocks indefinitely.
+    pub fn recv_timeout(&self) -> Option<Duration> {
+        self.opts.lock().recv_timeout
+    }
+
+    /// Sets the timeout of receiving, after which it fails with
+    /// [`WouldBlock`](AxError::WouldBlock).
+    ///
+    /// Returns [`InvalidInput`](AxError::InvalidInput) if `timeout` is zero.
+    pub fn set_recv_timeout(&self, timeout: Option<Duration>) -> AxResult {
+        if timeout == Some(Duration::ZERO) {
+            return ax_err!(InvalidInput, "zero timeout");
+        }
+        self.opts.lock().recv_timeout = timeout;
+        Ok(())
+    }
+
+    /// Returns the timeout of sending, or [`None`] if it blocks indefinitely.
+    pub fn send_timeout(&self) -> Option<Duration> {
+        self.opts.lock().send_timeout
+    }
+
+    /// Sets the timeout of sending, like
+    /// [`set_recv_timeout`](Self::set_recv_timeout).
+    pub fn set_send_timeout(&self, timeout: Option<Duration>) -> AxResult {
+        if timeout == Some(Duration::ZERO) {
+            return ax_err!(InvalidInput, "zero timeout");
+        }
+        self.opts.lock().send_timeout = timeout;
+        Ok(())
+    }
+
+    /// Whether the socket is readable or writable.
+    pub fn poll(&self) -> AxResult<PollState> {
+        let mut state = PollState {
+            readable: false,
+            writable: false,
+        };
+        for &handle in &self.handles {
+            SOCKET_SET.with_socket::<raw::Socket, _, _>(handle, |socket| {
+                state.readable |= socket.can_recv();
+                state.writable |= socket.can_send();
+            });
+        }
+        Ok(state)
+    }
+}
+
+/// Private methods
+impl IcmpSocket {
+    /// Registers `waker` to be woken up when the socket on any interface
+    /// becomes ready.
+    fn register_waker(&self, waker: &Waker) {
+        for &handle in &self.handles {
+            SOCKET_SET.with_socket_mut::<raw::Socket, _, _>(handle, |socket| {
+                socket.register_recv_waker(waker);
+                socket.register_send_waker(waker);
+            });
+        }
+    }
+
+    fn block_on<F, T>(&self, timeout: Option<Duration>, dont_wait: bool, mut f: F) -> AxResult<T>
+    where
+        F: FnMut() -> AxResult<T>,
+    {
+        if dont_wait || self.is_nonblocking() {
+            f()
+        } else {
+            self.waiter
+                .block_on(|waker| self.register_waker(waker), timeout, f)
+        }
+    }
+}
+
+impl Drop for IcmpSocket {
+    fn drop(&mut self) {
+        for &handle in &self.handles {
+            SOCKET_SET.remove(handle);
+        }
+    }
+}
@@ -1,26 +1,47 @@
 use alloc::{boxed::Box, collections::VecDeque};
 use core::ops::{Deref, DerefMut};
+use core::task::Waker;
 
 use axerrno::{AxError, AxResult, ax_err};
 use axsync::Mutex;
-use smoltcp::iface::{SocketHandle, SocketSet};
+use smoltcp::iface::SocketSet;
 use smoltcp::socket::tcp::{self, State};
 use smoltcp::wire::{IpAddress, IpEndpoint, IpListenEndpoint};
 
-use super::{LISTEN_QUEUE_SIZE, SOCKET_SET, SocketSetWrapper};
+use super::{LISTEN_QUEUE_SIZE, SOCKET_SET, SocketHandle, SocketSetWrapper};
 
 const PORT_NUM: usize = 65536;
 
 struct ListenTableEntry {
     listen_endpoint: IpListenEndpoint,
+    /// Connections in the handshake or established but not accepted yet.
     syn_queue: VecDeque<SocketHandle>,
+    /// Maximum length of `syn_queue`.
+    backlog: usize,
+    /// Number of the SYN packets dropped as `syn_queue` is full.
+    overflows: usize,
+    /// Waker of the listener, woken up when a connection is established.
+    waker: Option<Waker>,
+    /// Buffer sizes of the new connections.
+    recv_buf_size: usize,
+    send_buf_size: usize,
 }
 
 impl ListenTableEntry {
-    pub fn new(listen_endpoint: IpListenEndpoint) -> Self {
+    pub fn new(
+        listen_endpoint: IpListenEndpoint,
+        backlog: usize,
+        recv_buf_size: usize,
+        send_buf_size: usize,
+    ) -> Self {
         Self {
             listen_endpoint,
-            syn_queue: VecDeque::with_capacity(LISTEN_QUEUE_SIZE),
+            syn_queue: VecDeque::with_capacity(backlog),
+            backlog,
+            overflows: 0,
+            waker: None,
+            recv_buf_size,
+            send_buf_size,
         }
     }
 
@@ -61,12 +82,25 @@ impl ListenTable {
         self.tcp[port as usize].lock().is_none()
     }
 
-    pub fn listen(&self, listen_endpoint: IpListenEndpoint) -> AxResult {
+    /// Starts listening on `listen_endpoint`, with at most `backlog` pending
+    /// connections, which is clamped to `1..=LISTEN_QUEUE_SIZE`.
+    pub fn listen(
+        &self,
+        listen_endpoint: IpListenEndpoint,
+        backlog: usize,
+        recv_buf_size: usize,
+        send_buf_size: usize,
+    ) -> AxResult {
         let port = listen_endpoint.port;
         assert_ne!(port, 0);
         let mut entry = self.tcp[port as usize].lock();
         if entry.is_none() {
-            *entry = Some(Box::new(ListenTableEntry::new(listen_endpoint)));
+            *entry = Some(Box::new(ListenTableEntry::new(
+                listen_endpoint,
+                clamp_backlog(backlog),
+                recv_buf_size,
+                send_buf_size,
+            )));
             Ok(())
         } else {
             ax_err!(AddrInUse, "socket listen() failed")
@@ -78,6 +112,27 @@ impl ListenTable {
         *self.tcp[port as usize].lock() = None;
     }
 
+    /// Changes the maximum number of pending connections on `port`. The
+    /// pending connections beyond it are kept.
+    pub fn set_backlog(&self, port: u16, backlog: usize) -> AxResult {
+        if let Some(entry) = self.tcp[port as usize].lock().deref_mut() {
+            entry.backlog = clamp_backlog(backlog);
+            Ok(())
+        } else {
+            ax_err!(InvalidInput, "socket listen() failed: not listen")
+        }
+    }
+
+    /// Returns the number of the SYN packets to `port` dropped as the queue
+    /// of pending connections is full.
+    pub fn overflows(&self, port: u16) -> AxResult<usize> {
+        if let Some(entry) = self.tcp[port as usize].lock().deref() {
+            Ok(entry.overflows)
+        } else {
+            ax_err!(InvalidInput, "socket is not listening")
+        }
+    }
+
     pub fn can_accept(&self, port: u16) -> AxResult<bool> {
         if let Some(entry) = self.tcp[port as usize].lock().deref() {
             Ok(entry.syn_queue.iter().any(|&handle| is_connected(handle)))
@@ -86,6 +141,16 @@ impl ListenTable {
         }
     }
 
+    /// Registers `waker` to be woken up when a new connection to `port` is
+    /// established.
+    pub fn register_waker(&self, port: u16, waker: &Waker) {
+        if let Some(entry) = self.tcp[port as usize].lock().deref_mut() {
+            if !entry.waker.as_ref().is_some_and(|w| w.will_wake(waker)) {
+                entry.waker = Some(waker.clone());
+            }
+        }
+    }
+
     pub fn accept(&self, port: u16) -> AxResult<(SocketHandle, (IpEndpoint, IpEndpoint))> {
         if let Some(entry) = self.tcp[port as usize].lock().deref_mut() {
             let syn_queue = &mut entry.syn_queue;
@@ -110,10 +175,13 @@ impl ListenTable {
         }
     }
 
+    /// Handles the first packet of a TCP connection received on the interface
+    /// of index `iface`, whose sockets are `sockets`.
     pub fn incoming_tcp_packet(
         &self,
         src: IpEndpoint,
         dst: IpEndpoint,
+        iface: usize,
         sockets: &mut SocketSet<'_>,
     ) {
         if let Some(entry) = self.tcp[dst.port as usize].lock().deref_mut() {
@@ -121,14 +189,29 @@ impl ListenTable {
                 // not listening on this address
                 return;
             }
-            if entry.syn_queue.len() >= LISTEN_QUEUE_SIZE {
+            if entry.syn_queue.len() >= entry.backlog {
                 // SYN queue is full, drop the packet
-                warn!("SYN queue overflow!");
+                // only the first one is logged, not to flood the console under
+                // a SYN flood, see `ListenTable::overflows`
+                entry.overflows += 1;
+                if entry.overflows == 1 {
+                    warn!("SYN queue overflow on port {}", dst.port);
+                } else {
+                    trace!("SYN queue overflow on port {}", dst.port);
+                }
                 return;
             }
-            let mut socket = SocketSetWrapper::new_tcp_socket();
+            let mut socket =
+                SocketSetWrapper::new_tcp_socket(entry.recv_buf_size, entry.send_buf_size);
+            if let Some(waker) = &entry.waker {
+                // woken up when the handshake completes
+                socket.register_recv_waker(waker);
+            }
             if socket.listen(entry.listen_endpoint).is_ok() {
-                let handle = sockets.add(socket);
+                let handle = SocketHandle {
+                    iface,
+                    inner: sockets.add(socket),
+                };
                 debug!(
                     "TCP socket {}: prepare for connection {} -> {}",
                     handle, src, entry.listen_endpoint
@@ -139,6 +222,10 @@ impl ListenTable {
     }
 }
 
+fn clamp_backlog(backlog: usize) -> usize {
+    backlog.clamp(1, LISTEN_QUEUE_SIZE)
+}
+
 fn is_connected(handle: SocketHandle) -> bool {
     SOCKET_SET.with_socket::<tcp::Socket, _, _>(handle, |socket| {
         !matches!(socket.state(), State::Listen | State::SynReceived)
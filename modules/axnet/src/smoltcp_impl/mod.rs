@@ -1,28 +1,43 @@
 mod addr;
 mod bench;
+mod capture;
+#[cfg(feature = "dhcp")]
+mod dhcp;
 mod dns;
+mod icmp;
 mod listen_table;
+mod routing;
 mod tcp;
 mod udp;
+mod wait;
 
-use alloc::vec;
+use alloc::{collections::VecDeque, format, string::String, vec, vec::Vec};
 use core::cell::RefCell;
-use core::ops::DerefMut;
+use core::fmt;
+use core::net::IpAddr;
+use core::ops::{DerefMut, RangeInclusive};
+use core::sync::atomic::{AtomicUsize, Ordering};
 
 use axdriver::prelude::*;
 use axdriver_net::{DevError, NetBufPtr};
+use axerrno::{AxResult, ax_err};
 use axhal::time::{NANOS_PER_MICROS, wall_time_nanos};
 use axsync::Mutex;
 use lazyinit::LazyInit;
-use smoltcp::iface::{Config, Interface, SocketHandle, SocketSet};
+use smoltcp::iface::{Config, Interface, SocketSet};
 use smoltcp::phy::{Device, DeviceCapabilities, Medium, RxToken, TxToken};
 use smoltcp::socket::{self, AnySocket};
 use smoltcp::time::Instant;
-use smoltcp::wire::{EthernetAddress, HardwareAddress, IpAddress, IpCidr};
+use smoltcp::wire::{
+    EthernetAddress, HardwareAddress, IpAddress, IpCidr, IpListenEndpoint, IpProtocol, IpVersion,
+};
 
 use self::listen_table::ListenTable;
 
+pub use self::capture::{CaptureFilter, CaptureSink, ConsoleSink, start_capture, stop_capture};
 pub use self::dns::dns_query;
+pub use self::icmp::IcmpSocket;
+pub use self::routing::{RouteEntry, add_route, del_route, routes};
 pub use self::tcp::TcpSocket;
 pub use self::udp::UdpSocket;
 
@@ -41,63 +56,144 @@ const DNS_SEVER: &str = "8.8.8.8";
 const IP_PREFIX: u8 = 24;
 
 const STANDARD_MTU: usize = 1500;
+const LOOPBACK_MTU: usize = 65536;
 
 const RANDOM_SEED: u64 = 0xA2CE_05A2_CE05_A2CE;
 
-const TCP_RX_BUF_LEN: usize = 64 * 1024;
-const TCP_TX_BUF_LEN: usize = 64 * 1024;
-const UDP_RX_BUF_LEN: usize = 64 * 1024;
-const UDP_TX_BUF_LEN: usize = 64 * 1024;
+/// Valid sizes of socket buffers, see [`set_tcp_buffer_size`].
+const BUF_LEN_RANGE: RangeInclusive<usize> = 1024..=16 * 1024 * 1024;
+
+static TCP_BUF_LEN: AtomicUsize = AtomicUsize::new(64 * 1024);
+static UDP_BUF_LEN: AtomicUsize = AtomicUsize::new(64 * 1024);
+
+/// Size of both the receive and send buffers of raw sockets.
+const RAW_BUF_LEN: usize = 64 * 1024;
+
+/// Maximum number of pending connections of a listening TCP socket, larger
+/// backlogs are truncated to it.
 const LISTEN_QUEUE_SIZE: usize = 512;
 
 static LISTEN_TABLE: LazyInit<ListenTable> = LazyInit::new();
 static SOCKET_SET: LazyInit<SocketSetWrapper> = LazyInit::new();
-static ETH0: LazyInit<InterfaceWrapper> = LazyInit::new();
 
-struct SocketSetWrapper<'a>(Mutex<SocketSet<'a>>);
+/// All interfaces, the loopback interface is the first one.
+static IFACES: LazyInit<Vec<InterfaceWrapper>> = LazyInit::new();
+
+/// Index of the loopback interface `lo` in [`IFACES`].
+const LOOPBACK: usize = 0;
+
+/// Flags of a single send or receive call, like the `MSG_*` flags of `send`
+/// and `recv`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MsgFlags {
+    /// Receives data without removing it from the receive queue (`MSG_PEEK`).
+    pub peek: bool,
+    /// Returns [`WouldBlock`](axerrno::AxError::WouldBlock) instead of
+    /// blocking, even if the socket is in blocking mode (`MSG_DONTWAIT`).
+    pub dont_wait: bool,
+    /// Blocks until the buffer is full, unless the connection is closed, or
+    /// it times out or fails after receiving some data (`MSG_WAITALL`). It's
+    /// only for TCP sockets.
+    pub wait_all: bool,
+}
+
+/// Sockets of all interfaces, each interface has its own set of sockets, so
+/// that packets of a socket are sent only through the interface it is on.
+struct SocketSetWrapper<'a>(Vec<Mutex<SocketSet<'a>>>);
+
+/// Handle of a socket, with the interface it is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SocketHandle {
+    /// Index of the interface in [`IFACES`].
+    iface: usize,
+    inner: smoltcp::iface::SocketHandle,
+}
+
+enum NetDevice {
+    Nic(DeviceWrapper),
+    Loopback(LoopbackDevice),
+}
 
 struct DeviceWrapper {
     inner: RefCell<AxNetDevice>, // use `RefCell` is enough since it's wrapped in `Mutex` in `InterfaceWrapper`.
+    /// Index of the interface in [`IFACES`].
+    iface: usize,
+}
+
+/// A device that sends IP packets back to its own interface.
+struct LoopbackDevice {
+    queue: VecDeque<Vec<u8>>,
 }
 
 struct InterfaceWrapper {
-    name: &'static str,
-    ether_addr: EthernetAddress,
-    dev: Mutex<DeviceWrapper>,
+    name: String,
+    ether_addr: Option<EthernetAddress>,
+    dev: Mutex<NetDevice>,
     iface: Mutex<Interface>,
 }
 
+impl SocketHandle {
+    fn iface(&self) -> &'static InterfaceWrapper {
+        &IFACES[self.iface]
+    }
+}
+
+impl fmt::Display for SocketHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}@{}", self.inner, self.iface().name())
+    }
+}
+
 impl<'a> SocketSetWrapper<'a> {
-    fn new() -> Self {
-        Self(Mutex::new(SocketSet::new(vec![])))
+    fn new(iface_count: usize) -> Self {
+        Self(
+            (0..iface_count)
+                .map(|_| Mutex::new(SocketSet::new(vec![])))
+                .collect(),
+        )
     }
 
-    pub fn new_tcp_socket() -> socket::tcp::Socket<'a> {
-        let tcp_rx_buffer = socket::tcp::SocketBuffer::new(vec![0; TCP_RX_BUF_LEN]);
-        let tcp_tx_buffer = socket::tcp::SocketBuffer::new(vec![0; TCP_TX_BUF_LEN]);
+    pub fn new_tcp_socket(recv_len: usize, send_len: usize) -> socket::tcp::Socket<'a> {
+        let tcp_rx_buffer = socket::tcp::SocketBuffer::new(vec![0; recv_len]);
+        let tcp_tx_buffer = socket::tcp::SocketBuffer::new(vec![0; send_len]);
         socket::tcp::Socket::new(tcp_rx_buffer, tcp_tx_buffer)
     }
 
-    pub fn new_udp_socket() -> socket::udp::Socket<'a> {
+    pub fn new_udp_socket(recv_len: usize, send_len: usize) -> socket::udp::Socket<'a> {
         let udp_rx_buffer = socket::udp::PacketBuffer::new(
             vec![socket::udp::PacketMetadata::EMPTY; 8],
-            vec![0; UDP_RX_BUF_LEN],
+            vec![0; recv_len],
         );
         let udp_tx_buffer = socket::udp::PacketBuffer::new(
             vec![socket::udp::PacketMetadata::EMPTY; 8],
-            vec![0; UDP_TX_BUF_LEN],
+            vec![0; send_len],
         );
         socket::udp::Socket::new(udp_rx_buffer, udp_tx_buffer)
     }
 
-    pub fn new_dns_socket() -> socket::dns::Socket<'a> {
-        let server_addr = DNS_SEVER.parse().expect("invalid DNS server address");
-        socket::dns::Socket::new(&[server_addr], vec![])
+    pub fn new_raw_socket(version: IpVersion, protocol: IpProtocol) -> socket::raw::Socket<'a> {
+        let raw_rx_buffer = socket::raw::PacketBuffer::new(
+            vec![socket::raw::PacketMetadata::EMPTY; 8],
+            vec![0; RAW_BUF_LEN],
+        );
+        let raw_tx_buffer = socket::raw::PacketBuffer::new(
+            vec![socket::raw::PacketMetadata::EMPTY; 8],
+            vec![0; RAW_BUF_LEN],
+        );
+        socket::raw::Socket::new(Some(version), Some(protocol), raw_rx_buffer, raw_tx_buffer)
     }
 
-    pub fn add<T: AnySocket<'a>>(&self, socket: T) -> SocketHandle {
-        let handle = self.0.lock().add(socket);
+    pub fn new_dns_socket(servers: &[IpAddress]) -> socket::dns::Socket<'a> {
+        socket::dns::Socket::new(servers, vec![])
+    }
+
+    /// Adds a socket on the interface of index `iface`.
+    pub fn add<T: AnySocket<'a>>(&self, iface: usize, socket: T) -> SocketHandle {
+        let inner = self.0[iface].lock().add(socket);
+        let handle = SocketHandle { iface, inner };
         debug!("socket {}: created", handle);
+        #[cfg(all(feature = "multitask", feature = "irq"))]
+        wait::kick_poll_task();
         handle
     }
 
@@ -105,8 +201,8 @@ impl<'a> SocketSetWrapper<'a> {
     where
         F: FnOnce(&T) -> R,
     {
-        let set = self.0.lock();
-        let socket = set.get(handle);
+        let set = self.0[handle.iface].lock();
+        let socket = set.get(handle.inner);
         f(socket)
     }
 
@@ -114,33 +210,79 @@ impl<'a> SocketSetWrapper<'a> {
     where
         F: FnOnce(&mut T) -> R,
     {
-        let mut set = self.0.lock();
-        let socket = set.get_mut(handle);
-        f(socket)
+        let res = f(self.0[handle.iface].lock().get_mut(handle.inner));
+        #[cfg(all(feature = "multitask", feature = "irq"))]
+        wait::kick_poll_task();
+        res
     }
 
     pub fn poll_interfaces(&self) {
-        ETH0.poll(&self.0);
+        for (iface, sockets) in IFACES.iter().zip(&self.0) {
+            iface.poll(sockets);
+        }
+        #[cfg(feature = "dhcp")]
+        dhcp::handle_events();
+    }
+
+    /// Returns the time until any interface needs to be polled again, or
+    /// [`None`] if there are no pending timers.
+    #[cfg(all(feature = "multitask", feature = "irq"))]
+    pub fn poll_delay(&self) -> Option<core::time::Duration> {
+        IFACES
+            .iter()
+            .zip(&self.0)
+            .filter_map(|(iface, sockets)| iface.poll_delay(sockets))
+            .min()
+    }
+
+    /// Returns whether any socket of type `T`, whose local endpoint is given by
+    /// `local`, is using the port of `endpoint` on an overlapping address.
+    pub fn is_in_use<T: AnySocket<'a>>(
+        &self,
+        endpoint: IpListenEndpoint,
+        local: impl Fn(&T) -> Option<IpListenEndpoint>,
+    ) -> bool {
+        self.0.iter().any(|set| {
+            set.lock().iter().any(|(_, socket)| {
+                T::downcast(socket).and_then(&local).is_some_and(|other| {
+                    other.port == endpoint.port
+                        && (endpoint.addr.is_none()
+                            || other.addr.is_none()
+                            || endpoint.addr == other.addr)
+                })
+            })
+        })
     }
 
     pub fn remove(&self, handle: SocketHandle) {
-        self.0.lock().remove(handle);
+        self.0[handle.iface].lock().remove(handle.inner);
         debug!("socket {}: destroyed", handle);
+        #[cfg(all(feature = "multitask", feature = "irq"))]
+        wait::kick_poll_task();
     }
 }
 
 impl InterfaceWrapper {
-    fn new(name: &'static str, dev: AxNetDevice, ether_addr: EthernetAddress) -> Self {
-        let mut config = Config::new(HardwareAddress::Ethernet(ether_addr));
+    fn new(name: String, mut dev: NetDevice) -> Self {
+        let (hardware_addr, ether_addr) = match &dev {
+            NetDevice::Nic(dev) => {
+                let ether_addr = EthernetAddress(dev.inner.borrow().mac_address().0);
+                (HardwareAddress::Ethernet(ether_addr), Some(ether_addr))
+            }
+            NetDevice::Loopback(_) => (HardwareAddress::Ip, None),
+        };
+        let mut config = Config::new(hardware_addr);
         config.random_seed = RANDOM_SEED;
 
-        let mut dev = DeviceWrapper::new(dev);
-        let iface = Mutex::new(Interface::new(config, &mut dev, Self::current_time()));
+        let iface = match &mut dev {
+            NetDevice::Nic(dev) => Interface::new(config, dev, Self::current_time()),
+            NetDevice::Loopback(dev) => Interface::new(config, dev, Self::current_time()),
+        };
         Self {
             name,
             ether_addr,
             dev: Mutex::new(dev),
-            iface,
+            iface: Mutex::new(iface),
         }
     }
 
@@ -149,26 +291,27 @@ impl InterfaceWrapper {
     }
 
     pub fn name(&self) -> &str {
-        self.name
+        &self.name
     }
 
-    pub fn ethernet_address(&self) -> EthernetAddress {
+    pub fn ethernet_address(&self) -> Option<EthernetAddress> {
         self.ether_addr
     }
 
-    pub fn setup_ip_addr(&self, ip: IpAddress, prefix_len: u8) {
-        let mut iface = self.iface.lock();
-        iface.update_ip_addrs(|ip_addrs| {
-            ip_addrs.push(IpCidr::new(ip, prefix_len)).unwrap();
-        });
+    pub fn has_ip_addr(&self, addr: IpAddress) -> bool {
+        let iface = self.iface.lock();
+        iface.ip_addrs().iter().any(|cidr| cidr.address() == addr)
     }
 
-    pub fn setup_gateway(&self, gateway: IpAddress) {
+    /// Replaces the addresses of `version` by `cidr`.
+    pub fn set_ip_addr(&self, version: IpVersion, cidr: Option<IpCidr>) {
         let mut iface = self.iface.lock();
-        match gateway {
-            IpAddress::Ipv4(v4) => iface.routes_mut().add_default_ipv4_route(v4).unwrap(),
-            IpAddress::Ipv6(v6) => iface.routes_mut().add_default_ipv6_route(v6).unwrap(),
-        };
+        iface.update_ip_addrs(|ip_addrs| {
+            ip_addrs.retain(|cidr| cidr.address().version() != version);
+            if let Some(cidr) = cidr {
+                ip_addrs.push(cidr).unwrap();
+            }
+        });
     }
 
     pub fn poll(&self, sockets: &Mutex<SocketSet>) {
@@ -176,14 +319,27 @@ impl InterfaceWrapper {
         let mut iface = self.iface.lock();
         let mut sockets = sockets.lock();
         let timestamp = Self::current_time();
-        iface.poll(timestamp, dev.deref_mut(), &mut sockets);
+        match dev.deref_mut() {
+            NetDevice::Nic(dev) => iface.poll(timestamp, dev, &mut sockets),
+            NetDevice::Loopback(dev) => iface.poll(timestamp, dev, &mut sockets),
+        };
+    }
+
+    #[cfg(all(feature = "multitask", feature = "irq"))]
+    fn poll_delay(&self, sockets: &Mutex<SocketSet>) -> Option<core::time::Duration> {
+        let mut iface = self.iface.lock();
+        let sockets = sockets.lock();
+        iface
+            .poll_delay(Self::current_time(), &sockets)
+            .map(Into::into)
     }
 }
 
 impl DeviceWrapper {
-    fn new(inner: AxNetDevice) -> Self {
+    fn new(inner: AxNetDevice, iface: usize) -> Self {
         Self {
             inner: RefCell::new(inner),
+            iface,
         }
     }
 }
@@ -217,7 +373,10 @@ impl Device for DeviceWrapper {
                 return None;
             }
         };
-        Some((AxNetRxToken(&self.inner, rx_buf), AxNetTxToken(&self.inner)))
+        Some((
+            AxNetRxToken(&self.inner, rx_buf, self.iface),
+            AxNetTxToken(&self.inner, self.iface),
+        ))
     }
 
     fn transmit(&mut self, _timestamp: Instant) -> Option<Self::TxToken<'_>> {
@@ -227,7 +386,7 @@ impl Device for DeviceWrapper {
             return None;
         }
         if dev.can_transmit() {
-            Some(AxNetTxToken(&self.inner))
+            Some(AxNetTxToken(&self.inner, self.iface))
         } else {
             None
         }
@@ -242,12 +401,15 @@ impl Device for DeviceWrapper {
     }
 }
 
-struct AxNetRxToken<'a>(&'a RefCell<AxNetDevice>, NetBufPtr);
-struct AxNetTxToken<'a>(&'a RefCell<AxNetDevice>);
+struct AxNetRxToken<'a>(&'a RefCell<AxNetDevice>, NetBufPtr, usize);
+struct AxNetTxToken<'a>(&'a RefCell<AxNetDevice>, usize);
 
 impl RxToken for AxNetRxToken<'_> {
     fn preprocess(&self, sockets: &mut SocketSet<'_>) {
-        snoop_tcp_packet(self.1.packet(), sockets).ok();
+        use smoltcp::wire::EthernetFrame;
+        if let Ok(frame) = EthernetFrame::new_checked(self.1.packet()) {
+            snoop_tcp_packet(frame.payload(), self.2, sockets).ok();
+        }
     }
 
     fn consume<R, F>(self, f: F) -> R
@@ -260,6 +422,7 @@ impl RxToken for AxNetRxToken<'_> {
             rx_buf.packet_len(),
             rx_buf.packet()
         );
+        capture::capture_frame(self.2, rx_buf.packet());
         let result = f(rx_buf.packet());
         self.0.borrow_mut().recycle_rx_buffer(rx_buf).unwrap();
         result
@@ -275,30 +438,301 @@ impl TxToken for AxNetTxToken<'_> {
         let mut tx_buf = dev.alloc_tx_buffer(len).unwrap();
         let ret = f(tx_buf.packet_mut());
         trace!("SEND {} bytes: {:02X?}", len, tx_buf.packet());
+        capture::capture_frame(self.1, tx_buf.packet());
         dev.transmit(tx_buf).unwrap();
         ret
     }
 }
 
-fn snoop_tcp_packet(buf: &[u8], sockets: &mut SocketSet<'_>) -> Result<(), smoltcp::wire::Error> {
-    use smoltcp::wire::{EthernetFrame, IpProtocol, Ipv4Packet, TcpPacket};
+impl LoopbackDevice {
+    fn new() -> Self {
+        Self {
+            queue: VecDeque::new(),
+        }
+    }
+}
+
+impl Device for LoopbackDevice {
+    type RxToken<'a>
+        = LoopbackRxToken
+    where
+        Self: 'a;
+    type TxToken<'a>
+        = LoopbackTxToken<'a>
+    where
+        Self: 'a;
+
+    fn receive(&mut self, _timestamp: Instant) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
+        let packet = self.queue.pop_front()?;
+        Some((LoopbackRxToken(packet), LoopbackTxToken(&mut self.queue)))
+    }
+
+    fn transmit(&mut self, _timestamp: Instant) -> Option<Self::TxToken<'_>> {
+        Some(LoopbackTxToken(&mut self.queue))
+    }
+
+    fn capabilities(&self) -> DeviceCapabilities {
+        let mut caps = DeviceCapabilities::default();
+        caps.max_transmission_unit = LOOPBACK_MTU;
+        caps.max_burst_size = None;
+        caps.medium = Medium::Ip;
+        caps
+    }
+}
+
+struct LoopbackRxToken(Vec<u8>);
+struct LoopbackTxToken<'a>(&'a mut VecDeque<Vec<u8>>);
 
-    let ether_frame = EthernetFrame::new_checked(buf)?;
-    let ipv4_packet = Ipv4Packet::new_checked(ether_frame.payload())?;
+impl RxToken for LoopbackRxToken {
+    fn preprocess(&self, sockets: &mut SocketSet<'_>) {
+        snoop_tcp_packet(&self.0, LOOPBACK, sockets).ok();
+    }
 
-    if ipv4_packet.next_header() == IpProtocol::Tcp {
-        let tcp_packet = TcpPacket::new_checked(ipv4_packet.payload())?;
-        let src_addr = (ipv4_packet.src_addr(), tcp_packet.src_port()).into();
-        let dst_addr = (ipv4_packet.dst_addr(), tcp_packet.dst_port()).into();
-        let is_first = tcp_packet.syn() && !tcp_packet.ack();
-        if is_first {
-            // create a socket for the first incoming TCP packet, as the later accept() returns.
-            LISTEN_TABLE.incoming_tcp_packet(src_addr, dst_addr, sockets);
+    fn consume<R, F>(self, f: F) -> R
+    where
+        F: FnOnce(&[u8]) -> R,
+    {
+        f(&self.0)
+    }
+}
+
+impl TxToken for LoopbackTxToken<'_> {
+    fn consume<R, F>(self, len: usize, f: F) -> R
+    where
+        F: FnOnce(&mut [u8]) -> R,
+    {
+        let mut packet = vec![0; len];
+        let ret = f(&mut packet);
+        self.0.push_back(packet);
+        ret
+    }
+}
+
+/// Snoops an IP packet received on the interface of index `iface`.
+fn snoop_tcp_packet(
+    buf: &[u8],
+    iface: usize,
+    sockets: &mut SocketSet<'_>,
+) -> Result<(), smoltcp::wire::Error> {
+    use smoltcp::wire::{Ipv4Packet, Ipv6Packet, TcpPacket};
+
+    let (src_ip, dst_ip, payload) = match IpVersion::of_packet(buf)? {
+        IpVersion::Ipv4 => {
+            let ipv4_packet = Ipv4Packet::new_checked(buf)?;
+            if ipv4_packet.next_header() != IpProtocol::Tcp {
+                return Ok(());
+            }
+            let src_ip = IpAddress::from(ipv4_packet.src_addr());
+            let dst_ip = IpAddress::from(ipv4_packet.dst_addr());
+            (src_ip, dst_ip, ipv4_packet.payload())
         }
+        IpVersion::Ipv6 => {
+            // TCP after extension headers is not snooped
+            let ipv6_packet = Ipv6Packet::new_checked(buf)?;
+            if ipv6_packet.next_header() != IpProtocol::Tcp {
+                return Ok(());
+            }
+            let src_ip = IpAddress::from(ipv6_packet.src_addr());
+            let dst_ip = IpAddress::from(ipv6_packet.dst_addr());
+            (src_ip, dst_ip, ipv6_packet.payload())
+        }
+    };
+
+    let tcp_packet = TcpPacket::new_checked(payload)?;
+    let src_addr = (src_ip, tcp_packet.src_port()).into();
+    let dst_addr = (dst_ip, tcp_packet.dst_port()).into();
+    let is_first = tcp_packet.syn() && !tcp_packet.ack();
+    if is_first {
+        // create a socket for the first incoming TCP packet, as the later accept() returns.
+        LISTEN_TABLE.incoming_tcp_packet(src_addr, dst_addr, iface, sockets);
+    }
+    Ok(())
+}
+
+/// Returns the index of the interface to reach `addr`, or [`None`] if it is
+/// unreachable.
+///
+/// Loopback addresses and addresses of the local interfaces are reached by
+/// the loopback interface, others by the routing table.
+fn route(addr: IpAddress) -> Option<usize> {
+    if IpAddr::from(addr).is_loopback() || IFACES.iter().any(|iface| iface.has_ip_addr(addr)) {
+        Some(LOOPBACK)
+    } else {
+        routing::lookup(addr)
+    }
+}
+
+/// Replaces the addresses of `version` on the interface of index `index` by
+/// `cidr`, and its routes by the route to the network of `cidr` and the default
+/// route via `gateway`.
+fn set_ip_config(
+    index: usize,
+    version: IpVersion,
+    cidr: Option<IpCidr>,
+    gateway: Option<IpAddress>,
+) -> AxResult {
+    IFACES[index].set_ip_addr(version, cidr);
+    sync_loopback_addrs();
+    routing::remove_iface_routes(index, version);
+    if let Some(cidr) = cidr {
+        routing::add(cidr, None, index)?;
+    }
+    if let Some(gateway) = gateway {
+        let unspecified = match version {
+            IpVersion::Ipv4 => IpAddress::v4(0, 0, 0, 0),
+            IpVersion::Ipv6 => IpAddress::v6(0, 0, 0, 0, 0, 0, 0, 0),
+        };
+        routing::add(IpCidr::new(unspecified, 0), Some(gateway), index)?;
     }
     Ok(())
 }
 
+/// Updates the addresses of the loopback interface, which are the loopback
+/// addresses and the addresses of all other interfaces.
+fn sync_loopback_addrs() {
+    let mut addrs = vec![
+        IpCidr::new(IpAddress::v4(127, 0, 0, 1), 8),
+        IpCidr::new(IpAddress::v6(0, 0, 0, 0, 0, 0, 0, 1), 128),
+    ];
+    for iface in &IFACES[LOOPBACK + 1..] {
+        let iface = iface.iface.lock();
+        addrs.extend(iface.ip_addrs().iter().map(|cidr| {
+            let prefix_len = match cidr.address() {
+                IpAddress::Ipv4(_) => 32,
+                IpAddress::Ipv6(_) => 128,
+            };
+            IpCidr::new(cidr.address(), prefix_len)
+        }));
+    }
+    IFACES[LOOPBACK].iface.lock().update_ip_addrs(|ip_addrs| {
+        ip_addrs.clear();
+        for cidr in addrs {
+            if ip_addrs.push(cidr).is_err() {
+                warn!("too many addresses on the loopback interface");
+                break;
+            }
+        }
+    });
+}
+
+/// Returns the static address and gateway of the `n`-th NIC, which are the
+/// `n`-th items of the comma-separated lists in `AX_IP` and `AX_GW`.
+///
+/// The address may have a prefix length like `10.0.2.15/24`, which is `/24` if
+/// not given.
+fn static_config(n: usize) -> Option<(IpCidr, Option<IpAddress>)> {
+    let nth = |list: &'static str| {
+        list.split(',')
+            .map(str::trim)
+            .nth(n)
+            .filter(|s| !s.is_empty())
+    };
+    let ip = nth(IP)?;
+    let (ip, prefix_len) = match ip.split_once('/') {
+        Some((ip, len)) => (ip, len.parse().expect("invalid prefix length")),
+        None => (ip, IP_PREFIX),
+    };
+    let ip = ip.parse().expect("invalid IP address");
+    let gateway = nth(GATEWAY).map(|gw| gw.parse().expect("invalid gateway IP address"));
+    Some((IpCidr::new(ip, prefix_len), gateway))
+}
+
+/// Sets up the static configuration on the interface of index `index`, see
+/// [`static_config`].
+fn setup_static_config(index: usize) {
+    let iface = &IFACES[index];
+    let Some((cidr, gateway)) = static_config(index - 1) else {
+        warn!("no static IP address for {:?}", iface.name());
+        return;
+    };
+    info!("  ip:       {}", cidr);
+    if let Some(gateway) = gateway {
+        info!("  gateway:  {}", gateway);
+    }
+    if let Err(e) = set_ip_config(index, cidr.address().version(), Some(cidr), gateway) {
+        warn!("failed to configure {:?}: {:?}", iface.name(), e);
+    }
+}
+
+/// Returns the DNS servers, from the DHCP lease if there is one.
+fn dns_servers() -> Vec<IpAddress> {
+    #[cfg(feature = "dhcp")]
+    if let Some(servers) = dhcp::dns_servers() {
+        return servers;
+    }
+    vec![DNS_SEVER.parse().expect("invalid DNS server address")]
+}
+
+/// Runs `f` on the first NIC, if there is one.
+fn with_nic(f: impl FnOnce(&mut DeviceWrapper)) {
+    for iface in IFACES.iter() {
+        if let NetDevice::Nic(dev) = iface.dev.lock().deref_mut() {
+            return f(dev);
+        }
+    }
+    warn!("no NIC device found");
+}
+
+/// Information of a network interface.
+#[derive(Debug, Clone)]
+pub struct NetInterfaceInfo {
+    /// Name of the interface, `lo` for the loopback interface, and `eth0`,
+    /// `eth1`, ... for the NICs.
+    pub name: String,
+    /// MAC address of the NIC, or [`None`] for the loopback interface.
+    pub mac_addr: Option<[u8; 6]>,
+    /// IP addresses with their prefix lengths.
+    pub ip_addrs: Vec<(IpAddr, u8)>,
+}
+
+/// Returns information of all network interfaces.
+pub fn interfaces() -> Vec<NetInterfaceInfo> {
+    IFACES
+        .iter()
+        .map(|iface| {
+            let ip_addrs: Vec<_> = iface
+                .iface
+                .lock()
+                .ip_addrs()
+                .iter()
+                .map(|cidr| (cidr.address().into(), cidr.prefix_len()))
+                .collect();
+            NetInterfaceInfo {
+                name: iface.name().into(),
+                mac_addr: iface.ethernet_address().map(|addr| addr.0),
+                ip_addrs,
+            }
+        })
+        .collect()
+}
+
+/// Sets the address of the interface named `name` to `addr/prefix_len`, which
+/// replaces its addresses and routes of the same IP version. The default route
+/// via `gateway` is added if given.
+pub fn set_interface_config(
+    name: &str,
+    addr: IpAddr,
+    prefix_len: u8,
+    gateway: Option<IpAddr>,
+) -> AxResult {
+    let index = match IFACES.iter().position(|iface| iface.name() == name) {
+        Some(LOOPBACK) => return ax_err!(InvalidInput, "cannot configure loopback"),
+        Some(index) => index,
+        None => return ax_err!(NotFound, "no such interface"),
+    };
+    let max_len = if addr.is_ipv4() { 32 } else { 128 };
+    if prefix_len > max_len || gateway.is_some_and(|gw| gw.is_ipv4() != addr.is_ipv4()) {
+        return ax_err!(InvalidInput, "invalid interface config");
+    }
+    let cidr = IpCidr::new(addr.into(), prefix_len);
+    set_ip_config(
+        index,
+        cidr.address().version(),
+        Some(cidr),
+        gateway.map(Into::into),
+    )
+}
+
 /// Poll the network stack.
 ///
 /// It may receive packets from the NIC and process them, and transmit queued
@@ -309,29 +743,86 @@ pub fn poll_interfaces() {
 
 /// Benchmark raw socket transmit bandwidth.
 pub fn bench_transmit() {
-    ETH0.dev.lock().bench_transmit_bandwidth();
+    with_nic(|dev| dev.bench_transmit_bandwidth());
 }
 
 /// Benchmark raw socket receive bandwidth.
 pub fn bench_receive() {
-    ETH0.dev.lock().bench_receive_bandwidth();
+    with_nic(|dev| dev.bench_receive_bandwidth());
 }
 
-pub(crate) fn init(net_dev: AxNetDevice) {
-    let ether_addr = EthernetAddress(net_dev.mac_address().0);
-    let eth0 = InterfaceWrapper::new("eth0", net_dev, ether_addr);
+/// Returns the size in bytes of both the receive and send buffers of new TCP
+/// sockets.
+pub fn tcp_buffer_size() -> usize {
+    TCP_BUF_LEN.load(Ordering::Relaxed)
+}
+
+/// Sets the size in bytes of both the receive and send buffers of new TCP
+/// sockets, existing sockets are not affected.
+///
+/// Returns [`InvalidInput`](axerrno::AxError::InvalidInput) if `size` is not
+/// in 1 KiB to 16 MiB.
+pub fn set_tcp_buffer_size(size: usize) -> AxResult {
+    if !BUF_LEN_RANGE.contains(&size) {
+        return ax_err!(InvalidInput, "socket buffer size out of range");
+    }
+    TCP_BUF_LEN.store(size, Ordering::Relaxed);
+    Ok(())
+}
 
-    let ip = IP.parse().expect("invalid IP address");
-    let gateway = GATEWAY.parse().expect("invalid gateway IP address");
-    eth0.setup_ip_addr(ip, IP_PREFIX);
-    eth0.setup_gateway(gateway);
+/// Returns the size in bytes of both the receive and send buffers of new UDP
+/// sockets.
+pub fn udp_buffer_size() -> usize {
+    UDP_BUF_LEN.load(Ordering::Relaxed)
+}
 
-    ETH0.init_once(eth0);
-    SOCKET_SET.init_once(SocketSetWrapper::new());
+/// Clamps the size of a socket buffer set by `SO_RCVBUF` or `SO_SNDBUF` into
+/// the valid range.
+fn clamp_buffer_size(size: usize) -> usize {
+    size.clamp(*BUF_LEN_RANGE.start(), *BUF_LEN_RANGE.end())
+}
+
+/// Sets the size in bytes of both the receive and send buffers of new UDP
+/// sockets, like [`set_tcp_buffer_size`].
+pub fn set_udp_buffer_size(size: usize) -> AxResult {
+    if !BUF_LEN_RANGE.contains(&size) {
+        return ax_err!(InvalidInput, "socket buffer size out of range");
+    }
+    UDP_BUF_LEN.store(size, Ordering::Relaxed);
+    Ok(())
+}
+
+pub(crate) fn init(net_devs: Vec<AxNetDevice>) {
+    let lo = InterfaceWrapper::new("lo".into(), NetDevice::Loopback(LoopbackDevice::new()));
+    let mut ifaces = vec![lo];
+    for (i, net_dev) in net_devs.into_iter().enumerate() {
+        let dev = DeviceWrapper::new(net_dev, ifaces.len());
+        ifaces.push(InterfaceWrapper::new(
+            format!("eth{}", i),
+            NetDevice::Nic(dev),
+        ));
+    }
+
+    SOCKET_SET.init_once(SocketSetWrapper::new(ifaces.len()));
+    IFACES.init_once(ifaces);
     LISTEN_TABLE.init_once(ListenTable::new());
+    sync_loopback_addrs();
+
+    info!("created net interface {:?}", IFACES[LOOPBACK].name());
+    for (index, iface) in IFACES.iter().enumerate().skip(LOOPBACK + 1) {
+        info!("created net interface {:?}:", iface.name());
+        info!("  ether:    {}", iface.ethernet_address().unwrap());
+        // `eth0` is configured by DHCP later
+        if cfg!(not(feature = "dhcp")) || index > 1 {
+            setup_static_config(index);
+        }
+    }
+
+    #[cfg(feature = "dhcp")]
+    if IFACES.len() > 1 {
+        dhcp::init(1);
+    }
 
-    info!("created net interface {:?}:", ETH0.name());
-    info!("  ether:    {}", ETH0.ethernet_address());
-    info!("  ip:       {}/{}", ip, IP_PREFIX);
-    info!("  gateway:  {}", gateway);
+    #[cfg(all(feature = "multitask", feature = "irq"))]
+    wait::spawn_poll_task();
 }
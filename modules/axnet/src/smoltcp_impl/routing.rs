@@ -0,0 +1,141 @@
+//! The routing table, which selects the interface to send packets through for
+//! each destination.
+
+use alloc::{string::String, vec::Vec};
+use core::cmp::Reverse;
+use core::net::IpAddr;
+
+use axerrno::{AxResult, ax_err};
+use axsync::Mutex;
+use smoltcp::iface::Route as SmolRoute;
+use smoltcp::wire::{IpAddress, IpCidr, IpVersion};
+
+use super::{IFACES, LOOPBACK};
+
+/// An entry of the routing table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteEntry {
+    /// The destination network.
+    pub dest: IpAddr,
+    /// Prefix length of the destination network, `0` for the default route.
+    pub prefix_len: u8,
+    /// The router to send packets through, or [`None`] if the destination
+    /// network is directly connected.
+    pub gateway: Option<IpAddr>,
+    /// Name of the interface to send packets through.
+    pub iface: String,
+}
+
+struct Route {
+    cidr: IpCidr,
+    gateway: Option<IpAddress>,
+    /// Index of the interface in [`IFACES`].
+    iface: usize,
+}
+
+static ROUTES: Mutex<Vec<Route>> = Mutex::new(Vec::new());
+
+/// Returns the index of the interface to send packets to `addr` through, by the
+/// longest prefix match, or [`None`] if there is no route.
+pub(super) fn lookup(addr: IpAddress) -> Option<usize> {
+    // the first one of the longest matches
+    ROUTES
+        .lock()
+        .iter()
+        .filter(|route| route.cidr.contains_addr(&addr))
+        .min_by_key(|route| Reverse(route.cidr.prefix_len()))
+        .map(|route| route.iface)
+}
+
+/// Adds a route through the interface of index `iface`.
+pub(super) fn add(cidr: IpCidr, gateway: Option<IpAddress>, iface: usize) -> AxResult {
+    if let Some(via_router) = gateway {
+        let route = SmolRoute {
+            cidr,
+            via_router,
+            preferred_until: None,
+            expires_at: None,
+        };
+        let mut full = false;
+        let mut iface = IFACES[iface].iface.lock();
+        iface
+            .routes_mut()
+            .update(|routes| full = routes.push(route).is_err());
+        if full {
+            return ax_err!(NoMemory, "too many routes");
+        }
+    }
+    ROUTES.lock().push(Route {
+        cidr,
+        gateway,
+        iface,
+    });
+    Ok(())
+}
+
+/// Removes all routes of `version` through the interface of index `iface`.
+pub(super) fn remove_iface_routes(iface: usize, version: IpVersion) {
+    ROUTES
+        .lock()
+        .retain(|route| route.iface != iface || route.cidr.address().version() != version);
+    IFACES[iface].iface.lock().routes_mut().update(|routes| {
+        routes.retain(|route| route.cidr.address().version() != version);
+    });
+}
+
+/// Returns all entries of the routing table.
+pub fn routes() -> Vec<RouteEntry> {
+    ROUTES
+        .lock()
+        .iter()
+        .map(|route| RouteEntry {
+            dest: route.cidr.address().into(),
+            prefix_len: route.cidr.prefix_len(),
+            gateway: route.gateway.map(Into::into),
+            iface: IFACES[route.iface].name().into(),
+        })
+        .collect()
+}
+
+/// Adds a route to the network `dest/prefix_len` through the interface named
+/// `iface`, via `gateway` if it is not directly connected.
+///
+/// Packets are sent through the route with the longest matching prefix.
+pub fn add_route(dest: IpAddr, prefix_len: u8, gateway: Option<IpAddr>, iface: &str) -> AxResult {
+    let cidr = to_cidr(dest, prefix_len)?;
+    if gateway.is_some_and(|gateway| gateway.is_ipv4() != dest.is_ipv4()) {
+        return ax_err!(InvalidInput, "gateway of a different IP version");
+    }
+    let index = match IFACES.iter().position(|i| i.name() == iface) {
+        Some(LOOPBACK) => return ax_err!(InvalidInput, "cannot route through loopback"),
+        Some(index) => index,
+        None => return ax_err!(NotFound, "no such interface"),
+    };
+    add(cidr, gateway.map(Into::into), index)
+}
+
+/// Removes the route to the network `dest/prefix_len`.
+pub fn del_route(dest: IpAddr, prefix_len: u8) -> AxResult {
+    let cidr = to_cidr(dest, prefix_len)?;
+    let mut routes = ROUTES.lock();
+    let Some(idx) = routes.iter().position(|route| route.cidr == cidr) else {
+        return ax_err!(NotFound, "no such route");
+    };
+    let route = routes.remove(idx);
+    if route.gateway.is_some() {
+        IFACES[route.iface]
+            .iface
+            .lock()
+            .routes_mut()
+            .update(|routes| routes.retain(|r| r.cidr != cidr));
+    }
+    Ok(())
+}
+
+fn to_cidr(addr: IpAddr, prefix_len: u8) -> AxResult<IpCidr> {
+    let max_len = if addr.is_ipv4() { 32 } else { 128 };
+    if prefix_len > max_len {
+        return ax_err!(InvalidInput, "invalid prefix length");
+    }
+    Ok(IpCidr::new(addr.into(), prefix_len))
+}
@@ -1,17 +1,23 @@
 use core::cell::UnsafeCell;
 use core::net::SocketAddr;
 use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use core::task::Waker;
+use core::time::Duration;
 
 use axerrno::{AxError, AxResult, ax_err, ax_err_type};
 use axio::PollState;
 use axsync::Mutex;
 
-use smoltcp::iface::SocketHandle;
 use smoltcp::socket::tcp::{self, ConnectError, State};
+use smoltcp::time::Duration as SmolDuration;
 use smoltcp::wire::{IpEndpoint, IpListenEndpoint};
 
 use super::addr::UNSPECIFIED_ENDPOINT;
-use super::{ETH0, LISTEN_TABLE, SOCKET_SET, SocketSetWrapper};
+use super::wait::SocketWaiter;
+use super::{
+    LISTEN_TABLE, MsgFlags, SOCKET_SET, SocketHandle, SocketSetWrapper, clamp_buffer_size, route,
+    tcp_buffer_size,
+};
 
 // State transitions:
 // CLOSED -(connect)-> BUSY -> CONNECTING -> CONNECTED -(shutdown)-> BUSY -> CLOSED
@@ -25,6 +31,54 @@ const STATE_CONNECTING: u8 = 2;
 const STATE_CONNECTED: u8 = 3;
 const STATE_LISTENING: u8 = 4;
 
+/// Interval of keep-alive packets when the connection is idle, if keep-alive
+/// is enabled.
+const KEEP_ALIVE_INTERVAL: SmolDuration = SmolDuration::from_secs(75);
+/// Connections with keep-alive enabled are aborted if nothing is received
+/// from the peer in this time, i.e., 9 keep-alive packets are not answered.
+const KEEP_ALIVE_TIMEOUT: SmolDuration = SmolDuration::from_secs(75 * 10);
+
+/// Options of a TCP socket, which are inherited by the connections accepted
+/// by a listener.
+#[derive(Clone, Copy)]
+struct TcpOptions {
+    reuse_addr: bool,
+    keep_alive: bool,
+    nodelay: bool,
+    recv_buf_size: usize,
+    send_buf_size: usize,
+    recv_timeout: Option<Duration>,
+    send_timeout: Option<Duration>,
+    linger: Option<Duration>,
+}
+
+impl TcpOptions {
+    fn new() -> Self {
+        Self {
+            reuse_addr: false,
+            keep_alive: false,
+            nodelay: false,
+            recv_buf_size: tcp_buffer_size(),
+            send_buf_size: tcp_buffer_size(),
+            recv_timeout: None,
+            send_timeout: None,
+            linger: None,
+        }
+    }
+
+    /// Applies the options that can be changed at any time to the connection.
+    fn apply(&self, socket: &mut tcp::Socket) {
+        socket.set_nagle_enabled(!self.nodelay);
+        if self.keep_alive {
+            socket.set_keep_alive(Some(KEEP_ALIVE_INTERVAL));
+            socket.set_timeout(Some(KEEP_ALIVE_TIMEOUT));
+        } else {
+            socket.set_keep_alive(None);
+            socket.set_timeout(None);
+        }
+    }
+}
+
 /// A TCP socket that provides POSIX-like APIs.
 ///
 /// - [`connect`] is for TCP clients.
@@ -41,27 +95,36 @@ pub struct TcpSocket {
     local_addr: UnsafeCell<IpEndpoint>,
     peer_addr: UnsafeCell<IpEndpoint>,
     nonblock: AtomicBool,
+    waiter: SocketWaiter,
+    opts: Mutex<TcpOptions>,
+    /// The pending error of a failed nonblocking connection.
+    error: Mutex<Option<AxError>>,
 }
 
 unsafe impl Sync for TcpSocket {}
 
 impl TcpSocket {
     /// Creates a new TCP socket.
-    pub const fn new() -> Self {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
         Self {
             state: AtomicU8::new(STATE_CLOSED),
             handle: UnsafeCell::new(None),
             local_addr: UnsafeCell::new(UNSPECIFIED_ENDPOINT),
             peer_addr: UnsafeCell::new(UNSPECIFIED_ENDPOINT),
             nonblock: AtomicBool::new(false),
+            waiter: SocketWaiter::new(),
+            opts: Mutex::new(TcpOptions::new()),
+            error: Mutex::new(None),
         }
     }
 
     /// Creates a new TCP socket that is already connected.
-    const fn new_connected(
+    fn new_connected(
         handle: SocketHandle,
         local_addr: IpEndpoint,
         peer_addr: IpEndpoint,
+        opts: TcpOptions,
     ) -> Self {
         Self {
             state: AtomicU8::new(STATE_CONNECTED),
@@ -69,6 +132,9 @@ impl TcpSocket {
             local_addr: UnsafeCell::new(local_addr),
             peer_addr: UnsafeCell::new(peer_addr),
             nonblock: AtomicBool::new(false),
+            waiter: SocketWaiter::new(),
+            opts: Mutex::new(opts),
+            error: Mutex::new(None),
         }
     }
 
@@ -117,16 +183,23 @@ impl TcpSocket {
     ///
     /// The local port is generated automatically.
     pub fn connect(&self, remote_addr: SocketAddr) -> AxResult {
+        let opts = *self.opts.lock();
         self.update_state(STATE_CLOSED, STATE_CONNECTING, || {
+            let iface = route(IpEndpoint::from(remote_addr).addr).ok_or_else(|| {
+                ax_err_type!(ConnectionRefused, "socket connect() failed: unreachable")
+            })?;
             // SAFETY: no other threads can read or write these fields.
-            let handle = unsafe { self.handle.get().read() }
-                .unwrap_or_else(|| SOCKET_SET.add(SocketSetWrapper::new_tcp_socket()));
+            let handle = unsafe { self.handle.get().read() }.unwrap_or_else(|| {
+                let socket =
+                    SocketSetWrapper::new_tcp_socket(opts.recv_buf_size, opts.send_buf_size);
+                SOCKET_SET.add(iface, socket)
+            });
 
-            // TODO: check remote addr unreachable
             let bound_endpoint = self.bound_endpoint()?;
-            let iface = &ETH0.iface;
+            let iface = &handle.iface().iface;
             let (local_endpoint, remote_endpoint) = SOCKET_SET
                 .with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
+                    opts.apply(socket);
                     socket
                         .connect(iface.lock().context(), remote_addr, bound_endpoint)
                         .or_else(|e| match e {
@@ -157,13 +230,15 @@ impl TcpSocket {
         if self.is_nonblocking() {
             Err(AxError::WouldBlock)
         } else {
-            self.block_on(|| {
+            self.block_on(opts.send_timeout, || {
                 let PollState { writable, .. } = self.poll_connect()?;
                 if !writable {
                     Err(AxError::WouldBlock)
                 } else if self.get_state() == STATE_CONNECTED {
                     Ok(())
                 } else {
+                    // reported here instead of by `take_error`
+                    self.error.lock().take();
                     ax_err!(ConnectionRefused, "socket connect() failed")
                 }
             })
@@ -172,15 +247,31 @@ impl TcpSocket {
 
     /// Binds an unbound socket to the given address and port.
     ///
-    /// If the given port is 0, it generates one automatically.
+    /// If the given port is 0, it generates one automatically. It fails with
+    /// [`AddrInUse`](AxError::AddrInUse) if another socket is listening on the
+    /// port, or if a connection is using the address and port unless
+    /// [`set_reuse_address`](Self::set_reuse_address) is enabled.
     ///
     /// It's must be called before [`listen`](Self::listen) and
     /// [`accept`](Self::accept).
     pub fn bind(&self, mut local_addr: SocketAddr) -> AxResult {
+        let reuse_addr = self.opts.lock().reuse_addr;
         self.update_state(STATE_CLOSED, STATE_CLOSED, || {
-            // TODO: check addr is available
             if local_addr.port() == 0 {
                 local_addr.set_port(get_ephemeral_port()?);
+            } else {
+                let endpoint = IpListenEndpoint {
+                    addr: (!local_addr.ip().is_unspecified()).then(|| local_addr.ip().into()),
+                    port: local_addr.port(),
+                };
+                if !LISTEN_TABLE.can_listen(endpoint.port)
+                    || (!reuse_addr
+                        && SOCKET_SET.is_in_use::<tcp::Socket>(endpoint, |socket| {
+                            socket.local_endpoint().map(Into::into)
+                        }))
+                {
+                    return ax_err!(AddrInUse, "socket bind() failed");
+                }
             }
             // SAFETY: no other threads can read or write `self.local_addr` as we
             // have changed the state to `BUSY`.
@@ -196,21 +287,40 @@ impl TcpSocket {
         .unwrap_or_else(|_| ax_err!(InvalidInput, "socket bind() failed: already bound"))
     }
 
-    /// Starts listening on the bound address and port.
+    /// Starts listening on the bound address and port, over IPv4 or IPv6.
+    ///
+    /// At most `backlog` connections can be pending, including the ones still
+    /// in the handshake, whose SYN packets are dropped beyond it. `backlog` is
+    /// clamped to 1 to 512. Calling it again on a listening socket changes
+    /// the backlog.
     ///
     /// It's must be called after [`bind`](Self::bind) and before
     /// [`accept`](Self::accept).
-    pub fn listen(&self) -> AxResult {
+    pub fn listen(&self, backlog: usize) -> AxResult {
+        let opts = *self.opts.lock();
         self.update_state(STATE_CLOSED, STATE_LISTENING, || {
             let bound_endpoint = self.bound_endpoint()?;
             unsafe {
                 (*self.local_addr.get()).port = bound_endpoint.port;
             }
-            LISTEN_TABLE.listen(bound_endpoint)?;
+            LISTEN_TABLE.listen(
+                bound_endpoint,
+                backlog,
+                opts.recv_buf_size,
+                opts.send_buf_size,
+            )?;
             debug!("TCP socket listening on {}", bound_endpoint);
             Ok(())
         })
-        .unwrap_or(Ok(())) // ignore simultaneous `listen`s.
+        .unwrap_or_else(|state| {
+            if state == STATE_LISTENING {
+                // SAFETY: `self.local_addr` should be initialized in a listening socket.
+                let local_port = unsafe { self.local_addr.get().read().port };
+                LISTEN_TABLE.set_backlog(local_port, backlog)
+            } else {
+                Ok(()) // ignore simultaneous `listen`s.
+            }
+        })
     }
 
     /// Accepts a new connection.
@@ -226,10 +336,12 @@ impl TcpSocket {
 
         // SAFETY: `self.local_addr` should be initialized after `bind()`.
         let local_port = unsafe { self.local_addr.get().read().port };
-        self.block_on(|| {
+        let opts = *self.opts.lock();
+        self.block_on(opts.recv_timeout, || {
             let (handle, (local_addr, peer_addr)) = LISTEN_TABLE.accept(local_port)?;
             debug!("TCP socket accepted a new connection {}", peer_addr);
-            Ok(TcpSocket::new_connected(handle, local_addr, peer_addr))
+            SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| opts.apply(socket));
+            Ok(TcpSocket::new_connected(handle, local_addr, peer_addr, opts))
         })
     }
 
@@ -268,6 +380,12 @@ impl TcpSocket {
 
     /// Receives data from the socket, stores it in the given buffer.
     pub fn recv(&self, buf: &mut [u8]) -> AxResult<usize> {
+        self.recv_with(buf, MsgFlags::default())
+    }
+
+    /// Receives data from the socket like [`recv`](Self::recv), with the
+    /// given flags.
+    pub fn recv_with(&self, buf: &mut [u8], flags: MsgFlags) -> AxResult<usize> {
         if self.is_connecting() {
             return Err(AxError::WouldBlock);
         } else if !self.is_connected() {
@@ -276,31 +394,64 @@ impl TcpSocket {
 
         // SAFETY: `self.handle` should be initialized in a connected socket.
         let handle = unsafe { self.handle.get().read().unwrap() };
-        self.block_on(|| {
+        let timeout = self.opts.lock().recv_timeout;
+        let mut received = 0;
+        let mut f = || {
             SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
+                let wait_all = flags.wait_all && !buf.is_empty();
                 if !socket.is_active() {
                     // not open
                     ax_err!(ConnectionRefused, "socket recv() failed")
                 } else if !socket.may_recv() {
                     // connection closed
-                    Ok(0)
+                    Ok(received)
+                } else if flags.peek {
+                    let queued = socket.recv_queue();
+                    if queued == 0 || (wait_all && queued < buf.len()) {
+                        // not enough data
+                        Err(AxError::WouldBlock)
+                    } else {
+                        socket
+                            .peek_slice(buf)
+                            .map_err(|_| ax_err_type!(BadState, "socket recv() failed"))
+                    }
                 } else if socket.recv_queue() > 0 {
                     // data available
                     // TODO: use socket.recv(|buf| {...})
-                    let len = socket
-                        .recv_slice(buf)
+                    received += socket
+                        .recv_slice(&mut buf[received..])
                         .map_err(|_| ax_err_type!(BadState, "socket recv() failed"))?;
-                    Ok(len)
+                    if wait_all && received < buf.len() {
+                        Err(AxError::WouldBlock)
+                    } else {
+                        Ok(received)
+                    }
                 } else {
                     // no more data
                     Err(AxError::WouldBlock)
                 }
             })
-        })
+        };
+        let res = if flags.dont_wait {
+            f()
+        } else {
+            self.block_on(timeout, f)
+        };
+        match res {
+            // some data is received before timeout with `wait_all`
+            Err(AxError::WouldBlock) if received > 0 => Ok(received),
+            res => res,
+        }
     }
 
     /// Transmits data in the given buffer.
     pub fn send(&self, buf: &[u8]) -> AxResult<usize> {
+        self.send_with(buf, MsgFlags::default())
+    }
+
+    /// Transmits data in the given buffer like [`send`](Self::send), with the
+    /// given flags, where only [`dont_wait`](MsgFlags::dont_wait) is used.
+    pub fn send_with(&self, buf: &[u8], flags: MsgFlags) -> AxResult<usize> {
         if self.is_connecting() {
             return Err(AxError::WouldBlock);
         } else if !self.is_connected() {
@@ -309,7 +460,8 @@ impl TcpSocket {
 
         // SAFETY: `self.handle` should be initialized in a connected socket.
         let handle = unsafe { self.handle.get().read().unwrap() };
-        self.block_on(|| {
+        let timeout = self.opts.lock().send_timeout;
+        let mut f = || {
             SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
                 if !socket.is_active() || !socket.may_send() {
                     // closed by remote
@@ -326,7 +478,12 @@ impl TcpSocket {
                     Err(AxError::WouldBlock)
                 }
             })
-        })
+        };
+        if flags.dont_wait {
+            f()
+        } else {
+            self.block_on(timeout, f)
+        }
     }
 
     /// Whether the socket is readable or writable.
@@ -342,25 +499,140 @@ impl TcpSocket {
         }
     }
 
-    /// Checks if Nagle's algorithm is enabled for this TCP socket.
+    /// Checks if Nagle's algorithm is disabled for this TCP socket.
     pub fn nodelay(&self) -> AxResult<bool> {
-        if let Some(h) = unsafe { self.handle.get().read() } {
-            Ok(SOCKET_SET.with_socket::<tcp::Socket, _, _>(h, |socket| socket.nagle_enabled()))
-        } else {
-            ax_err!(NotConnected, "socket is not connected")
+        Ok(self.opts.lock().nodelay)
+    }
+
+    /// Disables or enables Nagle's algorithm for this TCP socket.
+    ///
+    /// If `nodelay` is true, small segments are sent at once instead of being
+    /// combined into larger ones.
+    pub fn set_nodelay(&self, nodelay: bool) -> AxResult<()> {
+        self.update_options(|opts| opts.nodelay = nodelay);
+        Ok(())
+    }
+
+    /// Returns whether the address and port can be reused by
+    /// [`bind`](Self::bind) while connections are using them.
+    pub fn reuse_address(&self) -> bool {
+        self.opts.lock().reuse_addr
+    }
+
+    /// Allows or disallows to reuse the address and port by
+    /// [`bind`](Self::bind) while connections are using them, e.g., to restart
+    /// a server before the connections accepted by the last one are closed.
+    pub fn set_reuse_address(&self, reuse: bool) {
+        self.opts.lock().reuse_addr = reuse;
+    }
+
+    /// Returns whether keep-alive packets are sent on the connection.
+    pub fn keep_alive(&self) -> bool {
+        self.opts.lock().keep_alive
+    }
+
+    /// Enables or disables keep-alive packets on the connection.
+    ///
+    /// If enabled, a keep-alive packet is sent every 75 seconds when the
+    /// connection is idle, and the connection is aborted if nothing is received
+    /// from the peer in 750 seconds.
+    pub fn set_keep_alive(&self, keep_alive: bool) {
+        self.update_options(|opts| opts.keep_alive = keep_alive);
+    }
+
+    /// Returns the size of the receive buffer in bytes.
+    pub fn recv_buffer_size(&self) -> usize {
+        self.recv_capacity()
+            .unwrap_or_else(|_| self.opts.lock().recv_buf_size)
+    }
+
+    /// Sets the size of the receive buffer in bytes, which is clamped into
+    /// 1 KiB to 16 MiB.
+    ///
+    /// It takes effect on the connections established by
+    /// [`connect`](Self::connect) or [`listen`](Self::listen) later, the buffer
+    /// of an existing connection is not resized.
+    pub fn set_recv_buffer_size(&self, size: usize) {
+        self.opts.lock().recv_buf_size = clamp_buffer_size(size);
+    }
+
+    /// Returns the size of the send buffer in bytes.
+    pub fn send_buffer_size(&self) -> usize {
+        self.send_capacity()
+            .unwrap_or_else(|_| self.opts.lock().send_buf_size)
+    }
+
+    /// Sets the size of the send buffer in bytes, like
+    /// [`set_recv_buffer_size`](Self::set_recv_buffer_size).
+    pub fn set_send_buffer_size(&self, size: usize) {
+        self.opts.lock().send_buf_size = clamp_buffer_size(size);
+    }
+
+    /// Returns the timeout of [`recv`](Self::recv) and [`accept`](Self::accept),
+    /// or [`None`] if they block indefinitely.
+    pub fn recv_timeout(&self) -> Option<Duration> {
+        self.opts.lock().recv_timeout
+    }
+
+    /// Sets the timeout of [`recv`](Self::recv) and [`accept`](Self::accept),
+    /// after which they fail with [`WouldBlock`](AxError::WouldBlock).
+    ///
+    /// Returns [`InvalidInput`](AxError::InvalidInput) if `timeout` is zero.
+    pub fn set_recv_timeout(&self, timeout: Option<Duration>) -> AxResult {
+        if timeout == Some(Duration::ZERO) {
+            return ax_err!(InvalidInput, "zero timeout");
         }
+        self.opts.lock().recv_timeout = timeout;
+        Ok(())
     }
 
-    /// Enables or disables Nagle's algorithm for this TCP socket.
-    pub fn set_nodelay(&self, enabled: bool) -> AxResult<()> {
-        if let Some(h) = unsafe { self.handle.get().read() } {
-            SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(h, |socket| {
-                socket.set_nagle_enabled(enabled);
-            });
-            Ok(())
-        } else {
-            ax_err!(NotConnected, "socket is not connected")
+    /// Returns the timeout of [`send`](Self::send) and
+    /// [`connect`](Self::connect), or [`None`] if they block indefinitely.
+    pub fn send_timeout(&self) -> Option<Duration> {
+        self.opts.lock().send_timeout
+    }
+
+    /// Sets the timeout of [`send`](Self::send) and [`connect`](Self::connect),
+    /// like [`set_recv_timeout`](Self::set_recv_timeout).
+    pub fn set_send_timeout(&self, timeout: Option<Duration>) -> AxResult {
+        if timeout == Some(Duration::ZERO) {
+            return ax_err!(InvalidInput, "zero timeout");
         }
+        self.opts.lock().send_timeout = timeout;
+        Ok(())
+    }
+
+    /// Returns how long the socket lingers when dropped, or [`None`] if it
+    /// does not.
+    pub fn linger(&self) -> Option<Duration> {
+        self.opts.lock().linger
+    }
+
+    /// Sets how long the socket lingers when dropped.
+    ///
+    /// If it is [`Some`], dropping the socket blocks until the remaining data
+    /// is sent and acknowledged, or the duration elapses. If the duration is
+    /// zero, the connection is reset at once instead. Otherwise, the
+    /// remaining data is discarded when dropped.
+    pub fn set_linger(&self, linger: Option<Duration>) {
+        self.opts.lock().linger = linger;
+    }
+
+    /// Returns and clears the pending error, e.g., the failure of a
+    /// nonblocking [`connect`](Self::connect).
+    pub fn take_error(&self) -> Option<AxError> {
+        self.error.lock().take()
+    }
+
+    /// Returns the number of the connection requests dropped as the queue of
+    /// pending connections is full, since the socket started listening.
+    pub fn listen_overflows(&self) -> AxResult<usize> {
+        if !self.is_listening() {
+            return ax_err!(InvalidInput, "socket is not listening");
+        }
+        // SAFETY: `self.local_addr` should be initialized in a listening socket.
+        let local_port = unsafe { self.local_addr.get().read().port };
+        LISTEN_TABLE.overflows(local_port)
     }
 
     /// Returns the maximum capacity of the receive buffer in bytes.
@@ -475,6 +747,7 @@ impl TcpSocket {
                         self.local_addr.get().write(UNSPECIFIED_ENDPOINT);
                         self.peer_addr.get().write(UNSPECIFIED_ENDPOINT);
                     }
+                    *self.error.lock() = Some(AxError::ConnectionRefused);
                     self.set_state(STATE_CLOSED); // connection failed
                     true
                 }
@@ -505,33 +778,97 @@ impl TcpSocket {
         })
     }
 
+    /// Registers `waker` to be woken up when the socket becomes ready, i.e., a
+    /// new connection is established for a listener, or the state or buffers
+    /// change for a connection.
+    fn register_waker(&self, waker: &Waker) {
+        if self.is_listening() {
+            // SAFETY: `self.local_addr` should be initialized in a listening socket.
+            let local_port = unsafe { self.local_addr.get().read().port };
+            LISTEN_TABLE.register_waker(local_port, waker);
+        } else if let Some(handle) = unsafe { self.handle.get().read() } {
+            SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
+                socket.register_recv_waker(waker);
+                socket.register_send_waker(waker);
+            });
+        }
+    }
+
+    /// Updates the options, and applies them to the connection if any.
+    fn update_options(&self, f: impl FnOnce(&mut TcpOptions)) {
+        let mut opts = self.opts.lock();
+        f(&mut opts);
+        if let Some(handle) = unsafe { self.handle.get().read() } {
+            SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| opts.apply(socket));
+        }
+    }
+
     /// Block the current thread until the given function completes or fails.
     ///
     /// If the socket is non-blocking, it calls the function once and returns
     /// immediately. Otherwise, it may call the function multiple times if it
-    /// returns [`Err(WouldBlock)`](AxError::WouldBlock).
-    fn block_on<F, T>(&self, mut f: F) -> AxResult<T>
+    /// returns [`Err(WouldBlock)`](AxError::WouldBlock), and sleeps until the
+    /// socket becomes ready between the calls, or until `timeout` expires.
+    fn block_on<F, T>(&self, timeout: Option<Duration>, mut f: F) -> AxResult<T>
     where
         F: FnMut() -> AxResult<T>,
     {
         if self.is_nonblocking() {
             f()
         } else {
-            loop {
-                SOCKET_SET.poll_interfaces();
-                match f() {
-                    Ok(t) => return Ok(t),
-                    Err(AxError::WouldBlock) => axtask::yield_now(),
-                    Err(e) => return Err(e),
-                }
-            }
+            self.waiter
+                .block_on(|waker| self.register_waker(waker), timeout, f)
+        }
+    }
+
+    /// Closes the connection, and waits until the remaining data is sent and
+    /// acknowledged, or `timeout` expires. If `timeout` is zero, the
+    /// connection is reset at once.
+    fn close_lingering(&self, timeout: Duration) {
+        // SAFETY: `self.handle` should be initialized in a connected socket.
+        let handle = unsafe { self.handle.get().read().unwrap() };
+        if timeout.is_zero() {
+            SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
+                debug!("TCP socket {}: resetting", handle);
+                socket.abort();
+            });
+        }
+        self.shutdown().ok(); // the reset is sent by polling in it
+        if timeout.is_zero() {
+            return;
+        }
+        let res = self.waiter.block_on(
+            |waker| self.register_waker(waker),
+            Some(timeout),
+            || {
+                SOCKET_SET.with_socket::<tcp::Socket, _, _>(handle, |socket| {
+                    match socket.state() {
+                        // FIN is not acknowledged
+                        State::FinWait1 | State::Closing | State::LastAck => {
+                            Err(AxError::WouldBlock)
+                        }
+                        _ => Ok(()),
+                    }
+                })
+            },
+        );
+        if res.is_err() {
+            warn!(
+                "TCP socket {}: linger timed out, discard unsent data",
+                handle
+            );
         }
     }
 }
 
 impl Drop for TcpSocket {
     fn drop(&mut self) {
-        self.shutdown().ok();
+        match self.linger() {
+            Some(timeout) if self.is_connected() => self.close_lingering(timeout),
+            _ => {
+                self.shutdown().ok();
+            }
+        }
         // Safe because we have mut reference to `self`.
         if let Some(handle) = unsafe { self.handle.get().read() } {
             SOCKET_SET.remove(handle);
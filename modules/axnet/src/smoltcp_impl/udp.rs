@@ -1,37 +1,62 @@
+use alloc::vec::Vec;
 use core::net::SocketAddr;
 use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::Waker;
+use core::time::Duration;
 
 use axerrno::{AxError, AxResult, ax_err, ax_err_type};
 use axio::PollState;
 use axsync::Mutex;
 use spin::RwLock;
 
-use smoltcp::iface::SocketHandle;
 use smoltcp::socket::udp::{self, BindError, SendError};
 use smoltcp::wire::{IpEndpoint, IpListenEndpoint};
 
 use super::addr::UNSPECIFIED_ENDPOINT;
-use super::{SOCKET_SET, SocketSetWrapper};
+use super::wait::SocketWaiter;
+use super::{
+    IFACES, MsgFlags, SOCKET_SET, SocketHandle, SocketSetWrapper, clamp_buffer_size, route,
+    udp_buffer_size,
+};
+
+/// Options of a UDP socket.
+#[derive(Clone, Copy)]
+struct UdpOptions {
+    reuse_addr: bool,
+    recv_buf_size: usize,
+    send_buf_size: usize,
+    recv_timeout: Option<Duration>,
+    send_timeout: Option<Duration>,
+}
 
 /// A UDP socket that provides POSIX-like APIs.
 pub struct UdpSocket {
-    handle: SocketHandle,
+    /// Sockets on the interfaces that it is bound to, one per interface.
+    handles: RwLock<Vec<SocketHandle>>,
     local_addr: RwLock<Option<IpEndpoint>>,
     peer_addr: RwLock<Option<IpEndpoint>>,
     nonblock: AtomicBool,
+    waiter: SocketWaiter,
+    opts: Mutex<UdpOptions>,
 }
 
 impl UdpSocket {
     /// Creates a new UDP socket.
     #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
-        let socket = SocketSetWrapper::new_udp_socket();
-        let handle = SOCKET_SET.add(socket);
         Self {
-            handle,
+            handles: RwLock::new(Vec::new()),
             local_addr: RwLock::new(None),
             peer_addr: RwLock::new(None),
             nonblock: AtomicBool::new(false),
+            waiter: SocketWaiter::new(),
+            opts: Mutex::new(UdpOptions {
+                reuse_addr: false,
+                recv_buf_size: udp_buffer_size(),
+                send_buf_size: udp_buffer_size(),
+                recv_timeout: None,
+                send_timeout: None,
+            }),
         }
     }
 
@@ -72,11 +97,18 @@ impl UdpSocket {
     /// Binds an unbound socket to the given address and port.
     ///
     /// It's must be called before [`send_to`](Self::send_to) and
-    /// [`recv_from`](Self::recv_from).
+    /// [`recv_from`](Self::recv_from). If the address is unspecified, it is
+    /// bound on all interfaces, otherwise on the interfaces with the address.
+    ///
+    /// It fails with [`AddrInUse`](AxError::AddrInUse) if another socket is
+    /// bound to the port on an overlapping address, unless
+    /// [`set_reuse_address`](Self::set_reuse_address) is enabled.
     pub fn bind(&self, mut local_addr: SocketAddr) -> AxResult {
         let mut self_local_addr = self.local_addr.write();
+        let opts = *self.opts.lock();
 
-        if local_addr.port() == 0 {
+        let ephemeral = local_addr.port() == 0;
+        if ephemeral {
             local_addr.set_port(get_ephemeral_port()?);
         }
         if self_local_addr.is_some() {
@@ -88,31 +120,71 @@ impl UdpSocket {
             addr: (!local_endpoint.addr.is_unspecified()).then_some(local_endpoint.addr),
             port: local_endpoint.port,
         };
-        SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
-            socket.bind(endpoint).or_else(|e| match e {
-                BindError::InvalidState => ax_err!(AlreadyExists, "socket bind() failed"),
-                BindError::Unaddressable => ax_err!(InvalidInput, "socket bind() failed"),
+        if !ephemeral
+            && !opts.reuse_addr
+            && SOCKET_SET.is_in_use::<udp::Socket>(endpoint, |socket| {
+                socket.is_open().then(|| socket.endpoint())
             })
-        })?;
+        {
+            return ax_err!(AddrInUse, "socket bind() failed");
+        }
+        let handles = (0..IFACES.len())
+            .filter(|&i| endpoint.addr.is_none_or(|addr| IFACES[i].has_ip_addr(addr)))
+            .map(|i| {
+                let socket =
+                    SocketSetWrapper::new_udp_socket(opts.recv_buf_size, opts.send_buf_size);
+                SOCKET_SET.add(i, socket)
+            })
+            .collect::<Vec<_>>();
+        if handles.is_empty() {
+            return ax_err!(InvalidInput, "socket bind() failed: address not available");
+        }
+        let res = handles.iter().try_for_each(|&handle| {
+            SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(handle, |socket| {
+                socket.bind(endpoint).or_else(|e| match e {
+                    BindError::InvalidState => ax_err!(AlreadyExists, "socket bind() failed"),
+                    BindError::Unaddressable => ax_err!(InvalidInput, "socket bind() failed"),
+                })
+            })
+        });
+        if let Err(e) = res {
+            for handle in handles {
+                SOCKET_SET.remove(handle);
+            }
+            return Err(e);
+        }
 
+        *self.handles.write() = handles;
         *self_local_addr = Some(local_endpoint);
-        debug!("UDP socket {}: bound on {}", self.handle, endpoint);
+        debug!("UDP socket bound on {}", endpoint);
         Ok(())
     }
 
     /// Sends data on the socket to the given address. On success, returns the
     /// number of bytes written.
     pub fn send_to(&self, buf: &[u8], remote_addr: SocketAddr) -> AxResult<usize> {
+        self.send_to_with(buf, remote_addr, MsgFlags::default())
+    }
+
+    /// Sends data on the socket to the given address like
+    /// [`send_to`](Self::send_to), with the given flags, where only
+    /// [`dont_wait`](MsgFlags::dont_wait) is used.
+    pub fn send_to_with(
+        &self,
+        buf: &[u8],
+        remote_addr: SocketAddr,
+        flags: MsgFlags,
+    ) -> AxResult<usize> {
         if remote_addr.port() == 0 || remote_addr.ip().is_unspecified() {
             return ax_err!(InvalidInput, "socket send_to() failed: invalid address");
         }
-        self.send_impl(buf, IpEndpoint::from(remote_addr))
+        self.send_impl(buf, IpEndpoint::from(remote_addr), flags)
     }
 
     /// Receives a single datagram message on the socket. On success, returns
     /// the number of bytes read and the origin.
     pub fn recv_from(&self, buf: &mut [u8]) -> AxResult<(usize, SocketAddr)> {
-        self.recv_impl(|socket| match socket.recv_slice(buf) {
+        self.recv_impl(false, |socket| match socket.recv_slice(buf) {
             Ok((len, meta)) => Ok((len, SocketAddr::from(meta.endpoint))),
             Err(_) => ax_err!(BadState, "socket recv_from() failed"),
         })
@@ -121,12 +193,35 @@ impl UdpSocket {
     /// Receives a single datagram message on the socket, without removing it from
     /// the queue. On success, returns the number of bytes read and the origin.
     pub fn peek_from(&self, buf: &mut [u8]) -> AxResult<(usize, SocketAddr)> {
-        self.recv_impl(|socket| match socket.peek_slice(buf) {
+        self.recv_impl(false, |socket| match socket.peek_slice(buf) {
             Ok((len, meta)) => Ok((len, SocketAddr::from(meta.endpoint))),
             Err(_) => ax_err!(BadState, "socket recv_from() failed"),
         })
     }
 
+    /// Receives a single datagram message on the socket, with the given flags,
+    /// where [`wait_all`](MsgFlags::wait_all) is ignored.
+    ///
+    /// The message is truncated if `buf` is too small. On success, returns the
+    /// number of bytes read, the full length of the message, and the origin.
+    pub fn recv_from_with(
+        &self,
+        buf: &mut [u8],
+        flags: MsgFlags,
+    ) -> AxResult<(usize, usize, SocketAddr)> {
+        self.recv_impl(flags.dont_wait, |socket| {
+            let (packet, meta) = if flags.peek {
+                socket.peek().map(|(packet, meta)| (packet, *meta))
+            } else {
+                socket.recv()
+            }
+            .map_err(|_| ax_err_type!(BadState, "socket recv_from() failed"))?;
+            let len = packet.len().min(buf.len());
+            buf[..len].copy_from_slice(&packet[..len]);
+            Ok((len, packet.len(), SocketAddr::from(meta.endpoint)))
+        })
+    }
+
     /// Connects this UDP socket to a remote address, allowing the `send` and
     /// `recv` to be used to send data and also applies filters to only receive
     /// data from the specified address.
@@ -142,21 +237,28 @@ impl UdpSocket {
         }
 
         *self_peer_addr = Some(IpEndpoint::from(addr));
-        debug!("UDP socket {}: connected to {}", self.handle, addr);
+        debug!("UDP socket connected to {}", addr);
         Ok(())
     }
 
     /// Sends data on the socket to the remote address to which it is connected.
     pub fn send(&self, buf: &[u8]) -> AxResult<usize> {
+        self.send_with(buf, MsgFlags::default())
+    }
+
+    /// Sends data on the socket to the remote address to which it is connected
+    /// like [`send`](Self::send), with the given flags, where only
+    /// [`dont_wait`](MsgFlags::dont_wait) is used.
+    pub fn send_with(&self, buf: &[u8], flags: MsgFlags) -> AxResult<usize> {
         let remote_endpoint = self.remote_endpoint()?;
-        self.send_impl(buf, remote_endpoint)
+        self.send_impl(buf, remote_endpoint, flags)
     }
 
     /// Receives a single datagram message on the socket from the remote address
     /// to which it is connected. On success, returns the number of bytes read.
     pub fn recv(&self, buf: &mut [u8]) -> AxResult<usize> {
         let remote_endpoint = self.remote_endpoint()?;
-        self.recv_impl(|socket| {
+        self.recv_impl(false, |socket| {
             let (len, meta) = socket
                 .recv_slice(buf)
                 .map_err(|_| ax_err_type!(BadState, "socket recv() failed"))?;
@@ -173,14 +275,93 @@ impl UdpSocket {
 
     /// Close the socket.
     pub fn shutdown(&self) -> AxResult {
-        SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
-            debug!("UDP socket {}: shutting down", self.handle);
-            socket.close();
-        });
+        for &handle in self.handles.read().iter() {
+            SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(handle, |socket| {
+                debug!("UDP socket {}: shutting down", handle);
+                socket.close();
+            });
+        }
         SOCKET_SET.poll_interfaces();
         Ok(())
     }
 
+    /// Returns whether the address and port can be reused by
+    /// [`bind`](Self::bind) while other sockets are bound to them.
+    pub fn reuse_address(&self) -> bool {
+        self.opts.lock().reuse_addr
+    }
+
+    /// Allows or disallows to reuse the address and port by
+    /// [`bind`](Self::bind) while other sockets are bound to them.
+    pub fn set_reuse_address(&self, reuse: bool) {
+        self.opts.lock().reuse_addr = reuse;
+    }
+
+    /// Returns the size of the receive buffer in bytes.
+    pub fn recv_buffer_size(&self) -> usize {
+        match self.handles.read().first() {
+            Some(&handle) => SOCKET_SET
+                .with_socket::<udp::Socket, _, _>(handle, |socket| socket.payload_recv_capacity()),
+            None => self.opts.lock().recv_buf_size,
+        }
+    }
+
+    /// Sets the size of the receive buffer in bytes, which is clamped into
+    /// 1 KiB to 16 MiB.
+    ///
+    /// It takes effect if it is set before [`bind`](Self::bind), the buffers
+    /// of a bound socket are not resized.
+    pub fn set_recv_buffer_size(&self, size: usize) {
+        self.opts.lock().recv_buf_size = clamp_buffer_size(size);
+    }
+
+    /// Returns the size of the send buffer in bytes.
+    pub fn send_buffer_size(&self) -> usize {
+        match self.handles.read().first() {
+            Some(&handle) => SOCKET_SET
+                .with_socket::<udp::Socket, _, _>(handle, |socket| socket.payload_send_capacity()),
+            None => self.opts.lock().send_buf_size,
+        }
+    }
+
+    /// Sets the size of the send buffer in bytes, like
+    /// [`set_recv_buffer_size`](Self::set_recv_buffer_size).
+    pub fn set_send_buffer_size(&self, size: usize) {
+        self.opts.lock().send_buf_size = clamp_buffer_size(size);
+    }
+
+    /// Returns the timeout of receiving, or [`None`] if it blocks indefinitely.
+    pub fn recv_timeout(&self) -> Option<Duration> {
+        self.opts.lock().recv_timeout
+    }
+
+    /// Sets the timeout of receiving, after which it fails with
+    /// [`WouldBlock`](AxError::WouldBlock).
+    ///
+    /// Returns [`InvalidInput`](AxError::InvalidInput) if `timeout` is zero.
+    pub fn set_recv_timeout(&self, timeout: Option<Duration>) -> AxResult {
+        if timeout == Some(Duration::ZERO) {
+            return ax_err!(InvalidInput, "zero timeout");
+        }
+        self.opts.lock().recv_timeout = timeout;
+        Ok(())
+    }
+
+    /// Returns the timeout of sending, or [`None`] if it blocks indefinitely.
+    pub fn send_timeout(&self) -> Option<Duration> {
+        self.opts.lock().send_timeout
+    }
+
+    /// Sets the timeout of sending, like
+    /// [`set_recv_timeout`](Self::set_recv_timeout).
+    pub fn set_send_timeout(&self, timeout: Option<Duration>) -> AxResult {
+        if timeout == Some(Duration::ZERO) {
+            return ax_err!(InvalidInput, "zero timeout");
+        }
+        self.opts.lock().send_timeout = timeout;
+        Ok(())
+    }
+
     /// Whether the socket is readable or writable.
     pub fn poll(&self) -> AxResult<PollState> {
         if self.local_addr.read().is_none() {
@@ -189,12 +370,17 @@ impl UdpSocket {
                 writable: false,
             });
         }
-        SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
-            Ok(PollState {
-                readable: socket.can_recv(),
-                writable: socket.can_send(),
-            })
-        })
+        let mut state = PollState {
+            readable: false,
+            writable: false,
+        };
+        for &handle in self.handles.read().iter() {
+            SOCKET_SET.with_socket::<udp::Socket, _, _>(handle, |socket| {
+                state.readable |= socket.can_recv();
+                state.writable |= socket.can_send();
+            });
+        }
+        Ok(state)
     }
 }
 
@@ -207,13 +393,29 @@ impl UdpSocket {
         }
     }
 
-    fn send_impl(&self, buf: &[u8], remote_endpoint: IpEndpoint) -> AxResult<usize> {
+    fn send_impl(
+        &self,
+        buf: &[u8],
+        remote_endpoint: IpEndpoint,
+        flags: MsgFlags,
+    ) -> AxResult<usize> {
         if self.local_addr.read().is_none() {
             return ax_err!(NotConnected, "socket send() failed");
         }
+        // send through the socket on the interface to reach the remote
+        let handle = route(remote_endpoint.addr)
+            .and_then(|iface| {
+                self.handles
+                    .read()
+                    .iter()
+                    .find(|h| h.iface == iface)
+                    .copied()
+            })
+            .ok_or_else(|| ax_err_type!(ConnectionRefused, "socket send() failed: unreachable"))?;
 
-        self.block_on(|| {
-            SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
+        let timeout = self.opts.lock().send_timeout;
+        self.block_on(timeout, flags.dont_wait, || {
+            SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(handle, |socket| {
                 if socket.can_send() {
                     socket
                         .send_slice(buf, remote_endpoint)
@@ -232,7 +434,9 @@ impl UdpSocket {
         })
     }
 
-    fn recv_impl<F, T>(&self, mut op: F) -> AxResult<T>
+    /// Calls `op` on the socket of any interface that has data, and blocks
+    /// until there is one unless `dont_wait` or in nonblocking mode.
+    fn recv_impl<F, T>(&self, dont_wait: bool, mut op: F) -> AxResult<T>
     where
         F: FnMut(&mut udp::Socket) -> AxResult<T>,
     {
@@ -240,34 +444,42 @@ impl UdpSocket {
             return ax_err!(NotConnected, "socket send() failed");
         }
 
-        self.block_on(|| {
-            SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
-                if socket.can_recv() {
+        let timeout = self.opts.lock().recv_timeout;
+        self.block_on(timeout, dont_wait, || {
+            for &handle in self.handles.read().iter() {
+                let res = SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(handle, |socket| {
                     // data available
-                    op(socket)
-                } else {
-                    // no more data
-                    Err(AxError::WouldBlock)
+                    socket.can_recv().then(|| op(socket))
+                });
+                if let Some(res) = res {
+                    return res;
                 }
-            })
+            }
+            // no more data
+            Err(AxError::WouldBlock)
         })
     }
 
-    fn block_on<F, T>(&self, mut f: F) -> AxResult<T>
+    /// Registers `waker` to be woken up when the socket on any interface
+    /// becomes ready.
+    fn register_waker(&self, waker: &Waker) {
+        for &handle in self.handles.read().iter() {
+            SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(handle, |socket| {
+                socket.register_recv_waker(waker);
+                socket.register_send_waker(waker);
+            });
+        }
+    }
+
+    fn block_on<F, T>(&self, timeout: Option<Duration>, dont_wait: bool, mut f: F) -> AxResult<T>
     where
         F: FnMut() -> AxResult<T>,
     {
-        if self.is_nonblocking() {
+        if dont_wait || self.is_nonblocking() {
             f()
         } else {
-            loop {
-                SOCKET_SET.poll_interfaces();
-                match f() {
-                    Ok(t) => return Ok(t),
-                    Err(AxError::WouldBlock) => axtask::yield_now(),
-                    Err(e) => return Err(e),
-                }
-            }
+            self.waiter
+                .block_on(|waker| self.register_waker(waker), timeout, f)
         }
     }
 }
@@ -275,7 +487,9 @@ impl UdpSocket {
 impl Drop for UdpSocket {
     fn drop(&mut self) {
         self.shutdown().ok();
-        SOCKET_SET.remove(self.handle);
+        for &handle in self.handles.read().iter() {
+            SOCKET_SET.remove(handle);
+        }
     }
 }
 
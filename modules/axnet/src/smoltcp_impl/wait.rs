@@ -0,0 +1,200 @@
+//! Blocking of sockets until they are ready.
+//!
+//! If both the `multitask` and `irq` features are enabled, a blocked socket
+//! sleeps on its own wait queue. It is woken up by the wakers registered on the
+//! smoltcp sockets when they become ready (i.e., receive data, have space to
+//! send, or change the state), or after a short interval at most, to poll the
+//! interfaces for received packets. The `net-poll` task polls the interfaces in
+//! the background only when smoltcp needs it, i.e., its timers expire or a
+//! socket is changed, so an idle system is not woken up periodically.
+//!
+//! The poll task cannot be woken up by NIC interrupts instead: the drivers
+//! from `axdriver_net` and `axdriver_virtio` neither tell the IRQ number of a
+//! device nor acknowledge its interrupts, and the platform configurations do
+//! not list the IRQs of the VirtIO devices either. Without acknowledging, the
+//! level-triggered interrupt of a VirtIO device keeps firing once it is
+//! enabled.
+//!
+//! Otherwise, a blocked socket polls the interfaces and yields the CPU in a
+//! loop.
+//!
+//! In both cases, it gives up with [`WouldBlock`](AxError::WouldBlock) if the
+//! socket has a timeout (e.g., set by `SO_RCVTIMEO`) and it expires.
+
+use core::task::Waker;
+use core::time::Duration;
+
+use axhal::time::{TimeValue, wall_time};
+
+use axerrno::{AxError, AxResult};
+
+use super::SOCKET_SET;
+
+cfg_if::cfg_if! {
+    if #[cfg(all(feature = "multitask", feature = "irq"))] {
+        use alloc::sync::Arc;
+        use alloc::task::Wake;
+        use core::sync::atomic::{AtomicBool, Ordering};
+
+        use axtask::WaitQueue;
+
+        /// The longest time for a blocked socket to sleep before polling the
+        /// interfaces again, it bounds the latency of receiving packets, as
+        /// there are no interrupts to tell that they arrive.
+        const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+        /// The wait queue of the `net-poll` task.
+        static POLL_WQ: WaitQueue = WaitQueue::new();
+        /// Whether a socket is changed since the `net-poll` task last computed
+        /// when to poll.
+        static POLL_KICKED: AtomicBool = AtomicBool::new(false);
+
+        struct WaitState {
+            wq: WaitQueue,
+            woken: AtomicBool,
+        }
+
+        impl Wake for WaitState {
+            fn wake(self: Arc<Self>) {
+                self.wake_by_ref();
+            }
+
+            fn wake_by_ref(self: &Arc<Self>) {
+                self.woken.store(true, Ordering::Release);
+                self.wq.notify_all(false);
+            }
+        }
+
+        /// The wait queue of a socket, whose tasks are woken up when the socket
+        /// becomes ready.
+        pub(super) struct SocketWaiter {
+            state: Arc<WaitState>,
+            waker: Waker,
+        }
+
+        impl SocketWaiter {
+            pub fn new() -> Self {
+                let state = Arc::new(WaitState {
+                    wq: WaitQueue::new(),
+                    woken: AtomicBool::new(false),
+                });
+                Self {
+                    waker: Waker::from(state.clone()),
+                    state,
+                }
+            }
+
+            /// Calls `f` until it completes or fails with an error other than
+            /// [`WouldBlock`](AxError::WouldBlock), or `timeout` expires, and
+            /// sleeps between the calls.
+            ///
+            /// `register` is called with the waker before each call of `f`, to
+            /// register it on the smoltcp sockets that `f` operates on.
+            pub fn block_on<R, F, T>(
+                &self,
+                register: R,
+                timeout: Option<Duration>,
+                mut f: F,
+            ) -> AxResult<T>
+            where
+                R: Fn(&Waker),
+                F: FnMut() -> AxResult<T>,
+            {
+                let deadline = deadline(timeout);
+                loop {
+                    // wakers are consumed once woken, so register them again
+                    self.state.woken.store(false, Ordering::Release);
+                    register(&self.waker);
+                    SOCKET_SET.poll_interfaces();
+                    match f() {
+                        Err(AxError::WouldBlock) => {
+                            let mut dur = POLL_INTERVAL;
+                            if let Some(deadline) = deadline {
+                                let now = wall_time();
+                                if now >= deadline {
+                                    return Err(AxError::WouldBlock);
+                                }
+                                dur = dur.min(deadline - now);
+                            }
+                            self.state.wq.wait_timeout_until(dur, || {
+                                self.state.woken.load(Ordering::Acquire)
+                            });
+                        }
+                        res => return res,
+                    }
+                }
+            }
+        }
+
+        /// Wakes up the `net-poll` task after a socket is changed, e.g., data
+        /// is queued to send, so that it polls at the new time smoltcp needs.
+        pub(super) fn kick_poll_task() {
+            POLL_KICKED.store(true, Ordering::Release);
+            POLL_WQ.notify_one(false);
+        }
+
+        /// Spawns the `net-poll` task, which polls the interfaces when timers
+        /// of smoltcp (e.g., TCP retransmission) expire, or sockets are
+        /// changed. It sleeps without a timeout if there are no timers.
+        pub(super) fn spawn_poll_task() {
+            axtask::spawn_raw(
+                || loop {
+                    SOCKET_SET.poll_interfaces();
+                    // changes made before this are seen by `poll_delay`
+                    POLL_KICKED.store(false, Ordering::Release);
+                    let kicked = || POLL_KICKED.load(Ordering::Acquire);
+                    match SOCKET_SET.poll_delay() {
+                        Some(delay) => {
+                            POLL_WQ.wait_timeout_until(delay, kicked);
+                        }
+                        None => POLL_WQ.wait_until(kicked),
+                    }
+                },
+                "net-poll".into(),
+                axconfig::TASK_STACK_SIZE,
+            );
+        }
+    } else {
+        /// The wait queue of a socket, which only yields the CPU as there is no
+        /// way to sleep.
+        pub(super) struct SocketWaiter;
+
+        impl SocketWaiter {
+            pub const fn new() -> Self {
+                Self
+            }
+
+            /// Calls `f` until it completes or fails with an error other than
+            /// [`WouldBlock`](AxError::WouldBlock), or `timeout` expires, and
+            /// polls the interfaces and yields the CPU between the calls.
+            pub fn block_on<R, F, T>(
+                &self,
+                _register: R,
+                timeout: Option<Duration>,
+                mut f: F,
+            ) -> AxResult<T>
+            where
+                R: Fn(&Waker),
+                F: FnMut() -> AxResult<T>,
+            {
+                let deadline = deadline(timeout);
+                loop {
+                    SOCKET_SET.poll_interfaces();
+                    match f() {
+                        Err(AxError::WouldBlock) => {
+                            if deadline.is_some_and(|deadline| wall_time() >= deadline) {
+                                return Err(AxError::WouldBlock);
+                            }
+                            axtask::yield_now();
+                        }
+                        res => return res,
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn deadline(timeout: Option<Duration>) -> Option<TimeValue> {
+    timeout.map(|timeout| wall_time() + timeout)
+}
@@ -0,0 +1,348 @@
+#![cfg(feature = "dhcp")]
+
+use std::collections::VecDeque;
+use std::net::{IpAddr, Ipv4Addr};
+use std::ptr::NonNull;
+use std::sync::Mutex;
+
+use axdriver::{AxDeviceContainer, prelude::*};
+use axdriver_net::{EthernetAddress, NetBufPtr};
+use axnet::RouteEntry;
+
+const CLIENT_MAC: [u8; 6] = [0x52, 0x54, 0x00, 0x12, 0x34, 0x56];
+const SERVER_MAC: [u8; 6] = [0x52, 0x54, 0x00, 0x12, 0x34, 0x02];
+const BROADCAST_MAC: [u8; 6] = [0xff; 6];
+
+const LEASED_IP: Ipv4Addr = Ipv4Addr::new(192, 168, 7, 20);
+const ROUTER: Ipv4Addr = Ipv4Addr::new(192, 168, 7, 1);
+const DNS_SERVER: Ipv4Addr = Ipv4Addr::new(192, 168, 7, 3);
+const RESOLVED_IP: Ipv4Addr = Ipv4Addr::new(192, 168, 7, 80);
+
+const DHCP_DISCOVER: u8 = 1;
+const DHCP_OFFER: u8 = 2;
+const DHCP_REQUEST: u8 = 3;
+const DHCP_ACK: u8 = 5;
+const DHCP_NAK: u8 = 6;
+
+/// A DHCP and DNS server on the other end of the wire of [`StandInNic`], which
+/// answers the frames sent by the client right away.
+struct StandInServer {
+    /// Frames to be received by the client.
+    rx_queue: VecDeque<Vec<u8>>,
+    /// Transaction ID of the last DHCP message of the client.
+    xid: [u8; 4],
+    /// Whether the server has gone away, so that DHCP messages are no longer
+    /// answered.
+    gone: bool,
+    /// Destination of the last DNS query.
+    dns_dest: Option<Ipv4Addr>,
+}
+
+static SERVER: Mutex<StandInServer> = Mutex::new(StandInServer {
+    rx_queue: VecDeque::new(),
+    xid: [0; 4],
+    gone: false,
+    dns_dest: None,
+});
+
+impl StandInServer {
+    fn handle_frame(&mut self, frame: &[u8]) {
+        // only IPv4 over UDP is answered
+        if frame[12..14] != [0x08, 0x00] || frame[23] != 17 {
+            return;
+        }
+        let udp = &frame[14 + (frame[14] & 0xf) as usize * 4..];
+        let payload = &udp[8..];
+        match u16::from_be_bytes([udp[2], udp[3]]) {
+            67 => self.handle_dhcp(payload),
+            53 => {
+                let client_ip = Ipv4Addr::from(<[u8; 4]>::try_from(&frame[26..30]).unwrap());
+                let dest = Ipv4Addr::from(<[u8; 4]>::try_from(&frame[30..34]).unwrap());
+                let client_port = u16::from_be_bytes([udp[0], udp[1]]);
+                self.dns_dest = Some(dest);
+                let reply = dns_reply(payload);
+                self.rx_queue.push_back(udp_frame(
+                    CLIENT_MAC,
+                    (dest, 53),
+                    (client_ip, client_port),
+                    &reply,
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_dhcp(&mut self, msg: &[u8]) {
+        self.xid.copy_from_slice(&msg[4..8]);
+        if self.gone {
+            return;
+        }
+        let reply_type = match dhcp_message_type(msg) {
+            Some(DHCP_DISCOVER) => DHCP_OFFER,
+            Some(DHCP_REQUEST) => DHCP_ACK,
+            _ => return,
+        };
+        let reply = dhcp_reply(reply_type, self.xid);
+        self.rx_queue.push_back(udp_frame(
+            BROADCAST_MAC,
+            (ROUTER, 67),
+            (Ipv4Addr::BROADCAST, 68),
+            &reply,
+        ));
+    }
+}
+
+/// A NIC whose wire is connected to the [`StandInServer`].
+struct StandInNic;
+
+impl BaseDriverOps for StandInNic {
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Net
+    }
+
+    fn device_name(&self) -> &str {
+        "stand-in-net"
+    }
+}
+
+impl NetDriverOps for StandInNic {
+    fn mac_address(&self) -> EthernetAddress {
+        EthernetAddress(CLIENT_MAC)
+    }
+
+    fn can_transmit(&self) -> bool {
+        true
+    }
+
+    fn can_receive(&self) -> bool {
+        !SERVER.lock().unwrap().rx_queue.is_empty()
+    }
+
+    fn rx_queue_size(&self) -> usize {
+        64
+    }
+
+    fn tx_queue_size(&self) -> usize {
+        64
+    }
+
+    fn recycle_rx_buffer(&mut self, rx_buf: NetBufPtr) -> DevResult {
+        free_buf(rx_buf);
+        Ok(())
+    }
+
+    fn recycle_tx_buffers(&mut self) -> DevResult {
+        Ok(())
+    }
+
+    fn transmit(&mut self, tx_buf: NetBufPtr) -> DevResult {
+        SERVER.lock().unwrap().handle_frame(tx_buf.packet());
+        free_buf(tx_buf);
+        Ok(())
+    }
+
+    fn receive(&mut self) -> DevResult<NetBufPtr> {
+        let frame = SERVER.lock().unwrap().rx_queue.pop_front();
+        frame.map(alloc_buf).ok_or(DevError::Again)
+    }
+
+    fn alloc_tx_buffer(&mut self, size: usize) -> DevResult<NetBufPtr> {
+        Ok(alloc_buf(vec![0; size]))
+    }
+}
+
+fn alloc_buf(data: Vec<u8>) -> NetBufPtr {
+    let len = data.len();
+    let ptr = NonNull::new(Box::into_raw(data.into_boxed_slice()) as *mut u8).unwrap();
+    NetBufPtr::new(ptr, ptr, len)
+}
+
+fn free_buf(buf: NetBufPtr) {
+    let data = std::ptr::slice_from_raw_parts_mut(buf.raw_ptr::<u8>(), buf.packet_len());
+    drop(unsafe { Box::from_raw(data) });
+}
+
+/// Returns the value of the DHCP message type option.
+fn dhcp_message_type(msg: &[u8]) -> Option<u8> {
+    let mut options = &msg[240..];
+    while let [code, len, rest @ ..] = options {
+        match *code {
+            0 => options = &options[1..],
+            255 => break,
+            53 => return rest.first().copied(),
+            _ => options = &rest[*len as usize..],
+        }
+    }
+    None
+}
+
+/// Builds a DHCP reply of type `msg_type` to the client, which leases
+/// [`LEASED_IP`] with [`ROUTER`] and [`DNS_SERVER`] unless it is a NAK.
+fn dhcp_reply(msg_type: u8, xid: [u8; 4]) -> Vec<u8> {
+    let mut msg = vec![0; 240];
+    msg[..4].copy_from_slice(&[2, 1, 6, 0]); // BOOTREPLY over Ethernet
+    msg[4..8].copy_from_slice(&xid);
+    if msg_type != DHCP_NAK {
+        msg[16..20].copy_from_slice(&LEASED_IP.octets());
+    }
+    msg[20..24].copy_from_slice(&ROUTER.octets());
+    msg[28..34].copy_from_slice(&CLIENT_MAC);
+    msg[236..240].copy_from_slice(&[99, 130, 83, 99]); // magic cookie
+    msg.extend([53, 1, msg_type]);
+    msg.extend([54, 4]);
+    msg.extend(ROUTER.octets());
+    if msg_type != DHCP_NAK {
+        msg.extend([51, 4]);
+        msg.extend(3600u32.to_be_bytes());
+        msg.extend([1, 4, 255, 255, 255, 0]);
+        msg.extend([3, 4]);
+        msg.extend(ROUTER.octets());
+        msg.extend([6, 4]);
+        msg.extend(DNS_SERVER.octets());
+    }
+    msg.push(255);
+    msg
+}
+
+/// Builds a DNS reply to `query`, which answers [`RESOLVED_IP`].
+fn dns_reply(query: &[u8]) -> Vec<u8> {
+    let mut reply = query.to_vec();
+    reply[2..4].copy_from_slice(&[0x81, 0x80]); // standard response, no error
+    reply[6..8].copy_from_slice(&1u16.to_be_bytes()); // one answer
+    reply.extend([0xc0, 0x0c, 0, 1, 0, 1]); // the queried name, A, IN
+    reply.extend(60u32.to_be_bytes());
+    reply.extend(4u16.to_be_bytes());
+    reply.extend(RESOLVED_IP.octets());
+    reply
+}
+
+/// Builds an Ethernet frame from the server with a UDP datagram.
+fn udp_frame(
+    dst_mac: [u8; 6],
+    (src_ip, src_port): (Ipv4Addr, u16),
+    (dst_ip, dst_port): (Ipv4Addr, u16),
+    payload: &[u8],
+) -> Vec<u8> {
+    let mut frame = ethernet_header(dst_mac, 0x0800);
+    let ip_start = frame.len();
+    frame.extend([0x45, 0]);
+    frame.extend((20 + 8 + payload.len() as u16).to_be_bytes());
+    frame.extend([0, 0, 0, 0, 64, 17, 0, 0]);
+    frame.extend(src_ip.octets());
+    frame.extend(dst_ip.octets());
+    let checksum = ipv4_checksum(&frame[ip_start..]);
+    frame[ip_start + 10..ip_start + 12].copy_from_slice(&checksum.to_be_bytes());
+    frame.extend(src_port.to_be_bytes());
+    frame.extend(dst_port.to_be_bytes());
+    frame.extend((8 + payload.len() as u16).to_be_bytes());
+    frame.extend([0, 0]); // no UDP checksum
+    frame.extend(payload);
+    frame
+}
+
+/// Builds an ARP request from the server for the leased address, which makes
+/// the client learn the MAC address of `sender_ip`.
+fn arp_announce(sender_ip: Ipv4Addr) -> Vec<u8> {
+    let mut frame = ethernet_header(BROADCAST_MAC, 0x0806);
+    frame.extend([0, 1, 8, 0, 6, 4, 0, 1]); // Ethernet, IPv4, request
+    frame.extend(SERVER_MAC);
+    frame.extend(sender_ip.octets());
+    frame.extend([0; 6]);
+    frame.extend(LEASED_IP.octets());
+    frame
+}
+
+fn ethernet_header(dst_mac: [u8; 6], ethertype: u16) -> Vec<u8> {
+    let mut header = dst_mac.to_vec();
+    header.extend(SERVER_MAC);
+    header.extend(ethertype.to_be_bytes());
+    header
+}
+
+fn ipv4_checksum(header: &[u8]) -> u16 {
+    let mut sum: u32 = header
+        .chunks(2)
+        .map(|word| u16::from_be_bytes([word[0], word[1]]) as u32)
+        .sum();
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+fn eth0_addrs() -> Vec<(IpAddr, u8)> {
+    let ifaces = axnet::interfaces();
+    let eth0 = ifaces.iter().find(|iface| iface.name == "eth0").unwrap();
+    eth0.ip_addrs.clone()
+}
+
+fn default_route_via(router: Ipv4Addr) -> RouteEntry {
+    RouteEntry {
+        dest: Ipv4Addr::UNSPECIFIED.into(),
+        prefix_len: 0,
+        gateway: Some(router.into()),
+        iface: "eth0".into(),
+    }
+}
+
+fn test_lease() {
+    assert!(eth0_addrs().contains(&(LEASED_IP.into(), 24)));
+    let routes = axnet::routes();
+    assert!(routes.contains(&default_route_via(ROUTER)));
+    assert!(routes.contains(&RouteEntry {
+        dest: Ipv4Addr::new(192, 168, 7, 0).into(),
+        prefix_len: 24,
+        gateway: None,
+        iface: "eth0".into(),
+    }));
+    println!("test_lease() OK!");
+}
+
+fn test_leased_dns() {
+    let announce = arp_announce(DNS_SERVER);
+    SERVER.lock().unwrap().rx_queue.push_back(announce);
+    axnet::poll_interfaces();
+
+    let addrs = axnet::dns_query("arceos.test").unwrap();
+    assert_eq!(addrs, [IpAddr::from(RESOLVED_IP)]);
+    assert_eq!(SERVER.lock().unwrap().dns_dest, Some(DNS_SERVER));
+    println!("test_leased_dns() OK!");
+}
+
+fn test_static_fallback() {
+    // the server goes away and refuses the lease
+    let mut server = SERVER.lock().unwrap();
+    server.gone = true;
+    let nak = dhcp_reply(DHCP_NAK, server.xid);
+    let frame = udp_frame(BROADCAST_MAC, (ROUTER, 67), (Ipv4Addr::BROADCAST, 68), &nak);
+    server.rx_queue.push_back(frame);
+    drop(server);
+    axnet::poll_interfaces();
+
+    // the static configuration of `eth0` from `AX_IP`, if any
+    let static_ip = option_env!("AX_IP")
+        .and_then(|list| list.split(',').next())
+        .and_then(|ip| ip.trim().split('/').next())
+        .filter(|ip| !ip.is_empty())
+        .map(|ip| ip.parse::<IpAddr>().unwrap());
+    let addrs = eth0_addrs();
+    assert!(!addrs.contains(&(LEASED_IP.into(), 24)));
+    match static_ip {
+        Some(ip) => assert!(addrs.iter().any(|&(addr, _)| addr == ip)),
+        None => assert!(addrs.is_empty()),
+    }
+    assert!(!axnet::routes().contains(&default_route_via(ROUTER)));
+    println!("test_static_fallback() OK!");
+}
+
+#[test]
+fn test_dhcp() {
+    println!("Testing DHCP with a stand-in server ...");
+
+    let nic: AxNetDevice = Box::new(StandInNic);
+    axnet::init_network(AxDeviceContainer::from_one(nic));
+
+    test_lease();
+    test_leased_dns();
+    test_static_fallback();
+}
@@ -0,0 +1,17 @@
+use std::{env, fs, path::PathBuf};
+
+fn main() {
+    // the initramfs archive embedded by the `initramfs` feature
+    println!("cargo:rerun-if-env-changed=AX_INITRAMFS");
+    let archive = match env::var("AX_INITRAMFS") {
+        Ok(path) if !path.is_empty() => {
+            println!("cargo:rerun-if-changed={path}");
+            let path = fs::canonicalize(&path)
+                .unwrap_or_else(|e| panic!("invalid AX_INITRAMFS {:?}: {}", path, e));
+            format!("include_bytes!({:?})", path)
+        }
+        _ => "b\"\"".into(),
+    };
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    fs::write(out_dir.join("initramfs.rs"), archive).unwrap();
+}
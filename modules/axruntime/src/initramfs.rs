@@ -0,0 +1,118 @@
+//! The initramfs, `newc` cpio archives unpacked to `/` before `main`.
+//!
+//! An archive can be embedded into the kernel by setting `AX_INITRAMFS` to its
+//! path at build time, or loaded by the bootloader (e.g. by `-initrd` of QEMU)
+//! and passed in the `/chosen` node of the device tree, which is not supported
+//! on x86_64. If both exist, the embedded one is unpacked first, then the one
+//! from the bootloader, whose pages are returned to the memory allocator after
+//! unpacking.
+
+use core::ops::Range;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use axhal::mem::phys_to_virt;
+
+static EMBEDDED: &[u8] = include!(concat!(env!("OUT_DIR"), "/initramfs.rs"));
+
+/// Physical address range of the archive loaded by the bootloader, empty if
+/// there is none.
+static BOOT_START: AtomicUsize = AtomicUsize::new(0);
+static BOOT_END: AtomicUsize = AtomicUsize::new(0);
+
+/// Finds the archive loaded by the bootloader, `arg` is the argument from it.
+///
+/// It must be called before the memory allocator is initialized, which needs
+/// to keep away from the archive.
+#[cfg_attr(target_arch = "x86_64", allow(unused_variables))]
+pub(crate) fn probe(arg: usize) {
+    #[cfg(not(target_arch = "x86_64"))] // `arg` is not a device tree on x86
+    if let Some(range) = fdt_initrd(arg) {
+        info!("Found initramfs at [{:#x}, {:#x})", range.start, range.end);
+        BOOT_START.store(range.start, Ordering::Relaxed);
+        BOOT_END.store(range.end, Ordering::Relaxed);
+    }
+    #[cfg(target_arch = "x86_64")]
+    if EMBEDDED.is_empty() {
+        warn!("no embedded initramfs, and the bootloader one is not supported on x86_64");
+    }
+}
+
+/// Returns the physical address range of the archive loaded by the
+/// bootloader.
+fn boot_archive_range() -> Option<Range<usize>> {
+    let range = BOOT_START.load(Ordering::Relaxed)..BOOT_END.load(Ordering::Relaxed);
+    (!range.is_empty()).then_some(range)
+}
+
+/// Returns the physical address range of the pages of the archive loaded by
+/// the bootloader, which are kept away from the memory allocator until the
+/// archive is unpacked.
+#[cfg(feature = "alloc")]
+pub(crate) fn boot_archive_pages() -> Option<Range<usize>> {
+    use axhal::mem::PAGE_SIZE_4K;
+
+    let range = boot_archive_range()?;
+    Some(range.start & !(PAGE_SIZE_4K - 1)..range.end.next_multiple_of(PAGE_SIZE_4K))
+}
+
+/// Unpacks the archives to `/`.
+pub(crate) fn unpack_initramfs() {
+    let boot = boot_archive_range().map(|range| unsafe {
+        core::slice::from_raw_parts(phys_to_virt(range.start.into()).as_ptr(), range.len())
+    });
+    for (archive, source) in [(Some(EMBEDDED), "embedded"), (boot, "bootloader")] {
+        let Some(archive) = archive.filter(|a| !a.is_empty()) else {
+            continue;
+        };
+        info!(
+            "Unpacking initramfs from {} ({} bytes)...",
+            source,
+            archive.len()
+        );
+        match axfs::cpio::unpack(archive, "/") {
+            Ok(count) => info!("  {} entries unpacked", count),
+            Err(e) => warn!("failed to unpack initramfs from {}: {:?}", source, e),
+        }
+    }
+    #[cfg(feature = "alloc")]
+    release_boot_archive();
+}
+
+/// Returns the pages of the archive loaded by the bootloader in the free
+/// memory regions to the memory allocator.
+#[cfg(feature = "alloc")]
+fn release_boot_archive() {
+    use axhal::mem::{MemRegionFlags, memory_regions};
+
+    let Some(pages) = boot_archive_pages() else {
+        return;
+    };
+    BOOT_START.store(0, Ordering::Relaxed);
+    BOOT_END.store(0, Ordering::Relaxed);
+    for r in memory_regions().filter(|r| r.flags.contains(MemRegionFlags::FREE)) {
+        let start = pages.start.max(r.paddr.as_usize());
+        let end = pages.end.min(r.paddr.as_usize() + r.size);
+        if start >= end {
+            continue;
+        }
+        let vaddr = phys_to_virt(start.into()).as_usize();
+        if let Err(e) = axalloc::global_add_memory(vaddr, end - start) {
+            warn!("failed to release the initramfs pages: {:?}", e);
+        }
+    }
+}
+
+/// Finds `linux,initrd-start` and `linux,initrd-end` in the `/chosen` node of
+/// the flattened device tree at physical address `dtb`.
+#[cfg(not(target_arch = "x86_64"))]
+fn fdt_initrd(dtb: usize) -> Option<Range<usize>> {
+    if dtb == 0 {
+        return None;
+    }
+    // Safety: the header is checked before reading the whole blob.
+    let fdt = unsafe { fdt::Fdt::from_ptr(phys_to_virt(dtb.into()).as_ptr()) }.ok()?;
+    let chosen = fdt.find_node("/chosen")?;
+    let start = chosen.property("linux,initrd-start")?.as_usize()?;
+    let end = chosen.property("linux,initrd-end")?.as_usize()?;
+    Some(start..end).filter(|range| !range.is_empty())
+}
@@ -1,7 +1,16 @@
 use core::panic::PanicInfo;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether a panic is being handled, so that a panic in the backtrace (e.g.,
+/// on a corrupted stack) does not recurse.
+static PANICKING: AtomicBool = AtomicBool::new(false);
 
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
     error!("{}", info);
-    axhal::power::system_off()
+    if !PANICKING.swap(true, Ordering::Relaxed) {
+        axhal::backtrace::dump();
+    }
+    // the locks may be held, do not sync the filesystems
+    axhal::power::system_off_now()
 }
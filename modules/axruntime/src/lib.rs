@@ -10,7 +10,13 @@
 //! - `irq`: Enable interrupt handling support.
 //! - `multitask`: Enable multi-threading support.
 //! - `smp`: Enable SMP (symmetric multiprocessing) support.
-//! - `fs`: Enable filesystem support.
+//! - `fs`: Enable filesystem support. System information such as memory
+//!   usage, tasks and uptime is provided in `/proc`, and devices and tunables
+//!   of other modules (e.g. the log level) in `/sys`.
+//! - `initramfs`: Unpack the initramfs (`newc` cpio archives) to `/` before
+//!   `main`, which is embedded by setting `AX_INITRAMFS` to its path at build
+//!   time, or loaded by the bootloader and passed in the device tree. With no
+//!   block device, `/` is a RAM filesystem. Implies `fs`.
 //! - `net`: Enable networking support.
 //! - `display`: Enable graphics support.
 //!
@@ -22,12 +28,24 @@
 #[macro_use]
 extern crate axlog;
 
+#[cfg(feature = "fs")]
+extern crate alloc;
+
 #[cfg(all(target_os = "none", not(test)))]
 mod lang_items;
 
 #[cfg(feature = "smp")]
 mod mp;
 
+#[cfg(feature = "fs")]
+mod procfs;
+
+#[cfg(feature = "initramfs")]
+mod initramfs;
+
+#[cfg(feature = "fs")]
+mod sysfs;
+
 #[cfg(feature = "smp")]
 pub use self::mp::rust_main_secondary;
 
@@ -148,6 +166,9 @@ pub fn rust_main(cpu_id: usize, arg: usize) -> ! {
         );
     }
 
+    #[cfg(feature = "initramfs")]
+    self::initramfs::probe(arg);
+
     #[cfg(feature = "alloc")]
     init_allocator();
 
@@ -166,11 +187,20 @@ pub fn rust_main(cpu_id: usize, arg: usize) -> ! {
         let all_devices = axdriver::init_drivers();
 
         #[cfg(feature = "fs")]
-        axfs::init_filesystems(all_devices.block);
+        {
+            axfs::init_filesystems(all_devices.block);
+            axhal::power::set_shutdown_hook(sync_filesystems);
+            #[cfg(feature = "initramfs")]
+            self::initramfs::unpack_initramfs();
+            self::procfs::init_procfs();
+        }
 
         #[cfg(feature = "net")]
         axnet::init_network(all_devices.net);
 
+        #[cfg(feature = "fs")]
+        self::sysfs::init_sysfs(&all_devices.infos);
+
         #[cfg(feature = "display")]
         axdisplay::init_display(all_devices.display);
     }
@@ -210,35 +240,68 @@ pub fn rust_main(cpu_id: usize, arg: usize) -> ! {
     }
 }
 
+/// Writes the cached data back to the disks before powering off, whichever
+/// way the application exits.
+#[cfg(feature = "fs")]
+fn sync_filesystems() {
+    if let Err(e) = axfs::api::sync() {
+        warn!("failed to sync filesystems: {:?}", e);
+    }
+}
+
 #[cfg(feature = "alloc")]
 fn init_allocator() {
-    use axhal::mem::{MemRegionFlags, memory_regions, phys_to_virt};
+    use axhal::mem::phys_to_virt;
 
     info!("Initialize global memory allocator...");
     info!("  use {} allocator.", axalloc::global_allocator().name());
 
     let mut max_region_size = 0;
-    let mut max_region_paddr = 0.into();
-    for r in memory_regions() {
-        if r.flags.contains(MemRegionFlags::FREE) && r.size > max_region_size {
-            max_region_size = r.size;
-            max_region_paddr = r.paddr;
+    let mut max_region_paddr = 0;
+    for (paddr, size) in free_memory_regions() {
+        if size > max_region_size {
+            max_region_size = size;
+            max_region_paddr = paddr;
         }
     }
-    for r in memory_regions() {
-        if r.flags.contains(MemRegionFlags::FREE) && r.paddr == max_region_paddr {
-            axalloc::global_init(phys_to_virt(r.paddr).as_usize(), r.size);
+    for (paddr, size) in free_memory_regions() {
+        if paddr == max_region_paddr {
+            axalloc::global_init(phys_to_virt(paddr.into()).as_usize(), size);
             break;
         }
     }
-    for r in memory_regions() {
-        if r.flags.contains(MemRegionFlags::FREE) && r.paddr != max_region_paddr {
-            axalloc::global_add_memory(phys_to_virt(r.paddr).as_usize(), r.size)
+    for (paddr, size) in free_memory_regions() {
+        if paddr != max_region_paddr {
+            axalloc::global_add_memory(phys_to_virt(paddr.into()).as_usize(), size)
                 .expect("add heap memory region failed");
         }
     }
 }
 
+/// Returns the free physical memory regions as `(paddr, size)`, except the
+/// pages of the initramfs loaded by the bootloader.
+#[cfg(feature = "alloc")]
+fn free_memory_regions() -> impl Iterator<Item = (usize, usize)> {
+    use axhal::mem::{MemRegionFlags, memory_regions};
+
+    memory_regions()
+        .filter(|r| r.flags.contains(MemRegionFlags::FREE))
+        .flat_map(|r| {
+            let (start, end) = (r.paddr.as_usize(), r.paddr.as_usize() + r.size);
+            #[cfg(feature = "initramfs")]
+            if let Some(archive) = self::initramfs::boot_archive_pages() {
+                // the parts before and after the archive, empty if not overlapped
+                return [
+                    (start, archive.start.min(end)),
+                    (archive.end.max(start), end),
+                ];
+            }
+            [(start, end), (end, end)]
+        })
+        .filter(|(start, end)| start < end)
+        .map(|(start, end)| (start, end - start))
+}
+
 #[cfg(feature = "irq")]
 fn init_interrupt() {
     // Setup timer interrupt handler
@@ -249,6 +312,12 @@ fn init_interrupt() {
     static NEXT_DEADLINE: u64 = 0;
 
     fn update_timer() {
+        // The timer is programmed to the next timer event by `axtask` instead.
+        #[cfg(all(feature = "tickless", feature = "multitask"))]
+        if axtask::tick_stopped() {
+            return;
+        }
+
         let now_ns = axhal::time::monotonic_time_nanos();
         // Safety: we have disabled preemption in IRQ handler.
         let mut deadline = unsafe { NEXT_DEADLINE.read_current_raw() };
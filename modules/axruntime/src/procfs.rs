@@ -0,0 +1,128 @@
+//! Files in `/proc` generated from the runtime state.
+
+use alloc::{format, string::String};
+use core::fmt::Write;
+
+#[cfg(feature = "multitask")]
+use alloc::vec::Vec;
+
+/// Adds the system information files to procfs.
+pub(crate) fn init_procfs() {
+    axfs::procfs::add_file("uptime", uptime);
+    axfs::procfs::add_file("cpuinfo", cpuinfo);
+    #[cfg(feature = "alloc")]
+    axfs::procfs::add_file("meminfo", meminfo);
+    #[cfg(feature = "multitask")]
+    {
+        axfs::procfs::set_task_source(task_ids, current_task_id);
+        axfs::procfs::add_task_file("status", task_status);
+    }
+    #[cfg(all(feature = "multitask", feature = "smp"))]
+    axfs::procfs::add_file("sched_balance", sched_balance);
+}
+
+fn uptime() -> String {
+    // idle time is not accounted yet
+    let now = axhal::time::monotonic_time();
+    format!("{}.{:02} 0.00\n", now.as_secs(), now.subsec_millis() / 10)
+}
+
+fn cpuinfo() -> String {
+    let mut info = String::new();
+    for cpu in 0..axconfig::plat::CPU_NUM {
+        writeln!(info, "processor\t: {}", cpu).ok();
+        writeln!(info, "arch\t\t: {}", axconfig::ARCH).ok();
+        writeln!(info, "platform\t: {}\n", axconfig::PLATFORM).ok();
+    }
+    info
+}
+
+#[cfg(feature = "alloc")]
+fn meminfo() -> String {
+    use axhal::mem::PAGE_SIZE_4K;
+
+    let allocator = axalloc::global_allocator();
+    // the byte allocator takes its memory from the page allocator
+    let total = (allocator.used_pages() + allocator.available_pages()) * PAGE_SIZE_4K;
+    let free = allocator.available_pages() * PAGE_SIZE_4K + allocator.available_bytes();
+    let mut info = String::new();
+    writeln!(info, "MemTotal:       {:8} kB", total / 1024).ok();
+    writeln!(info, "MemFree:        {:8} kB", free / 1024).ok();
+    writeln!(info, "MemAvailable:   {:8} kB", free / 1024).ok();
+    writeln!(
+        info,
+        "HeapUsed:       {:8} kB",
+        allocator.used_bytes() / 1024
+    )
+    .ok();
+    writeln!(
+        info,
+        "HeapFree:       {:8} kB",
+        allocator.available_bytes() / 1024
+    )
+    .ok();
+    info
+}
+
+#[cfg(all(feature = "multitask", feature = "smp"))]
+fn sched_balance() -> String {
+    let mut info = String::from("cpu\tready\tidle_steals\tperiodic_pulls\tmigrated_out\tfailed\n");
+    for cpu in 0..axconfig::plat::CPU_NUM {
+        let stats = axtask::balance_stats(cpu);
+        writeln!(
+            info,
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            cpu,
+            stats.nr_ready,
+            stats.idle_steals,
+            stats.periodic_pulls,
+            stats.migrated_out,
+            stats.failed
+        )
+        .ok();
+    }
+    info
+}
+
+#[cfg(feature = "multitask")]
+fn task_ids() -> Vec<u64> {
+    let mut ids = Vec::new();
+    axtask::for_each_task(|task| ids.push(task.id().as_u64()));
+    ids
+}
+
+#[cfg(feature = "multitask")]
+fn current_task_id() -> Option<u64> {
+    axtask::current_may_uninit().map(|curr| curr.id().as_u64())
+}
+
+#[cfg(feature = "multitask")]
+fn task_status(id: u64) -> Option<String> {
+    use axtask::TaskState;
+
+    let mut task = None;
+    axtask::for_each_task(|t| {
+        if t.id().as_u64() == id {
+            task = Some(t.clone());
+        }
+    });
+    let task = task?;
+
+    let state = match task.state() {
+        TaskState::Running | TaskState::Ready => "R (running)",
+        TaskState::Blocked => "S (sleeping)",
+        TaskState::Exited => "Z (zombie)",
+    };
+    let cpumask = task.cpumask();
+    let cpus = (0..axconfig::plat::CPU_NUM)
+        .filter(|&cpu| cpumask.get(cpu))
+        .map(|cpu| format!("{}", cpu))
+        .collect::<Vec<_>>();
+
+    let mut status = String::new();
+    writeln!(status, "Name:\t{}", task.name()).ok();
+    writeln!(status, "State:\t{}", state).ok();
+    writeln!(status, "Tid:\t{}", id).ok();
+    writeln!(status, "Cpus_allowed_list:\t{}", cpus.join(",")).ok();
+    Some(status)
+}
@@ -0,0 +1,146 @@
+//! Files in `/sys` generated from the devices and runtime state, and the
+//! tunables of other modules.
+//!
+//! - `class/{block,net,graphics}/<name>/`: devices by their names in the
+//!   subsystems (e.g. `vda`, `eth0`, `fb0`), with the `driver` name and the
+//!   `pci_address` if on the PCI bus.
+//! - `bus/pci/devices/<address>/`: PCI devices, with the `driver` name and
+//!   the `device` name in the subsystem.
+//! - `module/<module>/parameters/`: tunables, e.g. `axlog/parameters/max_level`.
+
+use alloc::{format, string::String, vec::Vec};
+use axdriver::{DeviceInfo, prelude::DeviceType};
+use axerrno::{AxError, AxResult, ax_err};
+
+/// Adds the device, clock source and tunable files to sysfs.
+///
+/// `devices` are all the probed devices, block devices must have been
+/// registered to the filesystem, and NICs to the network subsystem.
+pub(crate) fn init_sysfs(devices: &[DeviceInfo]) {
+    add_devices(devices);
+    add_clocksource();
+    add_tunables();
+}
+
+fn add_devices(devices: &[DeviceInfo]) {
+    let block_names = axfs::api::block_devices();
+    let nic_names = nic_names();
+    let (mut blocks, mut nics, mut displays) = (0, 0, 0);
+    for dev in devices {
+        let (class, name) = match dev.device_type {
+            DeviceType::Block => {
+                blocks += 1;
+                match block_names.get(blocks - 1) {
+                    Some(name) => ("block", name.clone()),
+                    None => continue,
+                }
+            }
+            DeviceType::Net => {
+                nics += 1;
+                match nic_names.get(nics - 1) {
+                    Some(name) => ("net", name.clone()),
+                    None => continue,
+                }
+            }
+            DeviceType::Display => {
+                displays += 1;
+                ("graphics", format!("fb{}", displays - 1))
+            }
+            _ => continue,
+        };
+
+        let dir = format!("class/{}/{}", class, name);
+        let driver = format!("{}\n", dev.name);
+        add_text(&format!("{}/driver", dir), driver.clone());
+        if let Some(addr) = dev.pci_address {
+            add_text(&format!("{}/pci_address", dir), format!("{}\n", addr));
+            let dir = format!("bus/pci/devices/{}", addr);
+            add_text(&format!("{}/driver", dir), driver);
+            add_text(&format!("{}/device", dir), format!("{}\n", name));
+        }
+    }
+}
+
+/// Returns the names of the interfaces of the NICs, in the order of probing.
+fn nic_names() -> Vec<String> {
+    #[cfg(feature = "net")]
+    let names = axnet::interfaces()
+        .into_iter()
+        .filter(|iface| iface.mac_addr.is_some())
+        .map(|iface| iface.name)
+        .collect();
+    #[cfg(not(feature = "net"))]
+    let names = Vec::new();
+    names
+}
+
+/// Adds a file with fixed content.
+fn add_text(path: &str, text: String) {
+    axfs::sysfs::add_file(path, move || text.clone());
+}
+
+fn add_clocksource() {
+    // names of the counters used by `axhal::time` in Linux
+    let name = if cfg!(target_arch = "x86_64") {
+        "tsc"
+    } else if cfg!(target_arch = "aarch64") {
+        "arch_sys_counter"
+    } else if cfg!(any(target_arch = "riscv32", target_arch = "riscv64")) {
+        "riscv_clocksource"
+    } else if cfg!(target_arch = "loongarch64") {
+        "Constant"
+    } else {
+        "unknown"
+    };
+    let dir = "devices/system/clocksource/clocksource0";
+    let name = format!("{}\n", name);
+    add_text(&format!("{}/current_clocksource", dir), name.clone());
+    add_text(&format!("{}/available_clocksource", dir), name);
+}
+
+/// Parses a written number.
+fn parse<T: core::str::FromStr>(value: &str) -> AxResult<T> {
+    value.parse().map_err(|_| AxError::InvalidInput)
+}
+
+fn add_tunables() {
+    axfs::sysfs::add_tunable(
+        "module/axlog/parameters/max_level",
+        || format!("{}\n", axlog::max_level()),
+        |level| match level {
+            "off" | "error" | "warn" | "info" | "debug" | "trace" => {
+                axlog::set_max_level(level);
+                Ok(())
+            }
+            _ => ax_err!(InvalidInput, "unknown log level"),
+        },
+    );
+
+    #[cfg(feature = "multitask")]
+    {
+        let scheduler = format!("{}\n", axtask::scheduler_name());
+        add_text("module/axtask/parameters/scheduler", scheduler);
+        axfs::sysfs::add_tunable(
+            "module/axtask/parameters/default_priority",
+            || format!("{}\n", axtask::default_priority()),
+            |prio| match axtask::set_default_priority(parse(prio)?) {
+                true => Ok(()),
+                false => ax_err!(InvalidInput, "priority out of range"),
+            },
+        );
+    }
+
+    #[cfg(feature = "net")]
+    {
+        axfs::sysfs::add_tunable(
+            "module/axnet/parameters/tcp_buffer_size",
+            || format!("{}\n", axnet::tcp_buffer_size()),
+            |size| axnet::set_tcp_buffer_size(parse(size)?),
+        );
+        axfs::sysfs::add_tunable(
+            "module/axnet/parameters/udp_buffer_size",
+            || format!("{}\n", axnet::udp_buffer_size()),
+            |size| axnet::set_udp_buffer_size(parse(size)?),
+        );
+    }
+}
@@ -1,18 +1,24 @@
 //! Task APIs for multi-task configuration.
 
 use alloc::{string::String, sync::Arc};
+use core::ops::RangeInclusive;
+use core::sync::atomic::{AtomicIsize, Ordering};
 
 use kernel_guard::NoPreemptIrqSave;
 
 pub(crate) use crate::run_queue::{current_run_queue, select_run_queue};
 
 #[doc(cfg(feature = "multitask"))]
-pub use crate::task::{CurrentTask, TaskId, TaskInner};
+pub use crate::task::{CpuTime, CurrentTask, TaskId, TaskInner, TaskState};
 #[doc(cfg(feature = "multitask"))]
 pub use crate::task_ext::{TaskExtMut, TaskExtRef};
 #[doc(cfg(feature = "multitask"))]
 pub use crate::wait_queue::WaitQueue;
 
+#[cfg(feature = "smp")]
+#[doc(cfg(feature = "smp"))]
+pub use crate::run_queue::BalanceStats;
+
 /// The reference type of a task.
 pub type AxTaskRef = Arc<AxTask>;
 
@@ -24,13 +30,16 @@ cfg_if::cfg_if! {
         const MAX_TIME_SLICE: usize = 5;
         pub(crate) type AxTask = axsched::RRTask<TaskInner, MAX_TIME_SLICE>;
         pub(crate) type Scheduler = axsched::RRScheduler<TaskInner, MAX_TIME_SLICE>;
+        const PRIORITY_RANGE: RangeInclusive<isize> = 0..=0;
     } else if #[cfg(feature = "sched-cfs")] {
         pub(crate) type AxTask = axsched::CFSTask<TaskInner>;
         pub(crate) type Scheduler = axsched::CFScheduler<TaskInner>;
+        const PRIORITY_RANGE: RangeInclusive<isize> = -20..=19;
     } else {
         // If no scheduler features are set, use FIFO as the default.
         pub(crate) type AxTask = axsched::FifoTask<TaskInner>;
         pub(crate) type Scheduler = axsched::FifoScheduler<TaskInner>;
+        const PRIORITY_RANGE: RangeInclusive<isize> = 0..=0;
     }
 }
 
@@ -68,6 +77,36 @@ pub fn current() -> CurrentTask {
     CurrentTask::get()
 }
 
+/// Calls `f` on every task that has been spawned and not dropped yet, in the
+/// order of task IDs.
+///
+/// Exited tasks are included until all references to them are dropped.
+pub fn for_each_task(f: impl FnMut(&AxTaskRef)) {
+    // `f` runs without the registry locked, and the references are dropped
+    // after it, as dropping the last one unregisters the task.
+    crate::task::all_tasks().iter().for_each(f);
+}
+
+/// Returns the CPU time used by the current task, including the current time
+/// slice.
+pub fn current_cpu_time() -> CpuTime {
+    let _guard = NoPreemptIrqSave::new();
+    let curr = current();
+    crate::run_queue::charge_cpu_time(curr.as_task_ref());
+    curr.cpu_time()
+}
+
+/// Returns the total CPU time used by all tasks except the idle tasks,
+/// including the exited ones.
+///
+/// Only the current time slice of the current task is included, those of
+/// tasks running on other CPUs are not.
+pub fn total_cpu_time() -> CpuTime {
+    let _guard = NoPreemptIrqSave::new();
+    crate::run_queue::charge_cpu_time(current().as_task_ref());
+    crate::run_queue::total_cpu_time()
+}
+
 /// Initializes the task scheduler (for the primary CPU).
 pub fn init_scheduler() {
     info!("Initialize scheduling...");
@@ -96,13 +135,39 @@ pub fn on_timer_tick() {
     crate::timers::check_events();
     // Since irq and preemption are both disabled here,
     // we can get current run queue with the default `kernel_guard::NoOp`.
-    current_run_queue::<NoOp>().scheduler_timer_tick();
+    let mut rq = current_run_queue::<NoOp>();
+    rq.scheduler_timer_tick();
+    #[cfg(feature = "smp")]
+    rq.balance_tick();
+    #[cfg(feature = "tickless")]
+    crate::tickless::reprogram(true);
+}
+
+/// Returns whether the periodic timer tick is stopped on the current CPU.
+///
+/// If it's stopped, the timer interrupt handler should not program the next
+/// tick, as the timer is programmed to the next timer event by
+/// [`on_timer_tick`] instead.
+#[cfg(feature = "tickless")]
+#[doc(cfg(feature = "tickless"))]
+pub fn tick_stopped() -> bool {
+    crate::tickless::tick_stopped()
 }
 
+/// Priority of new tasks, see [`set_default_priority`].
+static DEFAULT_PRIORITY: AtomicIsize = AtomicIsize::new(0);
+
 /// Adds the given task to the run queue, returns the task reference.
+///
+/// The task is given the [default priority](default_priority).
 pub fn spawn_task(task: TaskInner) -> AxTaskRef {
     let task_ref = task.into_arc();
-    select_run_queue::<NoPreemptIrqSave>(&task_ref).add_task(task_ref.clone());
+    let mut rq = select_run_queue::<NoPreemptIrqSave>(&task_ref);
+    let prio = default_priority();
+    if prio != 0 {
+        rq.set_task_priority(&task_ref, prio);
+    }
+    rq.add_task(task_ref.clone());
     task_ref
 }
 
@@ -142,6 +207,44 @@ pub fn set_priority(prio: isize) -> bool {
     current_run_queue::<NoPreemptIrqSave>().set_current_priority(prio)
 }
 
+/// Returns the priority given to new tasks, which is 0 by default.
+pub fn default_priority() -> isize {
+    DEFAULT_PRIORITY.load(Ordering::Relaxed)
+}
+
+/// Sets the priority given to new tasks, existing tasks are not affected.
+///
+/// Returns `false` if `prio` is out of the range of the scheduler (see
+/// [`set_priority`]). Schedulers without priorities only accept 0.
+pub fn set_default_priority(prio: isize) -> bool {
+    if PRIORITY_RANGE.contains(&prio) {
+        DEFAULT_PRIORITY.store(prio, Ordering::Relaxed);
+        true
+    } else {
+        false
+    }
+}
+
+/// Returns the name of the scheduler, e.g. `CFS`.
+pub fn scheduler_name() -> &'static str {
+    <Scheduler as axsched::BaseScheduler>::scheduler_name()
+}
+
+/// Returns the load balancing statistics of the given CPU.
+///
+/// Ready tasks are moved from busy CPUs to idle ones, and periodically between
+/// busy CPUs with uneven loads, if their CPU affinity allows.
+#[cfg(feature = "smp")]
+#[doc(cfg(feature = "smp"))]
+pub fn balance_stats(cpu_id: usize) -> BalanceStats {
+    assert!(
+        cpu_id < axconfig::plat::CPU_NUM,
+        "invalid CPU ID {}",
+        cpu_id
+    );
+    crate::run_queue::balance_stats(cpu_id)
+}
+
 /// Set the affinity for the current task.
 /// [`AxCpuMask`] is used to specify the CPU affinity.
 /// Returns `true` if the affinity is set successfully.
@@ -13,6 +13,20 @@
 //!   APIs can be used, such as [`sleep`], [`sleep_until`], and
 //!   [`WaitQueue::wait_timeout`].
 //! - `preempt`: Enable preemptive scheduling.
+//! - `tickless`: Stop the periodic timer tick of a CPU when it's not needed,
+//!   i.e., when it's idle, or runs a task without preemption. The timer is
+//!   programmed to the earliest timer event instead. Under `smp`, idle CPUs
+//!   keep the tick to find new tasks, as they can't be notified by IPIs, so
+//!   idle CPUs of SMP systems do not benefit from it until the platform layer
+//!   supports IPIs.
+//! - `smp`: Enable multi-core support. Each CPU has its own run queue, and
+//!   ready tasks are moved from busy CPUs to idle ones, and periodically
+//!   between busy CPUs if `irq` is also enabled, see [`balance_stats`].
+//! - `paging`: Map the kernel stacks of tasks in the kernel address space,
+//!   each with an unmapped guard page below it. Stack overflows fault in the
+//!   guard page, or are found when the task is switched out, and are reported
+//!   with the task, instead of corrupting the heap. Freed stacks stay mapped,
+//!   and are reused by new tasks.
 //! - `sched-fifo`: Use the [FIFO cooperative scheduler][1]. It also enables the
 //!   `multitask` feature if it is enabled. This feature is enabled by default,
 //!   and it can be overriden by other scheduler features.
@@ -41,6 +55,7 @@ cfg_if::cfg_if! {
 
         #[macro_use]
         mod run_queue;
+        mod stack;
         mod task;
         mod task_ext;
         mod api;
@@ -48,6 +63,8 @@ cfg_if::cfg_if! {
 
         #[cfg(feature = "irq")]
         mod timers;
+        #[cfg(feature = "tickless")]
+        mod tickless;
 
         #[doc(cfg(feature = "multitask"))]
         pub use self::api::*;
@@ -1,9 +1,13 @@
 use alloc::collections::VecDeque;
 use alloc::sync::Arc;
 use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::time::Duration;
 
 #[cfg(feature = "smp")]
 use alloc::sync::Weak;
+#[cfg(feature = "smp")]
+use core::sync::atomic::{AtomicBool, AtomicUsize};
 
 use axsched::BaseScheduler;
 use kernel_guard::BaseGuard;
@@ -12,7 +16,7 @@ use lazyinit::LazyInit;
 
 use axhal::percpu::this_cpu_id;
 
-use crate::task::{CurrentTask, TaskState};
+use crate::task::{CpuTime, CurrentTask, TaskState};
 use crate::wait_queue::WaitQueueGuard;
 use crate::{AxCpuMask, AxTaskRef, Scheduler, TaskInner, WaitQueue};
 
@@ -37,8 +41,22 @@ percpu_static! {
     /// Stores the weak reference to the previous task that is running on this CPU.
     #[cfg(feature = "smp")]
     PREV_TASK: Weak<crate::AxTask> = Weak::new(),
+    /// Monotonic time of the last charge of CPU time on this CPU, in nanoseconds.
+    LAST_CHARGE_NANOS: u64 = 0,
+    /// Time spent in IRQ handlers on this CPU at the last charge, in nanoseconds.
+    #[cfg(feature = "irq")]
+    LAST_IRQ_NANOS: u64 = 0,
+    /// Timer ticks since the last periodic load balancing on this CPU.
+    #[cfg(all(feature = "smp", feature = "irq"))]
+    BALANCE_TICKS: usize = 0,
 }
 
+/// Time spent running all tasks except the idle tasks, in nanoseconds.
+static TOTAL_RUN_NANOS: AtomicU64 = AtomicU64::new(0);
+/// Time spent in IRQ handlers while running all tasks except the idle tasks,
+/// in nanoseconds.
+static TOTAL_IRQ_NANOS: AtomicU64 = AtomicU64::new(0);
+
 /// An array of references to run queues, one for each CPU, indexed by cpu_id.
 ///
 /// This static variable holds references to the run queues for each CPU in the system.
@@ -53,6 +71,64 @@ static mut RUN_QUEUES: [MaybeUninit<&'static mut AxRunQueue>; axconfig::plat::CP
 #[allow(clippy::declare_interior_mutable_const)] // It's ok because it's used only for initialization `RUN_QUEUES`.
 const ARRAY_REPEAT_VALUE: MaybeUninit<&'static mut AxRunQueue> = MaybeUninit::uninit();
 
+/// Whether the run queue of each CPU in [`RUN_QUEUES`] is initialized, so that
+/// other CPUs can steal tasks from it.
+#[cfg(feature = "smp")]
+static RUN_QUEUE_ONLINE: [AtomicBool; axconfig::plat::CPU_NUM] =
+    [const { AtomicBool::new(false) }; axconfig::plat::CPU_NUM];
+
+/// Load balancing statistics of each CPU, see [`BalanceStats`].
+#[cfg(feature = "smp")]
+static BALANCE_STATS: [BalanceCounters; axconfig::plat::CPU_NUM] =
+    [const { BalanceCounters::new() }; axconfig::plat::CPU_NUM];
+
+/// Interval of the periodic load balancing, 100ms.
+#[cfg(all(feature = "smp", feature = "irq"))]
+const BALANCE_INTERVAL_TICKS: usize = if axconfig::TICKS_PER_SEC >= 10 {
+    axconfig::TICKS_PER_SEC / 10
+} else {
+    1
+};
+
+/// Load balancing statistics of a CPU.
+#[cfg(feature = "smp")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BalanceStats {
+    /// Number of ready tasks in the run queue of the CPU, not including the
+    /// running one.
+    pub nr_ready: usize,
+    /// Number of tasks stolen from other CPUs when the CPU became idle.
+    pub idle_steals: u64,
+    /// Number of tasks pulled from busier CPUs by the periodic balancing.
+    pub periodic_pulls: u64,
+    /// Number of tasks stolen or pulled away by other CPUs.
+    pub migrated_out: u64,
+    /// Number of balancing attempts that found a busier CPU, but could not
+    /// move its next task, as the task is not allowed to run on this CPU or
+    /// it's still being switched out.
+    pub failed: u64,
+}
+
+#[cfg(feature = "smp")]
+struct BalanceCounters {
+    idle_steals: AtomicU64,
+    periodic_pulls: AtomicU64,
+    migrated_out: AtomicU64,
+    failed: AtomicU64,
+}
+
+#[cfg(feature = "smp")]
+impl BalanceCounters {
+    const fn new() -> Self {
+        Self {
+            idle_steals: AtomicU64::new(0),
+            periodic_pulls: AtomicU64::new(0),
+            migrated_out: AtomicU64::new(0),
+            failed: AtomicU64::new(0),
+        }
+    }
+}
+
 /// Returns a reference to the current run queue in [`CurrentRunQueueRef`].
 ///
 /// ## Safety
@@ -148,10 +224,12 @@ fn get_run_queue(index: usize) -> &'static mut AxRunQueue {
 ///
 /// * [`AxRunQueueRef`] - a static reference to the selected [`AxRunQueue`] (current or remote).
 ///
+/// Tasks placed unevenly are moved later by the idle and periodic load
+/// balancing, see [`steal_task`].
+///
 /// ## TODO
 ///
-/// 1. Implement better load balancing across CPUs for more efficient task distribution.
-/// 2. Use a more generic load balancing algorithm that can be customized or replaced.
+/// 1. Use a more generic load balancing algorithm that can be customized or replaced.
 ///
 #[inline]
 pub(crate) fn select_run_queue<G: BaseGuard>(task: &AxTaskRef) -> AxRunQueueRef<'static, G> {
@@ -186,6 +264,10 @@ pub(crate) struct AxRunQueue {
     /// Since irq and preempt are preserved by the kernel guard hold by `AxRunQueueRef`,
     /// we just use a simple raw spin lock here.
     scheduler: SpinRaw<Scheduler>,
+    /// Number of tasks in the scheduler, only updated with the scheduler
+    /// locked, and read by other CPUs to find the busiest run queue.
+    #[cfg(feature = "smp")]
+    nr_ready: AtomicUsize,
 }
 
 /// A reference to the run queue with specific guard.
@@ -237,7 +319,21 @@ impl<G: BaseGuard> AxRunQueueRef<'_, G> {
             self.inner.cpu_id
         );
         assert!(task.is_ready());
-        self.inner.scheduler.lock().add_task(task);
+        task.set_cpu_id(self.inner.cpu_id);
+        let mut scheduler = self.inner.scheduler.lock();
+        scheduler.add_task(task);
+        #[cfg(feature = "smp")]
+        self.inner.nr_ready.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Sets the priority of a task in the scheduler of this run queue, or to
+    /// be added to it.
+    pub fn set_task_priority(&mut self, task: &AxTaskRef, prio: isize) -> bool {
+        let ok = self.inner.scheduler.lock().set_priority(task, prio);
+        if ok {
+            task.set_priority(prio);
+        }
+        ok
     }
 
     /// Unblock one task by inserting it into the run queue.
@@ -434,11 +530,41 @@ impl<G: BaseGuard> CurrentRunQueueRef<'_, G> {
         }
     }
 
+    /// Pulls a ready task from the busiest CPU every [`BALANCE_INTERVAL_TICKS`]
+    /// timer ticks, if it has at least 2 more ready tasks than this CPU.
+    ///
+    /// Idle CPUs steal tasks by themselves in [`AxRunQueue::resched`], so it
+    /// only balances the CPUs that are all busy.
+    #[cfg(all(feature = "smp", feature = "irq"))]
+    pub fn balance_tick(&mut self) {
+        // Safety: IRQs are disabled in the timer handler.
+        let ticks = unsafe { BALANCE_TICKS.current_ref_mut_raw() };
+        *ticks += 1;
+        if *ticks < BALANCE_INTERVAL_TICKS {
+            return;
+        }
+        *ticks = 0;
+
+        let cpu_id = self.inner.cpu_id;
+        let nr_ready = self.inner.nr_ready.load(Ordering::Relaxed);
+        if let Some(task) = steal_task(cpu_id, nr_ready.saturating_add(2)) {
+            BALANCE_STATS[cpu_id]
+                .periodic_pulls
+                .fetch_add(1, Ordering::Relaxed);
+            task.set_cpu_id(cpu_id);
+            let mut scheduler = self.inner.scheduler.lock();
+            scheduler.put_prev_task(task, false);
+            self.inner.nr_ready.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
     pub fn set_current_priority(&mut self, prio: isize) -> bool {
-        self.inner
-            .scheduler
-            .lock()
-            .set_priority(self.current_task.as_task_ref(), prio)
+        let curr = self.current_task.as_task_ref();
+        let ok = self.inner.scheduler.lock().set_priority(curr, prio);
+        if ok {
+            curr.set_priority(prio);
+        }
+        ok
     }
 }
 
@@ -449,12 +575,15 @@ impl AxRunQueue {
         let gc_task = TaskInner::new(gc_entry, "gc".into(), axconfig::TASK_STACK_SIZE).into_arc();
         // gc task should be pinned to the current CPU.
         gc_task.set_cpumask(AxCpuMask::one_shot(cpu_id));
+        gc_task.set_cpu_id(cpu_id);
 
         let mut scheduler = Scheduler::new();
         scheduler.add_task(gc_task);
         Self {
             cpu_id,
             scheduler: SpinRaw::new(scheduler),
+            #[cfg(feature = "smp")]
+            nr_ready: AtomicUsize::new(1),
         }
     }
 
@@ -494,7 +623,11 @@ impl AxRunQueue {
                 }
             }
             // TODO: priority
-            self.scheduler.lock().put_prev_task(task, preempt);
+            task.set_cpu_id(self.cpu_id);
+            let mut scheduler = self.scheduler.lock();
+            scheduler.put_prev_task(task, preempt);
+            #[cfg(feature = "smp")]
+            self.nr_ready.fetch_add(1, Ordering::Relaxed);
             true
         } else {
             false
@@ -504,14 +637,18 @@ impl AxRunQueue {
     /// Core reschedule subroutine.
     /// Pick the next task to run and switch to it.
     fn resched(&mut self) {
-        let next = self
-            .scheduler
-            .lock()
-            .pick_next_task()
-            .unwrap_or_else(|| unsafe {
-                // Safety: IRQs must be disabled at this time.
-                IDLE_TASK.current_ref_raw().get_unchecked().clone()
-            });
+        let next = self.pick_next_task().unwrap_or_else(|| {
+            // Steal a task from other CPUs before going idle.
+            #[cfg(feature = "smp")]
+            if let Some(task) = steal_task(self.cpu_id, 1) {
+                BALANCE_STATS[self.cpu_id]
+                    .idle_steals
+                    .fetch_add(1, Ordering::Relaxed);
+                return task;
+            }
+            // Safety: IRQs must be disabled at this time.
+            unsafe { IDLE_TASK.current_ref_raw().get_unchecked().clone() }
+        });
         assert!(
             next.is_ready(),
             "next {} is not ready: {:?}",
@@ -521,6 +658,17 @@ impl AxRunQueue {
         self.switch_to(crate::current(), next);
     }
 
+    /// Picks the next task from the scheduler.
+    fn pick_next_task(&mut self) -> Option<AxTaskRef> {
+        let mut scheduler = self.scheduler.lock();
+        let task = scheduler.pick_next_task();
+        #[cfg(feature = "smp")]
+        if task.is_some() {
+            self.nr_ready.fetch_sub(1, Ordering::Relaxed);
+        }
+        task
+    }
+
     fn switch_to(&mut self, prev_task: CurrentTask, next_task: AxTaskRef) {
         // Make sure that IRQs are disabled by kernel guard or other means.
         #[cfg(all(not(test), feature = "irq"))] // Note: irq is faked under unit tests.
@@ -533,9 +681,15 @@ impl AxRunQueue {
             prev_task.id_name(),
             next_task.id_name()
         );
+        charge_cpu_time(prev_task.as_task_ref());
+        #[cfg(feature = "paging")]
+        prev_task.check_stack_overflow();
+        #[cfg(feature = "tickless")]
+        crate::tickless::switch_to(&next_task);
         #[cfg(feature = "preempt")]
         next_task.set_preempt_pending(false);
         next_task.set_state(TaskState::Running);
+        next_task.set_cpu_id(self.cpu_id);
         if prev_task.ptr_eq(&next_task) {
             return;
         }
@@ -603,11 +757,133 @@ fn gc_entry() {
 /// then puts the task to the scheduler of target run queue.
 #[cfg(feature = "smp")]
 pub(crate) fn migrate_entry(migrated_task: AxTaskRef) {
-    select_run_queue::<kernel_guard::NoPreemptIrqSave>(&migrated_task)
-        .inner
-        .scheduler
-        .lock()
-        .put_prev_task(migrated_task, false)
+    let rq = select_run_queue::<kernel_guard::NoPreemptIrqSave>(&migrated_task);
+    migrated_task.set_cpu_id(rq.inner.cpu_id);
+    let mut scheduler = rq.inner.scheduler.lock();
+    scheduler.put_prev_task(migrated_task, false);
+    rq.inner.nr_ready.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Steals the next ready task from the run queue of the CPU with the most
+/// ready tasks, if it has at least `min_ready` of them.
+///
+/// The task is taken only if its CPU affinity allows this CPU, otherwise it's
+/// put back. As schedulers can't be searched, only the next task is tried, and
+/// putting it back moves it to the end of the queue under the FIFO scheduler.
+///
+/// IRQs must be disabled.
+#[cfg(feature = "smp")]
+fn steal_task(cpu_id: usize, min_ready: usize) -> Option<AxTaskRef> {
+    let loads = (0..axconfig::plat::CPU_NUM)
+        .filter(|&cpu| RUN_QUEUE_ONLINE[cpu].load(Ordering::Acquire))
+        .map(|cpu| (cpu, get_run_queue(cpu).nr_ready.load(Ordering::Relaxed)));
+    let busiest = busiest_cpu(cpu_id, min_ready, loads)?;
+
+    let victim = get_run_queue(busiest);
+    let mut scheduler = victim.scheduler.lock();
+    let task = scheduler.pick_next_task()?;
+    // The task may still be being switched out by its CPU after yielding,
+    // see `clear_prev_task_on_cpu()`.
+    if task.cpumask().get(cpu_id) && !task.on_cpu() {
+        victim.nr_ready.fetch_sub(1, Ordering::Relaxed);
+        drop(scheduler);
+        BALANCE_STATS[busiest]
+            .migrated_out
+            .fetch_add(1, Ordering::Relaxed);
+        debug!(
+            "task steal: {} from run_queue {} to {}",
+            task.id_name(),
+            busiest,
+            cpu_id
+        );
+        Some(task)
+    } else {
+        scheduler.put_prev_task(task, true);
+        BALANCE_STATS[cpu_id].failed.fetch_add(1, Ordering::Relaxed);
+        None
+    }
+}
+
+/// Returns the CPU other than `cpu_id` with the most ready tasks, if it has at
+/// least `min_ready` (and at least 1) of them, from the numbers of ready tasks
+/// by CPU in `loads`.
+#[cfg(feature = "smp")]
+pub(crate) fn busiest_cpu(
+    cpu_id: usize,
+    min_ready: usize,
+    loads: impl Iterator<Item = (usize, usize)>,
+) -> Option<usize> {
+    loads
+        .filter(|&(cpu, nr_ready)| cpu != cpu_id && nr_ready >= min_ready.max(1))
+        .max_by_key(|&(_, nr_ready)| nr_ready)
+        .map(|(cpu, _)| cpu)
+}
+
+/// Returns the load balancing statistics of the CPU `cpu_id`.
+#[cfg(feature = "smp")]
+pub(crate) fn balance_stats(cpu_id: usize) -> BalanceStats {
+    let counters = &BALANCE_STATS[cpu_id];
+    let nr_ready = if RUN_QUEUE_ONLINE[cpu_id].load(Ordering::Acquire) {
+        get_run_queue(cpu_id).nr_ready.load(Ordering::Relaxed)
+    } else {
+        0
+    };
+    BalanceStats {
+        nr_ready,
+        idle_steals: counters.idle_steals.load(Ordering::Relaxed),
+        periodic_pulls: counters.periodic_pulls.load(Ordering::Relaxed),
+        migrated_out: counters.migrated_out.load(Ordering::Relaxed),
+        failed: counters.failed.load(Ordering::Relaxed),
+    }
+}
+
+/// Charges the time since the last charge on this CPU to `task`, which must be
+/// the current task, with the time spent in IRQ handlers split out.
+///
+/// IRQs must be disabled.
+pub(crate) fn charge_cpu_time(task: &AxTaskRef) {
+    let now = axhal::time::monotonic_time_nanos();
+    // Safety: IRQs are disabled.
+    let elapsed = now - unsafe { LAST_CHARGE_NANOS.read_current_raw() };
+    unsafe { LAST_CHARGE_NANOS.write_current_raw(now) };
+
+    #[cfg(feature = "irq")]
+    let irq = {
+        let now = axhal::irq::irq_time_nanos();
+        let irq = now - unsafe { LAST_IRQ_NANOS.read_current_raw() };
+        unsafe { LAST_IRQ_NANOS.write_current_raw(now) };
+        irq
+    };
+    #[cfg(not(feature = "irq"))]
+    let irq = 0;
+
+    // The IRQ time is measured independently, so it may slightly exceed the
+    // elapsed time.
+    let run = elapsed.saturating_sub(irq);
+    task.charge_cpu_time(run, irq);
+    if !task.is_idle() {
+        TOTAL_RUN_NANOS.fetch_add(run, Ordering::Relaxed);
+        TOTAL_IRQ_NANOS.fetch_add(irq, Ordering::Relaxed);
+    }
+}
+
+/// Returns the total CPU time charged to all tasks except the idle tasks, see
+/// [`charge_cpu_time`].
+pub(crate) fn total_cpu_time() -> CpuTime {
+    CpuTime {
+        run: Duration::from_nanos(TOTAL_RUN_NANOS.load(Ordering::Relaxed)),
+        irq: Duration::from_nanos(TOTAL_IRQ_NANOS.load(Ordering::Relaxed)),
+    }
+}
+
+/// Starts charging CPU time on this CPU from now on.
+fn init_cpu_time() {
+    // Safety: it's called before the scheduler of this CPU is running.
+    unsafe {
+        LAST_CHARGE_NANOS.write_current_raw(axhal::time::monotonic_time_nanos());
+        #[cfg(feature = "irq")]
+        LAST_IRQ_NANOS.write_current_raw(axhal::irq::irq_time_nanos());
+    }
 }
 
 /// Clear the `on_cpu` field of previous task running on this CPU.
@@ -629,6 +905,7 @@ pub(crate) fn init() {
     let idle_task = TaskInner::new(|| crate::run_idle(), "idle".into(), IDLE_TASK_STACK_SIZE);
     // idle task should be pinned to the current CPU.
     idle_task.set_cpumask(AxCpuMask::one_shot(cpu_id));
+    idle_task.set_cpu_id(cpu_id);
     IDLE_TASK.with_current(|i| {
         i.init_once(idle_task.into_arc());
     });
@@ -636,7 +913,9 @@ pub(crate) fn init() {
     // Put the subsequent execution into the `main` task.
     let main_task = TaskInner::new_init("main".into()).into_arc();
     main_task.set_state(TaskState::Running);
+    main_task.set_cpu_id(cpu_id);
     unsafe { CurrentTask::init_current(main_task) }
+    init_cpu_time();
 
     RUN_QUEUE.with_current(|rq| {
         rq.init_once(AxRunQueue::new(cpu_id));
@@ -644,6 +923,8 @@ pub(crate) fn init() {
     unsafe {
         RUN_QUEUES[cpu_id].write(RUN_QUEUE.current_ref_mut_raw());
     }
+    #[cfg(feature = "smp")]
+    RUN_QUEUE_ONLINE[cpu_id].store(true, Ordering::Release);
 }
 
 pub(crate) fn init_secondary() {
@@ -652,10 +933,12 @@ pub(crate) fn init_secondary() {
     // Put the subsequent execution into the `idle` task.
     let idle_task = TaskInner::new_init("idle".into()).into_arc();
     idle_task.set_state(TaskState::Running);
+    idle_task.set_cpu_id(cpu_id);
     IDLE_TASK.with_current(|i| {
         i.init_once(idle_task.clone());
     });
     unsafe { CurrentTask::init_current(idle_task) }
+    init_cpu_time();
 
     RUN_QUEUE.with_current(|rq| {
         rq.init_once(AxRunQueue::new(cpu_id));
@@ -663,4 +946,6 @@ pub(crate) fn init_secondary() {
     unsafe {
         RUN_QUEUES[cpu_id].write(RUN_QUEUE.current_ref_mut_raw());
     }
+    #[cfg(feature = "smp")]
+    RUN_QUEUE_ONLINE[cpu_id].store(true, Ordering::Release);
 }
@@ -0,0 +1,236 @@
+//! Kernel stacks of tasks.
+
+use memory_addr::VirtAddr;
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "paging")] {
+        use alloc::vec::Vec;
+
+        use axhal::paging::MappingFlags;
+        use axhal::trap::{PAGE_FAULT, PageFaultFlags, register_trap_handler};
+        use kspin::SpinNoIrq;
+        use memory_addr::{MemoryAddr, PAGE_SIZE_4K, VirtAddrRange};
+
+        /// Size of the unmapped guard page below each stack.
+        const GUARD_SIZE: usize = PAGE_SIZE_4K;
+
+        /// Value of the word at the bottom of each stack, which is overwritten
+        /// once the stack is used up.
+        const STACK_CANARY: usize = 0xdead_beef_cafe_f00d;
+
+        /// Size of the per-CPU stack that stack overflows are reported on.
+        const OVERFLOW_STACK_SIZE: usize = 16 * 1024;
+
+        /// Freed stacks, as `(bottom, size)`.
+        ///
+        /// Stacks stay mapped when freed, and are reused by later stacks of the
+        /// same size, since unmapping only flushes the TLB of the current CPU,
+        /// and stale TLB entries on other CPUs would point to the freed pages
+        /// if the addresses were mapped again.
+        static FREE_STACKS: SpinNoIrq<Vec<(VirtAddr, usize)>> = SpinNoIrq::new(Vec::new());
+
+        #[repr(C, align(16))]
+        struct OverflowStack([u8; OVERFLOW_STACK_SIZE]);
+
+        /// The stack to report kernel stack overflows on, as the overflowed
+        /// stack has no space left for the panic.
+        #[percpu::def_percpu]
+        static OVERFLOW_STACK: OverflowStack = OverflowStack([0; OVERFLOW_STACK_SIZE]);
+
+        /// A kernel stack mapped in the kernel address space by [`axmm`], with
+        /// an unmapped guard page below it to catch stack overflows.
+        pub(crate) struct TaskStack {
+            bottom: VirtAddr,
+            size: usize,
+        }
+
+        /// Returns the region of the stacks, i.e., the upper half of the kernel
+        /// address space, which is above the linear mapping of the physical
+        /// memory.
+        fn stack_region(aspace: &axmm::AddrSpace) -> VirtAddrRange {
+            let start = (aspace.base() + aspace.size() / 2).align_up_4k();
+            VirtAddrRange::new(start, aspace.end())
+        }
+
+        impl TaskStack {
+            pub fn alloc(size: usize) -> Self {
+                let bottom = {
+                    let mut free = FREE_STACKS.lock();
+                    let idx = free.iter().position(|&(_, s)| s == size);
+                    idx.map(|idx| free.swap_remove(idx).0)
+                };
+                let bottom = bottom.unwrap_or_else(|| Self::map(size));
+                unsafe { bottom.as_mut_ptr_of::<usize>().write(STACK_CANARY) };
+                Self { bottom, size }
+            }
+
+            /// Maps a new stack of `size` bytes, returns its bottom.
+            fn map(size: usize) -> VirtAddr {
+                let mut aspace = axmm::kernel_aspace().lock();
+                let region = stack_region(&aspace);
+                let start = aspace
+                    .find_free_area(region.start, GUARD_SIZE + size, region)
+                    .expect("no free area for the task stack");
+                let bottom = start + GUARD_SIZE;
+                let flags = MappingFlags::READ | MappingFlags::WRITE;
+                aspace
+                    .map_alloc(bottom, size, flags, true)
+                    .expect("failed to map the task stack");
+                bottom
+            }
+
+            pub const fn top(&self) -> VirtAddr {
+                VirtAddr::from_usize(self.bottom.as_usize() + self.size)
+            }
+
+            /// Whether `vaddr` is in the guard page below the stack.
+            pub fn guard_contains(&self, vaddr: VirtAddr) -> bool {
+                (self.bottom - GUARD_SIZE..self.bottom).contains(&vaddr)
+            }
+
+            /// Whether the stack has overflowed, i.e., the stack pointer `sp` or
+            /// a write below it has reached the bottom of the stack.
+            ///
+            /// It catches overflows that the guard page cannot report, as the
+            /// fault of a stack pointer in the guard page faults again when the
+            /// trap frame is pushed.
+            pub fn overflowed(&self, sp: VirtAddr) -> bool {
+                sp < self.bottom + size_of::<usize>()
+                    || unsafe { self.bottom.as_ptr_of::<usize>().read() } != STACK_CANARY
+            }
+        }
+
+        impl Drop for TaskStack {
+            fn drop(&mut self) {
+                FREE_STACKS.lock().push((self.bottom, self.size));
+            }
+        }
+
+        /// Reports the overflow of the kernel stack of the current task, if the
+        /// fault is in its guard page.
+        ///
+        /// The report is made on the overflow stack of this CPU. The trap frame
+        /// is still saved on the faulting stack by the trap entry of `axcpu`,
+        /// so if the stack pointer itself is in the guard page, saving it
+        /// faults again, and whether the overflow can be reported here depends
+        /// on how the architecture handles the nested fault. Such overflows are
+        /// caught by the stack check when the task is switched out instead, as
+        /// long as the task survives.
+        #[register_trap_handler(PAGE_FAULT)]
+        fn stack_guard_page_fault(vaddr: VirtAddr, access_flags: PageFaultFlags) -> bool {
+            let curr = crate::current_may_uninit();
+            if curr.is_some_and(|curr| curr.in_stack_guard(vaddr)) {
+                let top = unsafe { OVERFLOW_STACK.current_ptr() } as usize + OVERFLOW_STACK_SIZE;
+                let flags = access_flags.bits() as usize;
+                unsafe { call_on_stack(top, report_stack_overflow, vaddr.as_usize(), flags) }
+            }
+            false
+        }
+
+        extern "C" fn report_stack_overflow(vaddr: usize, access_flags: usize) -> ! {
+            let curr = crate::current();
+            panic!(
+                "kernel stack overflow of {}: {:?} access at {:#x}, stack top {:#x}",
+                curr.id_name(),
+                PageFaultFlags::from_bits_retain(access_flags as _),
+                vaddr,
+                curr.kernel_stack_top().unwrap(),
+            );
+        }
+
+        /// Switches to the stack whose top is `top`, and calls `f(a0, a1)` on
+        /// it.
+        ///
+        /// The frame pointer is kept, so backtraces continue to the frames on
+        /// the old stack.
+        unsafe fn call_on_stack(
+            top: usize,
+            f: extern "C" fn(usize, usize) -> !,
+            a0: usize,
+            a1: usize,
+        ) -> ! {
+            cfg_if::cfg_if! {
+                if #[cfg(target_arch = "x86_64")] {
+                    unsafe {
+                        core::arch::asm!(
+                            "mov rsp, {top}",
+                            "call {f}",
+                            top = in(reg) top,
+                            f = in(reg) f,
+                            in("rdi") a0,
+                            in("rsi") a1,
+                            options(noreturn),
+                        )
+                    }
+                } else if #[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))] {
+                    unsafe {
+                        core::arch::asm!(
+                            "mv sp, {top}",
+                            "jalr {f}",
+                            top = in(reg) top,
+                            f = in(reg) f,
+                            in("a0") a0,
+                            in("a1") a1,
+                            options(noreturn),
+                        )
+                    }
+                } else if #[cfg(target_arch = "aarch64")] {
+                    unsafe {
+                        core::arch::asm!(
+                            "mov sp, {top}",
+                            "blr {f}",
+                            top = in(reg) top,
+                            f = in(reg) f,
+                            in("x0") a0,
+                            in("x1") a1,
+                            options(noreturn),
+                        )
+                    }
+                } else if #[cfg(target_arch = "loongarch64")] {
+                    unsafe {
+                        core::arch::asm!(
+                            "move $sp, {top}",
+                            "jirl $ra, {f}, 0",
+                            top = in(reg) top,
+                            f = in(reg) f,
+                            in("$a0") a0,
+                            in("$a1") a1,
+                            options(noreturn),
+                        )
+                    }
+                } else {
+                    let _ = top;
+                    f(a0, a1)
+                }
+            }
+        }
+    } else {
+        use core::{alloc::Layout, ptr::NonNull};
+
+        /// A kernel stack allocated from the global heap.
+        pub(crate) struct TaskStack {
+            ptr: NonNull<u8>,
+            layout: Layout,
+        }
+
+        impl TaskStack {
+            pub fn alloc(size: usize) -> Self {
+                let layout = Layout::from_size_align(size, 16).unwrap();
+                Self {
+                    ptr: NonNull::new(unsafe { alloc::alloc::alloc(layout) }).unwrap(),
+                    layout,
+                }
+            }
+
+            pub const fn top(&self) -> VirtAddr {
+                unsafe { core::mem::transmute(self.ptr.as_ptr().add(self.layout.size())) }
+            }
+        }
+
+        impl Drop for TaskStack {
+            fn drop(&mut self) {
+                unsafe { alloc::alloc::dealloc(self.ptr.as_ptr(), self.layout) }
+            }
+        }
+    }
+}
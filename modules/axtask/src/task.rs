@@ -1,10 +1,12 @@
-use alloc::{boxed::Box, string::String, sync::Arc};
+use alloc::collections::BTreeMap;
+use alloc::sync::{Arc, Weak};
+use alloc::{boxed::Box, string::String, vec::Vec};
 use core::ops::Deref;
-use core::sync::atomic::{AtomicBool, AtomicI32, AtomicU8, AtomicU64, Ordering};
-use core::{alloc::Layout, cell::UnsafeCell, fmt, ptr::NonNull};
-
-#[cfg(feature = "preempt")]
-use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::{
+    AtomicBool, AtomicI32, AtomicIsize, AtomicU8, AtomicU64, AtomicUsize, Ordering,
+};
+use core::time::Duration;
+use core::{cell::UnsafeCell, fmt};
 
 use kspin::SpinNoIrq;
 use memory_addr::{VirtAddr, align_up_4k};
@@ -13,6 +15,7 @@ use axhal::context::TaskContext;
 #[cfg(feature = "tls")]
 use axhal::tls::TlsArea;
 
+use crate::stack::TaskStack;
 use crate::task_ext::AxTaskExt;
 use crate::{AxCpuMask, AxTask, AxTaskRef, WaitQueue};
 
@@ -23,7 +26,7 @@ pub struct TaskId(u64);
 /// The possible states of a task.
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
-pub(crate) enum TaskState {
+pub enum TaskState {
     /// Task is running on some CPU.
     Running = 1,
     /// Task is ready to run on some scheduler's ready queue.
@@ -35,6 +38,27 @@ pub(crate) enum TaskState {
     Exited = 4,
 }
 
+/// CPU time used by a task, see [`TaskInner::cpu_time`].
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct CpuTime {
+    /// Time spent running the task itself.
+    pub run: Duration,
+    /// Time spent in IRQ handlers while the task was running, which is split
+    /// out of [`run`](Self::run).
+    pub irq: Duration,
+}
+
+impl CpuTime {
+    /// Returns the total CPU time, including the time in IRQ handlers.
+    pub fn total(&self) -> Duration {
+        self.run + self.irq
+    }
+}
+
+/// All tasks wrapped by [`TaskInner::into_arc`] and not dropped yet, by their
+/// IDs.
+static TASKS: SpinNoIrq<BTreeMap<u64, Weak<AxTask>>> = SpinNoIrq::new(BTreeMap::new());
+
 /// The inner task structure.
 pub struct TaskInner {
     id: TaskId,
@@ -47,6 +71,14 @@ pub struct TaskInner {
 
     /// CPU affinity mask.
     cpumask: SpinNoIrq<AxCpuMask>,
+    /// ID of the CPU whose run queue the task is on, or it last ran on.
+    cpu_id: AtomicUsize,
+    /// Priority set in the scheduler, see [`crate::set_priority`].
+    priority: AtomicIsize,
+    /// Time spent running the task, in nanoseconds.
+    run_time_nanos: AtomicU64,
+    /// Time spent in IRQ handlers while the task was running, in nanoseconds.
+    irq_time_nanos: AtomicU64,
 
     /// Mark whether the task is in the wait queue.
     in_wait_queue: AtomicBool,
@@ -193,6 +225,32 @@ impl TaskInner {
         }
     }
 
+    /// Returns whether `vaddr` is in the guard page below the kernel stack.
+    #[cfg(feature = "paging")]
+    pub(crate) fn in_stack_guard(&self, vaddr: VirtAddr) -> bool {
+        self.kstack
+            .as_ref()
+            .is_some_and(|s| s.guard_contains(vaddr))
+    }
+
+    /// Panics if the kernel stack of the task has overflowed.
+    ///
+    /// It must be called on the kernel stack of the task, e.g., when it is
+    /// switched out.
+    #[cfg(feature = "paging")]
+    pub(crate) fn check_stack_overflow(&self) {
+        // the address of a local variable is close enough to the stack pointer
+        let sp = VirtAddr::from(&self as *const _ as usize);
+        if self.kstack.as_ref().is_some_and(|s| s.overflowed(sp)) {
+            panic!(
+                "kernel stack overflow of {}: stack pointer {:#x}, stack top {:#x}",
+                self.id_name(),
+                sp,
+                self.kernel_stack_top().unwrap(),
+            );
+        }
+    }
+
     /// Gets the cpu affinity mask of the task.
     ///
     /// Returns the cpu affinity mask of the task in type [`AxCpuMask`].
@@ -209,6 +267,39 @@ impl TaskInner {
     pub fn set_cpumask(&self, cpumask: AxCpuMask) {
         *self.cpumask.lock() = cpumask
     }
+
+    /// Returns the ID of the CPU whose run queue the task is on. For blocked
+    /// or exited tasks, it's the CPU the task last ran on.
+    #[inline]
+    pub fn cpu_id(&self) -> usize {
+        self.cpu_id.load(Ordering::Acquire)
+    }
+
+    /// Returns the priority of the task in the scheduler, whose meaning
+    /// depends on the scheduler, see [`crate::set_priority`].
+    #[inline]
+    pub fn priority(&self) -> isize {
+        self.priority.load(Ordering::Acquire)
+    }
+
+    /// Returns whether the task is blocked in a [`WaitQueue`], rather than
+    /// sleeping until a deadline.
+    #[inline]
+    pub fn is_waiting(&self) -> bool {
+        self.state() == TaskState::Blocked && self.in_wait_queue()
+    }
+
+    /// Returns the CPU time used by the task.
+    ///
+    /// The time is charged at context switches, so it does not include the
+    /// current time slice if the task is running. Use
+    /// [`current_cpu_time`](crate::current_cpu_time) for the current task.
+    pub fn cpu_time(&self) -> CpuTime {
+        CpuTime {
+            run: Duration::from_nanos(self.run_time_nanos.load(Ordering::Relaxed)),
+            irq: Duration::from_nanos(self.irq_time_nanos.load(Ordering::Relaxed)),
+        }
+    }
 }
 
 // private methods
@@ -223,6 +314,10 @@ impl TaskInner {
             state: AtomicU8::new(TaskState::Ready as u8),
             // By default, the task is allowed to run on all CPUs.
             cpumask: SpinNoIrq::new(AxCpuMask::full()),
+            cpu_id: AtomicUsize::new(0),
+            priority: AtomicIsize::new(0),
+            run_time_nanos: AtomicU64::new(0),
+            irq_time_nanos: AtomicU64::new(0),
             in_wait_queue: AtomicBool::new(false),
             #[cfg(feature = "irq")]
             timer_ticket_id: AtomicU64::new(0),
@@ -262,14 +357,29 @@ impl TaskInner {
     }
 
     pub(crate) fn into_arc(self) -> AxTaskRef {
-        Arc::new(AxTask::new(self))
+        let task = Arc::new(AxTask::new(self));
+        TASKS
+            .lock()
+            .insert(task.id().as_u64(), Arc::downgrade(&task));
+        task
     }
 
+    /// Returns the current state of the task.
     #[inline]
-    pub(crate) fn state(&self) -> TaskState {
+    pub fn state(&self) -> TaskState {
         self.state.load(Ordering::Acquire).into()
     }
 
+    #[inline]
+    pub(crate) fn set_cpu_id(&self, cpu_id: usize) {
+        self.cpu_id.store(cpu_id, Ordering::Release)
+    }
+
+    #[inline]
+    pub(crate) fn set_priority(&self, prio: isize) {
+        self.priority.store(prio, Ordering::Release)
+    }
+
     #[inline]
     pub(crate) fn set_state(&self, state: TaskState) {
         self.state.store(state as u8, Ordering::Release)
@@ -305,11 +415,18 @@ impl TaskInner {
         self.is_init
     }
 
+    /// Returns whether the task is the idle task of a CPU.
     #[inline]
-    pub(crate) const fn is_idle(&self) -> bool {
+    pub const fn is_idle(&self) -> bool {
         self.is_idle
     }
 
+    #[inline]
+    pub(crate) fn charge_cpu_time(&self, run_nanos: u64, irq_nanos: u64) {
+        self.run_time_nanos.fetch_add(run_nanos, Ordering::Relaxed);
+        self.irq_time_nanos.fetch_add(irq_nanos, Ordering::Relaxed);
+    }
+
     #[inline]
     pub(crate) fn in_wait_queue(&self) -> bool {
         self.in_wait_queue.load(Ordering::Acquire)
@@ -431,32 +548,13 @@ impl fmt::Debug for TaskInner {
 impl Drop for TaskInner {
     fn drop(&mut self) {
         debug!("task drop: {}", self.id_name());
+        TASKS.lock().remove(&self.id.as_u64());
     }
 }
 
-struct TaskStack {
-    ptr: NonNull<u8>,
-    layout: Layout,
-}
-
-impl TaskStack {
-    pub fn alloc(size: usize) -> Self {
-        let layout = Layout::from_size_align(size, 16).unwrap();
-        Self {
-            ptr: NonNull::new(unsafe { alloc::alloc::alloc(layout) }).unwrap(),
-            layout,
-        }
-    }
-
-    pub const fn top(&self) -> VirtAddr {
-        unsafe { core::mem::transmute(self.ptr.as_ptr().add(self.layout.size())) }
-    }
-}
-
-impl Drop for TaskStack {
-    fn drop(&mut self) {
-        unsafe { alloc::alloc::dealloc(self.ptr.as_ptr(), self.layout) }
-    }
+/// Returns references to all live tasks, in the order of their IDs.
+pub(crate) fn all_tasks() -> Vec<AxTaskRef> {
+    TASKS.lock().values().filter_map(Weak::upgrade).collect()
 }
 
 use core::mem::ManuallyDrop;
@@ -128,3 +128,138 @@ fn test_task_join() {
         assert_eq!(tasks[i].join(), Some(i as _));
     }
 }
+
+#[test]
+fn test_for_each_task() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    let task = axtask::spawn_raw(|| axtask::exit(0), "listed".into(), 0x1000);
+    let id = task.id();
+
+    let mut found = false;
+    axtask::for_each_task(|t| found |= t.id() == id && t.name() == "listed");
+    assert!(found);
+
+    assert_eq!(task.join(), Some(0));
+    assert_eq!(task.state(), axtask::TaskState::Exited);
+    drop(task);
+
+    // the task may still be referenced by the run queue until it is recycled
+    while {
+        let mut found = false;
+        axtask::for_each_task(|t| found |= t.id() == id);
+        found
+    } {
+        axtask::yield_now();
+    }
+}
+
+#[test]
+fn test_task_info() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    static WQ: WaitQueue = WaitQueue::new();
+    let task = axtask::spawn_raw(|| WQ.wait(), "waiter".into(), 0x1000);
+    while !task.is_waiting() {
+        axtask::yield_now();
+    }
+    assert_eq!(task.state(), axtask::TaskState::Blocked);
+    assert_eq!(task.cpu_id(), 0);
+    assert_eq!(task.priority(), axtask::default_priority());
+    assert!(!task.is_idle());
+
+    WQ.notify_one(true);
+    assert_eq!(task.join(), Some(0));
+    assert!(!task.is_waiting());
+}
+
+#[cfg(feature = "smp")]
+#[test]
+fn test_busiest_cpu() {
+    use crate::run_queue::busiest_cpu;
+
+    let loads = [(0, 5), (1, 3), (2, 7), (3, 0)];
+    // the busiest CPU other than itself
+    assert_eq!(busiest_cpu(0, 1, loads.into_iter()), Some(2));
+    assert_eq!(busiest_cpu(2, 1, loads.into_iter()), Some(0));
+    // periodic pulls need at least 2 more ready tasks than the puller
+    assert_eq!(busiest_cpu(1, 3 + 2, loads.into_iter()), Some(2));
+    assert_eq!(busiest_cpu(2, 7 + 2, loads.into_iter()), None);
+    assert_eq!(
+        busiest_cpu(0, usize::MAX.saturating_add(2), loads.into_iter()),
+        None
+    );
+    // a CPU without ready tasks has nothing to steal
+    assert_eq!(busiest_cpu(0, 0, [(0, 1), (3, 0)].into_iter()), None);
+}
+
+#[cfg(feature = "smp")]
+#[test]
+fn test_balance_stats() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    const NUM_TASKS: usize = 4;
+    let before = axtask::balance_stats(0);
+    let tasks = (0..NUM_TASKS)
+        .map(|i| axtask::spawn_raw(axtask::yield_now, format!("B{}", i), 0x1000))
+        .collect::<Vec<_>>();
+    // the tasks are ready until the main task yields
+    let stats = axtask::balance_stats(0);
+    assert_eq!(stats.nr_ready, before.nr_ready + NUM_TASKS);
+
+    for task in tasks {
+        assert_eq!(task.join(), Some(0));
+    }
+    // nothing is migrated with only one CPU, and the count does not wrap
+    let after = axtask::balance_stats(0);
+    assert!(after.nr_ready <= before.nr_ready + NUM_TASKS);
+    assert_eq!(after.idle_steals, before.idle_steals);
+    assert_eq!(after.periodic_pulls, before.periodic_pulls);
+    assert_eq!(after.migrated_out, before.migrated_out);
+    assert_eq!(after.failed, before.failed);
+}
+
+#[cfg(all(feature = "tickless", not(feature = "preempt")))]
+#[test]
+fn test_tickless_reprogram() {
+    use crate::{task::TaskInner, tickless, timers};
+    use axhal::time::NANOS_PER_SEC;
+    use core::time::Duration;
+
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    // the clock does not advance on the host, so the events never expire
+    let task = TaskInner::new(|| {}, "alarm".into(), 0x1000).into_arc();
+    let _guard = kernel_guard::IrqSave::new();
+    // tasks without preemption run without the tick
+    tickless::switch_to(&task);
+    assert!(tickless::tick_stopped());
+    tickless::reprogram(true);
+    assert_eq!(timers::next_deadline(), None);
+    assert_eq!(tickless::timer_deadline(), u64::MAX);
+
+    // only an earlier event reprograms the timer
+    timers::set_alarm_wakeup(Duration::from_secs(3), task.clone());
+    assert_eq!(timers::next_deadline(), Some(Duration::from_secs(3)));
+    assert_eq!(tickless::timer_deadline(), 3 * NANOS_PER_SEC);
+    timers::set_alarm_wakeup(Duration::from_secs(5), task.clone());
+    assert_eq!(timers::next_deadline(), Some(Duration::from_secs(3)));
+    assert_eq!(tickless::timer_deadline(), 3 * NANOS_PER_SEC);
+    timers::set_alarm_wakeup(Duration::from_secs(1), task.clone());
+    assert_eq!(timers::next_deadline(), Some(Duration::from_secs(1)));
+    assert_eq!(tickless::timer_deadline(), NANOS_PER_SEC);
+
+    // a fired timer consumes the pending interrupt, and the next event is
+    // programmed again
+    tickless::reprogram(true);
+    assert_eq!(tickless::timer_deadline(), NANOS_PER_SEC);
+
+    timers::cancel_events(&task);
+    assert_eq!(timers::next_deadline(), None);
+    tickless::reset();
+    assert!(!tickless::tick_stopped());
+}
@@ -0,0 +1,112 @@
+//! Tickless mode, which stops the periodic timer tick of a CPU when it's not
+//! needed.
+//!
+//! While the tick is stopped, the timer is programmed to the deadline of the
+//! earliest timer event of the CPU instead, so an idle CPU sleeps until the
+//! next task wakes up.
+//!
+//! Under SMP, idle CPUs never stop the tick, since `axhal` can't send IPIs to
+//! notify them of new tasks. Only non-preemptive tasks run without the tick
+//! there.
+
+use axhal::time::{NANOS_PER_SEC, epochoffset_nanos, monotonic_time_nanos, set_oneshot_timer};
+use kernel_guard::IrqSave;
+
+use crate::AxTaskRef;
+
+/// Period of the timer tick, in nanoseconds.
+const TICK_NANOS: u64 = NANOS_PER_SEC / axconfig::TICKS_PER_SEC as u64;
+
+percpu_static! {
+    /// Whether the periodic tick is stopped on this CPU.
+    TICK_STOPPED: bool = false,
+    /// Monotonic deadline of the pending timer interrupt while the tick is
+    /// stopped, in nanoseconds, or `u64::MAX` if there is none.
+    TIMER_DEADLINE: u64 = u64::MAX,
+}
+
+/// Whether the tick is needed while `task` is running.
+///
+/// Other tasks need it only for preemption. The idle task needs it under SMP,
+/// as there are no IPIs to notify it of tasks queued by other CPUs, or tasks
+/// that can be stolen from them.
+fn need_tick(task: &AxTaskRef) -> bool {
+    if task.is_idle() {
+        cfg!(feature = "smp")
+    } else {
+        cfg!(feature = "preempt")
+    }
+}
+
+/// Returns whether the tick is stopped on the current CPU.
+pub(crate) fn tick_stopped() -> bool {
+    TICK_STOPPED.read_current()
+}
+
+/// Returns the deadline of the pending timer interrupt of the current CPU
+/// while the tick is stopped.
+#[cfg(test)]
+pub(crate) fn timer_deadline() -> u64 {
+    TIMER_DEADLINE.read_current()
+}
+
+/// Restarts the tick of the current CPU, and forgets the pending timer
+/// interrupt.
+#[cfg(test)]
+pub(crate) fn reset() {
+    let _guard = IrqSave::new();
+    unsafe {
+        TICK_STOPPED.write_current_raw(false);
+        TIMER_DEADLINE.write_current_raw(u64::MAX);
+    }
+}
+
+/// Stops or restarts the tick of the current CPU before switching to `next`.
+///
+/// IRQs must be disabled.
+pub(crate) fn switch_to(next: &AxTaskRef) {
+    // Safety: IRQs are disabled.
+    let stopped = unsafe { TICK_STOPPED.read_current_raw() };
+    let need = need_tick(next);
+    if need && stopped {
+        // Restart the tick, but keep an earlier timer event.
+        let pending = unsafe { TIMER_DEADLINE.read_current_raw() };
+        let deadline = (monotonic_time_nanos() + TICK_NANOS).min(pending);
+        unsafe { TICK_STOPPED.write_current_raw(false) };
+        set_oneshot_timer(deadline);
+        trace!("tick restarted for {}", next.id_name());
+    } else if !need && !stopped {
+        // The last tick is still pending, which fires within a period.
+        unsafe {
+            TICK_STOPPED.write_current_raw(true);
+            TIMER_DEADLINE.write_current_raw(monotonic_time_nanos() + TICK_NANOS);
+        }
+        trace!("tick stopped for {}", next.id_name());
+    }
+}
+
+/// Programs the timer to the earliest timer event of the current CPU if the
+/// tick is stopped, and it's earlier than the pending timer interrupt.
+///
+/// It's called after timer events are added, or checked on a timer interrupt
+/// (`timer_fired`), which consumes the pending one.
+pub(crate) fn reprogram(timer_fired: bool) {
+    let _guard = IrqSave::new();
+    // Safety: IRQs are disabled.
+    if !unsafe { TICK_STOPPED.read_current_raw() } {
+        return;
+    }
+    let pending = if timer_fired {
+        u64::MAX
+    } else {
+        unsafe { TIMER_DEADLINE.read_current_raw() }
+    };
+    // Timer events are in wall time.
+    let next = crate::timers::next_deadline()
+        .map(|deadline| (deadline.as_nanos() as u64).saturating_sub(epochoffset_nanos()))
+        .filter(|&deadline| deadline < pending);
+    if let Some(deadline) = next {
+        set_oneshot_timer(deadline);
+    }
+    unsafe { TIMER_DEADLINE.write_current_raw(next.unwrap_or(pending)) };
+}
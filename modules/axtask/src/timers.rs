@@ -40,7 +40,25 @@ pub fn set_alarm_wakeup(deadline: TimeValue, task: AxTaskRef) {
         let ticket_id = TIMER_TICKET_ID.fetch_add(1, Ordering::AcqRel);
         task.set_timer_ticket(ticket_id);
         timer_list.set(deadline, TaskWakeupEvent { ticket_id, task });
-    })
+    });
+    #[cfg(feature = "tickless")]
+    crate::tickless::reprogram(false);
+}
+
+/// Returns the deadline of the earliest timer event of the current CPU.
+///
+/// IRQs must be disabled.
+#[cfg(feature = "tickless")]
+pub fn next_deadline() -> Option<TimeValue> {
+    unsafe { TIMER_LIST.current_ref_raw() }.next_deadline()
+}
+
+/// Cancels the timer events of `task` on the current CPU.
+#[cfg(test)]
+pub fn cancel_events(task: &AxTaskRef) {
+    TIMER_LIST.with_current(|timer_list| {
+        timer_list.cancel(|event| alloc::sync::Arc::ptr_eq(&event.task, task));
+    });
 }
 
 pub fn check_events() {
@@ -17,7 +17,7 @@ build_args := \
   $(build_args-$(MODE)) \
   $(verbose)
 
-RUSTFLAGS := -A unsafe_op_in_unsafe_fn
+RUSTFLAGS := -A unsafe_op_in_unsafe_fn -C force-frame-pointers=yes
 RUSTFLAGS_LINK_ARGS := -C link-arg=-T$(LD_SCRIPT) -C link-arg=-no-pie -C link-arg=-znostart-stop-gc
 RUSTDOCFLAGS := -Z unstable-options --enable-index-page -D rustdoc::broken_intra_doc_links
 
@@ -51,5 +51,7 @@ endef
 define unit_test
   $(call run_cmd,cargo test,-p axfs $(1) $(verbose) -- --nocapture)
   $(call run_cmd,cargo test,-p axfs $(1) --features "myfs" $(verbose) -- --nocapture)
-  $(call run_cmd,cargo test,--workspace --exclude axfs $(1) $(verbose) -- --nocapture)
+  $(call run_cmd,cargo test,-p axfs $(1) --features "ext4" $(verbose) -- --nocapture)
+  $(call run_cmd,cargo test,-p axnet $(1) --features "dhcp" $(verbose) -- --nocapture)
+  $(call run_cmd,cargo test,--workspace --exclude axfs --exclude axnet $(1) $(verbose) -- --nocapture)
 endef
@@ -58,6 +58,10 @@ ifeq ($(shell test $(SMP) -gt 1; echo $$?),0)
   lib_feat += smp
 endif
 
+ifneq ($(INITRAMFS)$(INITRD),)
+  ax_feat += initramfs
+endif
+
 ax_feat += $(filter-out $(lib_features),$(FEATURES))
 lib_feat += $(filter $(lib_features),$(FEATURES))
 
@@ -65,6 +65,13 @@ else
   $(error "NET_DEV" must be one of "user", "tap", or "bridge")
 endif
 
+ifneq ($(INITRD),)
+  ifeq ($(ARCH), x86_64)
+    $(warning "INITRD" is ignored on x86_64, use "INITRAMFS" to embed the archive instead)
+  endif
+  qemu_args-y += -initrd $(INITRD)
+endif
+
 ifneq ($(VFIO_PCI),)
   qemu_args-y += --device vfio-pci,host=$(VFIO_PCI)
   QEMU := sudo $(QEMU)
@@ -54,41 +54,6 @@ int usleep(unsigned useconds)
 
 #ifdef AX_CONFIG_FS
 
-// TODO:
-int access(const char *pathname, int mode)
-{
-    unimplemented();
-    return 0;
-}
-
-// TODO:
-ssize_t readlink(const char *path, char *buf, size_t bufsiz)
-{
-    unimplemented();
-    return 0;
-}
-
-// TODO:
-int unlink(const char *pathname)
-{
-    unimplemented();
-    return 0;
-}
-
-// TODO:
-int rmdir(const char *pathname)
-{
-    unimplemented();
-    return 0;
-}
-
-// TODO:
-int fsync(int fd)
-{
-    unimplemented();
-    return 0;
-}
-
 // TODO
 int fdatasync(int __fildes)
 {
@@ -103,20 +68,6 @@ int fchown(int fd, uid_t owner, gid_t group)
     return 0;
 }
 
-// TODO:
-int ftruncate(int fd, off_t length)
-{
-    unimplemented();
-    return 0;
-}
-
-// TODO
-int chdir(const char *__path)
-{
-    unimplemented();
-    return 0;
-}
-
 // TODO
 int truncate(const char *path, off_t length)
 {
@@ -43,6 +43,11 @@ struct cmsghdr {
     int cmsg_type;
 };
 
+struct linger {
+    int l_onoff;
+    int l_linger;
+};
+
 struct sockaddr {
     sa_family_t sa_family;
     char sa_data[14];
@@ -55,6 +60,7 @@ struct sockaddr_storage {
 };
 
 int socket(int, int, int);
+int socketpair(int, int, int, int[2]);
 int shutdown(int, int);
 
 int bind(int, const struct sockaddr *, socklen_t);
@@ -69,6 +75,7 @@ ssize_t sendto(int, const void *, size_t, int, const struct sockaddr *, socklen_
 ssize_t recvfrom(int, void *__restrict, size_t, int, struct sockaddr *__restrict,
                  socklen_t *__restrict);
 ssize_t sendmsg(int, const struct msghdr *, int);
+ssize_t recvmsg(int, struct msghdr *, int);
 
 int getsockopt(int, int, int, void *__restrict, socklen_t *__restrict);
 int setsockopt(int, int, int, const void *, socklen_t);
@@ -115,7 +122,40 @@ int getpeername(int sockfd, struct sockaddr *restrict addr, socklen_t *restrict
 #define SO_PREFER_BUSY_POLL        69
 #define SO_BUSY_POLL_BUDGET        70
 
-#define MSG_NOSIGNAL 0x4000
+#define MSG_OOB          0x0001
+#define MSG_PEEK         0x0002
+#define MSG_DONTROUTE    0x0004
+#define MSG_CTRUNC       0x0008
+#define MSG_TRUNC        0x0020
+#define MSG_DONTWAIT     0x0040
+#define MSG_EOR          0x0080
+#define MSG_WAITALL      0x0100
+#define MSG_NOSIGNAL     0x4000
+#define MSG_MORE         0x8000
+#define MSG_CMSG_CLOEXEC 0x40000000
+
+#define __CMSG_LEN(cmsg)  (((cmsg)->cmsg_len + sizeof(long) - 1) & ~(long)(sizeof(long) - 1))
+#define __CMSG_NEXT(cmsg) ((unsigned char *)(cmsg) + __CMSG_LEN(cmsg))
+#define __MHDR_END(mhdr)  ((unsigned char *)(mhdr)->msg_control + (mhdr)->msg_controllen)
+
+#define CMSG_DATA(cmsg) ((unsigned char *)(((struct cmsghdr *)(cmsg)) + 1))
+#define CMSG_NXTHDR(mhdr, cmsg)                                                  \
+    ((cmsg)->cmsg_len < sizeof(struct cmsghdr) ||                                \
+             __CMSG_LEN(cmsg) + sizeof(struct cmsghdr) >=                        \
+                 __MHDR_END(mhdr) - (unsigned char *)(cmsg)                      \
+         ? 0                                                                     \
+         : (struct cmsghdr *)__CMSG_NEXT(cmsg))
+#define CMSG_FIRSTHDR(mhdr)                                                      \
+    ((size_t)(mhdr)->msg_controllen >= sizeof(struct cmsghdr)                    \
+         ? (struct cmsghdr *)(mhdr)->msg_control                                 \
+         : (struct cmsghdr *)0)
+
+#define CMSG_ALIGN(len) (((len) + sizeof(size_t) - 1) & (size_t) ~(sizeof(size_t) - 1))
+#define CMSG_SPACE(len) (CMSG_ALIGN(len) + CMSG_ALIGN(sizeof(struct cmsghdr)))
+#define CMSG_LEN(len)   (CMSG_ALIGN(sizeof(struct cmsghdr)) + (len))
+
+#define SCM_RIGHTS      0x01
+#define SCM_CREDENTIALS 0x02
 
 #define SHUT_RD   0
 #define SHUT_WR   1
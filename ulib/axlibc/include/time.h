@@ -4,9 +4,11 @@
 #include <stddef.h>
 #include <sys/time.h>
 
-#define CLOCK_REALTIME  0
-#define CLOCK_MONOTONIC 1
-#define CLOCKS_PER_SEC  1000000L
+#define CLOCK_REALTIME           0
+#define CLOCK_MONOTONIC          1
+#define CLOCK_PROCESS_CPUTIME_ID 2
+#define CLOCK_THREAD_CPUTIME_ID  3
+#define CLOCKS_PER_SEC           1000000L
 
 struct tm {
     int tm_sec;   /* seconds of minute */
@@ -1,7 +1,9 @@
-use core::ffi::{c_char, c_int};
+use core::ffi::{c_char, c_int, c_ulong, c_void};
 
 use arceos_posix_api::{
-    sys_fstat, sys_getcwd, sys_lseek, sys_lstat, sys_open, sys_rename, sys_stat,
+    sys_access, sys_chdir, sys_fchdir, sys_fstat, sys_fsync, sys_ftruncate, sys_getcwd,
+    sys_getdents64, sys_link, sys_lseek, sys_lstat, sys_mkdir, sys_mount, sys_open, sys_readlink,
+    sys_rename, sys_rmdir, sys_stat, sys_symlink, sys_umount2, sys_unlink,
 };
 
 use crate::{ctypes, utils::e};
@@ -57,6 +59,71 @@ pub unsafe extern "C" fn getcwd(buf: *mut c_char, size: usize) -> *mut c_char {
     sys_getcwd(buf, size)
 }
 
+/// Change the current directory to `path`.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chdir(path: *const c_char) -> c_int {
+    e(sys_chdir(path))
+}
+
+/// Change the current directory to the directory `fd`.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fchdir(fd: c_int) -> c_int {
+    e(sys_fchdir(fd))
+}
+
+/// Read directory entries of the directory `fd` into `dirp`.
+///
+/// Return the number of bytes read, 0 at the end of the directory, otherwise
+/// return -1.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn getdents64(fd: c_int, dirp: *mut c_void, count: usize) -> ctypes::ssize_t {
+    e(sys_getdents64(fd, dirp, count) as _) as _
+}
+
+/// Create a directory `pathname`.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mkdir(pathname: *const c_char, mode: ctypes::mode_t) -> c_int {
+    e(sys_mkdir(pathname, mode))
+}
+
+/// Remove the empty directory `pathname`.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rmdir(pathname: *const c_char) -> c_int {
+    e(sys_rmdir(pathname))
+}
+
+/// Truncate the file `fd` to `length` bytes.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ftruncate(fd: c_int, length: ctypes::off_t) -> c_int {
+    e(sys_ftruncate(fd, length))
+}
+
+/// Write the cached data of the file `fd` to the storage device.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fsync(fd: c_int) -> c_int {
+    e(sys_fsync(fd))
+}
+
+/// Check whether the file `pathname` can be accessed with `mode`.
+///
+/// Return 0 if the access is allowed, otherwise return -1.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn access(pathname: *const c_char, mode: c_int) -> c_int {
+    e(sys_access(pathname, mode))
+}
+
 /// Rename `old` to `new`
 /// If new exists, it is first removed.
 ///
@@ -65,3 +132,70 @@ pub unsafe extern "C" fn getcwd(buf: *mut c_char, size: usize) -> *mut c_char {
 pub unsafe extern "C" fn rename(old: *const c_char, new: *const c_char) -> c_int {
     e(sys_rename(old, new))
 }
+
+/// Create a symbolic link `linkpath` which contains the string `target`.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn symlink(target: *const c_char, linkpath: *const c_char) -> c_int {
+    e(sys_symlink(target, linkpath))
+}
+
+/// Read the target of the symbolic link `path` into `buf`.
+///
+/// Return the number of bytes placed in `buf`, otherwise return -1.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn readlink(
+    path: *const c_char,
+    buf: *mut c_char,
+    bufsize: usize,
+) -> ctypes::ssize_t {
+    e(sys_readlink(path, buf, bufsize) as _) as _
+}
+
+/// Create a new hard link `new` to the existing file `old`.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn link(old: *const c_char, new: *const c_char) -> c_int {
+    e(sys_link(old, new))
+}
+
+/// Remove the name `pathname` from the filesystem.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn unlink(pathname: *const c_char) -> c_int {
+    e(sys_unlink(pathname))
+}
+
+/// Mount the filesystem of type `fstype` from `source` on the directory
+/// `target`.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mount(
+    source: *const c_char,
+    target: *const c_char,
+    fstype: *const c_char,
+    flags: c_ulong,
+    data: *const c_void,
+) -> c_int {
+    e(sys_mount(source, target, fstype, flags, data))
+}
+
+/// Unmount the filesystem mounted on `target`.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn umount(target: *const c_char) -> c_int {
+    e(sys_umount2(target, 0))
+}
+
+/// Unmount the filesystem mounted on `target`, with `flags`.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn umount2(target: *const c_char, flags: c_int) -> c_int {
+    e(sys_umount2(target, flags))
+}
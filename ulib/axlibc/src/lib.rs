@@ -84,10 +84,10 @@ pub use self::io::{read, writev};
 pub use self::errno::strerror;
 pub use self::mktime::mktime;
 pub use self::rand::{rand, random, srand};
-pub use self::resource::{getrlimit, setrlimit};
+pub use self::resource::{getrlimit, getrusage, setrlimit};
 pub use self::setjmp::{longjmp, setjmp};
 pub use self::sys::sysconf;
-pub use self::time::{clock_gettime, nanosleep};
+pub use self::time::{clock_gettime, nanosleep, times};
 pub use self::unistd::{abort, exit, getpid};
 
 #[cfg(feature = "alloc")]
@@ -99,12 +99,15 @@ pub use self::strftime::strftime;
 pub use self::fd_ops::{ax_fcntl, close, dup, dup2, dup3};
 
 #[cfg(feature = "fs")]
-pub use self::fs::{ax_open, fstat, getcwd, lseek, lstat, rename, stat};
+pub use self::fs::{
+    access, ax_open, chdir, fchdir, fstat, fsync, ftruncate, getcwd, getdents64, link, lseek,
+    lstat, mkdir, mount, readlink, rename, rmdir, stat, symlink, umount, umount2, unlink,
+};
 
 #[cfg(feature = "net")]
 pub use self::net::{
-    accept, bind, connect, freeaddrinfo, getaddrinfo, getpeername, getsockname, listen, recv,
-    recvfrom, send, sendto, shutdown, socket,
+    accept, bind, connect, freeaddrinfo, getaddrinfo, getpeername, getsockname, getsockopt, listen,
+    recv, recvfrom, recvmsg, send, sendmsg, sendto, setsockopt, shutdown, socket, socketpair,
 };
 
 #[cfg(feature = "multitask")]
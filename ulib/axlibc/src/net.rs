@@ -1,7 +1,7 @@
 use arceos_posix_api::{
     sys_accept, sys_bind, sys_connect, sys_freeaddrinfo, sys_getaddrinfo, sys_getpeername,
-    sys_getsockname, sys_listen, sys_recv, sys_recvfrom, sys_send, sys_sendto, sys_shutdown,
-    sys_socket,
+    sys_getsockname, sys_getsockopt, sys_listen, sys_recv, sys_recvfrom, sys_recvmsg, sys_send,
+    sys_sendmsg, sys_sendto, sys_setsockopt, sys_shutdown, sys_socket, sys_socketpair,
 };
 use core::ffi::{c_char, c_int, c_void};
 
@@ -15,6 +15,20 @@ pub unsafe extern "C" fn socket(domain: c_int, socktype: c_int, protocol: c_int)
     e(sys_socket(domain, socktype, protocol))
 }
 
+/// Create a pair of connected sockets.
+///
+/// Return 0 if success.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn socketpair(
+    domain: c_int,
+    socktype: c_int,
+    protocol: c_int,
+    sv: *mut c_int,
+) -> c_int {
+    let sv = unsafe { core::slice::from_raw_parts_mut(sv, 2) };
+    e(sys_socketpair(domain, socktype, protocol, sv))
+}
+
 /// Bind a address to a socket.
 ///
 /// Return 0 if success.
@@ -47,7 +61,7 @@ pub unsafe extern "C" fn sendto(
     socket_fd: c_int,
     buf_ptr: *const c_void,
     len: ctypes::size_t,
-    flag: c_int, // currently not used
+    flag: c_int,
     socket_addr: *const ctypes::sockaddr,
     addrlen: ctypes::socklen_t,
 ) -> ctypes::ssize_t {
@@ -65,7 +79,7 @@ pub unsafe extern "C" fn send(
     socket_fd: c_int,
     buf_ptr: *const c_void,
     len: ctypes::size_t,
-    flag: c_int, // currently not used
+    flag: c_int,
 ) -> ctypes::ssize_t {
     e(sys_send(socket_fd, buf_ptr, len, flag) as _) as _
 }
@@ -78,7 +92,7 @@ pub unsafe extern "C" fn recvfrom(
     socket_fd: c_int,
     buf_ptr: *mut c_void,
     len: ctypes::size_t,
-    flag: c_int, // currently not used
+    flag: c_int,
     socket_addr: *mut ctypes::sockaddr,
     addrlen: *mut ctypes::socklen_t,
 ) -> ctypes::ssize_t {
@@ -88,6 +102,30 @@ pub unsafe extern "C" fn recvfrom(
     e(sys_recvfrom(socket_fd, buf_ptr, len, flag, socket_addr, addrlen) as _) as _
 }
 
+/// Send a message gathered from multiple buffers on a socket.
+///
+/// Return the number of bytes sent if success.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sendmsg(
+    socket_fd: c_int,
+    msg: *const ctypes::msghdr,
+    flags: c_int,
+) -> ctypes::ssize_t {
+    e(unsafe { sys_sendmsg(socket_fd, msg, flags) } as _) as _
+}
+
+/// Receive a message on a socket, scattered into multiple buffers.
+///
+/// Return the number of bytes received if success.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn recvmsg(
+    socket_fd: c_int,
+    msg: *mut ctypes::msghdr,
+    flags: c_int,
+) -> ctypes::ssize_t {
+    e(unsafe { sys_recvmsg(socket_fd, msg, flags) } as _) as _
+}
+
 /// Receive a message on a socket.
 ///
 /// Return the number of bytes received if success.
@@ -96,7 +134,7 @@ pub unsafe extern "C" fn recv(
     socket_fd: c_int,
     buf_ptr: *mut c_void,
     len: ctypes::size_t,
-    flag: c_int, // currently not used
+    flag: c_int,
 ) -> ctypes::ssize_t {
     e(sys_recv(socket_fd, buf_ptr, len, flag) as _) as _
 }
@@ -128,10 +166,7 @@ pub unsafe extern "C" fn accept(
 ///
 /// Return 0 if success.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn shutdown(
-    socket_fd: c_int,
-    flag: c_int, // currently not used
-) -> c_int {
+pub unsafe extern "C" fn shutdown(socket_fd: c_int, flag: c_int) -> c_int {
     e(sys_shutdown(socket_fd, flag))
 }
 
@@ -178,3 +213,31 @@ pub unsafe extern "C" fn getpeername(
 ) -> c_int {
     e(sys_getpeername(sock_fd, addr, addrlen))
 }
+
+/// Set options on a socket.
+///
+/// Return 0 if success.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn setsockopt(
+    fd: c_int,
+    level: c_int,
+    optname: c_int,
+    optval: *const c_void,
+    optlen: ctypes::socklen_t,
+) -> c_int {
+    e(sys_setsockopt(fd, level, optname, optval, optlen))
+}
+
+/// Get options on a socket.
+///
+/// Return 0 if success.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn getsockopt(
+    fd: c_int,
+    level: c_int,
+    optname: c_int,
+    optval: *mut c_void,
+    optlen: *mut ctypes::socklen_t,
+) -> c_int {
+    e(sys_getsockopt(fd, level, optname, optval, optlen))
+}
@@ -1,6 +1,6 @@
 use core::ffi::c_int;
 
-use arceos_posix_api::{sys_getrlimit, sys_setrlimit};
+use arceos_posix_api::{sys_getrlimit, sys_getrusage, sys_setrlimit};
 
 use crate::utils::e;
 
@@ -10,6 +10,12 @@ pub unsafe extern "C" fn getrlimit(resource: c_int, rlimits: *mut crate::ctypes:
     e(sys_getrlimit(resource, rlimits))
 }
 
+/// Get resource usages
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn getrusage(who: c_int, usage: *mut crate::ctypes::rusage) -> c_int {
+    e(sys_getrusage(who, usage))
+}
+
 /// Set resource limitations
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn setrlimit(resource: c_int, rlimits: *mut crate::ctypes::rlimit) -> c_int {
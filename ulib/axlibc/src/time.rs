@@ -1,4 +1,4 @@
-use arceos_posix_api::{sys_clock, sys_clock_gettime, sys_nanosleep};
+use arceos_posix_api::{sys_clock, sys_clock_gettime, sys_nanosleep, sys_times};
 
 #[cfg(all(feature = "multitask", feature = "irq"))]
 use arceos_posix_api::{sys_timer_create, sys_timer_delete, sys_timer_settime};
@@ -24,12 +24,18 @@ pub unsafe extern "C" fn nanosleep(
     e(sys_nanosleep(req, rem))
 }
 
-/// Get the clock tick
+/// Get the CPU time used by the process
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn clock() -> ctypes::clock_t {
     sys_clock()
 }
 
+/// Get the CPU times of the process
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn times(buf: *mut ctypes::tms) -> ctypes::clock_t {
+    sys_times(buf)
+}
+
 /// Create a timer.
 #[cfg(all(feature = "multitask", feature = "irq"))]
 #[unsafe(no_mangle)]
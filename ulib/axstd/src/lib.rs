@@ -15,6 +15,7 @@
 //!     - `fp-simd`: Enable floating point and SIMD support.
 //! - Interrupts:
 //!     - `irq`: Enable interrupt handling support.
+//!     - `tickless`: Stop the periodic timer tick when it's not needed, e.g., on idle CPUs.
 //! - Memory
 //!     - `alloc`: Enable dynamic memory allocation.
 //!     - `alloc-tlsf`: Use the TLSF allocator.
@@ -30,7 +31,10 @@
 //! - Upperlayer stacks
 //!     - `fs`: Enable file system support.
 //!     - `myfs`: Allow users to define their custom filesystems to override the default.
+//!     - `ext4`: Enable the ext4 filesystem, and use it as the root if the disk is ext4.
+//!     - `initramfs`: Unpack the initramfs (cpio archive) embedded or from the bootloader to `/`.
 //!     - `net`: Enable networking support.
+//!     - `dhcp`: Configure the network address by DHCP instead of `AX_IP`/`AX_GW`.
 //!     - `dns`: Enable DNS lookup support.
 //!     - `display`: Enable graphics support.
 //! - Device drivers